@@ -0,0 +1,57 @@
+//! Criterion benchmarks of the parsing, deduplication and formatting of the items of a feed,
+//! the steps timed by `multitude_bot bench`.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use multitude_bot::bench;
+
+/// The sizes of the generated feeds, in items.
+const SIZES: [usize; 3] = [10, 500, 5000];
+const RECENT_TITLES: usize = 1000;
+
+fn parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+    for items in SIZES {
+        let xml = bench::generate_feed(items);
+        group.bench_with_input(BenchmarkId::from_parameter(items), &xml, |b, xml| {
+            b.iter(|| bench::parse(black_box(xml.as_bytes())).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn dedup(c: &mut Criterion) {
+    let mut group = c.benchmark_group("dedup");
+    for items in SIZES {
+        let channel = bench::parse(bench::generate_feed(items).as_bytes()).unwrap();
+        let recent = bench::recent_titles(&channel, RECENT_TITLES);
+        group.bench_with_input(
+            BenchmarkId::from_parameter(items),
+            &channel,
+            |b, channel| b.iter(|| bench::count_duplicates(black_box(channel), &recent)),
+        );
+    }
+    group.finish();
+}
+
+fn format(c: &mut Criterion) {
+    let mut group = c.benchmark_group("format");
+    let feed = bench::sample_feed("Benchmark");
+    for items in SIZES {
+        let channel = bench::parse(bench::generate_feed(items).as_bytes()).unwrap();
+        group.bench_with_input(
+            BenchmarkId::new("message", items),
+            &channel,
+            |b, channel| b.iter(|| bench::format_messages(&feed, black_box(channel))),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("template", items),
+            &channel,
+            |b, channel| b.iter(|| bench::render_messages(&feed, black_box(channel))),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, parse, dedup, format);
+criterion_main!(benches);
@@ -1,13 +1,32 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.12.4
 
 use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
 #[sea_orm(table_name = "chat")]
 pub struct Model {
-    #[sea_orm(primary_key)]
+    #[sea_orm(primary_key, auto_increment = false)]
     pub id: i64,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub bot_id: i64,
     pub created_at: DateTime,
+    pub weekly_summary: bool,
+    pub subscription_tier: String,
+    pub premium_until: Option<DateTime>,
+    pub nsfw_mode: String,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub webhook_url: Option<String>,
+    pub webhook_secret: Option<String>,
+    pub shortener: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub shortener_url: Option<String>,
+    pub shortener_key: Option<String>,
+    pub track_clicks: bool,
+    pub breaking: bool,
+    pub catch_up: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub feed_defaults: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -1,29 +1,109 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.12.4
 
 use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
 #[sea_orm(table_name = "feed")]
 pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i64,
     pub chat_id: i64,
+    pub bot_id: i64,
     pub title: String,
     pub link: String,
     pub created_at: DateTime,
     pub updated_at: DateTime,
+    pub last_checked_at: Option<DateTime>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub last_error: Option<String>,
+    pub error_count: i32,
+    pub paused: bool,
+    pub check_count: i64,
+    pub failure_count: i64,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub category_filter: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub author_filter: Option<String>,
+    pub noise_suggested_at: Option<DateTime>,
+    pub dedup: bool,
+    pub edit_updates: bool,
+    pub pin: bool,
+    pub unpin_previous: bool,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub severity_rules: Option<String>,
+    pub icon: Option<String>,
+    pub thumbnail: bool,
+    pub hashtags: bool,
+    pub reading_time: bool,
+    pub max_rate: Option<String>,
+    pub muted_until: Option<DateTime>,
+    pub mute_buffer: bool,
+    pub delivery_window: Option<String>,
+    pub source_id: i64,
+    pub spam_filter: bool,
+    pub language_filter: Option<String>,
+    pub min_length: i32,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub pipeline: Option<String>,
+    pub priority: String,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub fediverse_profile: Option<String>,
+    pub fediverse_replies: bool,
+    pub fediverse_boosts: bool,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub download_webhook: Option<String>,
+    pub papers: bool,
+    pub release_notes: bool,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub release_level: Option<String>,
+    pub stable_releases: bool,
+    pub albums: bool,
+    pub comic: bool,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub iv_rhash: Option<String>,
+    pub archive: bool,
+    pub check_interval: Option<i32>,
+    pub next_check_at: Option<DateTime>,
+    pub content_hash: bool,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub body_hash: Option<String>,
+    pub gone_since: Option<DateTime>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub replacement_link: Option<String>,
+    pub replacement_prompted_at: Option<DateTime>,
+    pub checking_since: Option<DateTime>,
+    pub short_id: i32,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
     #[sea_orm(
         belongs_to = "super::chat::Entity",
-        from = "Column::ChatId",
-        to = "super::chat::Column::Id",
-        on_update = "NoAction",
+        from = "(Column::ChatId, Column::BotId)",
+        to = "(super::chat::Column::Id, super::chat::Column::BotId)",
+        on_update = "Cascade",
         on_delete = "Cascade"
     )]
     Chat,
+    #[sea_orm(has_many = "super::delivery::Entity")]
+    Delivery,
+    #[sea_orm(has_many = "super::destination::Entity")]
+    Destination,
+    #[sea_orm(has_many = "super::spam_item::Entity")]
+    SpamItem,
+    #[sea_orm(has_many = "super::folder_feed::Entity")]
+    FolderFeed,
+    #[sea_orm(has_many = "super::item_hash::Entity")]
+    ItemHash,
+    #[sea_orm(
+        belongs_to = "super::feed_source::Entity",
+        from = "Column::SourceId",
+        to = "super::feed_source::Column::Id",
+        on_update = "NoAction",
+        on_delete = "NoAction"
+    )]
+    FeedSource,
 }
 
 impl Related<super::chat::Entity> for Entity {
@@ -32,4 +112,40 @@ impl Related<super::chat::Entity> for Entity {
     }
 }
 
+impl Related<super::delivery::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Delivery.def()
+    }
+}
+
+impl Related<super::destination::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Destination.def()
+    }
+}
+
+impl Related<super::folder_feed::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::FolderFeed.def()
+    }
+}
+
+impl Related<super::item_hash::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ItemHash.def()
+    }
+}
+
+impl Related<super::spam_item::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::SpamItem.def()
+    }
+}
+
+impl Related<super::feed_source::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::FeedSource.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
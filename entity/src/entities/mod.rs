@@ -2,5 +2,30 @@
 
 pub mod prelude;
 
+pub mod account_event;
+pub mod blocklist;
+pub mod bridge;
+pub mod burst;
 pub mod chat;
+pub mod chat_link;
+pub mod delivery;
+pub mod destination;
+pub mod feature_flag;
 pub mod feed;
+pub mod feed_alias;
+pub mod feed_source;
+pub mod folder;
+pub mod folder_feed;
+pub mod follow;
+pub mod image_cache;
+pub mod item_hash;
+pub mod link_code;
+pub mod newsletter_item;
+pub mod report;
+pub mod sitemap_page;
+pub mod snooze;
+pub mod spam_item;
+pub mod undo_action;
+pub mod usage;
+pub mod watch;
+pub mod watch_change;
@@ -1,4 +1,29 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.12.4
 
+pub use super::account_event::Entity as AccountEvent;
+pub use super::blocklist::Entity as Blocklist;
+pub use super::bridge::Entity as Bridge;
+pub use super::burst::Entity as Burst;
 pub use super::chat::Entity as Chat;
+pub use super::chat_link::Entity as ChatLink;
+pub use super::delivery::Entity as Delivery;
+pub use super::destination::Entity as Destination;
+pub use super::feature_flag::Entity as FeatureFlag;
 pub use super::feed::Entity as Feed;
+pub use super::feed_alias::Entity as FeedAlias;
+pub use super::feed_source::Entity as FeedSource;
+pub use super::folder::Entity as Folder;
+pub use super::folder_feed::Entity as FolderFeed;
+pub use super::follow::Entity as Follow;
+pub use super::image_cache::Entity as ImageCache;
+pub use super::item_hash::Entity as ItemHash;
+pub use super::link_code::Entity as LinkCode;
+pub use super::newsletter_item::Entity as NewsletterItem;
+pub use super::report::Entity as Report;
+pub use super::sitemap_page::Entity as SitemapPage;
+pub use super::snooze::Entity as Snooze;
+pub use super::spam_item::Entity as SpamItem;
+pub use super::undo_action::Entity as UndoAction;
+pub use super::usage::Entity as Usage;
+pub use super::watch::Entity as Watch;
+pub use super::watch_change::Entity as WatchChange;
@@ -1,12 +1,142 @@
 pub use sea_orm_migration::prelude::*;
 
 mod m20231104_000001_create_table;
+mod m20261014_000002_add_feed_status;
+mod m20261014_000003_add_pause_and_deliveries;
+mod m20261015_000004_add_bot_id;
+mod m20261015_000005_add_chat_links;
+mod m20261015_000006_add_item_filters;
+mod m20261015_000007_add_engagement;
+mod m20261015_000008_add_feed_dedup;
+mod m20261015_000009_add_item_edits;
+mod m20261015_000010_add_feed_pin;
+mod m20261015_000011_add_severity_rules;
+mod m20261015_000012_add_feed_icon;
+mod m20261015_000013_add_thumbnails;
+mod m20261015_000014_add_feed_hashtags;
+mod m20261015_000015_add_feed_reading_time;
+mod m20261015_000016_add_weekly_summary;
+mod m20261015_000017_add_max_rate;
+mod m20261015_000018_add_feed_mute;
+mod m20261015_000019_add_snoozes;
+mod m20261015_000020_add_delivery_window;
+mod m20261015_000021_add_feed_sources;
+mod m20261015_000022_add_feature_flags;
+mod m20261015_000023_add_subscription_tier;
+mod m20261015_000024_add_usage;
+mod m20261015_000025_add_moderation;
+mod m20261015_000026_add_spam_filter;
+mod m20261015_000027_add_nsfw_mode;
+mod m20261015_000028_add_language_filter;
+mod m20261015_000029_add_min_length;
+mod m20261015_000030_add_pipeline;
+mod m20261015_000031_add_folders;
+mod m20261015_000032_add_feed_priority;
+mod m20261015_000033_add_destinations;
+mod m20261015_000034_add_newsletters;
+mod m20261015_000035_add_bridges;
+mod m20261015_000036_add_chat_webhook;
+mod m20261015_000037_add_fediverse;
+mod m20261015_000038_add_sitemap_pages;
+mod m20261015_000039_add_watches;
+mod m20261015_000040_add_watch_monitors;
+mod m20261015_000041_add_feed_download_webhook;
+mod m20261015_000042_add_feed_papers;
+mod m20261015_000043_add_feed_release_notes;
+mod m20261015_000044_add_feed_version_filter;
+mod m20261015_000045_add_feed_albums;
+mod m20261015_000046_add_feed_comic;
+mod m20261015_000047_add_feed_iv_rhash;
+mod m20261015_000048_add_feed_archive;
+mod m20261015_000049_add_chat_shortener;
+mod m20261015_000050_add_click_tracking;
+mod m20261015_000051_add_folder_top_items;
+mod m20261015_000052_add_bursts;
+mod m20261015_000053_add_follows;
+mod m20261015_000054_add_account_events;
+mod m20261015_000055_add_feed_schedule;
+mod m20261015_000056_add_source_bandwidth;
+mod m20261015_000057_add_source_tls;
+mod m20261015_000058_add_content_hashes;
+mod m20261015_000059_add_feed_gone;
+mod m20261015_000060_add_feed_alias;
+mod m20261015_000061_add_chat_catch_up;
+mod m20261015_000062_add_feed_checking_since;
+mod m20261015_000063_add_chat_feed_defaults;
+mod m20261015_000064_add_undo_actions;
+mod m20261015_000065_add_feed_short_id;
 
 pub struct Migrator;
 
 #[async_trait::async_trait]
 impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
-        vec![Box::new(m20231104_000001_create_table::Migration)]
+        vec![
+            Box::new(m20231104_000001_create_table::Migration),
+            Box::new(m20261014_000002_add_feed_status::Migration),
+            Box::new(m20261014_000003_add_pause_and_deliveries::Migration),
+            Box::new(m20261015_000004_add_bot_id::Migration),
+            Box::new(m20261015_000005_add_chat_links::Migration),
+            Box::new(m20261015_000006_add_item_filters::Migration),
+            Box::new(m20261015_000007_add_engagement::Migration),
+            Box::new(m20261015_000008_add_feed_dedup::Migration),
+            Box::new(m20261015_000009_add_item_edits::Migration),
+            Box::new(m20261015_000010_add_feed_pin::Migration),
+            Box::new(m20261015_000011_add_severity_rules::Migration),
+            Box::new(m20261015_000012_add_feed_icon::Migration),
+            Box::new(m20261015_000013_add_thumbnails::Migration),
+            Box::new(m20261015_000014_add_feed_hashtags::Migration),
+            Box::new(m20261015_000015_add_feed_reading_time::Migration),
+            Box::new(m20261015_000016_add_weekly_summary::Migration),
+            Box::new(m20261015_000017_add_max_rate::Migration),
+            Box::new(m20261015_000018_add_feed_mute::Migration),
+            Box::new(m20261015_000019_add_snoozes::Migration),
+            Box::new(m20261015_000020_add_delivery_window::Migration),
+            Box::new(m20261015_000021_add_feed_sources::Migration),
+            Box::new(m20261015_000022_add_feature_flags::Migration),
+            Box::new(m20261015_000023_add_subscription_tier::Migration),
+            Box::new(m20261015_000024_add_usage::Migration),
+            Box::new(m20261015_000025_add_moderation::Migration),
+            Box::new(m20261015_000026_add_spam_filter::Migration),
+            Box::new(m20261015_000027_add_nsfw_mode::Migration),
+            Box::new(m20261015_000028_add_language_filter::Migration),
+            Box::new(m20261015_000029_add_min_length::Migration),
+            Box::new(m20261015_000030_add_pipeline::Migration),
+            Box::new(m20261015_000031_add_folders::Migration),
+            Box::new(m20261015_000032_add_feed_priority::Migration),
+            Box::new(m20261015_000033_add_destinations::Migration),
+            Box::new(m20261015_000034_add_newsletters::Migration),
+            Box::new(m20261015_000035_add_bridges::Migration),
+            Box::new(m20261015_000036_add_chat_webhook::Migration),
+            Box::new(m20261015_000037_add_fediverse::Migration),
+            Box::new(m20261015_000038_add_sitemap_pages::Migration),
+            Box::new(m20261015_000039_add_watches::Migration),
+            Box::new(m20261015_000040_add_watch_monitors::Migration),
+            Box::new(m20261015_000041_add_feed_download_webhook::Migration),
+            Box::new(m20261015_000042_add_feed_papers::Migration),
+            Box::new(m20261015_000043_add_feed_release_notes::Migration),
+            Box::new(m20261015_000044_add_feed_version_filter::Migration),
+            Box::new(m20261015_000045_add_feed_albums::Migration),
+            Box::new(m20261015_000046_add_feed_comic::Migration),
+            Box::new(m20261015_000047_add_feed_iv_rhash::Migration),
+            Box::new(m20261015_000048_add_feed_archive::Migration),
+            Box::new(m20261015_000049_add_chat_shortener::Migration),
+            Box::new(m20261015_000050_add_click_tracking::Migration),
+            Box::new(m20261015_000051_add_folder_top_items::Migration),
+            Box::new(m20261015_000052_add_bursts::Migration),
+            Box::new(m20261015_000053_add_follows::Migration),
+            Box::new(m20261015_000054_add_account_events::Migration),
+            Box::new(m20261015_000055_add_feed_schedule::Migration),
+            Box::new(m20261015_000056_add_source_bandwidth::Migration),
+            Box::new(m20261015_000057_add_source_tls::Migration),
+            Box::new(m20261015_000058_add_content_hashes::Migration),
+            Box::new(m20261015_000059_add_feed_gone::Migration),
+            Box::new(m20261015_000060_add_feed_alias::Migration),
+            Box::new(m20261015_000061_add_chat_catch_up::Migration),
+            Box::new(m20261015_000062_add_feed_checking_since::Migration),
+            Box::new(m20261015_000063_add_chat_feed_defaults::Migration),
+            Box::new(m20261015_000064_add_undo_actions::Migration),
+            Box::new(m20261015_000065_add_feed_short_id::Migration),
+        ]
     }
 }
@@ -0,0 +1,46 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Feed::Table)
+                    .add_column(ColumnDef::new(Feed::LastCheckedAt).timestamp().null())
+                    .add_column(ColumnDef::new(Feed::LastError).text().null())
+                    .add_column(
+                        ColumnDef::new(Feed::ErrorCount)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Feed::Table)
+                    .drop_column(Feed::LastCheckedAt)
+                    .drop_column(Feed::LastError)
+                    .drop_column(Feed::ErrorCount)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Feed {
+    Table,
+    LastCheckedAt,
+    LastError,
+    ErrorCount,
+}
@@ -0,0 +1,119 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Feed::Table)
+                    .add_column(
+                        ColumnDef::new(Feed::Paused)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .add_column(
+                        ColumnDef::new(Feed::CheckCount)
+                            .big_integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .add_column(
+                        ColumnDef::new(Feed::FailureCount)
+                            .big_integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(Delivery::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Delivery::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Delivery::FeedId).big_integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("ForeignKey-Delivery-Feed")
+                            .from(Delivery::Table, Delivery::FeedId)
+                            .to(Feed::Table, Feed::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .col(ColumnDef::new(Delivery::ChatId).big_integer().not_null())
+                    .col(ColumnDef::new(Delivery::Title).string().not_null())
+                    .col(ColumnDef::new(Delivery::Link).string().not_null())
+                    .col(
+                        ColumnDef::new(Delivery::SentAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("Index-Delivery-SentAt")
+                    .table(Delivery::Table)
+                    .col(Delivery::SentAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Delivery::Table).to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Feed::Table)
+                    .drop_column(Feed::Paused)
+                    .drop_column(Feed::CheckCount)
+                    .drop_column(Feed::FailureCount)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Feed {
+    Table,
+    Id,
+    Paused,
+    CheckCount,
+    FailureCount,
+}
+
+#[derive(DeriveIden)]
+enum Delivery {
+    Table,
+    Id,
+    FeedId,
+    ChatId,
+    Title,
+    Link,
+    SentAt,
+}
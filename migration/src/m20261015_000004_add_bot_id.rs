@@ -0,0 +1,121 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// Keys chats by bot as well, so that the same Telegram chat can talk to several bots.
+///
+/// Existing rows get `bot_id` 0 and are claimed by the first configured bot on startup.
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Feed::Table)
+                    .drop_foreign_key(Alias::new("ForeignKey-Feed-Chat"))
+                    .add_column(
+                        ColumnDef::new(Feed::BotId)
+                            .big_integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Chat::Table)
+                    .add_column(
+                        ColumnDef::new(Chat::BotId)
+                            .big_integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        let db = manager.get_connection();
+        db.execute_unprepared("ALTER TABLE chat DROP CONSTRAINT chat_pkey")
+            .await?;
+        db.execute_unprepared("ALTER TABLE chat ADD PRIMARY KEY (id, bot_id)")
+            .await?;
+
+        manager
+            .create_foreign_key(
+                ForeignKey::create()
+                    .name("ForeignKey-Feed-Chat")
+                    .from(Feed::Table, (Feed::ChatId, Feed::BotId))
+                    .to(Chat::Table, (Chat::Id, Chat::BotId))
+                    .on_update(ForeignKeyAction::Cascade)
+                    .on_delete(ForeignKeyAction::Cascade)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_foreign_key(
+                ForeignKey::drop()
+                    .name("ForeignKey-Feed-Chat")
+                    .table(Feed::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        let db = manager.get_connection();
+        db.execute_unprepared("ALTER TABLE chat DROP CONSTRAINT chat_pkey")
+            .await?;
+        db.execute_unprepared("ALTER TABLE chat ADD PRIMARY KEY (id)")
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Chat::Table)
+                    .drop_column(Chat::BotId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Feed::Table)
+                    .drop_column(Feed::BotId)
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name("ForeignKey-Feed-Chat")
+                            .from_tbl(Feed::Table)
+                            .from_col(Feed::ChatId)
+                            .to_tbl(Chat::Table)
+                            .to_col(Chat::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Chat {
+    Table,
+    Id,
+    BotId,
+}
+
+#[derive(DeriveIden)]
+enum Feed {
+    Table,
+    ChatId,
+    BotId,
+}
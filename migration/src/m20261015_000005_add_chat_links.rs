@@ -0,0 +1,129 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(LinkCode::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(LinkCode::Code)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(LinkCode::ChatId).big_integer().not_null())
+                    .col(ColumnDef::new(LinkCode::BotId).big_integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("ForeignKey-LinkCode-Chat")
+                            .from(LinkCode::Table, (LinkCode::ChatId, LinkCode::BotId))
+                            .to(Chat::Table, (Chat::Id, Chat::BotId))
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .col(ColumnDef::new(LinkCode::ExpiresAt).timestamp().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(ChatLink::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ChatLink::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(ChatLink::BotId).big_integer().not_null())
+                    .col(ColumnDef::new(ChatLink::OwnerId).big_integer().not_null())
+                    .col(ColumnDef::new(ChatLink::ChatId).big_integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("ForeignKey-ChatLink-Owner")
+                            .from(ChatLink::Table, (ChatLink::OwnerId, ChatLink::BotId))
+                            .to(Chat::Table, (Chat::Id, Chat::BotId))
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("ForeignKey-ChatLink-Chat")
+                            .from(ChatLink::Table, (ChatLink::ChatId, ChatLink::BotId))
+                            .to(Chat::Table, (Chat::Id, Chat::BotId))
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .col(
+                        ColumnDef::new(ChatLink::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("Index-ChatLink-Unique")
+                    .table(ChatLink::Table)
+                    .col(ChatLink::BotId)
+                    .col(ChatLink::OwnerId)
+                    .col(ChatLink::ChatId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ChatLink::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(LinkCode::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Chat {
+    Table,
+    Id,
+    BotId,
+}
+
+#[derive(DeriveIden)]
+enum LinkCode {
+    Table,
+    Code,
+    ChatId,
+    BotId,
+    ExpiresAt,
+}
+
+#[derive(DeriveIden)]
+enum ChatLink {
+    Table,
+    Id,
+    BotId,
+    OwnerId,
+    ChatId,
+    CreatedAt,
+}
@@ -0,0 +1,83 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Delivery::Table)
+                    .add_column(ColumnDef::new(Delivery::MessageId).integer().null())
+                    .add_column(
+                        ColumnDef::new(Delivery::Clicks)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Feed::Table)
+                    .add_column(ColumnDef::new(Feed::NoiseSuggestedAt).timestamp().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("Index-Delivery-Message")
+                    .table(Delivery::Table)
+                    .col(Delivery::ChatId)
+                    .col(Delivery::MessageId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Feed::Table)
+                    .drop_column(Feed::NoiseSuggestedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Delivery::Table)
+                    .drop_column(Delivery::MessageId)
+                    .drop_column(Delivery::Clicks)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Feed {
+    Table,
+    NoiseSuggestedAt,
+}
+
+#[derive(DeriveIden)]
+enum Delivery {
+    Table,
+    ChatId,
+    MessageId,
+    Clicks,
+}
@@ -0,0 +1,54 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Snooze::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Snooze::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Snooze::DeliveryId).big_integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("ForeignKey-Snooze-Delivery")
+                            .from(Snooze::Table, Snooze::DeliveryId)
+                            .to(Delivery::Table, Delivery::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .col(ColumnDef::new(Snooze::ResendAt).timestamp().not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Snooze::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Delivery {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Snooze {
+    Table,
+    Id,
+    DeliveryId,
+    ResendAt,
+}
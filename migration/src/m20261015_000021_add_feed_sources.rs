@@ -0,0 +1,119 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// Splits the fetch state of a feed link into `feed_source`, shared by all the `feed` rows,
+/// which become the subscriptions of chats to a source with their own settings.
+///
+/// Existing feeds are assigned to one source per link, without trailing slashes.
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(FeedSource::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(FeedSource::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(FeedSource::Link)
+                            .text()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(ColumnDef::new(FeedSource::LastFetchedAt).timestamp().null())
+                    .col(ColumnDef::new(FeedSource::LastError).text().null())
+                    .col(
+                        ColumnDef::new(FeedSource::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Feed::Table)
+                    .add_column(ColumnDef::new(Feed::SourceId).big_integer().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "INSERT INTO feed_source (link) \
+             SELECT DISTINCT rtrim(trim(link), '/') FROM feed",
+        )
+        .await?;
+        db.execute_unprepared(
+            "UPDATE feed SET source_id = feed_source.id FROM feed_source \
+             WHERE feed_source.link = rtrim(trim(feed.link), '/')",
+        )
+        .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Feed::Table)
+                    .modify_column(ColumnDef::new(Feed::SourceId).big_integer().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_foreign_key(
+                ForeignKey::create()
+                    .name("ForeignKey-Feed-FeedSource")
+                    .from(Feed::Table, Feed::SourceId)
+                    .to(FeedSource::Table, FeedSource::Id)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Feed::Table)
+                    .drop_foreign_key(Alias::new("ForeignKey-Feed-FeedSource"))
+                    .drop_column(Feed::SourceId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(FeedSource::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Feed {
+    Table,
+    SourceId,
+}
+
+#[derive(DeriveIden)]
+enum FeedSource {
+    Table,
+    Id,
+    Link,
+    LastFetchedAt,
+    LastError,
+    CreatedAt,
+}
@@ -0,0 +1,38 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(FeatureFlag::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(FeatureFlag::Name)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(FeatureFlag::Value).string().not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(FeatureFlag::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum FeatureFlag {
+    Table,
+    Name,
+    Value,
+}
@@ -0,0 +1,91 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Usage::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(Usage::ChatId).big_integer().not_null())
+                    .col(ColumnDef::new(Usage::BotId).big_integer().not_null())
+                    .col(ColumnDef::new(Usage::Day).date().not_null())
+                    .col(
+                        ColumnDef::new(Usage::Commands)
+                            .big_integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(Usage::Bytes)
+                            .big_integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(Usage::Messages)
+                            .big_integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(Usage::Warned)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .col(
+                        ColumnDef::new(Usage::Limited)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(Usage::ChatId)
+                            .col(Usage::BotId)
+                            .col(Usage::Day),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("ForeignKey-Usage-Chat")
+                            .from(Usage::Table, (Usage::ChatId, Usage::BotId))
+                            .to(Chat::Table, (Chat::Id, Chat::BotId))
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Usage::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Chat {
+    Table,
+    Id,
+    BotId,
+}
+
+#[derive(DeriveIden)]
+enum Usage {
+    Table,
+    ChatId,
+    BotId,
+    Day,
+    Commands,
+    Bytes,
+    Messages,
+    Warned,
+    Limited,
+}
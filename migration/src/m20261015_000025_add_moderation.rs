@@ -0,0 +1,107 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Report::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Report::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Report::ChatId).big_integer().not_null())
+                    .col(ColumnDef::new(Report::BotId).big_integer().not_null())
+                    .col(ColumnDef::new(Report::FeedId).big_integer().not_null())
+                    .col(ColumnDef::new(Report::Link).text().not_null())
+                    .col(ColumnDef::new(Report::Reason).text().not_null())
+                    .col(
+                        ColumnDef::new(Report::Resolved)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .col(
+                        ColumnDef::new(Report::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("ForeignKey-Report-Chat")
+                            .from(Report::Table, (Report::ChatId, Report::BotId))
+                            .to(Chat::Table, (Chat::Id, Chat::BotId))
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(Blocklist::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Blocklist::Pattern)
+                            .text()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(Blocklist::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Blocklist::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Report::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Chat {
+    Table,
+    Id,
+    BotId,
+}
+
+#[derive(DeriveIden)]
+enum Report {
+    Table,
+    Id,
+    ChatId,
+    BotId,
+    FeedId,
+    Link,
+    Reason,
+    Resolved,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Blocklist {
+    Table,
+    Pattern,
+    CreatedAt,
+}
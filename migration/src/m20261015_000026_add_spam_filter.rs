@@ -0,0 +1,88 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Feed::Table)
+                    .add_column(
+                        ColumnDef::new(Feed::SpamFilter)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(SpamItem::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(SpamItem::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(SpamItem::FeedId).big_integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("ForeignKey-SpamItem-Feed")
+                            .from(SpamItem::Table, SpamItem::FeedId)
+                            .to(Feed::Table, Feed::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .col(ColumnDef::new(SpamItem::Title).string().not_null())
+                    .col(ColumnDef::new(SpamItem::Link).string().not_null())
+                    .col(ColumnDef::new(SpamItem::Reason).string().not_null())
+                    .col(
+                        ColumnDef::new(SpamItem::FilteredAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SpamItem::Table).to_owned())
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Feed::Table)
+                    .drop_column(Feed::SpamFilter)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Feed {
+    Table,
+    Id,
+    SpamFilter,
+}
+
+#[derive(DeriveIden)]
+enum SpamItem {
+    Table,
+    Id,
+    FeedId,
+    Title,
+    Link,
+    Reason,
+    FilteredAt,
+}
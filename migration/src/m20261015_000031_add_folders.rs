@@ -0,0 +1,123 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Folder::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Folder::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Folder::ChatId).big_integer().not_null())
+                    .col(ColumnDef::new(Folder::BotId).big_integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("ForeignKey-Folder-Chat")
+                            .from(Folder::Table, (Folder::ChatId, Folder::BotId))
+                            .to(Chat::Table, (Chat::Id, Chat::BotId))
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .col(ColumnDef::new(Folder::Name).string().not_null())
+                    .col(ColumnDef::new(Folder::MaxRate).string().null())
+                    .col(ColumnDef::new(Folder::DeliveryWindow).string().null())
+                    .col(ColumnDef::new(Folder::Template).text().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("Index-Folder-Unique")
+                    .table(Folder::Table)
+                    .col(Folder::ChatId)
+                    .col(Folder::BotId)
+                    .col(Folder::Name)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(FolderFeed::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(FolderFeed::FeedId)
+                            .big_integer()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(FolderFeed::FolderId).big_integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("ForeignKey-FolderFeed-Feed")
+                            .from(FolderFeed::Table, FolderFeed::FeedId)
+                            .to(Feed::Table, Feed::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("ForeignKey-FolderFeed-Folder")
+                            .from(FolderFeed::Table, FolderFeed::FolderId)
+                            .to(Folder::Table, Folder::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(FolderFeed::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Folder::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Chat {
+    Table,
+    Id,
+    BotId,
+}
+
+#[derive(DeriveIden)]
+enum Feed {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Folder {
+    Table,
+    Id,
+    ChatId,
+    BotId,
+    Name,
+    MaxRate,
+    DeliveryWindow,
+    Template,
+}
+
+#[derive(DeriveIden)]
+enum FolderFeed {
+    Table,
+    FeedId,
+    FolderId,
+}
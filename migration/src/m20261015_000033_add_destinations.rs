@@ -0,0 +1,58 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Destination::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Destination::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Destination::FeedId).big_integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("ForeignKey-Destination-Feed")
+                            .from(Destination::Table, Destination::FeedId)
+                            .to(Feed::Table, Feed::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .col(ColumnDef::new(Destination::ChatId).big_integer().not_null())
+                    .col(ColumnDef::new(Destination::ThreadId).integer().null())
+                    .col(ColumnDef::new(Destination::MutedUntil).timestamp().null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Destination::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Feed {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Destination {
+    Table,
+    Id,
+    FeedId,
+    ChatId,
+    ThreadId,
+    MutedUntil,
+}
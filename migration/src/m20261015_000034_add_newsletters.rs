@@ -0,0 +1,64 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(NewsletterItem::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(NewsletterItem::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(NewsletterItem::Inbox).string().not_null())
+                    .col(ColumnDef::new(NewsletterItem::Sender).text().not_null())
+                    .col(ColumnDef::new(NewsletterItem::Subject).text().not_null())
+                    .col(ColumnDef::new(NewsletterItem::Text).text().not_null())
+                    .col(ColumnDef::new(NewsletterItem::Html).text().null())
+                    .col(
+                        ColumnDef::new(NewsletterItem::ReceivedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("Index-NewsletterItem-Inbox")
+                    .table(NewsletterItem::Table)
+                    .col(NewsletterItem::Inbox)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(NewsletterItem::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum NewsletterItem {
+    Table,
+    Id,
+    Inbox,
+    Sender,
+    Subject,
+    Text,
+    Html,
+    ReceivedAt,
+}
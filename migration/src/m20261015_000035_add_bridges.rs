@@ -0,0 +1,60 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Bridge::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Bridge::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Bridge::ChatId).big_integer().not_null())
+                    .col(ColumnDef::new(Bridge::BotId).big_integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("ForeignKey-Bridge-Chat")
+                            .from(Bridge::Table, (Bridge::ChatId, Bridge::BotId))
+                            .to(Chat::Table, (Chat::Id, Chat::BotId))
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .col(ColumnDef::new(Bridge::Platform).string().not_null())
+                    .col(ColumnDef::new(Bridge::Target).text().not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Bridge::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Chat {
+    Table,
+    Id,
+    BotId,
+}
+
+#[derive(DeriveIden)]
+enum Bridge {
+    Table,
+    Id,
+    ChatId,
+    BotId,
+    Platform,
+    Target,
+}
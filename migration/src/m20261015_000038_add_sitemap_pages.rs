@@ -0,0 +1,57 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(SitemapPage::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(SitemapPage::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(SitemapPage::Sitemap).text().not_null())
+                    .col(ColumnDef::new(SitemapPage::Url).text().not_null())
+                    .col(ColumnDef::new(SitemapPage::Title).text().not_null())
+                    .col(ColumnDef::new(SitemapPage::FirstSeenAt).timestamp().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("Index-SitemapPage-Unique")
+                    .table(SitemapPage::Table)
+                    .col(SitemapPage::Sitemap)
+                    .col(SitemapPage::Url)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SitemapPage::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum SitemapPage {
+    Table,
+    Id,
+    Sitemap,
+    Url,
+    Title,
+    FirstSeenAt,
+}
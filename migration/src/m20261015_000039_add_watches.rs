@@ -0,0 +1,93 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Watch::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Watch::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Watch::Url).text().not_null())
+                    .col(ColumnDef::new(Watch::Selector).text().null())
+                    .col(ColumnDef::new(Watch::Value).text().null())
+                    .col(
+                        ColumnDef::new(Watch::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(WatchChange::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(WatchChange::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(WatchChange::WatchId).big_integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("ForeignKey-WatchChange-Watch")
+                            .from(WatchChange::Table, WatchChange::WatchId)
+                            .to(Watch::Table, Watch::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .col(ColumnDef::new(WatchChange::Title).text().not_null())
+                    .col(
+                        ColumnDef::new(WatchChange::ChangedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(WatchChange::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Watch::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Watch {
+    Table,
+    Id,
+    Url,
+    Selector,
+    Value,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum WatchChange {
+    Table,
+    Id,
+    WatchId,
+    Title,
+    ChangedAt,
+}
@@ -0,0 +1,50 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(AccountEvent::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(AccountEvent::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(AccountEvent::ChatId).big_integer().not_null())
+                    .col(ColumnDef::new(AccountEvent::BotId).big_integer().not_null())
+                    .col(ColumnDef::new(AccountEvent::Kind).string().not_null())
+                    .col(
+                        ColumnDef::new(AccountEvent::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AccountEvent::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum AccountEvent {
+    Table,
+    Id,
+    ChatId,
+    BotId,
+    Kind,
+    CreatedAt,
+}
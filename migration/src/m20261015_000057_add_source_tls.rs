@@ -0,0 +1,51 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(FeedSource::Table)
+                    .add_column(
+                        ColumnDef::new(FeedSource::TlsLegacy)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .add_column(
+                        ColumnDef::new(FeedSource::TlsInsecure)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .add_column(ColumnDef::new(FeedSource::TlsCa).text().null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(FeedSource::Table)
+                    .drop_column(FeedSource::TlsLegacy)
+                    .drop_column(FeedSource::TlsInsecure)
+                    .drop_column(FeedSource::TlsCa)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum FeedSource {
+    Table,
+    TlsLegacy,
+    TlsInsecure,
+    TlsCa,
+}
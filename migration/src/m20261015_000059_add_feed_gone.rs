@@ -0,0 +1,41 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Feed::Table)
+                    .add_column(ColumnDef::new(Feed::GoneSince).timestamp().null())
+                    .add_column(ColumnDef::new(Feed::ReplacementLink).text().null())
+                    .add_column(ColumnDef::new(Feed::ReplacementPromptedAt).timestamp().null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Feed::Table)
+                    .drop_column(Feed::GoneSince)
+                    .drop_column(Feed::ReplacementLink)
+                    .drop_column(Feed::ReplacementPromptedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Feed {
+    Table,
+    GoneSince,
+    ReplacementLink,
+    ReplacementPromptedAt,
+}
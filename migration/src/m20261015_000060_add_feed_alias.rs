@@ -0,0 +1,45 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(FeedAlias::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(FeedAlias::Pattern)
+                            .text()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(FeedAlias::Successor).text().not_null())
+                    .col(
+                        ColumnDef::new(FeedAlias::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(FeedAlias::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum FeedAlias {
+    Table,
+    Pattern,
+    Successor,
+    CreatedAt,
+}
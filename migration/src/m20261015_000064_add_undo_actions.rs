@@ -0,0 +1,52 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(UndoAction::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(UndoAction::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(UndoAction::ChatId).big_integer().not_null())
+                    .col(ColumnDef::new(UndoAction::BotId).big_integer().not_null())
+                    .col(ColumnDef::new(UndoAction::Kind).string().not_null())
+                    .col(ColumnDef::new(UndoAction::Feeds).text().not_null())
+                    .col(
+                        ColumnDef::new(UndoAction::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(UndoAction::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum UndoAction {
+    Table,
+    Id,
+    ChatId,
+    BotId,
+    Kind,
+    Feeds,
+    CreatedAt,
+}
@@ -0,0 +1,71 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// Numbers the feeds of each chat from 1, in the order they were subscribed to.
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Feed::Table)
+                    .add_column(ColumnDef::new(Feed::ShortId).integer().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "UPDATE feed SET short_id = numbered.short_id FROM \
+             (SELECT id, ROW_NUMBER() OVER (PARTITION BY chat_id, bot_id ORDER BY id) AS short_id \
+             FROM feed) AS numbered \
+             WHERE feed.id = numbered.id",
+        )
+        .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Feed::Table)
+                    .modify_column(ColumnDef::new(Feed::ShortId).integer().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("Index-Feed-ShortId")
+                    .table(Feed::Table)
+                    .col(Feed::ChatId)
+                    .col(Feed::BotId)
+                    .col(Feed::ShortId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Feed::Table)
+                    .drop_column(Feed::ShortId)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Feed {
+    Table,
+    ChatId,
+    BotId,
+    ShortId,
+}
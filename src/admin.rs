@@ -0,0 +1,29 @@
+//! The operator's admin chat.
+//!
+//! `ADMIN_CHAT_ID` names a chat with the default bot that receives operator reports and is the
+//! only chat allowed to use the admin commands.
+
+use std::env;
+
+/// The admin chat, injected as a dependency into the handlers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct AdminChat(pub(crate) Option<i64>);
+
+impl AdminChat {
+    /// Reads the admin chat from `ADMIN_CHAT_ID`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ADMIN_CHAT_ID` is set but is not a chat id.
+    pub(crate) fn from_env() -> Self {
+        AdminChat(
+            env::var("ADMIN_CHAT_ID")
+                .ok()
+                .map(|id| id.parse().expect("ADMIN_CHAT_ID is not a chat id")),
+        )
+    }
+
+    pub(crate) fn is(self, chat_id: i64) -> bool {
+        self.0 == Some(chat_id)
+    }
+}
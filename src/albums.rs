@@ -0,0 +1,117 @@
+//! Items sent as albums of photos.
+//!
+//! With `/albums <feed id> on`, items with several images, in `media:content` elements, possibly
+//! inside a `media:group`, in their enclosure or in their description, are sent as a Telegram
+//! media group, with the message as the caption of the first photo.
+
+use rss::extension::Extension;
+use rss::Item;
+use teloxide::{
+    prelude::*,
+    types::{InputFile, InputMedia, InputMediaPhoto, ParseMode},
+    ApiError, RequestError,
+};
+
+use entity::feed;
+
+/// Telegram media groups have at most this many photos.
+const MAX_PHOTOS: usize = 10;
+
+fn is_image(medium: Option<&String>, mime_type: Option<&String>) -> bool {
+    medium.is_some_and(|medium| medium == "image")
+        || mime_type.is_some_and(|mime_type| mime_type.starts_with("image/"))
+}
+
+/// Returns the images of the `media:content` elements `contents`.
+fn media_images(contents: &[Extension]) -> Vec<String> {
+    contents
+        .iter()
+        .filter(|content| is_image(content.attrs().get("medium"), content.attrs().get("type")))
+        .filter_map(|content| content.attrs().get("url").cloned())
+        .collect()
+}
+
+/// Returns the `src` of the `<img>` tags of `html`.
+fn html_images(html: &str) -> Vec<String> {
+    let mut images = Vec::new();
+    let mut rest = html;
+    while let Some(start) = rest.find("<img") {
+        rest = &rest[start..];
+        let end = rest.find('>').unwrap_or(rest.len());
+        let tag = &rest[..end];
+        let src = tag.split_once("src=").and_then(|(_, value)| {
+            let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+            value[1..].split(quote).next()
+        });
+        images.extend(src.map(str::to_string));
+        rest = &rest[end..];
+    }
+    images
+}
+
+/// Returns the images of `item`, without duplicates.
+fn images(item: &Item) -> Vec<String> {
+    let mut images = Vec::new();
+    if let Some(media) = item.extensions().get("media") {
+        let contents = media.get("content").map(Vec::as_slice).unwrap_or_default();
+        images.extend(media_images(contents));
+        for group in media.get("group").into_iter().flatten() {
+            let contents = group.children().get("content").map(Vec::as_slice).unwrap_or_default();
+            images.extend(media_images(contents));
+        }
+    }
+    if let Some(enclosure) = item.enclosure() {
+        if enclosure.mime_type().starts_with("image/") {
+            images.push(enclosure.url().to_string());
+        }
+    }
+    images.extend(html_images(item.description().unwrap_or_default()));
+    let mut unique = Vec::new();
+    for image in images {
+        if !unique.contains(&image) {
+            unique.push(image);
+        }
+    }
+    unique
+}
+
+/// Returns the photos of the album of `item`, if `feed` sends albums and `item` has more than
+/// one image.
+pub(crate) fn album(feed: &feed::Model, item: &Item) -> Option<Vec<String>> {
+    if !feed.albums {
+        return None;
+    }
+    let mut images = images(item);
+    images.truncate(MAX_PHOTOS);
+    Some(images).filter(|images| images.len() > 1)
+}
+
+/// Sends `photos` to the chat of `feed` as an album captioned with `text`, if it isn't empty.
+/// Returns the first message of the album.
+pub(crate) async fn send(
+    bot: &Bot,
+    feed: &feed::Model,
+    photos: &[String],
+    text: String,
+    silent: bool,
+) -> Result<Message, RequestError> {
+    let mut media = Vec::new();
+    for (index, photo) in photos.iter().enumerate() {
+        let url = photo
+            .parse()
+            .map_err(|_| RequestError::Api(ApiError::Unknown(format!("Invalid URL {}", photo))))?;
+        let mut photo = InputMediaPhoto::new(InputFile::url(url));
+        if index == 0 && !text.is_empty() {
+            photo = photo.caption(text.clone()).parse_mode(ParseMode::Html);
+        }
+        media.push(InputMedia::Photo(photo));
+    }
+    let messages = bot
+        .send_media_group(ChatId(feed.chat_id), media)
+        .disable_notification(silent)
+        .await?;
+    messages
+        .into_iter()
+        .next()
+        .ok_or_else(|| RequestError::Api(ApiError::Unknown("Empty album".to_string())))
+}
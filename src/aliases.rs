@@ -0,0 +1,199 @@
+//! Aliases of feeds that moved for good.
+//!
+//! Whole services retire their feed addresses, such as FeedBurner did. `/alias <pattern>
+//! <successor>` in the admin chat maps the links matching `pattern` to `successor`: a pattern
+//! is a link, or the start of links followed by `*`, in which case the rest of the link is
+//! appended to the successor. New subscriptions follow the aliases, and [`sweep`] moves the
+//! existing subscriptions to their successor once a day and as soon as an alias is added,
+//! telling their chats. `/alias` lists the aliases and `/alias remove <pattern>` removes one.
+
+use std::error::Error;
+
+use chrono::Utc;
+use sea_orm::{
+    sea_query::{Expr, OnConflict},
+    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder, Set,
+};
+use teloxide::{prelude::Requester, types::ChatId};
+
+use crate::bots::Bots;
+use crate::sources::normalize_link;
+use entity::{feed, feed_alias, feed_source};
+
+/// Returns `pattern` as it is stored: a normalized link, keeping the trailing `*` of prefixes.
+fn normalize_pattern(pattern: &str) -> String {
+    match pattern.trim().strip_suffix('*') {
+        Some(prefix) => format!("{}*", prefix),
+        None => normalize_link(pattern),
+    }
+}
+
+/// Returns the successor of `link` under `alias`, if the alias matches it.
+fn successor_of(alias: &feed_alias::Model, link: &str) -> Option<String> {
+    let link = normalize_link(link);
+    match alias.pattern.strip_suffix('*') {
+        Some(prefix) => {
+            let rest = link.strip_prefix(prefix)?;
+            Some(format!("{}{}", alias.successor, rest))
+        }
+        None if link == alias.pattern => Some(alias.successor.clone()),
+        None => None,
+    }
+}
+
+/// Returns the link that a subscription to `link` follows, which is `link` itself unless an
+/// alias matches it.
+pub(crate) async fn resolve(db: &DatabaseConnection, link: &str) -> String {
+    let aliases = match entity::prelude::FeedAlias::find().all(db).await {
+        Ok(aliases) => aliases,
+        Err(err) => {
+            println!("Error fetching the feed aliases: {:?}", err);
+            return link.to_string();
+        }
+    };
+    aliases
+        .iter()
+        .find_map(|alias| successor_of(alias, link))
+        .unwrap_or_else(|| link.to_string())
+}
+
+/// Moves the feeds of `source` to `successor`, telling their chats, and returns how many moved.
+async fn move_source(
+    bots: &Bots,
+    db: &DatabaseConnection,
+    source: &feed_source::Model,
+    successor: &str,
+) -> Result<u64, Box<dyn Error + Send + Sync>> {
+    if crate::moderation::is_blocked(db, successor).await? {
+        return Err(format!("{} is blocked", successor).into());
+    }
+    let channel = crate::validate_feed(&successor.to_string()).await?;
+    let new_source = crate::sources::source_for(db, successor).await?;
+    let feeds = entity::prelude::Feed::find()
+        .filter(feed::Column::SourceId.eq(source.id))
+        .all(db)
+        .await?;
+    // Items already published by the successor are not sent
+    let moved = entity::prelude::Feed::update_many()
+        .col_expr(feed::Column::SourceId, Expr::value(new_source.id))
+        .col_expr(feed::Column::Link, Expr::value(successor))
+        .col_expr(feed::Column::UpdatedAt, Expr::value(Utc::now().naive_utc()))
+        .filter(feed::Column::SourceId.eq(source.id))
+        .exec(db)
+        .await?;
+    for feed in &feeds {
+        crate::registry::invalidate(feed.id);
+    }
+    entity::prelude::FeedSource::delete_many()
+        .filter(feed_source::Column::Id.eq(source.id))
+        .exec(db)
+        .await?;
+
+    for feed in feeds {
+        let Some(bot) = bots.get(feed.bot_id) else {
+            continue;
+        };
+        let text = format!(
+            "{} moved from {} to {} ({}), and your subscription followed it.",
+            feed.title,
+            feed.link,
+            channel.title(),
+            successor
+        );
+        if let Err(err) = bot.send_message(ChatId(feed.chat_id), text).await {
+            log::error!("Error telling chat {} about a moved feed: {}", feed.chat_id, err);
+        }
+    }
+    Ok(moved.rows_affected)
+}
+
+/// Moves every subscription to a link with an alias to its successor, and returns how many
+/// moved.
+pub(crate) async fn sweep(bots: &Bots, db: &DatabaseConnection) -> Result<u64, DbErr> {
+    let aliases = entity::prelude::FeedAlias::find().all(db).await?;
+    if aliases.is_empty() {
+        return Ok(0);
+    }
+    let sources = entity::prelude::FeedSource::find().all(db).await?;
+    let mut moved = 0;
+    for source in sources {
+        let successor = aliases.iter().find_map(|alias| successor_of(alias, &source.link));
+        let Some(successor) = successor else {
+            continue;
+        };
+        if normalize_link(&successor) == source.link {
+            continue;
+        }
+        match move_source(bots, db, &source, &successor).await {
+            Ok(count) => moved += count,
+            Err(err) => println!("Error moving {} to {}: {:?}", source.link, successor, err),
+        }
+    }
+    Ok(moved)
+}
+
+/// Runs [`sweep`] once a day.
+pub(crate) async fn sweep_daily(bots: Bots, db: DatabaseConnection) {
+    match sweep(&bots, &db).await {
+        Ok(0) => {}
+        Ok(moved) => println!("Moved {} feeds to the successors of their aliases", moved),
+        Err(err) => println!("Error sweeping the feed aliases: {:?}", err),
+    }
+}
+
+fn format_aliases(aliases: &[feed_alias::Model]) -> String {
+    if aliases.is_empty() {
+        return "No aliases.".to_string();
+    }
+    aliases
+        .iter()
+        .map(|alias| format!("{} → {}", alias.pattern, alias.successor))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Runs `/alias` with `args`, which are nothing, `<pattern> <successor>` or `remove <pattern>`.
+pub(crate) async fn run(
+    bots: &Bots,
+    db: &DatabaseConnection,
+    args: &str,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let words: Vec<&str> = args.split_whitespace().collect();
+    match words.as_slice() {
+        [] => {
+            let aliases = entity::prelude::FeedAlias::find()
+                .order_by_asc(feed_alias::Column::Pattern)
+                .all(db)
+                .await?;
+            Ok(format_aliases(&aliases))
+        }
+        ["remove", pattern] => {
+            let pattern = normalize_pattern(pattern);
+            let deleted = entity::prelude::FeedAlias::delete_by_id(pattern.clone())
+                .exec(db)
+                .await?;
+            Ok(format!("Removed {} alias {}", deleted.rows_affected, pattern))
+        }
+        [pattern, successor] => {
+            if !pattern.contains("://") || !successor.contains("://") {
+                return Err("Expected a link or the start of links followed by *".into());
+            }
+            let alias = feed_alias::ActiveModel {
+                pattern: Set(normalize_pattern(pattern)),
+                successor: Set(successor.trim().to_string()),
+                ..Default::default()
+            };
+            entity::prelude::FeedAlias::insert(alias)
+                .on_conflict(
+                    OnConflict::column(feed_alias::Column::Pattern)
+                        .update_column(feed_alias::Column::Successor)
+                        .to_owned(),
+                )
+                .exec_without_returning(db)
+                .await?;
+            let moved = sweep(bots, db).await?;
+            Ok(format!("Added the alias and moved {} feed", moved))
+        }
+        _ => Err("Expected <pattern> <successor> or remove <pattern>".into()),
+    }
+}
@@ -0,0 +1,359 @@
+//! Optional HTTP management API.
+//!
+//! Exposes CRUD endpoints for chats and feeds, the polling status of each feed and a way to
+//! trigger a poll on demand. Every request must carry the operator token in an
+//! `Authorization: Bearer <token>` header. The same server hosts the [`crate::dashboard`], the
+//! [`crate::user_view`] and the inbound webhook of the [`crate::newsletters`].
+
+use std::error::Error;
+use std::net::SocketAddr;
+
+use axum::{
+    extract::{Path, Query, Request, State},
+    http::{header, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use sea_orm::{ActiveModelTrait, DatabaseConnection, EntityTrait, Set};
+use serde::{Deserialize, Serialize};
+use subtle::ConstantTimeEq;
+use teloxide::prelude::Requester;
+
+use crate::bots::Bots;
+use entity::{chat, feed};
+
+#[derive(Clone)]
+pub(crate) struct ApiState {
+    pub(crate) db: DatabaseConnection,
+    pub(crate) bots: Bots,
+    pub(crate) token: String,
+    pub(crate) bot_username: String,
+}
+
+impl ApiState {
+    /// Returns `bot_id`, or the id of the default bot if none was given.
+    fn bot_id(&self, bot_id: Option<i64>) -> i64 {
+        bot_id.unwrap_or_else(|| self.bots.default_id())
+    }
+
+    /// Returns whether `token` is the operator token, comparing them in constant time.
+    pub(crate) fn is_token(&self, token: &str) -> bool {
+        token.as_bytes().ct_eq(self.token.as_bytes()).into()
+    }
+}
+
+/// Error returned by the API handlers, rendered as a status code and a plain text body.
+struct ApiError(StatusCode, String);
+
+impl ApiError {
+    fn not_found() -> Self {
+        ApiError(StatusCode::NOT_FOUND, "Not found".to_string())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, self.1).into_response()
+    }
+}
+
+impl<E: Into<Box<dyn Error + Send + Sync>>> From<E> for ApiError {
+    fn from(err: E) -> Self {
+        ApiError(StatusCode::INTERNAL_SERVER_ERROR, err.into().to_string())
+    }
+}
+
+type ApiResult<T> = Result<T, ApiError>;
+
+#[derive(Deserialize)]
+struct BotQuery {
+    bot_id: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct NewChat {
+    id: i64,
+    bot_id: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct NewFeed {
+    chat_id: i64,
+    bot_id: Option<i64>,
+    link: String,
+}
+
+#[derive(Deserialize)]
+struct FeedUpdate {
+    title: Option<String>,
+    link: Option<String>,
+    paused: Option<bool>,
+}
+
+#[derive(Serialize)]
+struct FeedStatus {
+    id: i64,
+    updated_at: sea_orm::prelude::DateTime,
+    last_checked_at: Option<sea_orm::prelude::DateTime>,
+    last_error: Option<String>,
+    error_count: i32,
+    paused: bool,
+    check_count: i64,
+    failure_count: i64,
+    /// Bytes downloaded for the source of the feed, shared with other subscriptions to it.
+    downloaded_bytes: i64,
+    /// Size of the downloaded bodies once decompressed.
+    decoded_bytes: i64,
+}
+
+/// Serves the management API and the web views on `addr` until the process exits.
+///
+/// # Arguments
+///
+/// * `addr` - The socket address to listen on.
+/// * `token` - The bearer token every request must present.
+/// * `db` - A `DatabaseConnection` for reading and writing chats and feeds.
+/// * `bots` - The `Bots` of the chats. The web view for users logs in through the default one.
+pub(crate) async fn serve(
+    addr: SocketAddr,
+    token: String,
+    db: DatabaseConnection,
+    bots: Bots,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let bot_username = bots.default_bot().get_me().await?.username().to_string();
+    let state = ApiState {
+        db,
+        bots,
+        token,
+        bot_username,
+    };
+    let app = Router::new()
+        .route("/api/chats", get(list_chats).post(add_chat))
+        .route("/api/chats/{id}", get(get_chat).delete(remove_chat))
+        .route("/api/chats/{id}/feeds", get(list_chat_feeds))
+        .route("/api/feeds", get(list_feeds).post(add_feed))
+        .route(
+            "/api/feeds/{id}",
+            get(get_feed).patch(edit_feed).delete(remove_feed),
+        )
+        .route("/api/feeds/{id}/status", get(feed_status))
+        .route("/api/feeds/{id}/poll", post(poll_feed))
+        .route("/api/poll", post(poll_all))
+        .layer(middleware::from_fn_with_state(state.clone(), authenticate))
+        .merge(crate::dashboard::routes(state.clone()))
+        .merge(crate::user_view::routes(state.clone()))
+        // Incoming newsletters and clicks are still taken, they can't be sent again later
+        .layer(middleware::from_fn(refuse_during_maintenance))
+        .merge(crate::newsletters::routes())
+        .merge(crate::clicks::routes())
+        .with_state(state);
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    log::info!("Management API listening on {}", addr);
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+async fn authenticate(State(state): State<ApiState>, request: Request, next: Next) -> Response {
+    let authorized = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|token| state.is_token(token));
+    if !authorized {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    next.run(request).await
+}
+
+/// Answers with the maintenance notice instead of `next` while in maintenance mode.
+async fn refuse_during_maintenance(request: Request, next: Next) -> Response {
+    if crate::maintenance::is_on() {
+        return (StatusCode::SERVICE_UNAVAILABLE, crate::maintenance::NOTICE).into_response();
+    }
+    next.run(request).await
+}
+
+async fn list_chats(State(state): State<ApiState>) -> ApiResult<Json<Vec<chat::Model>>> {
+    Ok(Json(entity::prelude::Chat::find().all(&state.db).await?))
+}
+
+async fn get_chat(
+    State(state): State<ApiState>,
+    Path(id): Path<i64>,
+    Query(query): Query<BotQuery>,
+) -> ApiResult<Json<chat::Model>> {
+    entity::prelude::Chat::find_by_id((id, state.bot_id(query.bot_id)))
+        .one(&state.db)
+        .await?
+        .map(Json)
+        .ok_or_else(ApiError::not_found)
+}
+
+async fn add_chat(
+    State(state): State<ApiState>,
+    Json(new_chat): Json<NewChat>,
+) -> ApiResult<(StatusCode, Json<chat::Model>)> {
+    let bot_id = state.bot_id(new_chat.bot_id);
+    let chat = crate::create_chat(&state.db, new_chat.id, bot_id).await?;
+    Ok((StatusCode::CREATED, Json(chat)))
+}
+
+async fn remove_chat(
+    State(state): State<ApiState>,
+    Path(id): Path<i64>,
+    Query(query): Query<BotQuery>,
+) -> ApiResult<StatusCode> {
+    let deleted = crate::delete_chat(&state.db, id, state.bot_id(query.bot_id)).await?;
+    if deleted.rows_affected == 0 {
+        return Err(ApiError::not_found());
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn list_chat_feeds(
+    State(state): State<ApiState>,
+    Path(id): Path<i64>,
+    Query(query): Query<BotQuery>,
+) -> ApiResult<Json<Vec<feed::Model>>> {
+    let bot_id = state.bot_id(query.bot_id);
+    Ok(Json(crate::read_feed(&state.db, id, bot_id).await?))
+}
+
+async fn list_feeds(State(state): State<ApiState>) -> ApiResult<Json<Vec<feed::Model>>> {
+    Ok(Json(entity::prelude::Feed::find().all(&state.db).await?))
+}
+
+async fn find_feed(db: &DatabaseConnection, id: i64) -> ApiResult<feed::Model> {
+    entity::prelude::Feed::find_by_id(id)
+        .one(db)
+        .await?
+        .ok_or_else(ApiError::not_found)
+}
+
+async fn get_feed(
+    State(state): State<ApiState>,
+    Path(id): Path<i64>,
+) -> ApiResult<Json<feed::Model>> {
+    Ok(Json(find_feed(&state.db, id).await?))
+}
+
+async fn add_feed(
+    State(state): State<ApiState>,
+    Json(new_feed): Json<NewFeed>,
+) -> ApiResult<(StatusCode, Json<feed::Model>)> {
+    let link = crate::aliases::resolve(&state.db, &new_feed.link).await;
+    let channel = crate::validate_feed(&link)
+        .await
+        .map_err(|err| ApiError(StatusCode::UNPROCESSABLE_ENTITY, err.to_string()))?;
+    let bot_id = state.bot_id(new_feed.bot_id);
+    let feed = crate::create_feed(&state.db, &channel, new_feed.chat_id, bot_id).await?;
+    Ok((StatusCode::CREATED, Json(feed)))
+}
+
+/// Returns the link of the feed at `link`, resolving its alias, if it is a valid feed that is not
+/// blocked.
+async fn checked_link(db: &DatabaseConnection, link: &str) -> ApiResult<String> {
+    let link = crate::aliases::resolve(db, link).await;
+    let channel = crate::validate_feed(&link)
+        .await
+        .map_err(|err| ApiError(StatusCode::BAD_REQUEST, err.to_string()))?;
+    if crate::moderation::is_blocked(db, &channel.link).await? {
+        return Err(ApiError(
+            StatusCode::BAD_REQUEST,
+            "This feed is blocked on this bot".to_string(),
+        ));
+    }
+    Ok(channel.link)
+}
+
+async fn edit_feed(
+    State(state): State<ApiState>,
+    Path(id): Path<i64>,
+    Json(update): Json<FeedUpdate>,
+) -> ApiResult<Json<feed::Model>> {
+    let found = find_feed(&state.db, id).await?;
+    // New links are checked like new subscriptions
+    let link = match update.link {
+        Some(link) => Some(checked_link(&state.db, &link).await?),
+        None => None,
+    };
+    if update.title.is_some() {
+        let action = crate::undo::Action::Rename;
+        crate::undo::record(&state.db, found.chat_id, found.bot_id, action, &[id]).await?;
+    }
+    let mut feed: feed::ActiveModel = found.into();
+    if let Some(title) = update.title {
+        feed.title = Set(title);
+    }
+    if let Some(link) = link {
+        feed.source_id = Set(crate::sources::source_for(&state.db, &link).await?.id);
+        feed.link = Set(link);
+    }
+    if let Some(paused) = update.paused {
+        feed.paused = Set(paused);
+    }
+    let feed = feed.update(&state.db).await?;
+    crate::registry::invalidate(id);
+    Ok(Json(feed))
+}
+
+async fn remove_feed(State(state): State<ApiState>, Path(id): Path<i64>) -> ApiResult<StatusCode> {
+    let deleted = entity::prelude::Feed::delete_by_id(id)
+        .exec(&state.db)
+        .await?;
+    crate::registry::invalidate(id);
+    if deleted.rows_affected == 0 {
+        return Err(ApiError::not_found());
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn feed_status(
+    State(state): State<ApiState>,
+    Path(id): Path<i64>,
+) -> ApiResult<Json<FeedStatus>> {
+    let feed = find_feed(&state.db, id).await?;
+    let source = entity::prelude::FeedSource::find_by_id(feed.source_id)
+        .one(&state.db)
+        .await?;
+    let (downloaded_bytes, decoded_bytes) = source
+        .map_or((0, 0), |source| (source.downloaded_bytes, source.decoded_bytes));
+    Ok(Json(FeedStatus {
+        id: feed.id,
+        updated_at: feed.updated_at,
+        last_checked_at: feed.last_checked_at,
+        last_error: feed.last_error,
+        error_count: feed.error_count,
+        paused: feed.paused,
+        check_count: feed.check_count,
+        failure_count: feed.failure_count,
+        downloaded_bytes,
+        decoded_bytes,
+    }))
+}
+
+async fn poll_feed(State(state): State<ApiState>, Path(id): Path<i64>) -> ApiResult<StatusCode> {
+    let feed = find_feed(&state.db, id).await?;
+    if feed.paused {
+        return Err(ApiError(
+            StatusCode::CONFLICT,
+            format!("Feed {} is paused", feed.id),
+        ));
+    }
+    // The poller checks the feed, so that it is never checked twice at the same time
+    if !crate::schedule::make_due(&state.db, feed.id).await? {
+        return Err(ApiError(
+            StatusCode::CONFLICT,
+            format!("Feed {} is already being checked", feed.id),
+        ));
+    }
+    Ok(StatusCode::ACCEPTED)
+}
+
+async fn poll_all(State(state): State<ApiState>) -> ApiResult<StatusCode> {
+    crate::schedule::make_all_due(&state.db).await?;
+    Ok(StatusCode::ACCEPTED)
+}
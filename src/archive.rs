@@ -0,0 +1,98 @@
+//! Archive links for paywalled sites.
+//!
+//! With `/archive <feed id> on`, items linking to a site in [`PAYWALLED_DOMAINS`] are sent with
+//! links to the article on archive.today and on the Wayback Machine. If the Wayback Machine has
+//! no snapshot of the article yet, it is asked to save one.
+
+use std::error::Error;
+
+use rss::Item;
+use serde::Deserialize;
+use teloxide::utils::html::escape;
+
+use entity::feed;
+
+const WAYBACK_API: &str = "https://archive.org/wayback/available";
+const WAYBACK_URL: &str = "https://web.archive.org";
+const ARCHIVE_TODAY_URL: &str = "https://archive.ph/newest";
+/// Sites that usually put their articles behind a paywall, with their subdomains.
+const PAYWALLED_DOMAINS: [&str; 16] = [
+    "bloomberg.com",
+    "economist.com",
+    "faz.net",
+    "ft.com",
+    "handelsblatt.com",
+    "lemonde.fr",
+    "newyorker.com",
+    "nytimes.com",
+    "nzz.ch",
+    "spiegel.de",
+    "theathletic.com",
+    "theatlantic.com",
+    "thetimes.co.uk",
+    "washingtonpost.com",
+    "wired.com",
+    "wsj.com",
+];
+
+#[derive(Deserialize)]
+struct Availability {
+    archived_snapshots: Snapshots,
+}
+
+#[derive(Deserialize)]
+struct Snapshots {
+    closest: Option<Snapshot>,
+}
+
+#[derive(Deserialize)]
+struct Snapshot {
+    url: String,
+}
+
+fn is_paywalled(link: &str) -> bool {
+    let Ok(url) = reqwest::Url::parse(link) else {
+        return false;
+    };
+    let host = url.host_str().unwrap_or_default();
+    PAYWALLED_DOMAINS
+        .iter()
+        .any(|domain| host == *domain || host.ends_with(&format!(".{}", domain)))
+}
+
+/// Returns the closest Wayback Machine snapshot of `link`, asking for one if there is none.
+async fn wayback_snapshot(link: &str) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let url = format!("{}?url={}", WAYBACK_API, urlencoding::encode(link));
+    let availability: Availability = reqwest::get(&url)
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    if let Some(snapshot) = availability.archived_snapshots.closest {
+        return Ok(snapshot.url);
+    }
+    // The snapshot is taken in the background, the link shows it once it is ready
+    reqwest::get(format!("{}/save/{}", WAYBACK_URL, link))
+        .await?
+        .error_for_status()?;
+    Ok(format!("{}/web/{}", WAYBACK_URL, link))
+}
+
+/// Returns the HTML archive links of `item`, if `feed` asks for them and `item` links to a
+/// paywalled site.
+pub(crate) async fn details(feed: &feed::Model, item: &Item) -> Option<String> {
+    let link = item.link().filter(|link| feed.archive && is_paywalled(link))?;
+    let archive_today = format!("{}/{}", ARCHIVE_TODAY_URL, link);
+    let wayback = match wayback_snapshot(link).await {
+        Ok(wayback) => wayback,
+        Err(err) => {
+            println!("Error reading the Wayback Machine for {}: {:?}", link, err);
+            format!("{}/web/{}", WAYBACK_URL, link)
+        }
+    };
+    Some(format!(
+        "🗄 <a href='{}'>archive.today</a> · <a href='{}'>Wayback Machine</a>\n",
+        escape(&archive_today),
+        escape(&wayback)
+    ))
+}
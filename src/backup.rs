@@ -0,0 +1,175 @@
+//! Backup and restore of chats, feeds and their settings.
+//!
+//! `multitude_bot backup [file]` writes a JSON dump of the chats, their links, the feed sources,
+//! the feeds with their folders and destinations, the bridges, the watches, the followed names,
+//! the blocklist, the feed aliases, the feature flags and the hashes of the items seen by the
+//! feeds, without the delivery history, and `multitude_bot restore <file>` loads it into an
+//! empty database. Unlike `pg_dump`, the dump doesn't depend on the database server. Dumps of
+//! older versions can still be restored: version 1 only held the chats, their links, the sources
+//! and the feeds, and version 2 didn't hold the blocklist, the aliases, the flags and the item
+//! hashes.
+
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
+
+use sea_orm::{
+    ActiveModelTrait, ConnectionTrait, DatabaseConnection, EntityTrait, Statement,
+    TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+
+use entity::{
+    blocklist, bridge, chat, chat_link, destination, feature_flag, feed, feed_alias, feed_source,
+    folder, folder_feed, follow, item_hash, watch,
+};
+
+const VERSION: u32 = 3;
+/// The tables whose ids are restored as they were.
+const SEQUENCES: [&str; 9] = [
+    "chat_link",
+    "feed_source",
+    "watch",
+    "feed",
+    "folder",
+    "destination",
+    "bridge",
+    "follow",
+    "item_hash",
+];
+
+#[derive(Serialize, Deserialize)]
+struct Backup {
+    version: u32,
+    chats: Vec<chat::Model>,
+    chat_links: Vec<chat_link::Model>,
+    sources: Vec<feed_source::Model>,
+    feeds: Vec<feed::Model>,
+    // Missing from version 1
+    #[serde(default)]
+    watches: Vec<watch::Model>,
+    #[serde(default)]
+    folders: Vec<folder::Model>,
+    #[serde(default)]
+    folder_feeds: Vec<folder_feed::Model>,
+    #[serde(default)]
+    destinations: Vec<destination::Model>,
+    #[serde(default)]
+    bridges: Vec<bridge::Model>,
+    #[serde(default)]
+    follows: Vec<follow::Model>,
+    // Missing from version 2
+    #[serde(default)]
+    blocklist: Vec<blocklist::Model>,
+    #[serde(default)]
+    feed_aliases: Vec<feed_alias::Model>,
+    #[serde(default)]
+    feature_flags: Vec<feature_flag::Model>,
+    #[serde(default)]
+    item_hashes: Vec<item_hash::Model>,
+}
+
+/// Writes a backup to `path`, or to the standard output if there is none.
+pub(crate) async fn backup(
+    db: &DatabaseConnection,
+    path: Option<&str>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let backup = Backup {
+        version: VERSION,
+        chats: entity::prelude::Chat::find().all(db).await?,
+        chat_links: entity::prelude::ChatLink::find().all(db).await?,
+        sources: entity::prelude::FeedSource::find().all(db).await?,
+        feeds: entity::prelude::Feed::find().all(db).await?,
+        watches: entity::prelude::Watch::find().all(db).await?,
+        folders: entity::prelude::Folder::find().all(db).await?,
+        folder_feeds: entity::prelude::FolderFeed::find().all(db).await?,
+        destinations: entity::prelude::Destination::find().all(db).await?,
+        bridges: entity::prelude::Bridge::find().all(db).await?,
+        follows: entity::prelude::Follow::find().all(db).await?,
+        blocklist: entity::prelude::Blocklist::find().all(db).await?,
+        feed_aliases: entity::prelude::FeedAlias::find().all(db).await?,
+        feature_flags: entity::prelude::FeatureFlag::find().all(db).await?,
+        item_hashes: entity::prelude::ItemHash::find().all(db).await?,
+    };
+    let json = serde_json::to_string_pretty(&backup)?;
+    match path {
+        Some(path) => fs::write(path, json)?,
+        None => io::stdout().write_all(json.as_bytes())?,
+    }
+    Ok(())
+}
+
+/// Restores the backup in `path`. The restored rows must not exist yet.
+pub(crate) async fn restore(
+    db: &DatabaseConnection,
+    path: &str,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let backup: Backup = serde_json::from_str(&fs::read_to_string(path)?)?;
+    if !(1..=VERSION).contains(&backup.version) {
+        return Err(format!("Unsupported backup version {}", backup.version).into());
+    }
+    let txn = db.begin().await?;
+    for chat in backup.chats {
+        chat::ActiveModel::from(chat).reset_all().insert(&txn).await?;
+    }
+    for link in backup.chat_links {
+        chat_link::ActiveModel::from(link).reset_all().insert(&txn).await?;
+    }
+    for source in backup.sources {
+        feed_source::ActiveModel::from(source).reset_all().insert(&txn).await?;
+    }
+    for watch in backup.watches {
+        watch::ActiveModel::from(watch).reset_all().insert(&txn).await?;
+    }
+    for feed in backup.feeds {
+        feed::ActiveModel::from(feed).reset_all().insert(&txn).await?;
+    }
+    for folder in backup.folders {
+        folder::ActiveModel::from(folder).reset_all().insert(&txn).await?;
+    }
+    for folder_feed in backup.folder_feeds {
+        folder_feed::ActiveModel::from(folder_feed)
+            .reset_all()
+            .insert(&txn)
+            .await?;
+    }
+    for destination in backup.destinations {
+        destination::ActiveModel::from(destination)
+            .reset_all()
+            .insert(&txn)
+            .await?;
+    }
+    for bridge in backup.bridges {
+        bridge::ActiveModel::from(bridge).reset_all().insert(&txn).await?;
+    }
+    for follow in backup.follows {
+        follow::ActiveModel::from(follow).reset_all().insert(&txn).await?;
+    }
+    for pattern in backup.blocklist {
+        blocklist::ActiveModel::from(pattern).reset_all().insert(&txn).await?;
+    }
+    for alias in backup.feed_aliases {
+        feed_alias::ActiveModel::from(alias).reset_all().insert(&txn).await?;
+    }
+    for flag in backup.feature_flags {
+        feature_flag::ActiveModel::from(flag).reset_all().insert(&txn).await?;
+    }
+    for item_hash in backup.item_hashes {
+        item_hash::ActiveModel::from(item_hash)
+            .reset_all()
+            .insert(&txn)
+            .await?;
+    }
+    // The ids were inserted explicitly, the sequences must continue after them
+    for table in SEQUENCES {
+        let sql = format!(
+            "SELECT setval(pg_get_serial_sequence('{0}', 'id'), \
+             COALESCE((SELECT MAX(id) FROM {0}), 0) + 1, false)",
+            table
+        );
+        txn.execute(Statement::from_string(txn.get_database_backend(), sql))
+            .await?;
+    }
+    txn.commit().await?;
+    Ok(())
+}
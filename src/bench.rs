@@ -0,0 +1,232 @@
+//! Benchmarks of the polling pipeline.
+//!
+//! `multitude_bot bench [items]` times the parsing of a generated feed with `items` items,
+//! 5000 by default, the deduplication of its titles against [`RECENT_TITLES`] recent ones and
+//! the formatting of its messages with the settings of [`sample_feed`]. Each step runs
+//! [`ITERATIONS`] times and the mean duration is printed. `cargo bench` runs the same steps
+//! under criterion, from `benches/pipeline.rs`.
+//!
+//! `multitude_bot loadtest [feeds] [concurrency]` serves the fixture feeds on a local port and
+//! downloads, parses, deduplicates and formats `feeds` copies of them, 10000 by default, with
+//! `concurrency` polls at a time, then prints the throughput and the percentiles of the poll
+//! durations. Neither needs a database. For a load test of the whole poller and its queries,
+//! `multitude_bot seed [address] [copies]` subscribes to many copies of the fixture feeds, and
+//! the poller prints how long every poll took.
+
+use std::error::Error;
+use std::time::{Duration, Instant};
+
+use rss::Channel;
+use tokio::task::JoinSet;
+
+use crate::{dedup, fixtures, format, pipeline};
+use entity::feed;
+
+pub const DEFAULT_ITEMS: usize = 5000;
+pub const DEFAULT_FEEDS: usize = 10_000;
+pub const DEFAULT_CONCURRENCY: usize = 64;
+const ITERATIONS: u32 = 10;
+const RECENT_TITLES: usize = 1000;
+const TEMPLATE: &str = "<b>{title}</b>\n{feed}\n{link}";
+
+/// Returns an RSS feed with `items` items.
+pub fn generate_feed(items: usize) -> String {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?><rss version=\"2.0\"><channel>\
+         <title>Benchmark</title><link>https://example.com</link>\
+         <description>Generated feed</description>",
+    );
+    for i in 0..items {
+        xml.push_str(&format!(
+            "<item><title>Item {0} about topic {1} and story {2}</title>\
+             <link>https://example.com/items/{0}</link>\
+             <description>&lt;p&gt;The &lt;b&gt;description&lt;/b&gt; of item {0}.&lt;/p&gt;\
+             </description><guid>https://example.com/items/{0}</guid>\
+             <pubDate>Thu, 15 Oct 2026 06:00:00 GMT</pubDate></item>",
+            i,
+            i % 97,
+            i % 13
+        ));
+    }
+    xml.push_str("</channel></rss>");
+    xml
+}
+
+/// Returns a feed with the default settings, titled `title`.
+pub fn sample_feed(title: &str) -> feed::Model {
+    serde_json::from_value(serde_json::json!({
+        "id": 1,
+        "chat_id": 1001,
+        "bot_id": 0,
+        "title": title,
+        "link": "https://example.com/feed",
+        "created_at": "2026-10-15T00:00:00",
+        "updated_at": "2026-10-15T00:00:00",
+        "error_count": 0,
+        "paused": false,
+        "check_count": 0,
+        "failure_count": 0,
+        "dedup": false,
+        "edit_updates": false,
+        "pin": false,
+        "unpin_previous": false,
+        "thumbnail": false,
+        "hashtags": false,
+        "reading_time": false,
+        "mute_buffer": false,
+        "source_id": 1,
+        "spam_filter": false,
+        "min_length": 0,
+        "priority": "normal",
+        "fediverse_replies": false,
+        "fediverse_boosts": false,
+        "papers": false,
+        "release_notes": false,
+        "stable_releases": false,
+        "albums": false,
+        "comic": false,
+        "archive": false,
+        "content_hash": false,
+        "short_id": 1,
+    }))
+    .expect("The sample feed doesn't match the entity")
+}
+
+/// Parses the feed `content`.
+pub fn parse(content: &[u8]) -> Result<Channel, Box<dyn Error + Send + Sync>> {
+    crate::parse_channel(content)
+}
+
+/// Returns the first `count` titles of `channel`, as recently sent titles.
+pub fn recent_titles(channel: &Channel, count: usize) -> Vec<String> {
+    channel
+        .items()
+        .iter()
+        .take(count)
+        .map(|item| item.title().unwrap_or_default().to_string())
+        .collect()
+}
+
+/// Returns the number of items of `channel` that duplicate one of `recent` titles.
+pub fn count_duplicates(channel: &Channel, recent: &[String]) -> usize {
+    channel
+        .items()
+        .iter()
+        .filter(|item| dedup::is_duplicate(item.title().unwrap_or_default(), recent))
+        .count()
+}
+
+/// Returns the messages of the items of `channel` sent by `feed`.
+pub fn format_messages(feed: &feed::Model, channel: &Channel) -> Vec<String> {
+    channel
+        .items()
+        .iter()
+        .map(|item| format::item_message(feed, item))
+        .collect()
+}
+
+/// Returns the messages of the items of `channel` sent by `feed`, formatted by a template.
+pub fn render_messages(feed: &feed::Model, channel: &Channel) -> Vec<String> {
+    channel
+        .items()
+        .iter()
+        .map(|item| pipeline::render(TEMPLATE, feed, item))
+        .collect()
+}
+
+/// Runs `step` [`ITERATIONS`] times and prints its mean duration.
+fn time<T>(name: &str, mut step: impl FnMut() -> T) {
+    let started = Instant::now();
+    for _ in 0..ITERATIONS {
+        std::hint::black_box(step());
+    }
+    let mean: Duration = started.elapsed() / ITERATIONS;
+    println!("{}: {:.2?}", name, mean);
+}
+
+/// Times the steps of the pipeline for a feed with `items` items.
+pub(crate) fn run(items: usize) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let xml = generate_feed(items);
+    println!("Feed of {} items, {} bytes", items, xml.len());
+    time("parse", || parse(xml.as_bytes()));
+
+    let channel = parse(xml.as_bytes())?;
+    let recent = recent_titles(&channel, RECENT_TITLES);
+    time("dedup", || count_duplicates(&channel, &recent));
+
+    let feed = sample_feed(channel.title());
+    time("format", || format_messages(&feed, &channel));
+    time("template", || render_messages(&feed, &channel));
+    Ok(())
+}
+
+/// Downloads and polls the feed at `link` like the poller, without sending anything.
+async fn poll(link: String) -> Result<Duration, Box<dyn Error + Send + Sync>> {
+    let started = Instant::now();
+    let download = crate::http::download(&link).await?;
+    let channel = crate::read_channel(download.body).await?;
+    let recent = recent_titles(&channel, RECENT_TITLES);
+    count_duplicates(&channel, &recent);
+    format_messages(&sample_feed(channel.title()), &channel);
+    Ok(started.elapsed())
+}
+
+/// Returns the duration at `percentile` of the sorted `durations`.
+fn percentile(durations: &[Duration], percentile: usize) -> Duration {
+    let index = (durations.len() * percentile / 100).min(durations.len().saturating_sub(1));
+    durations.get(index).copied().unwrap_or_default()
+}
+
+/// Polls `feeds` copies of the fixture feeds, served locally, `concurrency` at a time.
+pub(crate) async fn load_test(
+    feeds: usize,
+    concurrency: usize,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    tokio::spawn(async move { axum::serve(listener, fixtures::router()).await });
+
+    let started = Instant::now();
+    let mut polls = JoinSet::new();
+    let mut durations = Vec::with_capacity(feeds);
+    let mut failures = 0;
+    let links = fixtures::FEEDS
+        .iter()
+        .cycle()
+        .zip(0..feeds)
+        .map(|((name, _), copy)| format!("http://{}/feeds/{}?copy={}", addr, name, copy));
+    for link in links {
+        // Up to `concurrency` feeds are polled at the same time, like the poller's sources
+        if polls.len() >= concurrency.max(1) {
+            match polls.join_next().await {
+                Some(Ok(Ok(duration))) => durations.push(duration),
+                _ => failures += 1,
+            }
+        }
+        polls.spawn(poll(link));
+    }
+    while let Some(poll) = polls.join_next().await {
+        match poll {
+            Ok(Ok(duration)) => durations.push(duration),
+            _ => failures += 1,
+        }
+    }
+    let elapsed = started.elapsed();
+
+    durations.sort();
+    println!(
+        "Polled {} feeds in {:.2?}, {:.0} feeds/s, {} failed",
+        durations.len(),
+        elapsed,
+        feeds as f64 / elapsed.as_secs_f64(),
+        failures
+    );
+    println!(
+        "Poll durations: p50 {:.2?}, p95 {:.2?}, p99 {:.2?}, max {:.2?}",
+        percentile(&durations, 50),
+        percentile(&durations, 95),
+        percentile(&durations, 99),
+        durations.last().copied().unwrap_or_default()
+    );
+    Ok(())
+}
@@ -0,0 +1,4 @@
+#[tokio::main]
+async fn main() {
+    multitude_bot::run().await;
+}
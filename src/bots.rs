@@ -0,0 +1,73 @@
+//! The Telegram bots served by this process.
+//!
+//! Every line of the token file configures one bot. Bots share the database and the poller;
+//! chats and feeds are keyed by the id of the bot they talk to, which is the numeric part of
+//! the bot token before the colon. `TELEGRAM_API_URL` points the bots to another Bot API
+//! server, such as a local one or the fake one of the [`crate::fixtures`].
+
+use std::collections::HashMap;
+use std::env;
+
+use teloxide::prelude::Bot;
+
+/// The id of the bot a dispatcher is serving, injected as a dependency into the handlers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct BotId(pub(crate) i64);
+
+/// Map from bot id to bot instance. The first configured bot is the default one, used by the
+/// web views and by API requests that don't name a bot.
+#[derive(Clone)]
+pub(crate) struct Bots {
+    bots: HashMap<i64, Bot>,
+    default_id: i64,
+}
+
+impl Bots {
+    /// Creates one bot for every non-empty line of `tokens`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are no tokens, if a token doesn't start with a numeric bot id or if
+    /// `TELEGRAM_API_URL` is not a URL.
+    pub(crate) fn from_tokens(tokens: &str) -> Self {
+        let api_url = env::var("TELEGRAM_API_URL")
+            .ok()
+            .map(|url| reqwest::Url::parse(&url).expect("TELEGRAM_API_URL is not a URL"));
+        let mut bots = HashMap::new();
+        let mut default_id = None;
+        for token in tokens.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let id = bot_id(token).unwrap_or_else(|| panic!("Invalid bot token {}", token));
+            default_id.get_or_insert(id);
+            let bot = match &api_url {
+                Some(api_url) => Bot::new(token).set_api_url(api_url.clone()),
+                None => Bot::new(token),
+            };
+            bots.insert(id, bot);
+        }
+        Bots {
+            bots,
+            default_id: default_id.expect("No bot token configured"),
+        }
+    }
+
+    pub(crate) fn get(&self, id: i64) -> Option<&Bot> {
+        self.bots.get(&id)
+    }
+
+    pub(crate) fn default_id(&self) -> i64 {
+        self.default_id
+    }
+
+    pub(crate) fn default_bot(&self) -> &Bot {
+        &self.bots[&self.default_id]
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (BotId, &Bot)> {
+        self.bots.iter().map(|(id, bot)| (BotId(*id), bot))
+    }
+}
+
+/// Returns the bot id encoded in a token of the form `<bot id>:<secret>`.
+fn bot_id(token: &str) -> Option<i64> {
+    token.split_once(':')?.0.parse().ok()
+}
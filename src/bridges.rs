@@ -0,0 +1,230 @@
+//! Mirrors of the deliveries of a chat to other platforms.
+//!
+//! The admin chat can bridge a chat to a Matrix room or a Discord webhook with `/bridge`, and
+//! every item delivered to the chat is then also posted there. Each platform is a [`Notifier`].
+//! Matrix rooms are written to by the account of the secret `MATRIX_ACCESS_TOKEN` on the
+//! homeserver `MATRIX_HOMESERVER`, which must have joined them.
+
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use rand::{distributions::Alphanumeric, Rng};
+use rss::Item;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DeleteResult, EntityTrait, QueryFilter,
+    QueryOrder, Set,
+};
+use serde_json::json;
+
+use entity::{bridge, feed};
+
+const USAGE: &str = "Expected /bridge <chat id> [list], matrix <room id>, discord <webhook url> \
+                     or off";
+
+/// A platform that items can be posted to.
+pub(crate) trait Notifier {
+    async fn notify(
+        &self,
+        feed: &feed::Model,
+        item: &Item,
+    ) -> Result<(), Box<dyn Error + Send + Sync>>;
+}
+
+/// A room of the Matrix account of the bot.
+pub(crate) struct MatrixRoom {
+    room_id: String,
+}
+
+impl Notifier for MatrixRoom {
+    async fn notify(
+        &self,
+        feed: &feed::Model,
+        item: &Item,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let homeserver =
+            env::var("MATRIX_HOMESERVER").map_err(|_| "MATRIX_HOMESERVER is not set")?;
+        let access_token = crate::secrets::get("MATRIX_ACCESS_TOKEN").await?;
+        let transaction_id: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(16)
+            .map(char::from)
+            .collect();
+        let url = format!(
+            "{}/_matrix/client/v3/rooms/{}/send/m.room.message/{}",
+            homeserver.trim_end_matches('/'),
+            urlencoding::encode(&self.room_id),
+            transaction_id
+        );
+        let body = json!({
+            "msgtype": "m.text",
+            "body": plain_message(feed, item),
+            "format": "org.matrix.custom.html",
+            "formatted_body": crate::format::item_message(feed, item),
+        });
+        reqwest::Client::new()
+            .put(&url)
+            .bearer_auth(access_token)
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// An incoming webhook of a Discord channel.
+pub(crate) struct DiscordWebhook {
+    url: String,
+}
+
+impl Notifier for DiscordWebhook {
+    async fn notify(
+        &self,
+        feed: &feed::Model,
+        item: &Item,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        reqwest::Client::new()
+            .post(&self.url)
+            .json(&json!({ "content": plain_message(feed, item) }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// The message posted for `item` on platforms without HTML.
+fn plain_message(feed: &feed::Model, item: &Item) -> String {
+    format!(
+        "{}\n{}\n{}",
+        crate::format::feed_label(feed),
+        item.title().unwrap_or_default(),
+        item.link().unwrap_or_default()
+    )
+}
+
+/// The platforms a chat can be bridged to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Platform {
+    Matrix,
+    Discord,
+}
+
+impl FromStr for Platform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "matrix" => Ok(Platform::Matrix),
+            "discord" => Ok(Platform::Discord),
+            _ => Err(format!("Expected matrix or discord, got {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Platform::Matrix => write!(f, "matrix"),
+            Platform::Discord => write!(f, "discord"),
+        }
+    }
+}
+
+async fn notify(bridge: &bridge::Model, feed: &feed::Model, item: &Item) {
+    let sent = match bridge.platform.parse() {
+        Ok(Platform::Matrix) => {
+            let room_id = bridge.target.clone();
+            MatrixRoom { room_id }.notify(feed, item).await
+        }
+        Ok(Platform::Discord) => {
+            let url = bridge.target.clone();
+            DiscordWebhook { url }.notify(feed, item).await
+        }
+        Err(err) => Err(err.into()),
+    };
+    if let Err(err) = sent {
+        println!("Error posting to {} bridge {}: {:?}", bridge.platform, bridge.id, err);
+    }
+}
+
+/// Posts `item` of `feed` to the bridges of its chat.
+pub(crate) async fn send_item(db: &DatabaseConnection, feed: &feed::Model, item: &Item) {
+    let bridges = entity::prelude::Bridge::find()
+        .filter(bridge::Column::ChatId.eq(feed.chat_id))
+        .filter(bridge::Column::BotId.eq(feed.bot_id))
+        .all(db)
+        .await;
+    match bridges {
+        Ok(bridges) => {
+            for bridge in &bridges {
+                notify(bridge, feed, item).await;
+            }
+        }
+        Err(err) => println!("Error fetching bridges: {:?}", err),
+    }
+}
+
+async fn remove_all(
+    db: &DatabaseConnection,
+    chat_id: i64,
+    bot_id: i64,
+) -> Result<DeleteResult, Box<dyn Error + Send + Sync>> {
+    Ok(entity::prelude::Bridge::delete_many()
+        .filter(bridge::Column::ChatId.eq(chat_id))
+        .filter(bridge::Column::BotId.eq(bot_id))
+        .exec(db)
+        .await?)
+}
+
+/// Runs `/bridge <args>` from the admin chat and returns the answer.
+pub(crate) async fn run(
+    db: &DatabaseConnection,
+    bot_id: i64,
+    args: &str,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let words: Vec<&str> = args.split_whitespace().collect();
+    let Some(chat_id) = words.first().and_then(|chat_id| chat_id.parse::<i64>().ok()) else {
+        return Err(USAGE.into());
+    };
+    match words[1..] {
+        [] | ["list"] => {
+            let bridges = entity::prelude::Bridge::find()
+                .filter(bridge::Column::ChatId.eq(chat_id))
+                .filter(bridge::Column::BotId.eq(bot_id))
+                .order_by_asc(bridge::Column::Id)
+                .all(db)
+                .await?;
+            if bridges.is_empty() {
+                return Ok(format!("Chat {} has no bridges.", chat_id));
+            }
+            Ok(bridges
+                .iter()
+                .map(|bridge| format!("{}: {} {}", bridge.id, bridge.platform, bridge.target))
+                .collect::<Vec<String>>()
+                .join("\n"))
+        }
+        ["off"] => {
+            let removed = remove_all(db, chat_id, bot_id).await?;
+            Ok(format!("Removed {} bridge", removed.rows_affected))
+        }
+        [platform, target] => {
+            let platform: Platform = platform.parse()?;
+            if platform == Platform::Discord && !target.starts_with("https://") {
+                return Err("Expected the https address of a Discord webhook".into());
+            }
+            let new_bridge = bridge::ActiveModel {
+                chat_id: Set(chat_id),
+                bot_id: Set(bot_id),
+                platform: Set(platform.to_string()),
+                target: Set(target.to_string()),
+                ..Default::default()
+            };
+            let bridge = new_bridge.insert(db).await?;
+            Ok(format!("Bridged chat {} to {} {}", chat_id, platform, bridge.target))
+        }
+        _ => Err(USAGE.into()),
+    }
+}
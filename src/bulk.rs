@@ -0,0 +1,224 @@
+//! Subscriptions to and from many feeds at once.
+//!
+//! `/subscribe` accepts several links separated by spaces or newlines, or, without links, in
+//! reply to a message listing them. The feeds are downloaded and validated concurrently, then
+//! subscribed to in order, and a single message reports the outcome of each link.
+//!
+//! `/unsubscribe all`, `/unsubscribe #<folder>` and `/unsubscribe matching <text>` list the
+//! feeds of the chat they select, every feed, those of a folder or those whose title or link
+//! contains the text, with a button to confirm. The selection is made again on confirmation.
+
+use std::error::Error;
+
+use rss::Channel;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder};
+use teloxide::{
+    payloads::SendMessageSetters,
+    prelude::{Bot, Requester, ResponseResult},
+    types::{CallbackQuery, InlineKeyboardButton, InlineKeyboardMarkup, Message},
+};
+use tokio::task::JoinSet;
+
+use crate::bots::BotId;
+use crate::undo::{self, Action};
+use entity::{feed, folder_feed};
+
+/// Maximum number of links subscribed to by a single command.
+const MAX_LINKS: usize = 50;
+const UNSUBSCRIBE_PREFIX: &str = "unsub:";
+/// Telegram refuses callback data longer than this many bytes.
+const MAX_CALLBACK_BYTES: usize = 64;
+/// Number of selected feeds listed in the confirmation.
+const LISTED_FEEDS: usize = 30;
+
+/// Returns the links and Fediverse handles in `text`.
+fn links_in(text: &str) -> Vec<String> {
+    text.split_whitespace()
+        .filter(|word| word.contains("://") || crate::fediverse::is_handle(word))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Returns the links of a `/subscribe` with `args` sent as `msg`: those in `args`, or those of
+/// the message it replies to if it has none.
+pub(crate) fn links(args: &str, msg: &Message) -> Vec<String> {
+    if !args.trim().is_empty() {
+        return args.split_whitespace().map(str::to_string).collect();
+    }
+    msg.reply_to_message()
+        .and_then(|reply| reply.text().or(reply.caption()))
+        .map(links_in)
+        .unwrap_or_default()
+}
+
+/// Returns whether a `/subscribe` with `args` sent as `msg` subscribes to several feeds, or to
+/// the links of the message it replies to.
+pub(crate) fn is_bulk(args: &str, msg: &Message) -> bool {
+    args.split_whitespace().count() > 1 || (args.trim().is_empty() && !links(args, msg).is_empty())
+}
+
+/// Subscribes `chat_id` to every one of `links` and returns the outcome of each.
+pub(crate) async fn subscribe(
+    db: &DatabaseConnection,
+    links: Vec<String>,
+    chat_id: i64,
+    bot_id: i64,
+) -> String {
+    let skipped = links.len().saturating_sub(MAX_LINKS);
+    let mut validations = JoinSet::new();
+    for (index, link) in links.into_iter().take(MAX_LINKS).enumerate() {
+        let db = db.clone();
+        validations.spawn(async move {
+            if crate::fediverse::is_handle(&link) {
+                return (index, link, None);
+            }
+            let resolved = crate::aliases::resolve(&db, &link).await;
+            (index, link, Some(crate::validate_feed(&resolved).await))
+        });
+    }
+    let mut validated: Vec<(usize, String, Option<Result<Channel, _>>)> = Vec::new();
+    while let Some(validation) = validations.join_next().await {
+        match validation {
+            Ok(validation) => validated.push(validation),
+            Err(err) => println!("Error validating a feed: {:?}", err),
+        }
+    }
+    validated.sort_by_key(|(index, ..)| *index);
+
+    let mut lines = Vec::new();
+    for (_, link, channel) in validated {
+        let created: Result<_, Box<dyn Error + Send + Sync>> = match channel {
+            None => crate::fediverse::subscribe(db, &link, chat_id, bot_id).await,
+            Some(Ok(channel)) => crate::create_feed(db, &channel, chat_id, bot_id).await,
+            Some(Err(err)) => Err(err),
+        };
+        lines.push(match created {
+            Ok(feed) => format!("✅ {} - {}", feed.title, feed.link),
+            Err(err) => format!("❌ {}: {}", link, err),
+        });
+    }
+    if skipped > 0 {
+        lines.push(format!("Skipped {} links beyond the first {}", skipped, MAX_LINKS));
+    }
+    lines.join("\n")
+}
+
+/// Returns the feeds of `chat_id` selected by `selector`: `all`, `#<folder>` or `matching
+/// <text>`.
+async fn select(
+    db: &DatabaseConnection,
+    chat_id: i64,
+    bot_id: i64,
+    selector: &str,
+) -> Result<Vec<feed::Model>, Box<dyn Error + Send + Sync>> {
+    let feeds = entity::prelude::Feed::find()
+        .filter(feed::Column::ChatId.eq(chat_id))
+        .filter(feed::Column::BotId.eq(bot_id))
+        .order_by_asc(feed::Column::Id);
+    let selector = selector.trim();
+    if selector.eq_ignore_ascii_case("all") {
+        return Ok(feeds.all(db).await?);
+    }
+    if let Some(name) = selector.strip_prefix('#') {
+        let folder = crate::folders::find(db, chat_id, bot_id, name).await?;
+        let members = entity::prelude::FolderFeed::find()
+            .filter(folder_feed::Column::FolderId.eq(folder.id))
+            .all(db)
+            .await?;
+        let ids = members.into_iter().map(|member| member.feed_id);
+        return Ok(feeds.filter(feed::Column::Id.is_in(ids)).all(db).await?);
+    }
+    if let Some(text) = selector.strip_prefix("matching ") {
+        let text = text.trim().to_lowercase();
+        let matches = |feed: &feed::Model| {
+            feed.title.to_lowercase().contains(&text) || feed.link.to_lowercase().contains(&text)
+        };
+        return Ok(feeds.all(db).await?.into_iter().filter(matches).collect());
+    }
+    Err("Expected a feed id, all, #<folder> or matching <text>".into())
+}
+
+/// Asks `msg`'s chat to confirm unsubscribing from the feeds selected by `selector`.
+pub(crate) async fn confirm_unsubscribe(
+    bot: &Bot,
+    msg: &Message,
+    db: &DatabaseConnection,
+    bot_id: BotId,
+    selector: &str,
+) -> ResponseResult<()> {
+    let data = format!("{}{}", UNSUBSCRIBE_PREFIX, selector.trim());
+    let feeds = if data.len() > MAX_CALLBACK_BYTES {
+        Err("The text to match is too long".into())
+    } else {
+        select(db, msg.chat.id.0, bot_id.0, selector).await
+    };
+    let feeds = match feeds {
+        Ok(feeds) if feeds.is_empty() => {
+            bot.send_message(msg.chat.id, "No feed matches.").await?;
+            return Ok(());
+        }
+        Ok(feeds) => feeds,
+        Err(error) => {
+            bot.send_message(msg.chat.id, format!("Error: {}", error))
+                .await?;
+            return Ok(());
+        }
+    };
+    let mut lines = vec![format!("Unsubscribe from {} feeds?", feeds.len())];
+    for feed in feeds.iter().take(LISTED_FEEDS) {
+        lines.push(format!("{} - {}", feed.short_id, crate::format::feed_label(feed)));
+    }
+    if feeds.len() > LISTED_FEEDS {
+        lines.push(format!("and {} more", feeds.len() - LISTED_FEEDS));
+    }
+    let keyboard = InlineKeyboardMarkup::new(vec![vec![
+        InlineKeyboardButton::callback("Unsubscribe", data),
+        InlineKeyboardButton::callback("Cancel", format!("{}cancel", UNSUBSCRIBE_PREFIX)),
+    ]]);
+    bot.send_message(msg.chat.id, lines.join("\n"))
+        .reply_markup(keyboard)
+        .await?;
+    Ok(())
+}
+
+/// Returns whether the callback `data` belongs to an unsubscribe confirmation.
+pub(crate) fn is_unsubscribe_button(data: &str) -> bool {
+    data.starts_with(UNSUBSCRIBE_PREFIX)
+}
+
+/// Handles the buttons of an unsubscribe confirmation, replacing it with the outcome.
+pub(crate) async fn handle_unsubscribe(
+    bot: &Bot,
+    q: &CallbackQuery,
+    data: &str,
+    db: &DatabaseConnection,
+    bot_id: BotId,
+) -> ResponseResult<()> {
+    let (Some(message), Some(selector)) = (&q.message, data.strip_prefix(UNSUBSCRIBE_PREFIX))
+    else {
+        return Ok(());
+    };
+    let chat_id = message.chat.id;
+    let text = if selector == "cancel" {
+        "Kept the feeds.".to_string()
+    } else {
+        let deleted = async {
+            let feeds = select(db, chat_id.0, bot_id.0, selector).await?;
+            let ids: Vec<i64> = feeds.iter().map(|feed| feed.id).collect();
+            undo::record(db, chat_id.0, bot_id.0, Action::Unsubscribe, &ids).await?;
+            let mut deleted = 0;
+            for feed in feeds {
+                deleted += crate::delete_feed(db, feed.id, chat_id.0, bot_id.0)
+                    .await?
+                    .rows_affected;
+            }
+            Ok::<_, Box<dyn Error + Send + Sync>>(deleted)
+        };
+        match deleted.await {
+            Ok(deleted) => format!("Deleted {} feeds", deleted),
+            Err(error) => format!("Error: {}", error),
+        }
+    };
+    bot.edit_message_text(chat_id, message.id, text).await?;
+    Ok(())
+}
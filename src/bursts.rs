@@ -0,0 +1,186 @@
+//! Breaking news bursts.
+//!
+//! With `/breaking on`, when an item shares at least [`SHARED_KEYWORDS`] keywords with items
+//! sent by other feeds of the chat in the last [`BURST_MINUTES`] minutes, and [`BURST_FEEDS`]
+//! feeds report the story together, a single 🔴 message lists all their items. Later items
+//! on the same story are added to that message instead of being sent on their own, until no
+//! feed reports it for [`BURST_MINUTES`] minutes.
+
+use std::collections::HashSet;
+use std::error::Error;
+
+use chrono::{Duration, Utc};
+use rss::Item;
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter,
+    Set, UpdateResult,
+};
+use teloxide::{
+    payloads::{EditMessageTextSetters, SendMessageSetters},
+    prelude::{Bot, Requester},
+    types::{ChatId, MessageId, ParseMode},
+};
+
+use crate::similarity::{keywords, shared};
+use entity::{burst, chat, delivery, feed};
+
+const BURST_MINUTES: i64 = 30;
+/// Number of feeds that must report a story for it to be a burst.
+const BURST_FEEDS: usize = 3;
+/// Number of keywords two titles share when they are about the same story.
+const SHARED_KEYWORDS: usize = 2;
+
+fn item_line(link: &str, title: &str) -> String {
+    format!("• <a href='{}'>{}</a>\n", link, title)
+}
+
+/// Adds `item` of `feed` to the burst message `burst`.
+async fn extend_burst(
+    bot: &Bot,
+    db: &DatabaseConnection,
+    feed: &feed::Model,
+    item: &Item,
+    burst: burst::Model,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let text = format!(
+        "{}{}",
+        burst.text,
+        item_line(item.link().unwrap_or_default(), item.title().unwrap_or_default())
+    );
+    bot.edit_message_text(ChatId(feed.chat_id), MessageId(burst.message_id), &text)
+        .parse_mode(ParseMode::Html)
+        .disable_web_page_preview(true)
+        .await?;
+    let mut extended: burst::ActiveModel = burst.into();
+    extended.text = Set(text);
+    extended.updated_at = Set(Utc::now().naive_utc());
+    extended.update(db).await?;
+    Ok(())
+}
+
+/// Sends a new burst message with the `reports` of other feeds and `item` of `feed`.
+async fn start_burst(
+    bot: &Bot,
+    db: &DatabaseConnection,
+    feed: &feed::Model,
+    item: &Item,
+    reports: &[delivery::Model],
+    item_keywords: &HashSet<String>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut text = "🔴 <b>Breaking</b>\n".to_string();
+    for report in reports {
+        text.push_str(&item_line(&report.link, &report.title));
+    }
+    text.push_str(&item_line(
+        item.link().unwrap_or_default(),
+        item.title().unwrap_or_default(),
+    ));
+    let sent = bot
+        .send_message(ChatId(feed.chat_id), &text)
+        .parse_mode(ParseMode::Html)
+        .disable_web_page_preview(true)
+        .await?;
+    let new_burst = burst::ActiveModel {
+        chat_id: Set(feed.chat_id),
+        bot_id: Set(feed.bot_id),
+        message_id: Set(sent.id.0),
+        keywords: Set(item_keywords.iter().cloned().collect::<Vec<String>>().join(" ")),
+        text: Set(text),
+        ..Default::default()
+    };
+    new_burst.insert(db).await?;
+    Ok(())
+}
+
+/// Sends `item` of `feed` as part of a burst if it belongs to one. Returns whether it did.
+async fn try_absorb(
+    bot: &Bot,
+    db: &DatabaseConnection,
+    feed: &feed::Model,
+    item: &Item,
+) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    let chat = entity::prelude::Chat::find_by_id((feed.chat_id, feed.bot_id))
+        .one(db)
+        .await?;
+    if !chat.is_some_and(|chat| chat.breaking) {
+        return Ok(false);
+    }
+    let item_keywords = keywords(item.title().unwrap_or_default());
+    if item_keywords.len() < SHARED_KEYWORDS {
+        return Ok(false);
+    }
+    let since = Utc::now().naive_utc() - Duration::minutes(BURST_MINUTES);
+    let ongoing = entity::prelude::Burst::find()
+        .filter(burst::Column::ChatId.eq(feed.chat_id))
+        .filter(burst::Column::BotId.eq(feed.bot_id))
+        .filter(burst::Column::UpdatedAt.gt(since))
+        .all(db)
+        .await?
+        .into_iter()
+        .find(|burst| {
+            let burst_keywords = burst.keywords.split(' ').map(str::to_string).collect();
+            shared(&item_keywords, &burst_keywords) >= SHARED_KEYWORDS
+        });
+    if let Some(burst) = ongoing {
+        extend_burst(bot, db, feed, item, burst).await?;
+        crate::create_delivery(db, feed, item, None).await?;
+        return Ok(true);
+    }
+    let other_feeds: Vec<i64> = entity::prelude::Feed::find()
+        .filter(feed::Column::ChatId.eq(feed.chat_id))
+        .filter(feed::Column::BotId.eq(feed.bot_id))
+        .filter(feed::Column::Id.ne(feed.id))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|other| other.id)
+        .collect();
+    let reports: Vec<delivery::Model> = entity::prelude::Delivery::find()
+        .filter(delivery::Column::FeedId.is_in(other_feeds))
+        .filter(delivery::Column::SentAt.gt(since))
+        .filter(delivery::Column::Held.eq(false))
+        .all(db)
+        .await?
+        .into_iter()
+        .filter(|report| shared(&item_keywords, &keywords(&report.title)) >= SHARED_KEYWORDS)
+        .collect();
+    let reporting: HashSet<i64> = reports.iter().map(|report| report.feed_id).collect();
+    if reporting.len() + 1 < BURST_FEEDS {
+        return Ok(false);
+    }
+    start_burst(bot, db, feed, item, &reports, &item_keywords).await?;
+    crate::create_delivery(db, feed, item, None).await?;
+    Ok(true)
+}
+
+/// Sends `item` of `feed` as part of a burst of breaking news if its chat groups them and it
+/// belongs to one. Returns whether it did, otherwise the item should be sent on its own.
+pub(crate) async fn absorb(
+    bot: &Bot,
+    db: &DatabaseConnection,
+    feed: &feed::Model,
+    item: &Item,
+) -> bool {
+    match try_absorb(bot, db, feed, item).await {
+        Ok(absorbed) => absorbed,
+        Err(err) => {
+            println!("Error checking bursts of chat {}: {:?}", feed.chat_id, err);
+            false
+        }
+    }
+}
+
+/// Turns the grouping of breaking news of `chat_id` on or off.
+pub(crate) async fn set_breaking(
+    db: &DatabaseConnection,
+    chat_id: i64,
+    bot_id: i64,
+    enabled: bool,
+) -> Result<UpdateResult, Box<dyn Error + Send + Sync>> {
+    Ok(entity::prelude::Chat::update_many()
+        .col_expr(chat::Column::Breaking, Expr::value(enabled))
+        .filter(chat::Column::Id.eq(chat_id))
+        .filter(chat::Column::BotId.eq(bot_id))
+        .exec(db)
+        .await?)
+}
@@ -0,0 +1,92 @@
+//! Optional Redis cache of feed bodies.
+//!
+//! When `REDIS_URL` is set, fetched feed bodies are cached together with their ETag. Feeds with
+//! the same link polled within [`FRESH_SECONDS`] share a single download, and later fetches send
+//! `If-None-Match` so that servers can answer that the feed didn't change. Without Redis, or if
+//! it fails, every feed is downloaded directly.
+
+use std::error::Error;
+use std::sync::OnceLock;
+
+use redis::{aio::ConnectionManager, AsyncCommands, RedisResult};
+use reqwest::{
+    header::{ETAG, IF_NONE_MATCH},
+    StatusCode,
+};
+
+use crate::http::{self, Download};
+
+/// Shorter than the polling interval, so that every cycle checks the feed again.
+const FRESH_SECONDS: u64 = 25;
+const BODY_SECONDS: u64 = 24 * 60 * 60;
+
+static REDIS: OnceLock<ConnectionManager> = OnceLock::new();
+
+/// Connects to the Redis server at `url` and enables the cache.
+pub(crate) async fn connect(url: &str) -> RedisResult<()> {
+    let client = redis::Client::open(url)?;
+    let manager = ConnectionManager::new(client).await?;
+    let _ = REDIS.set(manager);
+    Ok(())
+}
+
+/// Returns the body of the feed at `link`, from the cache if it is fresh or unchanged.
+pub(crate) async fn fetch(link: &str) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+    Ok(fetch_download(link).await?.body)
+}
+
+/// Like [`fetch`], but also returns how many bytes were downloaded.
+pub(crate) async fn fetch_download(link: &str) -> Result<Download, Box<dyn Error + Send + Sync>> {
+    let Some(redis) = REDIS.get() else {
+        return http::download(link).await;
+    };
+    match fetch_cached(&mut redis.clone(), link).await {
+        Ok(download) => Ok(download),
+        Err(err) if err.is::<redis::RedisError>() => {
+            println!("Error using the feed cache: {:?}", err);
+            http::download(link).await
+        }
+        Err(err) => Err(err),
+    }
+}
+
+async fn fetch_cached(
+    redis: &mut ConnectionManager,
+    link: &str,
+) -> Result<Download, Box<dyn Error + Send + Sync>> {
+    let body_key = format!("feed-body:{}", link);
+    let etag_key = format!("feed-etag:{}", link);
+    let fresh_key = format!("feed-fresh:{}", link);
+    let body: Option<Vec<u8>> = redis.get(&body_key).await?;
+    if let Some(body) = &body {
+        if redis.exists(&fresh_key).await? {
+            return Ok(Download::local(body.clone()));
+        }
+    }
+
+    let mut request = http::get(link)?;
+    let etag: Option<String> = redis.get(&etag_key).await?;
+    if let (Some(etag), Some(_)) = (&etag, &body) {
+        request = request.header(IF_NONE_MATCH, etag);
+    }
+    let response = request.send().await?;
+    let download = match body {
+        Some(body) if response.status() == StatusCode::NOT_MODIFIED => Download::local(body),
+        _ => {
+            let etag = response
+                .headers()
+                .get(ETAG)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string);
+            let download = http::read(response).await?;
+            redis.set_ex(&body_key, &download.body, BODY_SECONDS).await?;
+            match etag {
+                Some(etag) => redis.set_ex(&etag_key, etag, BODY_SECONDS).await?,
+                None => redis.del(&etag_key).await?,
+            }
+            download
+        }
+    };
+    redis.set_ex(&fresh_key, 1, FRESH_SECONDS).await?;
+    Ok(download)
+}
@@ -0,0 +1,137 @@
+//! iCalendar (`.ics`) feeds.
+//!
+//! A calendar is turned into a feed with one item per event, published [`REMINDER_MINUTES`]
+//! minutes before the event starts, so that the poller sends it as a reminder once that time
+//! has come. Events whose reminder is still in the future are left out until then. Times
+//! without a time zone are read as UTC, and recurring events only remind of their first
+//! occurrence.
+
+use std::error::Error;
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use rss::{Channel, Guid, Item};
+
+use crate::dates;
+
+const REMINDER_MINUTES: i64 = 60;
+/// Reminders older than this many days are not listed.
+const PAST_DAYS: i64 = 7;
+
+#[derive(Debug, Default)]
+struct Event {
+    uid: Option<String>,
+    summary: Option<String>,
+    location: Option<String>,
+    url: Option<String>,
+    start: Option<NaiveDateTime>,
+}
+
+/// Returns whether `content` is an iCalendar file rather than an RSS feed.
+pub(crate) fn is_calendar(content: &[u8]) -> bool {
+    let start = &content[..content.len().min(64)];
+    String::from_utf8_lossy(start)
+        .trim_start_matches('\u{feff}')
+        .trim_start()
+        .starts_with("BEGIN:VCALENDAR")
+}
+
+/// Joins the folded lines of `content`, which continue with a space or a tab.
+fn unfold(content: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in content.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+fn unescape(value: &str) -> String {
+    value
+        .replace("\\n", " ")
+        .replace("\\N", " ")
+        .replace("\\,", ",")
+        .replace("\\;", ";")
+        .replace("\\\\", "\\")
+}
+
+fn parse_events(content: &str) -> Vec<Event> {
+    let mut events = Vec::new();
+    let mut current: Option<Event> = None;
+    for line in unfold(content) {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        // Parameters such as `DTSTART;VALUE=DATE` are ignored
+        let name = name.split(';').next().unwrap_or_default().to_uppercase();
+        match (name.as_str(), current.as_mut()) {
+            ("BEGIN", _) if value == "VEVENT" => current = Some(Event::default()),
+            ("END", Some(_)) if value == "VEVENT" => events.extend(current.take()),
+            ("UID", Some(event)) => event.uid = Some(value.to_string()),
+            ("SUMMARY", Some(event)) => event.summary = Some(unescape(value)),
+            ("LOCATION", Some(event)) => event.location = Some(unescape(value)),
+            ("URL", Some(event)) => event.url = Some(value.to_string()),
+            ("DTSTART", Some(event)) => event.start = dates::parse_ical(value),
+            _ => {}
+        }
+    }
+    events
+}
+
+fn reminder_time(start: NaiveDateTime) -> NaiveDateTime {
+    start - Duration::minutes(REMINDER_MINUTES)
+}
+
+fn item(event: &Event, start: NaiveDateTime) -> Item {
+    let mut title = format!(
+        "⏰ {} – {} UTC",
+        event.summary.as_deref().unwrap_or("Event"),
+        start.format("%a %d %b %H:%M")
+    );
+    if let Some(location) = event.location.as_ref().filter(|location| !location.is_empty()) {
+        title.push_str(&format!(" · {}", location));
+    }
+    let mut item = Item::default();
+    item.set_title(title);
+    item.set_link(event.url.clone());
+    item.set_pub_date(dates::rfc2822(reminder_time(start)));
+    if let Some(uid) = &event.uid {
+        let mut guid = Guid::default();
+        guid.set_value(format!("{}@{}", uid, start.format("%Y%m%dT%H%M%S")));
+        guid.set_permalink(false);
+        item.set_guid(guid);
+    }
+    item
+}
+
+/// Parses the calendar `content` into a feed of the reminders that are due.
+pub(crate) fn channel(content: &[u8]) -> Result<Channel, Box<dyn Error + Send + Sync>> {
+    let content = std::str::from_utf8(content)?;
+    let mut title = "Calendar".to_string();
+    for line in unfold(content) {
+        if let Some(name) = line.strip_prefix("X-WR-CALNAME:") {
+            title = unescape(name);
+            break;
+        }
+    }
+    let now = Utc::now().naive_utc();
+    let mut events: Vec<(NaiveDateTime, Event)> = parse_events(content)
+        .into_iter()
+        .filter_map(|event| Some((event.start?, event)))
+        .filter(|(start, _)| {
+            let reminder = reminder_time(*start);
+            reminder <= now && reminder > now - Duration::days(PAST_DAYS)
+        })
+        .collect();
+    events.sort_by_key(|(start, _)| std::cmp::Reverse(*start));
+    let mut channel = Channel::default();
+    channel.set_title(title);
+    channel.set_items(
+        events
+            .iter()
+            .map(|(start, event)| item(event, *start))
+            .collect::<Vec<Item>>(),
+    );
+    Ok(channel)
+}
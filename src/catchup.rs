@@ -0,0 +1,111 @@
+//! What a chat receives after the bot was down.
+//!
+//! A feed that wasn't checked for longer than [`DOWNTIME_INTERVALS`] times its interval, and at
+//! least [`MIN_DOWNTIME_MINUTES`] minutes, was missed by a downtime of the bot. `/catchup`
+//! chooses what its chat receives at the next poll:
+//!
+//! - `all` (the default): every item published since the last item sent;
+//! - `<hours>`: the same, but only the items of the last `hours` hours;
+//! - `skip`: only the items published since the feed was last checked, skipping to now.
+
+use std::error::Error;
+
+use chrono::{Duration, NaiveDateTime};
+use sea_orm::{
+    sea_query::Expr, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, UpdateResult,
+};
+
+use entity::{chat, feed};
+
+const DOWNTIME_INTERVALS: i32 = 3;
+const MIN_DOWNTIME_MINUTES: i64 = 60;
+/// Longest catch-up window, in hours.
+const MAX_HOURS: i64 = 24 * 30;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum CatchUp {
+    All,
+    Hours(i64),
+    Skip,
+}
+
+impl CatchUp {
+    fn parse(value: &str) -> Result<Self, String> {
+        match value.trim().to_lowercase().as_str() {
+            "all" => Ok(CatchUp::All),
+            "skip" => Ok(CatchUp::Skip),
+            hours => match hours.trim_end_matches('h').parse::<i64>() {
+                Ok(hours) if (1..=MAX_HOURS).contains(&hours) => Ok(CatchUp::Hours(hours)),
+                _ => Err(format!(
+                    "Expected all, skip or a number of hours up to {}, got {}",
+                    MAX_HOURS, value
+                )),
+            },
+        }
+    }
+
+    /// Returns the value stored in the chat, `None` for the default.
+    fn stored(self) -> Option<String> {
+        match self {
+            CatchUp::All => None,
+            CatchUp::Hours(hours) => Some(hours.to_string()),
+            CatchUp::Skip => Some("skip".to_string()),
+        }
+    }
+
+    fn of(chat: &chat::Model) -> Self {
+        chat.catch_up
+            .as_deref()
+            .and_then(|value| CatchUp::parse(value).ok())
+            .unwrap_or(CatchUp::All)
+    }
+}
+
+/// Returns the date after which items of `feed` are new when it is polled at `now`: the date of
+/// the last item sent, unless the feed was missed by a downtime and its chat catches up on less.
+pub(crate) async fn cutoff(
+    db: &DatabaseConnection,
+    feed: &feed::Model,
+    now: NaiveDateTime,
+) -> NaiveDateTime {
+    let Some(last_checked_at) = feed.last_checked_at else {
+        return feed.updated_at;
+    };
+    let next_check_at = crate::schedule::next_check_at(feed, last_checked_at, feed.error_count);
+    let downtime = (next_check_at - last_checked_at) * DOWNTIME_INTERVALS;
+    if now - last_checked_at < downtime.max(Duration::minutes(MIN_DOWNTIME_MINUTES)) {
+        return feed.updated_at;
+    }
+    let chat = entity::prelude::Chat::find_by_id((feed.chat_id, feed.bot_id))
+        .one(db)
+        .await;
+    let catch_up = match chat {
+        Ok(chat) => chat.as_ref().map_or(CatchUp::All, CatchUp::of),
+        Err(err) => {
+            println!("Error fetching the chat of feed {}: {:?}", feed.id, err);
+            CatchUp::All
+        }
+    };
+    match catch_up {
+        CatchUp::All => feed.updated_at,
+        CatchUp::Hours(hours) => feed.updated_at.max(now - Duration::hours(hours)),
+        CatchUp::Skip => feed.updated_at.max(last_checked_at),
+    }
+}
+
+/// Sets what `chat_id` receives after a downtime to `value`, and returns it.
+pub(crate) async fn set_catch_up(
+    db: &DatabaseConnection,
+    chat_id: i64,
+    bot_id: i64,
+    value: &str,
+) -> Result<(CatchUp, UpdateResult), Box<dyn Error + Send + Sync>> {
+    let catch_up = CatchUp::parse(value)?;
+    let updated = entity::prelude::Chat::update_many()
+        .col_expr(chat::Column::CatchUp, Expr::value(catch_up.stored()))
+        .filter(chat::Column::Id.eq(chat_id))
+        .filter(chat::Column::BotId.eq(bot_id))
+        .exec(db)
+        .await?;
+    Ok((catch_up, updated))
+}
@@ -0,0 +1,85 @@
+//! Fault injection for soak tests.
+//!
+//! Debug builds read the rate of each kind of fault, between 0 and 1, from the environment, so
+//! that the retries, queues and backoffs can be exercised against a fixture server:
+//!
+//! - `CHAOS_FETCH_TIMEOUTS`: downloads of feeds hang for [`TIMEOUT_DELAY`] and time out;
+//! - `CHAOS_TELEGRAM_429`: items are refused by Telegram with 429 Too Many Requests;
+//! - `CHAOS_DB_ERRORS`: polls of feeds and the storage of their outcome fail.
+//!
+//! Release builds ignore these variables.
+
+use std::env;
+use std::error::Error;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use sea_orm::DbErr;
+use teloxide::RequestError;
+
+/// How long an injected timeout hangs before failing.
+const TIMEOUT_DELAY: Duration = Duration::from_secs(5);
+/// The delay asked by injected 429 errors.
+const RETRY_AFTER: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Rates {
+    fetch_timeouts: f64,
+    telegram_429: f64,
+    db_errors: f64,
+}
+
+static RATES: OnceLock<Rates> = OnceLock::new();
+
+fn rate(name: &str) -> f64 {
+    let Ok(value) = env::var(name) else {
+        return 0.0;
+    };
+    match value.parse::<f64>() {
+        Ok(rate) if (0.0..=1.0).contains(&rate) => rate,
+        _ => panic!("{} is not a rate between 0 and 1", name),
+    }
+}
+
+fn rates() -> Rates {
+    *RATES.get_or_init(|| {
+        if !cfg!(debug_assertions) {
+            return Rates::default();
+        }
+        let rates = Rates {
+            fetch_timeouts: rate("CHAOS_FETCH_TIMEOUTS"),
+            telegram_429: rate("CHAOS_TELEGRAM_429"),
+            db_errors: rate("CHAOS_DB_ERRORS"),
+        };
+        if rates.fetch_timeouts > 0.0 || rates.telegram_429 > 0.0 || rates.db_errors > 0.0 {
+            log::warn!("Injecting faults: {:?}", rates);
+        }
+        rates
+    })
+}
+
+fn strikes(rate: f64) -> bool {
+    rate > 0.0 && rand::random::<f64>() < rate
+}
+
+/// Hangs and fails, at the rate of `CHAOS_FETCH_TIMEOUTS`, before the download of `link`.
+pub(crate) async fn fetch(link: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if !strikes(rates().fetch_timeouts) {
+        return Ok(());
+    }
+    tokio::time::sleep(TIMEOUT_DELAY).await;
+    Err(format!("Injected timeout downloading {}", link).into())
+}
+
+/// Returns a 429 error to send instead of an item, at the rate of `CHAOS_TELEGRAM_429`.
+pub(crate) fn telegram() -> Option<RequestError> {
+    strikes(rates().telegram_429).then_some(RequestError::RetryAfter(RETRY_AFTER))
+}
+
+/// Fails at the rate of `CHAOS_DB_ERRORS`.
+pub(crate) fn database() -> Result<(), DbErr> {
+    if strikes(rates().db_errors) {
+        return Err(DbErr::Custom("Injected database error".to_string()));
+    }
+    Ok(())
+}
@@ -0,0 +1,87 @@
+//! Detection of feeds that rewrite the GUIDs and dates of their items.
+//!
+//! Some feeds give every item a new GUID and date at every fetch, so that all of their items
+//! look new at every poll. Before the new items of a feed are sent, they are run through its
+//! [`crate::pipeline`], like the items it sent, and compared by their normalized title and link
+//! with the items it sent in the last [`WINDOW_HOURS`] hours, without the highlight of followed
+//! names: if at least [`MIN_REPEATS`] of them, and at least half, were already sent, the feed is
+//! switched to [`crate::content_hash`] deduplication for good, the switch is logged for the
+//! operator, and nothing is sent for that poll.
+
+use std::collections::HashSet;
+
+use chrono::{Duration, Utc};
+use rss::Item;
+use sea_orm::{
+    sea_query::Expr, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter,
+};
+
+use crate::follows::unhighlighted;
+use crate::sources::normalize_link;
+use entity::{delivery, feed};
+
+const WINDOW_HOURS: i64 = 48;
+/// Minimum number of new items that were already sent for a feed to be churning.
+const MIN_REPEATS: usize = 3;
+
+fn key(title: &str, link: &str) -> (String, String) {
+    let title = title
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .to_lowercase();
+    (title, normalize_link(link))
+}
+
+/// Returns whether the `new_items` of `feed` are mostly items it already sent.
+pub(crate) async fn is_churning(
+    db: &DatabaseConnection,
+    feed: &feed::Model,
+    new_items: &[&Item],
+) -> Result<bool, DbErr> {
+    if new_items.len() < MIN_REPEATS {
+        return Ok(false);
+    }
+    let since = Utc::now().naive_utc() - Duration::hours(WINDOW_HOURS);
+    let sent: HashSet<(String, String)> = entity::prelude::Delivery::find()
+        .filter(delivery::Column::FeedId.eq(feed.id))
+        .filter(delivery::Column::SentAt.gt(since))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|delivery| key(unhighlighted(&delivery.title), &delivery.link))
+        .collect();
+    // Deliveries record the items as the pipeline of the feed rewrote and highlighted them
+    let repeats = new_items
+        .iter()
+        .filter_map(|item| crate::pipeline::apply(feed, item))
+        .filter(|item| {
+            let title = item.title().unwrap_or_default();
+            sent.contains(&key(title, item.link().unwrap_or_default()))
+        })
+        .count();
+    Ok(repeats >= MIN_REPEATS && repeats * 2 >= new_items.len())
+}
+
+/// Switches `feed` to content hashes, after [`is_churning`] found that its `new_items` new items
+/// were mostly sent already.
+pub(crate) async fn switch_to_hashes(
+    db: &DatabaseConnection,
+    feed: &feed::Model,
+    new_items: usize,
+) -> Result<(), DbErr> {
+    entity::prelude::Feed::update_many()
+        .col_expr(feed::Column::ContentHash, Expr::value(true))
+        .filter(feed::Column::Id.eq(feed.id))
+        .exec(db)
+        .await?;
+    crate::registry::invalidate(feed.id);
+    log::warn!(
+        "Feed {} ({}) rewrites its items: {} of them looked new again, switched it to content \
+         hashes",
+        feed.id,
+        feed.link,
+        new_items
+    );
+    Ok(())
+}
@@ -0,0 +1,206 @@
+//! Click counts of delivered links.
+//!
+//! With `/trackclicks on`, the links of the items sent to a chat point to `<PUBLIC_URL>/r/<id>`,
+//! where `id` is the delivery of the item, which counts the click and redirects to the item.
+//! `/stats` ranks the feeds of the chat by their engagement over the last [`STATS_DAYS`] days,
+//! the clicks on their links and on their 👍 buttons per item sent, lists the most read items
+//! and suggests pausing the feeds whose items are never opened.
+
+use std::collections::HashMap;
+use std::env;
+use std::error::Error;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Redirect, Response},
+    routing::get,
+    Router,
+};
+use chrono::{Duration, Utc};
+use rss::Item;
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, ModelTrait,
+    QueryFilter, Set, UpdateResult,
+};
+use teloxide::types::MessageId;
+
+use crate::api::ApiState;
+use entity::{chat, delivery, feed};
+
+const STATS_DAYS: i64 = 30;
+/// Number of most read items listed by `/stats`.
+const TOP_ITEMS: usize = 5;
+/// Feeds that sent at least this many items without any click are suggested to be paused.
+const UNREAD_ITEMS: usize = 10;
+
+/// The engagement of a feed over the last [`STATS_DAYS`] days.
+#[derive(Clone, Copy, Debug, Default)]
+struct Engagement {
+    items: usize,
+    link_clicks: i32,
+    likes: i32,
+}
+
+impl Engagement {
+    fn interactions(&self) -> i32 {
+        self.link_clicks + self.likes
+    }
+
+    /// Interactions per item sent.
+    fn score(&self) -> f64 {
+        if self.items == 0 {
+            0.0
+        } else {
+            self.interactions() as f64 / self.items as f64
+        }
+    }
+}
+
+pub(crate) fn routes() -> Router<ApiState> {
+    Router::new().route("/r/{id}", get(redirect))
+}
+
+/// Counts a click on delivery `id` and redirects to its link.
+async fn redirect(State(state): State<ApiState>, Path(id): Path<i64>) -> Response {
+    let clicked = entity::prelude::Delivery::update_many()
+        .col_expr(
+            delivery::Column::LinkClicks,
+            Expr::col(delivery::Column::LinkClicks).add(1),
+        )
+        .filter(delivery::Column::Id.eq(id))
+        .exec_with_returning(&state.db)
+        .await;
+    match clicked.map(|mut deliveries| deliveries.pop()) {
+        Ok(Some(delivery)) => Redirect::temporary(&delivery.link).into_response(),
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response(),
+    }
+}
+
+/// Records the delivery of `item` of `feed` before it is sent, if its chat tracks clicks, and
+/// returns it with the item linking to the redirect.
+pub(crate) async fn track(
+    db: &DatabaseConnection,
+    feed: &feed::Model,
+    item: &Item,
+) -> Option<(delivery::Model, Item)> {
+    let public_url = env::var("PUBLIC_URL").ok()?;
+    item.link().filter(|link| !link.is_empty())?;
+    let tracked = async {
+        let chat = entity::prelude::Chat::find_by_id((feed.chat_id, feed.bot_id))
+            .one(db)
+            .await?;
+        if !chat.is_some_and(|chat| chat.track_clicks) {
+            return Ok(None);
+        }
+        let delivery = crate::create_delivery(db, feed, item, None).await?;
+        let mut tracked_item = item.clone();
+        tracked_item.set_link(format!("{}/r/{}", public_url.trim_end_matches('/'), delivery.id));
+        Ok::<_, Box<dyn Error + Send + Sync>>(Some((delivery, tracked_item)))
+    }
+    .await;
+    tracked.unwrap_or_else(|err| {
+        println!("Error tracking an item of feed {}: {:?}", feed.id, err);
+        None
+    })
+}
+
+/// Records that the tracked `delivery` was sent as `message_id`.
+pub(crate) async fn sent(
+    db: &DatabaseConnection,
+    delivery: delivery::Model,
+    message_id: MessageId,
+) -> Result<delivery::Model, Box<dyn Error + Send + Sync>> {
+    let mut sent_delivery: delivery::ActiveModel = delivery.into();
+    sent_delivery.message_id = Set(Some(message_id.0));
+    Ok(sent_delivery.update(db).await?)
+}
+
+/// Deletes the tracked `delivery` of an item that could not be sent.
+pub(crate) async fn discard(db: &DatabaseConnection, delivery: delivery::Model) {
+    if let Err(err) = delivery.delete(db).await {
+        println!("Error deleting an unsent delivery: {:?}", err);
+    }
+}
+
+/// Turns click tracking of `chat_id` on or off. Tracking needs `PUBLIC_URL`.
+pub(crate) async fn set_tracking(
+    db: &DatabaseConnection,
+    chat_id: i64,
+    bot_id: i64,
+    enabled: bool,
+) -> Result<UpdateResult, Box<dyn Error + Send + Sync>> {
+    if enabled && env::var("PUBLIC_URL").is_err() {
+        return Err("Click tracking needs the PUBLIC_URL of the bot".into());
+    }
+    Ok(entity::prelude::Chat::update_many()
+        .col_expr(chat::Column::TrackClicks, Expr::value(enabled))
+        .filter(chat::Column::Id.eq(chat_id))
+        .filter(chat::Column::BotId.eq(bot_id))
+        .exec(db)
+        .await?)
+}
+
+/// Returns the feeds of `chat_id` ranked by engagement, its most read items and the feeds it
+/// never reads.
+pub(crate) async fn stats(
+    db: &DatabaseConnection,
+    chat_id: i64,
+    bot_id: i64,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let feeds = entity::prelude::Feed::find()
+        .filter(feed::Column::ChatId.eq(chat_id))
+        .filter(feed::Column::BotId.eq(bot_id))
+        .all(db)
+        .await?;
+    let since = Utc::now().naive_utc() - Duration::days(STATS_DAYS);
+    let mut deliveries = entity::prelude::Delivery::find()
+        .filter(delivery::Column::FeedId.is_in(feeds.iter().map(|feed| feed.id)))
+        .filter(delivery::Column::SentAt.gt(since))
+        .all(db)
+        .await?;
+    let mut per_feed: HashMap<i64, Engagement> = HashMap::new();
+    for delivery in &deliveries {
+        let engagement = per_feed.entry(delivery.feed_id).or_default();
+        engagement.items += 1;
+        engagement.link_clicks += delivery.link_clicks;
+        engagement.likes += delivery.clicks;
+    }
+    let mut ranked: Vec<(&feed::Model, Engagement)> = feeds
+        .iter()
+        .map(|feed| (feed, per_feed.get(&feed.id).copied().unwrap_or_default()))
+        .collect();
+    ranked.sort_by(|(_, a), (_, b)| b.score().total_cmp(&a.score()));
+    let mut lines = vec![format!("Engagement of the last {} days:", STATS_DAYS)];
+    for (feed, engagement) in &ranked {
+        lines.push(format!(
+            "{} - {}: {} clicks and {} 👍 on {} items",
+            feed.short_id,
+            crate::format::feed_label(feed),
+            engagement.link_clicks,
+            engagement.likes,
+            engagement.items
+        ));
+    }
+    let unread: Vec<String> = ranked
+        .iter()
+        .filter(|(_, engagement)| {
+            engagement.items >= UNREAD_ITEMS && engagement.interactions() == 0
+        })
+        .map(|(feed, _)| format!("{} - {}", feed.short_id, crate::format::feed_label(feed)))
+        .collect();
+    if !unread.is_empty() {
+        lines.push("\nYou never open these, /pause or /unsubscribe them?".to_string());
+        lines.extend(unread);
+    }
+    deliveries.retain(|delivery| delivery.link_clicks > 0);
+    deliveries.sort_by_key(|delivery| std::cmp::Reverse(delivery.link_clicks));
+    if !deliveries.is_empty() {
+        lines.push("\nMost read:".to_string());
+    }
+    for delivery in deliveries.iter().take(TOP_ITEMS) {
+        lines.push(format!("{} clicks - {}", delivery.link_clicks, delivery.title));
+    }
+    Ok(lines.join("\n"))
+}
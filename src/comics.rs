@@ -0,0 +1,54 @@
+//! Full images of comic feeds.
+//!
+//! With `/comic <feed id> on`, the page of each item of a feed is read and its main image, the
+//! `og:image` of the page or else its largest `<img>`, is sent as a photo with the message as
+//! caption, so that webcomics can be read without leaving Telegram.
+
+use reqwest::Url;
+use rss::Item;
+use scraper::{Html, Selector};
+
+use entity::feed;
+
+/// Returns the main image of the page `html` at `page`.
+fn main_image(html: &str, page: &Url) -> Option<Url> {
+    let document = Html::parse_document(html);
+    let og_image = Selector::parse("meta[property='og:image']").expect("valid selector");
+    let images = Selector::parse("img[src]").expect("valid selector");
+    let size = |name: &str, image: &scraper::ElementRef| -> u64 {
+        image
+            .value()
+            .attr(name)
+            .and_then(|value| value.trim_end_matches("px").parse().ok())
+            .unwrap_or_default()
+    };
+    let src = document
+        .select(&og_image)
+        .find_map(|meta| meta.value().attr("content"))
+        .or_else(|| {
+            document
+                .select(&images)
+                .max_by_key(|image| size("width", image) * size("height", image))
+                .and_then(|image| image.value().attr("src"))
+        })?;
+    page.join(src).ok()
+}
+
+/// Returns the main image of the page of `item`, if `feed` is a comic.
+pub(crate) async fn image(feed: &feed::Model, item: &Item) -> Option<Url> {
+    if !feed.comic {
+        return None;
+    }
+    let page = Url::parse(item.link()?).ok()?;
+    let html = match reqwest::get(page.clone()).await {
+        Ok(response) => response.text().await,
+        Err(err) => Err(err),
+    };
+    match html {
+        Ok(html) => main_image(&html, &page),
+        Err(err) => {
+            println!("Error fetching the page of an item of feed {}: {:?}", feed.id, err);
+            None
+        }
+    }
+}
@@ -0,0 +1,120 @@
+//! Configuration file, reloaded without restarting.
+//!
+//! `CONFIG_FILE` names a JSON file such as
+//!
+//! ```json
+//! {"poll_interval_seconds": 60, "concurrency": 4, "log_level": "info",
+//!  "flags": {"thumbnails": "off", "max_feeds": "50"}}
+//! ```
+//!
+//! where every key is optional. It is read on startup and again on `SIGHUP` or on `/reload` in
+//! the admin chat, without stopping the bots. `log_level` can only lower the level allowed by
+//! `RUST_LOG`, and `flags` are stored like `/setflag` would.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::sync::RwLock;
+use std::time::Duration;
+
+use log::LevelFilter;
+use sea_orm::DatabaseConnection;
+use serde::Deserialize;
+
+use crate::flags;
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Seconds between two checks of a feed, unless it has its own interval.
+    pub(crate) poll_interval_seconds: u64,
+    /// Number of feed sources fetched at the same time.
+    pub(crate) concurrency: usize,
+    pub(crate) log_level: Option<String>,
+    pub(crate) flags: BTreeMap<String, String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            poll_interval_seconds: 30,
+            concurrency: 1,
+            log_level: None,
+            flags: BTreeMap::new(),
+        }
+    }
+}
+
+impl Config {
+    pub(crate) fn poll_interval(&self) -> Duration {
+        Duration::from_secs(self.poll_interval_seconds.max(1))
+    }
+}
+
+static CONFIG: RwLock<Option<Config>> = RwLock::new(None);
+
+/// Returns the current configuration.
+pub(crate) fn get() -> Config {
+    CONFIG
+        .read()
+        .expect("config lock poisoned")
+        .clone()
+        .unwrap_or_default()
+}
+
+/// Reads `CONFIG_FILE`, or returns the default configuration if it isn't set.
+fn read() -> Result<Config, Box<dyn Error + Send + Sync>> {
+    let Ok(path) = env::var("CONFIG_FILE") else {
+        return Ok(Config::default());
+    };
+    let content = fs::read_to_string(&path).map_err(|err| format!("Can't read {}: {}", path, err))?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+/// Reads the configuration file and applies it. Returns a description of the new configuration.
+pub(crate) async fn reload(
+    db: &DatabaseConnection,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let config = read()?;
+    if config.concurrency == 0 {
+        return Err("concurrency must be at least 1".into());
+    }
+    if let Some(level) = &config.log_level {
+        let level: LevelFilter = level
+            .parse()
+            .map_err(|_| format!("Unknown log level {}", level))?;
+        log::set_max_level(level);
+    }
+    for (flag, value) in &config.flags {
+        flags::set(db, flag, value).await?;
+    }
+    let description = format!(
+        "Checking feeds every {} seconds, {} sources at a time, log level {}, {} flags set",
+        config.poll_interval_seconds,
+        config.concurrency,
+        log::max_level(),
+        config.flags.len()
+    );
+    *CONFIG.write().expect("config lock poisoned") = Some(config);
+    Ok(description)
+}
+
+/// Reloads the configuration whenever the process receives `SIGHUP`.
+pub(crate) async fn reload_on_hangup(db: DatabaseConnection) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangups = match signal(SignalKind::hangup()) {
+        Ok(hangups) => hangups,
+        Err(err) => {
+            log::error!("Can't listen for SIGHUP: {}", err);
+            return;
+        }
+    };
+    while hangups.recv().await.is_some() {
+        match reload(&db).await {
+            Ok(description) => log::info!("Reloaded the configuration: {}", description),
+            Err(err) => log::error!("Error reloading the configuration: {}", err),
+        }
+    }
+}
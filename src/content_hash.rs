@@ -0,0 +1,94 @@
+//! Change detection by content hashes, for feeds whose dates and GUIDs can't be trusted.
+//!
+//! Feeds with `/contenthash <feed id> on`, and feeds none of whose items has a readable date,
+//! recognize new items by a hash of their normalized title and link rather than by their date:
+//! an item is new if its hash wasn't seen in the last [`KEEP_DAYS`] days. The first poll only
+//! records the items already in the feed, so that they are not all sent at once.
+//!
+//! Every feed also keeps the hash of the body it was last polled with, and the items of a body
+//! that didn't change are not read again.
+
+use std::collections::HashSet;
+
+use chrono::{Duration, Utc};
+use rss::{Channel, Item};
+use sea_orm::{
+    sea_query::OnConflict, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, Set,
+};
+use sha2::{Digest, Sha256};
+
+use crate::sources::normalize_link;
+use entity::{feed, item_hash};
+
+/// Hashes of items that weren't in the feed for this many days are forgotten.
+const KEEP_DAYS: i64 = 30;
+
+/// Returns the hash of the body of a feed.
+pub(crate) fn body_hash(body: &[u8]) -> String {
+    hex::encode(Sha256::digest(body))
+}
+
+/// Returns the hash of the title and link of `item`, ignoring case, whitespace and fragments.
+pub(crate) fn item_hash(item: &Item) -> String {
+    let title = item
+        .title()
+        .unwrap_or_default()
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .to_lowercase();
+    let link = normalize_link(item.link().unwrap_or_default());
+    hex::encode(Sha256::digest(format!("{}\n{}", title, link)))
+}
+
+/// Returns whether new items of `feed` are recognized by their hash in `channel`.
+pub(crate) fn uses_hashes(feed: &feed::Model, channel: &Channel) -> bool {
+    let undated = |item: &Item| crate::dates::published(item).ok().flatten().is_none();
+    feed.content_hash || (!channel.items.is_empty() && channel.items.iter().all(undated))
+}
+
+/// Returns the hashes of the items of `feed` seen in the last [`KEEP_DAYS`] days.
+pub(crate) async fn known(
+    db: &DatabaseConnection,
+    feed: &feed::Model,
+) -> Result<HashSet<String>, DbErr> {
+    Ok(entity::prelude::ItemHash::find()
+        .filter(item_hash::Column::FeedId.eq(feed.id))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|seen| seen.hash)
+        .collect())
+}
+
+/// Records that `items` are in `feed` now, and forgets the items that left it long ago.
+pub(crate) async fn record(
+    db: &DatabaseConnection,
+    feed: &feed::Model,
+    items: &[Item],
+) -> Result<(), DbErr> {
+    let now = Utc::now().naive_utc();
+    let hashes: HashSet<String> = items.iter().map(item_hash).collect();
+    if !hashes.is_empty() {
+        let seen = hashes.into_iter().map(|hash| item_hash::ActiveModel {
+            feed_id: Set(feed.id),
+            hash: Set(hash),
+            seen_at: Set(now),
+            ..Default::default()
+        });
+        entity::prelude::ItemHash::insert_many(seen)
+            .on_conflict(
+                OnConflict::columns([item_hash::Column::FeedId, item_hash::Column::Hash])
+                    .update_column(item_hash::Column::SeenAt)
+                    .to_owned(),
+            )
+            .exec(db)
+            .await?;
+    }
+    entity::prelude::ItemHash::delete_many()
+        .filter(item_hash::Column::FeedId.eq(feed.id))
+        .filter(item_hash::Column::SeenAt.lt(now - Duration::days(KEEP_DAYS)))
+        .exec(db)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,301 @@
+//! Minimal embedded web dashboard for operators.
+//!
+//! Shows the health of every feed and the most recent deliveries, and lets the operator pause,
+//! resume or remove feeds. The dashboard is protected by the same token as the management API,
+//! which is entered once in a login form and then kept in a cookie.
+
+use axum::{
+    extract::{Path, Request, State},
+    http::{header, HeaderMap},
+    middleware::{self, Next},
+    response::{Html, IntoResponse, Redirect, Response},
+    routing::{get, post},
+    Form, Router,
+};
+use sea_orm::{
+    sea_query::Expr, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder,
+    QuerySelect,
+};
+use serde::Deserialize;
+use teloxide::utils::html::escape;
+
+use crate::api::ApiState;
+use entity::{delivery, feed};
+
+const COOKIE_NAME: &str = "dashboard_token";
+const RECENT_DELIVERIES: u64 = 50;
+
+const STYLE: &str = "body{font-family:sans-serif;margin:2em}\
+table{border-collapse:collapse;margin-bottom:2em}\
+td,th{border:1px solid #ccc;padding:.3em .6em;text-align:left}\
+.ok{color:green}.failing{color:#c00}.paused{color:#888}\
+form{display:inline}";
+
+#[derive(Deserialize)]
+struct Login {
+    token: String,
+}
+
+pub(crate) fn routes(state: ApiState) -> Router<ApiState> {
+    Router::new()
+        .route("/dashboard", get(show))
+        .route("/dashboard/feeds/{id}/pause", post(pause))
+        .route("/dashboard/feeds/{id}/resume", post(resume))
+        .route("/dashboard/feeds/{id}/delete", post(remove))
+        .layer(middleware::from_fn_with_state(state, require_login))
+        .route("/dashboard/login", get(login_form).post(login))
+}
+
+/// Returns the decoded value of the cookie called `name`, if the request carries it.
+pub(crate) fn cookie(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .find(|(cookie_name, _)| *cookie_name == name)
+        .and_then(|(_, value)| urlencoding::decode(value).ok())
+        .map(|value| value.into_owned())
+}
+
+async fn require_login(State(state): State<ApiState>, request: Request, next: Next) -> Response {
+    if cookie(request.headers(), COOKIE_NAME).is_some_and(|token| state.is_token(&token)) {
+        next.run(request).await
+    } else {
+        Redirect::to("/dashboard/login").into_response()
+    }
+}
+
+/// Returns `value` escaped for a double-quoted HTML attribute.
+pub(crate) fn escape_attribute(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Returns a link to `href` showing `text`, or only `text` unless `href` is an `http` or `https`
+/// URL, so that feeds can't run scripts in the page.
+pub(crate) fn link(href: &str, text: &str) -> String {
+    let is_web = reqwest::Url::parse(href)
+        .is_ok_and(|url| url.scheme() == "http" || url.scheme() == "https");
+    if is_web {
+        format!(
+            "<a href=\"{}\">{}</a>",
+            escape_attribute(href),
+            escape(text)
+        )
+    } else {
+        escape(text)
+    }
+}
+
+pub(crate) fn page(body: &str) -> Html<String> {
+    Html(format!(
+        "<!DOCTYPE html><html><head><meta charset='utf-8'><title>multitude_bot</title>\
+         <style>{}</style></head><body>{}</body></html>",
+        STYLE, body
+    ))
+}
+
+async fn login_form() -> Html<String> {
+    page(
+        "<h1>multitude_bot</h1>\
+         <form method='post' action='/dashboard/login'>\
+         <input type='password' name='token' placeholder='API token' autofocus>\
+         <button type='submit'>Log in</button></form>",
+    )
+}
+
+async fn login(State(state): State<ApiState>, Form(login): Form<Login>) -> Response {
+    if !state.is_token(&login.token) {
+        return Redirect::to("/dashboard/login").into_response();
+    }
+    let cookie = format!(
+        "{}={}; Path=/dashboard; HttpOnly; SameSite=Strict",
+        COOKIE_NAME,
+        urlencoding::encode(&login.token)
+    );
+    ([(header::SET_COOKIE, cookie)], Redirect::to("/dashboard")).into_response()
+}
+
+fn feed_row(feed: &feed::Model) -> String {
+    let (class, health) = if feed.paused {
+        ("paused", "paused".to_string())
+    } else if feed.error_count > 0 {
+        ("failing", format!("failing ({} in a row)", feed.error_count))
+    } else {
+        ("ok", "ok".to_string())
+    };
+    let error_rate = if feed.check_count > 0 {
+        format!(
+            "{:.1}%",
+            100.0 * feed.failure_count as f64 / feed.check_count as f64
+        )
+    } else {
+        "-".to_string()
+    };
+    let last_checked = feed
+        .last_checked_at
+        .map(|date| date.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|| "never".to_string());
+    let toggle = if feed.paused { "resume" } else { "pause" };
+    format!(
+        "<tr><td>{id}</td><td>{bot}</td><td>{chat}</td><td>{link}</td>\
+         <td class='{class}'>{health}</td><td>{error_rate}</td><td>{last_checked}</td>\
+         <td>{error}</td><td>\
+         <form method='post' action='/dashboard/feeds/{id}/{toggle}'>\
+         <button type='submit'>{toggle}</button></form> \
+         <form method='post' action='/dashboard/feeds/{id}/delete' \
+         onsubmit=\"return confirm('Remove this feed?')\">\
+         <button type='submit'>remove</button></form></td></tr>",
+        id = feed.id,
+        bot = feed.bot_id,
+        chat = feed.chat_id,
+        link = link(&feed.link, &feed.title),
+        class = class,
+        health = health,
+        error_rate = error_rate,
+        last_checked = last_checked,
+        error = escape(feed.last_error.as_deref().unwrap_or("")),
+        toggle = toggle,
+    )
+}
+
+fn delivery_row(delivery: &delivery::Model, feeds: &[feed::Model]) -> String {
+    let feed_title = feeds
+        .iter()
+        .find(|feed| feed.id == delivery.feed_id)
+        .map(|feed| feed.title.as_str())
+        .unwrap_or("");
+    format!(
+        "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+        delivery.sent_at.format("%Y-%m-%d %H:%M:%S"),
+        delivery.chat_id,
+        escape(feed_title),
+        link(&delivery.link, &delivery.title),
+    )
+}
+
+async fn render(db: &DatabaseConnection) -> Result<String, DbErr> {
+    let feeds = entity::prelude::Feed::find()
+        .order_by_asc(feed::Column::Id)
+        .all(db)
+        .await?;
+    let deliveries = entity::prelude::Delivery::find()
+        .order_by_desc(delivery::Column::SentAt)
+        .limit(RECENT_DELIVERIES)
+        .all(db)
+        .await?;
+    let failing = feeds
+        .iter()
+        .filter(|feed| !feed.paused && feed.error_count > 0)
+        .count();
+    let paused = feeds.iter().filter(|feed| feed.paused).count();
+
+    let mut body = String::from("<h1>multitude_bot</h1>");
+    body.push_str(&format!(
+        "<p>{} feeds, {} failing, {} paused</p>",
+        feeds.len(),
+        failing,
+        paused
+    ));
+    body.push_str(
+        "<h2>Feeds</h2><table><tr><th>id</th><th>bot</th><th>chat</th><th>title</th>\
+         <th>health</th><th>error rate</th><th>last checked</th><th>last error</th><th></th></tr>",
+    );
+    for feed in &feeds {
+        body.push_str(&feed_row(feed));
+    }
+    body.push_str("</table>");
+    body.push_str(
+        "<h2>Recent deliveries</h2><table><tr><th>sent at</th><th>chat</th><th>feed</th>\
+         <th>item</th></tr>",
+    );
+    for delivery in &deliveries {
+        body.push_str(&delivery_row(delivery, &feeds));
+    }
+    body.push_str("</table>");
+    Ok(body)
+}
+
+async fn show(State(state): State<ApiState>) -> Response {
+    match render(&state.db).await {
+        Ok(body) => page(&body).into_response(),
+        Err(err) => page(&format!("<p>Error: {}</p>", escape(&err.to_string()))).into_response(),
+    }
+}
+
+async fn set_paused(db: &DatabaseConnection, id: i64, paused: bool) -> Response {
+    let updated = entity::prelude::Feed::update_many()
+        .col_expr(feed::Column::Paused, Expr::value(paused))
+        .filter(feed::Column::Id.eq(id))
+        .exec(db)
+        .await;
+    if let Err(err) = updated {
+        log::error!("Error updating feed {}: {}", id, err);
+    }
+    crate::registry::invalidate(id);
+    Redirect::to("/dashboard").into_response()
+}
+
+async fn pause(State(state): State<ApiState>, Path(id): Path<i64>) -> Response {
+    set_paused(&state.db, id, true).await
+}
+
+async fn resume(State(state): State<ApiState>, Path(id): Path<i64>) -> Response {
+    set_paused(&state.db, id, false).await
+}
+
+async fn remove(State(state): State<ApiState>, Path(id): Path<i64>) -> Response {
+    if let Err(err) = entity::prelude::Feed::delete_by_id(id)
+        .exec(&state.db)
+        .await
+    {
+        log::error!("Error removing feed {}: {}", id, err);
+    }
+    crate::registry::invalidate(id);
+    Redirect::to("/dashboard").into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_quotes_in_links() {
+        let mut feed = crate::bench::sample_feed("<b>Feed</b>");
+        feed.link = "https://example.com/'onmouseover='alert(1)\"".to_string();
+        let row = feed_row(&feed);
+        assert!(row.contains(
+            "<a href=\"https://example.com/&#39;onmouseover=&#39;alert(1)&quot;\">\
+             &lt;b&gt;Feed&lt;/b&gt;</a>"
+        ));
+        assert!(!row.contains("onmouseover='"));
+    }
+
+    #[test]
+    fn drops_links_that_are_not_web_links() {
+        let mut feed = crate::bench::sample_feed("Feed");
+        for href in [
+            "javascript:alert(1)",
+            "JavaScript:alert(1)",
+            "data:text/html,x",
+            "/local",
+        ] {
+            feed.link = href.to_string();
+            let row = feed_row(&feed);
+            assert!(!row.contains("href"), "{}", href);
+            assert!(row.contains("<td>Feed</td>"), "{}", href);
+        }
+    }
+}
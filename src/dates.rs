@@ -0,0 +1,195 @@
+//! Parsing and formatting of the dates of items.
+//!
+//! Feeds date their items with an RFC 2822 `pubDate`, which is often malformed, with an RFC 3339
+//! date in `pubDate` instead, or only with a Dublin Core `dc:date`. Every date is read as UTC.
+//! Items without a date are distinguished from items whose date can't be read, so that the
+//! latter can be reported instead of being silently dated 1970.
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use rss::Item;
+
+/// Parses `value` as RFC 2822, fixing common mistakes such as wrong day names, or as RFC 3339.
+pub(crate) fn parse(value: &str) -> Option<NaiveDateTime> {
+    let value = value.trim();
+    rfc822_sanitizer::parse_from_rfc2822_with_fallback(value)
+        .ok()
+        .or_else(|| DateTime::parse_from_rfc3339(value).ok())
+        .map(|date| date.naive_utc())
+}
+
+/// Parses a W3C date such as `dc:date`, which is RFC 3339 or only a date such as `2026-10-15`.
+fn parse_w3c(value: &str) -> Option<NaiveDateTime> {
+    let value = value.trim();
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|date| date.naive_utc())
+        .or_else(|| NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?.and_hms_opt(0, 0, 0))
+}
+
+/// Parses an iCalendar date such as `20261015T090000Z`, `20261015T090000` or `20261015`.
+pub(crate) fn parse_ical(value: &str) -> Option<NaiveDateTime> {
+    let value = value.trim_end_matches('Z');
+    NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+        .ok()
+        .or_else(|| NaiveDate::parse_from_str(value, "%Y%m%d").ok()?.and_hms_opt(0, 0, 0))
+}
+
+/// Returns the publication date of `item`, from its `pubDate` or else its `dc:date`, or `None`
+/// if it has neither. Fails with the malformed date if there is one but it can't be read.
+pub(crate) fn published(item: &Item) -> Result<Option<NaiveDateTime>, String> {
+    if let Some(pub_date) = item.pub_date() {
+        return parse(pub_date)
+            .map(Some)
+            .ok_or_else(|| format!("Malformed pubDate {}", pub_date));
+    }
+    let dc_date = item
+        .dublin_core_ext()
+        .and_then(|dublin_core| dublin_core.dates().first());
+    match dc_date {
+        Some(dc_date) => parse_w3c(dc_date)
+            .map(Some)
+            .ok_or_else(|| format!("Malformed dc:date {}", dc_date)),
+        None => Ok(None),
+    }
+}
+
+/// Formats `date` for a `pubDate`.
+pub(crate) fn rfc2822(date: NaiveDateTime) -> String {
+    date.and_utc().to_rfc2822()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use rss::extension::dublincore::DublinCoreExtension;
+
+    /// Dates from 1970 to 2100, to the second.
+    fn date() -> impl Strategy<Value = NaiveDateTime> {
+        (0i64..4_102_444_800)
+            .prop_map(|seconds| DateTime::from_timestamp(seconds, 0).unwrap().naive_utc())
+    }
+
+    fn item(pub_date: Option<&str>, dc_date: Option<&str>) -> Item {
+        let mut item = Item::default();
+        item.set_pub_date(pub_date.map(String::from));
+        if let Some(dc_date) = dc_date {
+            let mut dublin_core = DublinCoreExtension::default();
+            dublin_core.set_dates(vec![dc_date.to_string()]);
+            item.set_dublin_core_ext(dublin_core);
+        }
+        item
+    }
+
+    proptest! {
+        #[test]
+        fn parses_rfc2822(date in date()) {
+            prop_assert_eq!(parse(&rfc2822(date)), Some(date));
+        }
+
+        #[test]
+        fn parses_rfc3339(date in date()) {
+            prop_assert_eq!(parse(&date.and_utc().to_rfc3339()), Some(date));
+            prop_assert_eq!(parse_w3c(&date.and_utc().to_rfc3339()), Some(date));
+        }
+
+        #[test]
+        fn parses_ical(date in date()) {
+            let value = date.format("%Y%m%dT%H%M%S").to_string();
+            prop_assert_eq!(parse_ical(&value), Some(date));
+            prop_assert_eq!(parse_ical(&format!("{}Z", value)), Some(date));
+        }
+
+        #[test]
+        fn parses_dates_without_time(date in date()) {
+            let midnight = date.date().and_hms_opt(0, 0, 0);
+            prop_assert_eq!(parse_w3c(&date.format("%Y-%m-%d").to_string()), midnight);
+            prop_assert_eq!(parse_ical(&date.format("%Y%m%d").to_string()), midnight);
+        }
+
+        #[test]
+        fn publishes_at_pub_date(date in date()) {
+            prop_assert_eq!(published(&item(Some(&rfc2822(date)), None)), Ok(Some(date)));
+        }
+
+        #[test]
+        fn never_panics(value in "\\PC*") {
+            parse(&value);
+            parse_w3c(&value);
+            parse_ical(&value);
+        }
+    }
+
+    #[test]
+    fn reads_dates_as_utc() {
+        let date = NaiveDate::from_ymd_opt(2026, 10, 15)
+            .unwrap()
+            .and_hms_opt(7, 0, 0)
+            .unwrap();
+        assert_eq!(parse("Thu, 15 Oct 2026 09:00:00 +0200"), Some(date));
+        assert_eq!(parse(" 2026-10-15T09:00:00+02:00 "), Some(date));
+        assert_eq!(parse_w3c("2026-10-15T07:00:00Z"), Some(date));
+    }
+
+    #[test]
+    fn fixes_wrong_day_names() {
+        let date = NaiveDate::from_ymd_opt(2026, 10, 15)
+            .unwrap()
+            .and_hms_opt(7, 0, 0)
+            .unwrap();
+        assert_eq!(parse("Mon, 15 Oct 2026 07:00:00 GMT"), Some(date));
+    }
+
+    #[test]
+    fn rejects_malformed_dates() {
+        let malformed = [
+            "",
+            "yesterday",
+            "15/10/2026",
+            "Thu, 15 Oct 2026 25:00:00 GMT",
+        ];
+        for value in malformed {
+            assert_eq!(parse(value), None, "{}", value);
+        }
+        for value in ["", "2026-13-01", "15 Oct 2026"] {
+            assert_eq!(parse_w3c(value), None, "{}", value);
+        }
+        for value in ["", "tomorrow", "20261015T25", "20261315"] {
+            assert_eq!(parse_ical(value), None, "{}", value);
+        }
+    }
+
+    #[test]
+    fn publishes_at_dc_date_without_pub_date() {
+        let date = NaiveDate::from_ymd_opt(2026, 10, 15)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        assert_eq!(published(&item(None, Some("2026-10-15"))), Ok(Some(date)));
+    }
+
+    #[test]
+    fn prefers_pub_date_to_dc_date() {
+        let date = NaiveDate::from_ymd_opt(2026, 10, 15)
+            .unwrap()
+            .and_hms_opt(7, 0, 0)
+            .unwrap();
+        let item = item(Some("Thu, 15 Oct 2026 07:00:00 GMT"), Some("2020-01-01"));
+        assert_eq!(published(&item), Ok(Some(date)));
+    }
+
+    #[test]
+    fn distinguishes_missing_from_malformed_dates() {
+        assert_eq!(published(&item(None, None)), Ok(None));
+        assert_eq!(
+            published(&item(Some("yesterday"), None)),
+            Err("Malformed pubDate yesterday".to_string())
+        );
+        assert_eq!(
+            published(&item(None, Some("yesterday"))),
+            Err("Malformed dc:date yesterday".to_string())
+        );
+        // A malformed pubDate is reported even if there is a dc:date
+        assert!(published(&item(Some("yesterday"), Some("2026-10-15"))).is_err());
+    }
+}
@@ -0,0 +1,42 @@
+//! Fuzzy deduplication of item titles.
+//!
+//! Some feeds publish the same story several times with different GUIDs. Titles are normalized
+//! to their lowercase words, and an item is a duplicate if its words are similar enough to
+//! those of an item sent by the same feed in the last [`WINDOW_HOURS`] hours.
+
+use chrono::{Duration, Utc};
+use sea_orm::{ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter};
+
+use crate::similarity::{jaccard, words};
+use entity::{delivery, feed};
+
+const WINDOW_HOURS: i64 = 24;
+/// Minimum Jaccard similarity between the words of two titles for them to be duplicates.
+const SIMILARITY: f64 = 0.8;
+
+/// Returns whether `title` is similar to one of `recent_titles`.
+pub(crate) fn is_duplicate(title: &str, recent_titles: &[String]) -> bool {
+    let title = words(title);
+    if title.is_empty() {
+        return false;
+    }
+    recent_titles
+        .iter()
+        .any(|recent| jaccard(&title, &words(recent)) >= SIMILARITY)
+}
+
+/// Returns the titles of the items sent by `feed` within the deduplication window.
+pub(crate) async fn recent_titles(
+    db: &DatabaseConnection,
+    feed: &feed::Model,
+) -> Result<Vec<String>, DbErr> {
+    let since = Utc::now().naive_utc() - Duration::hours(WINDOW_HOURS);
+    Ok(entity::prelude::Delivery::find()
+        .filter(delivery::Column::FeedId.eq(feed.id))
+        .filter(delivery::Column::SentAt.gt(since))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|delivery| delivery.title)
+        .collect())
+}
@@ -0,0 +1,119 @@
+//! Default settings of the new feeds of a chat.
+//!
+//! `/defaults <option> <value>` stores a setting on the chat that every feed it subscribes to
+//! afterwards starts with, instead of setting it on each feed: the on/off options named after
+//! their commands, such as `dedup` or `thumbnail`, and `priority high|normal|low`, where `low`
+//! sends the items silently in digests. `/defaults <option> default` forgets one, `/defaults
+//! reset` forgets all of them and `/defaults` lists them. Existing feeds don't change.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter,
+};
+
+use crate::priority::Priority;
+use crate::Switch;
+use entity::{chat, feed};
+
+/// The on/off options, by name, with the column of the feed they set.
+const SWITCHES: [(&str, feed::Column); 11] = [
+    ("albums", feed::Column::Albums),
+    ("archive", feed::Column::Archive),
+    ("contenthash", feed::Column::ContentHash),
+    ("dedup", feed::Column::Dedup),
+    ("editupdates", feed::Column::EditUpdates),
+    ("hashtags", feed::Column::Hashtags),
+    ("papers", feed::Column::Papers),
+    ("readingtime", feed::Column::ReadingTime),
+    ("releasenotes", feed::Column::ReleaseNotes),
+    ("spamfilter", feed::Column::SpamFilter),
+    ("thumbnail", feed::Column::Thumbnail),
+];
+const PRIORITY: &str = "priority";
+
+type Defaults = BTreeMap<String, String>;
+
+fn defaults_of(chat: &chat::Model) -> Defaults {
+    chat.feed_defaults
+        .as_deref()
+        .and_then(|defaults| serde_json::from_str(defaults).ok())
+        .unwrap_or_default()
+}
+
+fn switch_column(name: &str) -> Option<feed::Column> {
+    SWITCHES
+        .iter()
+        .find(|(switch, _)| *switch == name)
+        .map(|(_, column)| *column)
+}
+
+/// Sets the defaults of `chat` on `new_feed`.
+pub(crate) fn apply(chat: &chat::Model, new_feed: &mut feed::ActiveModel) {
+    for (name, value) in defaults_of(chat) {
+        if name == PRIORITY {
+            new_feed.set(feed::Column::Priority, value.into());
+        } else if let Some(column) = switch_column(&name) {
+            new_feed.set(column, (value == "on").into());
+        }
+    }
+}
+
+fn format_defaults(defaults: &Defaults) -> String {
+    if defaults.is_empty() {
+        return "New feeds start with the default settings.".to_string();
+    }
+    let mut lines = vec!["New feeds start with:".to_string()];
+    lines.extend(defaults.iter().map(|(name, value)| format!("{} {}", name, value)));
+    lines.join("\n")
+}
+
+/// Runs `/defaults` with `args`, which are nothing, `reset` or `<option> <value>|default`, and
+/// returns the defaults of `chat_id`.
+pub(crate) async fn run(
+    db: &DatabaseConnection,
+    chat_id: i64,
+    bot_id: i64,
+    args: &str,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let chat = entity::prelude::Chat::find_by_id((chat_id, bot_id))
+        .one(db)
+        .await?
+        .ok_or("Chat not found")?;
+    let mut defaults = defaults_of(&chat);
+    let words: Vec<String> = args.split_whitespace().map(str::to_lowercase).collect();
+    match words.iter().map(String::as_str).collect::<Vec<&str>>().as_slice() {
+        [] => return Ok(format_defaults(&defaults)),
+        ["reset"] => defaults.clear(),
+        [name, "default"] => {
+            defaults.remove(*name);
+        }
+        [PRIORITY, value] => {
+            let priority: Priority = value.parse()?;
+            defaults.insert(PRIORITY.to_string(), priority.to_string());
+        }
+        [name, value] if switch_column(name).is_some() => {
+            let Switch(enabled) = value.parse()?;
+            let value = if enabled { "on" } else { "off" };
+            defaults.insert(name.to_string(), value.to_string());
+        }
+        _ => {
+            let names: Vec<&str> = SWITCHES.iter().map(|(name, _)| *name).collect();
+            return Err(format!(
+                "Expected reset or <option> <value>|default, with {} on|off or priority \
+                 high|normal|low",
+                names.join(", ")
+            )
+            .into());
+        }
+    }
+    let stored = (!defaults.is_empty()).then(|| serde_json::to_string(&defaults));
+    entity::prelude::Chat::update_many()
+        .col_expr(chat::Column::FeedDefaults, Expr::value(stored.transpose()?))
+        .filter(chat::Column::Id.eq(chat_id))
+        .filter(chat::Column::BotId.eq(bot_id))
+        .exec(db)
+        .await?;
+    Ok(format_defaults(&defaults))
+}
@@ -0,0 +1,201 @@
+//! Additional destinations of a feed.
+//!
+//! Besides the chat it belongs to, a feed can send its items to other chats managed by that
+//! chat, such as a private chat and a channel, and to topics of forum groups, without a second
+//! subscription fetching the same feed. Items and digests are copied from the message sent to
+//! the chat of the feed. Every destination can be muted on its own, while muting the feed
+//! itself mutes all of them.
+
+use std::error::Error;
+
+use chrono::Utc;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter,
+    QueryOrder, Set,
+};
+use teloxide::{
+    payloads::CopyMessageSetters,
+    prelude::{Bot, Requester},
+    types::{ChatId, MessageId},
+};
+
+use entity::{destination, feed};
+
+const USAGE: &str = "Expected /destinations <feed id> [list], add <chat id>|here [topic id], \
+                     remove <destination id> or mute <destination id> <duration>|off";
+
+fn is_muted(destination: &destination::Model) -> bool {
+    destination
+        .muted_until
+        .is_some_and(|until| until > Utc::now().naive_utc())
+}
+
+async fn destinations(
+    db: &DatabaseConnection,
+    feed: &feed::Model,
+) -> Result<Vec<destination::Model>, DbErr> {
+    entity::prelude::Destination::find()
+        .filter(destination::Column::FeedId.eq(feed.id))
+        .order_by_asc(destination::Column::Id)
+        .all(db)
+        .await
+}
+
+/// Copies the message `message_id`, sent to the chat of `feed`, to the destinations of the feed
+/// that are not muted.
+pub(crate) async fn send_copies(
+    bot: &Bot,
+    db: &DatabaseConnection,
+    feed: &feed::Model,
+    message_id: MessageId,
+    silent: bool,
+) {
+    let destinations = match destinations(db, feed).await {
+        Ok(destinations) => destinations,
+        Err(err) => {
+            println!("Error fetching destinations: {:?}", err);
+            return;
+        }
+    };
+    for destination in destinations.iter().filter(|destination| !is_muted(destination)) {
+        let mut copy = bot
+            .copy_message(ChatId(destination.chat_id), ChatId(feed.chat_id), message_id)
+            .disable_notification(silent);
+        if let Some(thread_id) = destination.thread_id {
+            copy = copy.message_thread_id(thread_id);
+        }
+        if let Err(err) = copy.await {
+            println!("Error copying item to {}: {:?}", destination.chat_id, err);
+        }
+    }
+}
+
+fn describe(chat_id: i64, thread_id: Option<i32>) -> String {
+    match thread_id {
+        Some(thread_id) => format!("chat {} topic {}", chat_id, thread_id),
+        None => format!("chat {}", chat_id),
+    }
+}
+
+async fn list(
+    db: &DatabaseConnection,
+    feed: &feed::Model,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let mut lines = vec![format!("Destinations of {}:", feed.title)];
+    lines.push(format!("main: {}", describe(feed.chat_id, None)));
+    for destination in destinations(db, feed).await? {
+        let mut line = format!(
+            "{}: {}",
+            destination.id,
+            describe(destination.chat_id, destination.thread_id)
+        );
+        if let Some(until) = destination.muted_until.filter(|_| is_muted(&destination)) {
+            line.push_str(&format!(", muted until {} UTC", until.format("%Y-%m-%d %H:%M")));
+        }
+        lines.push(line);
+    }
+    Ok(lines.join("\n"))
+}
+
+async fn find(
+    db: &DatabaseConnection,
+    feed: &feed::Model,
+    id: &str,
+) -> Result<destination::Model, Box<dyn Error + Send + Sync>> {
+    let id: i64 = id
+        .parse()
+        .map_err(|_| format!("Expected a destination id, got {}", id))?;
+    Ok(entity::prelude::Destination::find_by_id(id)
+        .filter(destination::Column::FeedId.eq(feed.id))
+        .one(db)
+        .await?
+        .ok_or_else(|| format!("No destination {}", id))?)
+}
+
+/// Runs `/destinations <feed id> <args>` sent from `chat_id`, in topic `thread_id` if any, and
+/// returns the answer.
+///
+/// New destinations must be `chat_id`, a chat linked to it, or the chat of the feed, so that
+/// nobody can send items to chats they don't manage.
+pub(crate) async fn run(
+    db: &DatabaseConnection,
+    feed_id: i64,
+    chat_id: i64,
+    thread_id: Option<i32>,
+    bot_id: i64,
+    args: &str,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let feed = crate::pipeline::managed_feed(db, feed_id, chat_id, bot_id).await?;
+    let words: Vec<&str> = args.split_whitespace().collect();
+    match words[..] {
+        [] | ["list"] => list(db, &feed).await,
+        ["add", target, ref topic @ ..] => {
+            let here = target == "here";
+            let target = if here { chat_id } else { parse_chat(target)? };
+            let thread_id = match topic {
+                [] if here => thread_id,
+                [] => None,
+                [topic] => Some(
+                    topic
+                        .parse::<i32>()
+                        .map_err(|_| format!("Expected a topic id, got {}", topic))?,
+                ),
+                _ => return Err(USAGE.into()),
+            };
+            let linked = crate::links::linked_chats(db, chat_id, bot_id).await?;
+            let managed = target == chat_id || target == feed.chat_id || linked.contains(&target);
+            if !managed {
+                return Err(format!("Chat {} is not this chat or linked to it", target).into());
+            }
+            if target == feed.chat_id && thread_id.is_none() {
+                return Err("Items of the feed are already sent to its chat".into());
+            }
+            let new_destination = destination::ActiveModel {
+                feed_id: Set(feed.id),
+                chat_id: Set(target),
+                thread_id: Set(thread_id),
+                ..Default::default()
+            };
+            let destination = new_destination.insert(db).await?;
+            Ok(format!(
+                "Added destination {}: {}",
+                destination.id,
+                describe(target, thread_id)
+            ))
+        }
+        ["remove", id] => {
+            let destination = find(db, &feed, id).await?;
+            entity::prelude::Destination::delete_by_id(destination.id)
+                .exec(db)
+                .await?;
+            Ok(format!("Removed destination {}", destination.id))
+        }
+        ["mute", id, duration] => {
+            let destination = find(db, &feed, id).await?;
+            let until = if duration.eq_ignore_ascii_case("off") {
+                None
+            } else {
+                Some(Utc::now().naive_utc() + crate::mute::parse_duration(duration)?)
+            };
+            let id = destination.id;
+            let mut muted_destination: destination::ActiveModel = destination.into();
+            muted_destination.muted_until = Set(until);
+            muted_destination.update(db).await?;
+            Ok(match until {
+                Some(until) => format!(
+                    "Muted destination {} until {} UTC",
+                    id,
+                    until.format("%Y-%m-%d %H:%M")
+                ),
+                None => format!("Unmuted destination {}", id),
+            })
+        }
+        _ => Err(USAGE.into()),
+    }
+}
+
+fn parse_chat(target: &str) -> Result<i64, String> {
+    target
+        .parse()
+        .map_err(|_| format!("Expected a chat id or here, got {}", target))
+}
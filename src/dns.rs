@@ -0,0 +1,68 @@
+//! Caching DNS resolver of the feed downloads.
+//!
+//! Feed hosts are resolved with hickory rather than the system resolver, keeping answers in
+//! memory for up to `DNS_CACHE_SECONDS` (300 by default, 0 to disable the cache) so that every
+//! poll doesn't wait for a lookup. `IP_PREFERENCE` chooses which addresses are used:
+//!
+//! - `both` (the default): IPv4 and IPv6 addresses, tried in parallel by the connector;
+//! - `ipv4-first` or `ipv6-first`: the other family only if the first has no address;
+//! - `ipv4` or `ipv6`: that family only, for hosts whose broken AAAA records stall downloads.
+
+use std::env;
+use std::error::Error;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use hickory_resolver::config::LookupIpStrategy;
+use hickory_resolver::TokioAsyncResolver;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+
+const DEFAULT_CACHE_SECONDS: u64 = 300;
+
+pub(crate) struct CachingResolver(TokioAsyncResolver);
+
+impl Resolve for CachingResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let resolver = self.0.clone();
+        Box::pin(async move {
+            let lookup = resolver.lookup_ip(name.as_str()).await?;
+            let addrs: Vec<SocketAddr> = lookup.iter().map(|ip| SocketAddr::new(ip, 0)).collect();
+            Ok::<Addrs, Box<dyn Error + Send + Sync>>(Box::new(addrs.into_iter()))
+        })
+    }
+}
+
+fn ip_strategy(preference: &str) -> Result<LookupIpStrategy, String> {
+    match preference.to_lowercase().as_str() {
+        "both" => Ok(LookupIpStrategy::Ipv4AndIpv6),
+        "ipv4-first" => Ok(LookupIpStrategy::Ipv4thenIpv6),
+        "ipv6-first" => Ok(LookupIpStrategy::Ipv6thenIpv4),
+        "ipv4" => Ok(LookupIpStrategy::Ipv4Only),
+        "ipv6" => Ok(LookupIpStrategy::Ipv6Only),
+        _ => Err(format!(
+            "Expected both, ipv4-first, ipv6-first, ipv4 or ipv6, got {}",
+            preference
+        )),
+    }
+}
+
+/// Returns the resolver configured by `DNS_CACHE_SECONDS` and `IP_PREFERENCE`, on top of the
+/// name servers of the system.
+pub(crate) fn resolver() -> Result<Arc<CachingResolver>, Box<dyn Error + Send + Sync>> {
+    let (config, mut options) = hickory_resolver::system_conf::read_system_conf()?;
+    let cache_seconds = match env::var("DNS_CACHE_SECONDS") {
+        Ok(seconds) => seconds
+            .parse()
+            .map_err(|_| format!("Expected a number of seconds, got {}", seconds))?,
+        Err(_) => DEFAULT_CACHE_SECONDS,
+    };
+    if cache_seconds == 0 {
+        options.cache_size = 0;
+    }
+    options.positive_max_ttl = Some(Duration::from_secs(cache_seconds));
+    let preference = env::var("IP_PREFERENCE").unwrap_or_else(|_| "both".to_string());
+    options.ip_strategy = ip_strategy(&preference)?;
+    let resolver = TokioAsyncResolver::tokio(config, options);
+    Ok(Arc::new(CachingResolver(resolver)))
+}
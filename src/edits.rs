@@ -0,0 +1,86 @@
+//! Updates of items that were already sent.
+//!
+//! Feeds often republish an item with the same GUID and a corrected headline. When a feed has
+//! `edit_updates` on, the message that announced the item is edited to match the new title.
+//! Items sent with a thumbnail are photos, whose caption is edited instead. Hidden NSFW items
+//! are not edited.
+
+use std::collections::HashMap;
+
+use rss::Item;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, Set,
+};
+use teloxide::{
+    payloads::{EditMessageCaptionSetters, EditMessageTextSetters},
+    prelude::{Bot, Requester},
+    types::{ChatId, MessageId, ParseMode},
+};
+
+use crate::nsfw::NsfwMode;
+use entity::{delivery, feed};
+
+/// Returns the deliveries of `feed` for the GUIDs of `items`, keyed by GUID.
+pub(crate) async fn sent_items(
+    db: &DatabaseConnection,
+    feed: &feed::Model,
+    items: &[Item],
+) -> Result<HashMap<String, delivery::Model>, DbErr> {
+    let guids: Vec<&str> = items
+        .iter()
+        .filter_map(|item| item.guid())
+        .map(|guid| guid.value())
+        .collect();
+    if guids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    Ok(entity::prelude::Delivery::find()
+        .filter(delivery::Column::FeedId.eq(feed.id))
+        .filter(delivery::Column::Guid.is_in(guids))
+        .all(db)
+        .await?
+        .into_iter()
+        .filter_map(|delivery| Some((delivery.guid.clone()?, delivery)))
+        .collect())
+}
+
+/// Edits the message of `delivery` if the title of `item` changed since it was sent.
+pub(crate) async fn update_item(
+    bot: &Bot,
+    db: &DatabaseConnection,
+    feed: &feed::Model,
+    delivery: &delivery::Model,
+    item: &Item,
+) {
+    let title = item.title().unwrap_or_default();
+    let Some(message_id) = delivery.message_id.filter(|_| delivery.title != title) else {
+        return;
+    };
+    // Editing would reveal NSFW items that are hidden behind a spoiler or a button
+    if crate::nsfw::is_nsfw(item) && crate::nsfw::chat_mode(db, feed).await != NsfwMode::Show {
+        return;
+    }
+    let chat_id = ChatId(feed.chat_id);
+    let text = crate::format::item_message(feed, item);
+    let edited = if feed.thumbnail {
+        bot.edit_message_caption(chat_id, MessageId(message_id))
+            .caption(text)
+            .parse_mode(ParseMode::Html)
+            .reply_markup(crate::noise::item_keyboard(feed.id))
+            .await
+    } else {
+        bot.edit_message_text(chat_id, MessageId(message_id), text)
+            .parse_mode(ParseMode::Html)
+            .reply_markup(crate::noise::item_keyboard(feed.id))
+            .await
+    };
+    if let Err(err) = edited {
+        println!("Error editing message: {:?}", err);
+        return;
+    }
+    let mut updated_delivery: delivery::ActiveModel = delivery.clone().into();
+    updated_delivery.title = Set(title.to_string());
+    if let Err(err) = updated_delivery.update(db).await {
+        println!("Error updating delivery: {:?}", err);
+    }
+}
@@ -0,0 +1,114 @@
+//! Subscriptions to Mastodon and other Fediverse accounts.
+//!
+//! `/subscribe @user@instance` looks up the profile page of the account with WebFinger and
+//! subscribes to its RSS feed at `<profile>.rss`. Replies, whose text starts with a mention,
+//! and boosts, which link to a post outside of the profile, are skipped unless they are turned
+//! on with `/fediverse <feed id> replies|boosts on`, so that accounts behave the same whatever
+//! their server puts in the feed.
+
+use std::error::Error;
+
+use rss::Item;
+use sea_orm::{ActiveModelTrait, DatabaseConnection, Set, UpdateResult};
+use serde::Deserialize;
+
+use entity::feed;
+
+const PROFILE_PAGE: &str = "http://webfinger.net/rel/profile-page";
+
+#[derive(Deserialize)]
+struct WebFinger {
+    links: Vec<WebFingerLink>,
+}
+
+#[derive(Deserialize)]
+struct WebFingerLink {
+    rel: String,
+    href: Option<String>,
+}
+
+/// Returns whether `link` is a handle such as `@user@instance` rather than a link.
+pub(crate) fn is_handle(link: &str) -> bool {
+    link.strip_prefix('@')
+        .and_then(|handle| handle.split_once('@'))
+        .is_some_and(|(user, instance)| {
+            !user.is_empty() && !instance.is_empty() && !instance.contains('/')
+        })
+}
+
+/// Returns the profile page of the account `handle` from the WebFinger server of its instance.
+async fn profile_page(handle: &str) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let account = handle.trim_start_matches('@');
+    let (_, instance) = account
+        .split_once('@')
+        .ok_or_else(|| format!("Expected a handle such as @user@instance, got {}", handle))?;
+    let url = format!(
+        "https://{}/.well-known/webfinger?resource=acct:{}",
+        instance,
+        urlencoding::encode(account)
+    );
+    let webfinger: WebFinger = reqwest::get(&url)
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    Ok(webfinger
+        .links
+        .into_iter()
+        .find(|link| link.rel == PROFILE_PAGE)
+        .and_then(|link| link.href)
+        .ok_or_else(|| format!("{} has no profile page", handle))?)
+}
+
+/// Subscribes `chat_id` to the account `handle`.
+pub(crate) async fn subscribe(
+    db: &DatabaseConnection,
+    handle: &str,
+    chat_id: i64,
+    bot_id: i64,
+) -> Result<feed::Model, Box<dyn Error + Send + Sync>> {
+    let profile = profile_page(handle).await?;
+    let link = format!("{}.rss", profile.trim_end_matches('/'));
+    let channel = crate::validate_feed(&link).await?;
+    let new_feed = crate::create_feed(db, &channel, chat_id, bot_id).await?;
+    let mut fediverse_feed: feed::ActiveModel = new_feed.into();
+    fediverse_feed.fediverse_profile = Set(Some(profile));
+    let feed = fediverse_feed.update(db).await?;
+    crate::registry::invalidate(feed.id);
+    Ok(feed)
+}
+
+fn is_reply(item: &Item) -> bool {
+    crate::format::plain_text(item).trim_start().starts_with('@')
+}
+
+fn is_boost(profile: &str, item: &Item) -> bool {
+    item.link().is_some_and(|link| !link.starts_with(profile))
+}
+
+/// Returns whether `item` of `feed` is a post that should be sent, if `feed` is an account.
+pub(crate) fn allows_item(feed: &feed::Model, item: &Item) -> bool {
+    let Some(profile) = &feed.fediverse_profile else {
+        return true;
+    };
+    let replies = feed.fediverse_replies || !is_reply(item);
+    let boosts = feed.fediverse_boosts || !is_boost(profile, item);
+    replies && boosts
+}
+
+/// Turns the replies or boosts of feed `id` on or off, as given by `option`.
+pub(crate) async fn set_option(
+    db: &DatabaseConnection,
+    id: i64,
+    chat_id: i64,
+    bot_id: i64,
+    option: &str,
+    enabled: bool,
+) -> Result<UpdateResult, Box<dyn Error + Send + Sync>> {
+    let column = match option.to_lowercase().as_str() {
+        "replies" => feed::Column::FediverseReplies,
+        "boosts" => feed::Column::FediverseBoosts,
+        _ => return Err(format!("Expected replies or boosts, got {}", option).into()),
+    };
+    crate::set_managed_feed_option(db, id, chat_id, bot_id, column, enabled).await
+}
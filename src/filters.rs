@@ -0,0 +1,125 @@
+//! Per-feed item filters on categories and authors.
+//!
+//! A filter is a list of rules such as `+Politics -Sports`. Items must match at least one `+`
+//! rule, if there are any, and no `-` rule. Rules are case insensitive and may contain spaces,
+//! `+Local News` being a single rule.
+//!
+//! Feeds with a minimum length also skip the items whose title and description together are
+//! shorter, such as the link-only placeholders some feeds publish.
+
+use std::error::Error;
+
+use rss::Item;
+use sea_orm::{
+    sea_query::Expr, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, UpdateResult,
+};
+
+use entity::feed;
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct Filter {
+    include: Vec<String>,
+    exclude: Vec<String>,
+}
+
+impl Filter {
+    /// Parses rules of the form `+Included -Excluded`.
+    pub(crate) fn parse(rules: &str) -> Result<Self, String> {
+        let mut filter = Filter::default();
+        let mut current: Option<(bool, String)> = None;
+        for word in rules.split_whitespace() {
+            let rule = if let Some(name) = word.strip_prefix('+') {
+                Some((true, name.to_string()))
+            } else {
+                word.strip_prefix('-').map(|name| (false, name.to_string()))
+            };
+            match (rule, current.as_mut()) {
+                (Some(rule), _) => {
+                    filter.push(current.take());
+                    current = Some(rule);
+                }
+                (None, Some((_, name))) => {
+                    name.push(' ');
+                    name.push_str(word);
+                }
+                (None, None) => return Err(format!("Rule {} must start with + or -", word)),
+            }
+        }
+        filter.push(current);
+        Ok(filter)
+    }
+
+    fn push(&mut self, rule: Option<(bool, String)>) {
+        match rule {
+            Some((true, name)) => self.include.push(name.to_lowercase()),
+            Some((false, name)) => self.exclude.push(name.to_lowercase()),
+            None => {}
+        }
+    }
+
+    /// Returns whether an item with the given categories or authors passes the filter.
+    pub(crate) fn allows<'a>(&self, values: impl IntoIterator<Item = &'a str>) -> bool {
+        let values: Vec<String> = values.into_iter().map(str::to_lowercase).collect();
+        let included = self.include.is_empty()
+            || self.include.iter().any(|rule| values.contains(rule));
+        let excluded = self.exclude.iter().any(|rule| values.contains(rule));
+        included && !excluded
+    }
+
+    /// Returns whether `text` contains one of the `+` rules, if there are any, and none of the
+    /// `-` rules.
+    pub(crate) fn allows_text(&self, text: &str) -> bool {
+        let text = text.to_lowercase();
+        let included = self.include.is_empty()
+            || self.include.iter().any(|rule| text.contains(rule.as_str()));
+        let excluded = self.exclude.iter().any(|rule| text.contains(rule.as_str()));
+        included && !excluded
+    }
+}
+
+fn allows(rules: Option<&str>, values: Vec<&str>) -> bool {
+    match rules.map(Filter::parse) {
+        Some(Ok(filter)) => filter.allows(values),
+        _ => true,
+    }
+}
+
+/// Returns whether `item` passes the category and author filters of `feed`.
+pub(crate) fn allows_item(feed: &feed::Model, item: &Item) -> bool {
+    let categories = item.categories().iter().map(|category| category.name()).collect();
+    let mut authors: Vec<&str> = item.author().into_iter().collect();
+    if let Some(dublin_core) = item.dublin_core_ext() {
+        authors.extend(dublin_core.creators().iter().map(String::as_str));
+    }
+    allows(feed.category_filter.as_deref(), categories)
+        && allows(feed.author_filter.as_deref(), authors)
+}
+
+/// Returns whether the title and description of `item` are at least as long as the minimum
+/// length of `feed`.
+pub(crate) fn allows_length(feed: &feed::Model, item: &Item) -> bool {
+    let length = crate::format::plain_text(item).trim().chars().count();
+    feed.min_length <= 0 || length >= feed.min_length as usize
+}
+
+/// Stores the filter `rules` in `column` of feed `id`. Empty rules remove the filter.
+pub(crate) async fn set_feed_filter(
+    db: &DatabaseConnection,
+    id: i64,
+    chat_id: i64,
+    bot_id: i64,
+    column: feed::Column,
+    rules: &str,
+) -> Result<UpdateResult, Box<dyn Error + Send + Sync>> {
+    Filter::parse(rules)?;
+    let rules = Some(rules.to_string()).filter(|rules| !rules.is_empty());
+    let updated = entity::prelude::Feed::update_many()
+        .col_expr(column, Expr::value(rules))
+        .filter(feed::Column::ChatId.eq(chat_id))
+        .filter(feed::Column::BotId.eq(bot_id))
+        .filter(feed::Column::Id.eq(id))
+        .exec(db)
+        .await?;
+    crate::registry::invalidate(id);
+    Ok(updated)
+}
@@ -0,0 +1,164 @@
+//! Development fixtures.
+//!
+//! `multitude_bot fixtures [address]` serves the feeds bundled in `fixtures/` at
+//! `/feeds/<name>`, and a fake Telegram Bot API that prints the messages the bots send instead
+//! of delivering them. `multitude_bot seed [fixture server]` then fills a development database
+//! with sample chats subscribed to these feeds. Running the bot with `TELEGRAM_API_URL` set to
+//! the fixture server and any token of the form `<number>:<secret>` polls the sample feeds
+//! through the whole pipeline, without a real Telegram token. `blog.atom` and `updates.json`
+//! are an Atom feed and a JSON Feed. `edge_cases.xml` holds items that are hard to format, such
+//! as HTML in titles, missing links, very long titles and right-to-left text. The messages of
+//! all of them are checked by the snapshot tests of [`crate::format`].
+
+use std::error::Error;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::Duration;
+
+use axum::{
+    body::Bytes,
+    extract::Path,
+    http::{header, StatusCode},
+    response::IntoResponse,
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use sea_orm::{sea_query::Expr, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use serde_json::{json, Value};
+
+use entity::feed;
+
+pub(crate) const DEFAULT_ADDRESS: &str = "127.0.0.1:8088";
+/// The bundled feeds, by name.
+pub(crate) const FEEDS: [(&str, &str); 5] = [
+    ("news.xml", include_str!("../fixtures/news.xml")),
+    ("podcast.xml", include_str!("../fixtures/podcast.xml")),
+    ("edge_cases.xml", include_str!("../fixtures/edge_cases.xml")),
+    ("blog.atom", include_str!("../fixtures/blog.atom")),
+    ("updates.json", include_str!("../fixtures/updates.json")),
+];
+/// The sample chats. Their bot id is 0, so that they are assigned to the default bot.
+const CHATS: [i64; 2] = [1001, 1002];
+/// The fake Bot API answers `getUpdates` after this many seconds, as if no one wrote.
+const UPDATES_DELAY_SECONDS: u64 = 10;
+
+static MESSAGE_ID: AtomicI64 = AtomicI64::new(1);
+
+async fn feed(Path(name): Path<String>) -> Result<impl IntoResponse, StatusCode> {
+    let (_, content) = FEEDS
+        .iter()
+        .find(|(feed_name, _)| *feed_name == name)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let content_type = match name.rsplit_once('.') {
+        Some((_, "atom")) => "application/atom+xml",
+        Some((_, "json")) => "application/feed+json",
+        _ => "application/rss+xml",
+    };
+    Ok(([(header::CONTENT_TYPE, content_type)], *content))
+}
+
+/// Returns the message the fake Bot API sends back for `request` to `chat_id`.
+fn message(chat_id: i64, request: &Value) -> Value {
+    let text = request["text"]
+        .as_str()
+        .or(request["caption"].as_str())
+        .unwrap_or_default();
+    json!({
+        "message_id": MESSAGE_ID.fetch_add(1, Ordering::Relaxed),
+        "date": Utc::now().timestamp(),
+        "chat": {"id": chat_id, "type": "private", "first_name": "Fixture"},
+        "text": text,
+    })
+}
+
+/// Answers the Bot API request `method` of the bot with `token`.
+async fn telegram(Path((token, method)): Path<(String, String)>, body: Bytes) -> Json<Value> {
+    let bot_id: i64 = token
+        .trim_start_matches("bot")
+        .split(':')
+        .next()
+        .and_then(|id| id.parse().ok())
+        .unwrap_or_default();
+    // Files are sent as multipart forms, whose fields are not read
+    let request: Value = serde_json::from_slice(&body).unwrap_or_default();
+    let chat_id = request["chat_id"].as_i64().unwrap_or_default();
+    let result = match method.as_str() {
+        "getMe" => json!({
+            "id": bot_id,
+            "is_bot": true,
+            "first_name": "Fixture bot",
+            "username": "fixture_bot",
+            "can_join_groups": true,
+            "can_read_all_group_messages": false,
+            "supports_inline_queries": false,
+        }),
+        "getUpdates" => {
+            tokio::time::sleep(Duration::from_secs(UPDATES_DELAY_SECONDS)).await;
+            json!([])
+        }
+        "sendMediaGroup" => json!([message(chat_id, &request)]),
+        method if method.starts_with("send") || method.starts_with("edit") => {
+            let message = message(chat_id, &request);
+            println!("{} to chat {} by bot {}: {}", method, chat_id, bot_id, message["text"]);
+            message
+        }
+        _ => json!(true),
+    };
+    Json(json!({"ok": true, "result": result}))
+}
+
+/// Serves the bundled feeds and the fake Bot API on `addr`.
+pub(crate) async fn serve(addr: SocketAddr) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    log::info!("Fixtures listening on {}", listener.local_addr()?);
+    axum::serve(listener, router()).await?;
+    Ok(())
+}
+
+/// Returns the routes of the bundled feeds and of the fake Bot API.
+pub(crate) fn router() -> Router {
+    Router::new()
+        .route("/feeds/{name}", get(feed))
+        .route("/{token}/{method}", post(telegram))
+}
+
+/// Creates the sample chats, subscribed to `copies` copies of every bundled feed served at
+/// `server`, which differ by their query string. All items of the feeds are sent on the first
+/// poll. Chats that already exist are left as they are.
+pub(crate) async fn seed(
+    db: &DatabaseConnection,
+    server: &str,
+    copies: usize,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    for chat_id in CHATS {
+        if entity::prelude::Chat::find_by_id((chat_id, 0)).one(db).await?.is_some() {
+            println!("Chat {} already exists", chat_id);
+            continue;
+        }
+        crate::create_chat(db, chat_id, 0).await?;
+        let feeds = FEEDS
+            .iter()
+            .flat_map(|feed| std::iter::repeat(feed).zip(0..copies));
+        for (&(name, content), copy) in feeds {
+            let mut channel = crate::parse_channel(content.as_bytes())?;
+            let link = format!("http://{}/feeds/{}", server, name);
+            channel.set_link(if copy == 0 {
+                link
+            } else {
+                format!("{}?copy={}", link, copy)
+            });
+            let new_feed = crate::create_feed(db, &channel, chat_id, 0).await?;
+            entity::prelude::Feed::update_many()
+                .col_expr(
+                    feed::Column::UpdatedAt,
+                    Expr::value(DateTime::UNIX_EPOCH.naive_utc()),
+                )
+                .filter(feed::Column::Id.eq(new_feed.id))
+                .exec(db)
+                .await?;
+            println!("Subscribed chat {} to {}", chat_id, new_feed.link);
+        }
+    }
+    Ok(())
+}
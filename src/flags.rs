@@ -0,0 +1,155 @@
+//! Runtime feature flags.
+//!
+//! Operators can turn features off, or limit them, without redeploying: the admin chat sends
+//! `/setflag <flag> <value>` and the value is stored in the `feature_flag` table. Flags that
+//! were never set have their default value.
+
+use std::error::Error;
+use std::str::FromStr;
+
+use sea_orm::{sea_query::OnConflict, DatabaseConnection, DbErr, EntityTrait, Set};
+
+use entity::feature_flag;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Flag {
+    /// Whether chats may subscribe to new feeds.
+    Subscriptions,
+    /// Maximum number of feeds of a chat, 0 for no limit.
+    MaxFeeds,
+    /// Whether feed images are fetched and sent with items.
+    Thumbnails,
+    /// Whether weekly summaries are sent.
+    WeeklySummary,
+    /// Daily commands a chat may send, 0 for no limit.
+    QuotaCommands,
+    /// Daily bytes downloaded for the feeds of a chat, 0 for no limit.
+    QuotaBytes,
+    /// Daily messages a chat may receive, 0 for no limit.
+    QuotaMessages,
+    /// Whether video enclosures are downloaded and sent as videos.
+    Videos,
+    /// Whether new and deleted accounts are reported to the admin chat as they happen.
+    SignupAlerts,
+}
+
+impl Flag {
+    pub(crate) const ALL: [Flag; 9] = [
+        Flag::Subscriptions,
+        Flag::MaxFeeds,
+        Flag::Thumbnails,
+        Flag::WeeklySummary,
+        Flag::QuotaCommands,
+        Flag::QuotaBytes,
+        Flag::QuotaMessages,
+        Flag::Videos,
+        Flag::SignupAlerts,
+    ];
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Flag::Subscriptions => "subscriptions",
+            Flag::MaxFeeds => "max_feeds",
+            Flag::Thumbnails => "thumbnails",
+            Flag::WeeklySummary => "weekly_summary",
+            Flag::QuotaCommands => "quota_commands",
+            Flag::QuotaBytes => "quota_bytes",
+            Flag::QuotaMessages => "quota_messages",
+            Flag::Videos => "videos",
+            Flag::SignupAlerts => "signup_alerts",
+        }
+    }
+
+    fn default_value(self) -> &'static str {
+        if self.is_numeric() {
+            "0"
+        } else {
+            "on"
+        }
+    }
+
+    fn is_numeric(self) -> bool {
+        matches!(
+            self,
+            Flag::MaxFeeds | Flag::QuotaCommands | Flag::QuotaBytes | Flag::QuotaMessages
+        )
+    }
+}
+
+impl FromStr for Flag {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Flag::ALL
+            .into_iter()
+            .find(|flag| flag.name() == s.to_lowercase())
+            .ok_or_else(|| format!("Unknown flag {}", s))
+    }
+}
+
+/// Returns the value of `flag`, or its default if it was never set or can't be read.
+pub(crate) async fn value(db: &DatabaseConnection, flag: Flag) -> String {
+    match entity::prelude::FeatureFlag::find_by_id(flag.name()).one(db).await {
+        Ok(Some(stored)) => stored.value,
+        Ok(None) => flag.default_value().to_string(),
+        Err(err) => {
+            println!("Error reading flag {}: {:?}", flag.name(), err);
+            flag.default_value().to_string()
+        }
+    }
+}
+
+/// Returns whether the on/off `flag` is on.
+pub(crate) async fn is_enabled(db: &DatabaseConnection, flag: Flag) -> bool {
+    value(db, flag).await == "on"
+}
+
+/// Returns the limit set by the numeric `flag`, or `None` if there is no limit.
+pub(crate) async fn limit(db: &DatabaseConnection, flag: Flag) -> Option<u64> {
+    value(db, flag).await.parse().ok().filter(|&limit| limit > 0)
+}
+
+/// Returns every flag with its current value.
+pub(crate) async fn list(db: &DatabaseConnection) -> Result<Vec<(Flag, String)>, DbErr> {
+    let stored = entity::prelude::FeatureFlag::find().all(db).await?;
+    Ok(Flag::ALL
+        .into_iter()
+        .map(|flag| {
+            let value = stored
+                .iter()
+                .find(|stored| stored.name == flag.name())
+                .map_or(flag.default_value(), |stored| stored.value.as_str());
+            (flag, value.to_string())
+        })
+        .collect())
+}
+
+/// Sets `flag` to `value`, which must be `on` or `off`, or a number for numeric flags.
+pub(crate) async fn set(
+    db: &DatabaseConnection,
+    flag: &str,
+    value: &str,
+) -> Result<Flag, Box<dyn Error + Send + Sync>> {
+    let flag: Flag = flag.parse()?;
+    let value = value.to_lowercase();
+    if flag.is_numeric() {
+        value
+            .parse::<u64>()
+            .map_err(|_| format!("Expected a number for {}, got {}", flag.name(), value))?;
+    } else if value != "on" && value != "off" {
+        return Err(format!("Expected on or off for {}, got {}", flag.name(), value).into());
+    }
+    let stored = feature_flag::ActiveModel {
+        name: Set(flag.name().to_string()),
+        value: Set(value),
+    };
+    entity::prelude::FeatureFlag::insert(stored)
+        .on_conflict(
+            OnConflict::column(feature_flag::Column::Name)
+                .update_column(feature_flag::Column::Value)
+                .to_owned(),
+        )
+        .exec(db)
+        .await?;
+    Ok(flag)
+}
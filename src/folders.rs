@@ -0,0 +1,302 @@
+//! Folders combining several feeds of a chat.
+//!
+//! `/folder create Tech 3 5 9` groups feeds 3, 5 and 9 in the folder `Tech`, and a feed is in at
+//! most one folder. The settings of a folder, changed with `/folder set <name> <setting>
+//! <value>`, override those of its feeds: `rate` is a maximum rate shared by all its feeds,
+//! `window` a delivery window and `template` a message template. The items held by the feeds of
+//! a folder are sent together in a single digest. `top` turns the folder into a daily digest of
+//! its best items, ranked with its comma separated `keywords`, see [`crate::ranking`].
+
+use std::collections::HashMap;
+use std::error::Error;
+
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait,
+    QueryFilter, QueryOrder, Set,
+};
+
+use crate::rate::Rate;
+use crate::window::DeliveryWindow;
+use entity::{feed, folder, folder_feed};
+
+const USAGE: &str = "Expected /folder create|add|remove <name> <feed ids>, /folder delete \
+                     <name>, /folder set <name> rate|window|template|top|keywords <value> or \
+                     /folder list";
+
+/// Returns the folder of `feed`, if it is in one.
+pub(crate) async fn folder_of(
+    db: &DatabaseConnection,
+    feed: &feed::Model,
+) -> Result<Option<folder::Model>, DbErr> {
+    let membership = entity::prelude::FolderFeed::find_by_id(feed.id)
+        .find_also_related(entity::prelude::Folder)
+        .one(db)
+        .await?;
+    Ok(membership.and_then(|(_, folder)| folder))
+}
+
+/// Returns `feed` with the settings of its folder, and the folder if it is in one.
+pub(crate) async fn resolve(
+    db: &DatabaseConnection,
+    feed: &feed::Model,
+) -> Result<(feed::Model, Option<folder::Model>), DbErr> {
+    let mut resolved = feed.clone();
+    let Some(folder) = folder_of(db, feed).await? else {
+        return Ok((resolved, None));
+    };
+    if folder.max_rate.is_some() {
+        resolved.max_rate = folder.max_rate.clone();
+    }
+    if folder.delivery_window.is_some() {
+        resolved.delivery_window = folder.delivery_window.clone();
+    }
+    if let Some(template) = &folder.template {
+        resolved.pipeline = crate::pipeline::with_template(feed, template);
+    }
+    Ok((resolved, Some(folder)))
+}
+
+/// Returns the feeds whose deliveries count towards the maximum rate of `feed`: all the feeds
+/// of its folder if the folder has a rate, or just `feed`.
+pub(crate) async fn rate_feed_ids(
+    db: &DatabaseConnection,
+    feed: &feed::Model,
+) -> Result<Vec<i64>, DbErr> {
+    match folder_of(db, feed).await? {
+        Some(folder) if folder.max_rate.is_some() => Ok(entity::prelude::FolderFeed::find()
+            .filter(folder_feed::Column::FolderId.eq(folder.id))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|membership| membership.feed_id)
+            .collect()),
+        _ => Ok(vec![feed.id]),
+    }
+}
+
+/// Returns the folder `name` of `chat_id`.
+pub(crate) async fn find(
+    db: &DatabaseConnection,
+    chat_id: i64,
+    bot_id: i64,
+    name: &str,
+) -> Result<folder::Model, Box<dyn Error + Send + Sync>> {
+    Ok(entity::prelude::Folder::find()
+        .filter(folder::Column::ChatId.eq(chat_id))
+        .filter(folder::Column::BotId.eq(bot_id))
+        .filter(folder::Column::Name.eq(name))
+        .one(db)
+        .await?
+        .ok_or_else(|| format!("No folder {}", name))?)
+}
+
+/// Returns `args` after its first `count` words.
+fn rest(args: &str, count: usize) -> &str {
+    let mut rest = args;
+    for _ in 0..count {
+        rest = rest.trim_start();
+        rest = &rest[rest.find(char::is_whitespace).unwrap_or(rest.len())..];
+    }
+    rest.trim()
+}
+
+/// Returns the ids of the feeds of `chat_id` that `references` refer to, see
+/// [`crate::short_ids`].
+async fn parse_ids(
+    db: &DatabaseConnection,
+    chat_id: i64,
+    bot_id: i64,
+    references: &[&str],
+) -> Result<Vec<i64>, Box<dyn Error + Send + Sync>> {
+    let mut ids = Vec::new();
+    for reference in references {
+        let id = crate::short_ids::resolve(db, chat_id, bot_id, reference)
+            .await?
+            .ok_or_else(|| format!("Expected a feed, got {}", reference))?;
+        ids.push(id);
+    }
+    Ok(ids)
+}
+
+async fn add_feeds(
+    db: &DatabaseConnection,
+    folder: &folder::Model,
+    ids: &[i64],
+) -> Result<usize, Box<dyn Error + Send + Sync>> {
+    let feeds = entity::prelude::Feed::find()
+        .filter(feed::Column::Id.is_in(ids.to_vec()))
+        .filter(feed::Column::ChatId.eq(folder.chat_id))
+        .filter(feed::Column::BotId.eq(folder.bot_id))
+        .all(db)
+        .await?;
+    for feed in &feeds {
+        entity::prelude::FolderFeed::delete_by_id(feed.id)
+            .exec(db)
+            .await?;
+        let membership = folder_feed::ActiveModel {
+            feed_id: Set(feed.id),
+            folder_id: Set(folder.id),
+        };
+        membership.insert(db).await?;
+    }
+    Ok(feeds.len())
+}
+
+async fn list(
+    db: &DatabaseConnection,
+    chat_id: i64,
+    bot_id: i64,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let folders = entity::prelude::Folder::find()
+        .filter(folder::Column::ChatId.eq(chat_id))
+        .filter(folder::Column::BotId.eq(bot_id))
+        .order_by_asc(folder::Column::Name)
+        .find_with_related(entity::prelude::FolderFeed)
+        .all(db)
+        .await?;
+    if folders.is_empty() {
+        return Ok("No folders.".to_string());
+    }
+    let short_ids: HashMap<i64, i32> = entity::prelude::Feed::find()
+        .filter(feed::Column::ChatId.eq(chat_id))
+        .filter(feed::Column::BotId.eq(bot_id))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|feed| (feed.id, feed.short_id))
+        .collect();
+    Ok(folders
+        .iter()
+        .map(|(folder, memberships)| {
+            let ids: Vec<String> = memberships
+                .iter()
+                .filter_map(|membership| short_ids.get(&membership.feed_id))
+                .map(i32::to_string)
+                .collect();
+            let mut line = format!("📁 {}: feeds {}", folder.name, ids.join(", "));
+            if let Some(rate) = &folder.max_rate {
+                line.push_str(&format!(", rate {}", rate));
+            }
+            if let Some(window) = &folder.delivery_window {
+                line.push_str(&format!(", window {}", window));
+            }
+            if folder.template.is_some() {
+                line.push_str(", with a template");
+            }
+            if let Some(top) = folder.top_items {
+                line.push_str(&format!(", top {} daily", top));
+            }
+            if let Some(keywords) = &folder.keywords {
+                line.push_str(&format!(", keywords {}", keywords));
+            }
+            line
+        })
+        .collect::<Vec<String>>()
+        .join("\n"))
+}
+
+async fn set(
+    db: &DatabaseConnection,
+    folder: &folder::Model,
+    setting: &str,
+    value: &str,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let value = Some(value).filter(|value| !value.is_empty() && !value.eq_ignore_ascii_case("off"));
+    let column = match setting.to_lowercase().as_str() {
+        "rate" => {
+            value.map(str::parse::<Rate>).transpose()?;
+            folder::Column::MaxRate
+        }
+        "window" => {
+            value.map(str::parse::<DeliveryWindow>).transpose()?;
+            folder::Column::DeliveryWindow
+        }
+        "template" => folder::Column::Template,
+        "top" => {
+            let top = value
+                .map(|value| {
+                    let error = format!("Expected a number of top items, got {}", value);
+                    value.parse::<u16>().map(i32::from).map_err(|_| error)
+                })
+                .transpose()?;
+            entity::prelude::Folder::update_many()
+                .col_expr(folder::Column::TopItems, Expr::value(top))
+                .filter(folder::Column::Id.eq(folder.id))
+                .exec(db)
+                .await?;
+            return Ok(());
+        }
+        "keywords" => folder::Column::Keywords,
+        _ => {
+            let error = format!(
+                "Unknown setting {}, expected rate, window, template, top or keywords",
+                setting
+            );
+            return Err(error.into());
+        }
+    };
+    let value = value.map(|value| value.replace("\\n", "\n"));
+    entity::prelude::Folder::update_many()
+        .col_expr(column, Expr::value(value))
+        .filter(folder::Column::Id.eq(folder.id))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+/// Runs the `/folder` subcommand in `args` for chat `chat_id` and returns the reply.
+pub(crate) async fn run(
+    db: &DatabaseConnection,
+    chat_id: i64,
+    bot_id: i64,
+    args: &str,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let words: Vec<&str> = args.split_whitespace().collect();
+    match words[..] {
+        [] | ["list"] => list(db, chat_id, bot_id).await,
+        ["create", name, ref ids @ ..] => {
+            let new_folder = folder::ActiveModel {
+                chat_id: Set(chat_id),
+                bot_id: Set(bot_id),
+                name: Set(name.to_string()),
+                ..Default::default()
+            };
+            let ids = parse_ids(db, chat_id, bot_id, ids).await?;
+            if find(db, chat_id, bot_id, name).await.is_ok() {
+                return Err(format!("Folder {} already exists", name).into());
+            }
+            let folder = new_folder.insert(db).await?;
+            let added = add_feeds(db, &folder, &ids).await?;
+            Ok(format!("Created folder {} with {} feeds", name, added))
+        }
+        ["add", name, ref ids @ ..] => {
+            let ids = parse_ids(db, chat_id, bot_id, ids).await?;
+            let folder = find(db, chat_id, bot_id, name).await?;
+            let added = add_feeds(db, &folder, &ids).await?;
+            Ok(format!("Added {} feeds to folder {}", added, name))
+        }
+        ["remove", name, ref ids @ ..] => {
+            let ids = parse_ids(db, chat_id, bot_id, ids).await?;
+            let folder = find(db, chat_id, bot_id, name).await?;
+            let removed = entity::prelude::FolderFeed::delete_many()
+                .filter(folder_feed::Column::FolderId.eq(folder.id))
+                .filter(folder_feed::Column::FeedId.is_in(ids))
+                .exec(db)
+                .await?;
+            Ok(format!("Removed {} feeds from folder {}", removed.rows_affected, name))
+        }
+        ["delete", name] => {
+            let folder = find(db, chat_id, bot_id, name).await?;
+            entity::prelude::Folder::delete_by_id(folder.id)
+                .exec(db)
+                .await?;
+            Ok(format!("Deleted folder {}", name))
+        }
+        ["set", name, setting, ..] => {
+            let folder = find(db, chat_id, bot_id, name).await?;
+            set(db, &folder, setting, rest(args, 3)).await?;
+            Ok(format!("Set {} of folder {}", setting, name))
+        }
+        _ => Err(USAGE.into()),
+    }
+}
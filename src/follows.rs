@@ -0,0 +1,188 @@
+//! Follows of names across the feeds of a chat.
+//!
+//! Names, such as companies and people, are extracted from item titles as runs of capitalized
+//! words. `/follow "OpenAI"` makes the items of every feed of the chat whose title mentions the
+//! name be sent right away with a notification, never held, and highlighted with ⭐. `/follow`
+//! alone lists the names followed by the chat and suggests the names most mentioned by its
+//! items of the last [`SUGGESTION_DAYS`] days.
+
+use std::collections::HashMap;
+use std::error::Error;
+
+use chrono::{Duration, Utc};
+use rss::Item;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, Set,
+};
+
+use entity::{delivery, follow};
+
+const SUGGESTION_DAYS: i64 = 7;
+const SUGGESTIONS: usize = 10;
+
+/// Returns the words of `text`, without the punctuation around them.
+fn words(text: &str) -> Vec<&str> {
+    text.split_whitespace()
+        .map(|word| word.trim_matches(|c: char| !c.is_alphanumeric()))
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+fn is_capitalized(word: &str) -> bool {
+    word.chars().next().is_some_and(char::is_uppercase)
+}
+
+/// Returns the names in `title`, the runs of capitalized words. The first word of the title is
+/// a name only if it is part of a longer run or has other capitals, such as `OpenAI` or `NASA`.
+pub(crate) fn names(title: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut run: Vec<&str> = Vec::new();
+    let mut run_start = 0;
+    // The empty word at the end closes the last run
+    for (index, word) in words(title).into_iter().chain([""]).enumerate() {
+        if is_capitalized(word) {
+            if run.is_empty() {
+                run_start = index;
+            }
+            run.push(word);
+            continue;
+        }
+        let first_word_only = run_start == 0
+            && run.len() == 1
+            && !run[0].chars().skip(1).any(char::is_uppercase);
+        if !run.is_empty() && !first_word_only {
+            names.push(run.join(" "));
+        }
+        run.clear();
+    }
+    names
+}
+
+/// Returns whether `title` mentions `name`, ignoring case and punctuation.
+fn mentions(title: &str, name: &str) -> bool {
+    let title: Vec<String> = words(title).iter().map(|word| word.to_lowercase()).collect();
+    let name: Vec<String> = words(name).iter().map(|word| word.to_lowercase()).collect();
+    !name.is_empty() && title.windows(name.len()).any(|window| window == name.as_slice())
+}
+
+/// Returns `name` without the quotes around it.
+fn unquote(name: &str) -> &str {
+    name.trim()
+        .trim_matches(|c: char| matches!(c, '"' | '“' | '”'))
+        .trim()
+}
+
+/// Returns the names followed by `chat_id`.
+pub(crate) async fn followed(
+    db: &DatabaseConnection,
+    chat_id: i64,
+    bot_id: i64,
+) -> Result<Vec<String>, DbErr> {
+    Ok(entity::prelude::Follow::find()
+        .filter(follow::Column::ChatId.eq(chat_id))
+        .filter(follow::Column::BotId.eq(bot_id))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|follow| follow.name)
+        .collect())
+}
+
+/// Returns `item` highlighted with the first of the `followed` names it mentions, if any.
+pub(crate) fn highlight(followed: &[String], item: &Item) -> Option<Item> {
+    let title = item.title().unwrap_or_default();
+    let name = followed.iter().find(|name| mentions(title, name))?;
+    let mut highlighted = item.clone();
+    highlighted.set_title(format!("⭐ {} · {}", name, title));
+    Some(highlighted)
+}
+
+/// Returns `title` without the highlight that [`highlight`] added to it, if any.
+pub(crate) fn unhighlighted(title: &str) -> &str {
+    title
+        .strip_prefix("⭐ ")
+        .and_then(|highlighted| highlighted.split_once(" · "))
+        .map_or(title, |(_, title)| title)
+}
+
+/// Returns the names most mentioned by the items recently sent to `chat_id`.
+async fn suggestions(
+    db: &DatabaseConnection,
+    chat_id: i64,
+    followed: &[String],
+) -> Result<Vec<String>, DbErr> {
+    let since = Utc::now().naive_utc() - Duration::days(SUGGESTION_DAYS);
+    let deliveries = entity::prelude::Delivery::find()
+        .filter(delivery::Column::ChatId.eq(chat_id))
+        .filter(delivery::Column::SentAt.gt(since))
+        .all(db)
+        .await?;
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for delivery in &deliveries {
+        for name in names(&delivery.title) {
+            *counts.entry(name).or_default() += 1;
+        }
+    }
+    let mut counts: Vec<(String, usize)> = counts
+        .into_iter()
+        .filter(|(name, count)| *count > 1 && !followed.contains(name))
+        .collect();
+    counts.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+    Ok(counts
+        .into_iter()
+        .take(SUGGESTIONS)
+        .map(|(name, _)| name)
+        .collect())
+}
+
+/// Runs `/follow` with `name` for `chat_id` and returns the reply. Without a name, lists the
+/// followed names and suggestions.
+pub(crate) async fn follow(
+    db: &DatabaseConnection,
+    chat_id: i64,
+    bot_id: i64,
+    name: &str,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let name = unquote(name);
+    let followed = followed(db, chat_id, bot_id).await?;
+    if name.is_empty() {
+        let mut reply = if followed.is_empty() {
+            "No followed names.".to_string()
+        } else {
+            format!("Following: {}", followed.join(", "))
+        };
+        let suggestions = suggestions(db, chat_id, &followed).await?;
+        if !suggestions.is_empty() {
+            reply.push_str(&format!("\nIn the news: {}", suggestions.join(", ")));
+        }
+        return Ok(reply);
+    }
+    if followed.iter().any(|followed| followed.eq_ignore_ascii_case(name)) {
+        return Ok(format!("Already following {}", name));
+    }
+    let new_follow = follow::ActiveModel {
+        chat_id: Set(chat_id),
+        bot_id: Set(bot_id),
+        name: Set(name.to_string()),
+        ..Default::default()
+    };
+    new_follow.insert(db).await?;
+    Ok(format!("Following {} in all the feeds of this chat", name))
+}
+
+/// Stops following `name` in `chat_id`.
+pub(crate) async fn unfollow(
+    db: &DatabaseConnection,
+    chat_id: i64,
+    bot_id: i64,
+    name: &str,
+) -> Result<u64, DbErr> {
+    let name = unquote(name);
+    let deleted = entity::prelude::Follow::delete_many()
+        .filter(follow::Column::ChatId.eq(chat_id))
+        .filter(follow::Column::BotId.eq(bot_id))
+        .filter(follow::Column::Name.eq(name))
+        .exec(db)
+        .await?;
+    Ok(deleted.rows_affected)
+}
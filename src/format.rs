@@ -0,0 +1,349 @@
+//! Formatting of feed items into Telegram messages. Large digests are grouped by topic.
+
+use rss::Item;
+use teloxide::utils::html::escape;
+
+use entity::{delivery, feed, folder};
+
+/// Maximum number of characters of a feed icon, enough for emoji sequences with modifiers.
+pub(crate) const MAX_ICON_CHARS: usize = 8;
+/// Telegram refuses messages longer than this many characters.
+const MAX_MESSAGE_CHARS: usize = 4096;
+/// Characters kept free at the end of a top digest, to close its quote and count the items
+/// left out.
+const RESERVED_CHARS: usize = 64;
+/// Titles are shortened to this many characters in digests.
+const MAX_DIGEST_TITLE_CHARS: usize = 200;
+/// Links longer than this many characters are left out of digests.
+const MAX_DIGEST_LINK_CHARS: usize = 1000;
+/// Digests of at least this many items are grouped by topic.
+const MIN_CLUSTERED_ITEMS: usize = 10;
+/// Maximum number of hashtags appended to a message.
+const MAX_HASHTAGS: usize = 3;
+const WORDS_PER_MINUTE: usize = 200;
+
+/// Returns the title of `feed`, prefixed by its icon if it has one, as plain text.
+pub(crate) fn feed_label(feed: &feed::Model) -> String {
+    match &feed.icon {
+        Some(icon) => format!("{} {}", icon, feed.title),
+        None => feed.title.clone(),
+    }
+}
+
+/// Returns a Telegram hashtag for `category`, or `None` if it has no letters.
+///
+/// Hashtags may only contain letters, digits and underscores, so every other sequence of
+/// characters becomes a single underscore.
+fn hashtag(category: &str) -> Option<String> {
+    let mut tag = String::new();
+    for c in category.chars() {
+        if c.is_alphanumeric() {
+            tag.extend(c.to_lowercase());
+        } else if !tag.is_empty() && !tag.ends_with('_') {
+            tag.push('_');
+        }
+    }
+    let tag = tag.trim_end_matches('_');
+    tag.chars()
+        .any(char::is_alphabetic)
+        .then(|| format!("#{}", tag))
+}
+
+/// Returns the first distinct hashtags derived from the categories of `item`.
+fn hashtags(item: &Item) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in item.categories().iter().filter_map(|c| hashtag(c.name())) {
+        if tags.len() == MAX_HASHTAGS {
+            break;
+        }
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+/// Returns the number of words of the full text of `item`, or of its description if the feed
+/// only publishes a summary. HTML tags are not counted.
+fn word_count(item: &Item) -> usize {
+    let text = item.content().or(item.description()).unwrap_or_default();
+    let mut count = 0;
+    let mut in_tag = false;
+    let mut in_word = false;
+    for c in text.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if in_tag => {}
+            c if c.is_whitespace() => in_word = false,
+            _ => {
+                if !in_word {
+                    count += 1;
+                }
+                in_word = true;
+            }
+        }
+    }
+    count
+}
+
+/// Returns the title and description of `item` without HTML tags.
+pub(crate) fn plain_text(item: &Item) -> String {
+    let text = format!(
+        "{} {}",
+        item.title().unwrap_or_default(),
+        item.description().unwrap_or_default()
+    );
+    let mut stripped = String::with_capacity(text.len());
+    let mut in_tag = false;
+    for c in text.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => stripped.push(c),
+            _ => {}
+        }
+    }
+    stripped
+}
+
+/// Returns the HTML message announcing `item` of `feed`, formatted by the template of its
+/// pipeline if it has one.
+pub(crate) fn item_message(feed: &feed::Model, item: &Item) -> String {
+    if let Some(template) = crate::pipeline::template(feed) {
+        return crate::pipeline::render(&template, feed, item);
+    }
+    format!("<i>{}</i>\n{}", escape(&feed_label(feed)), item_body(feed, item))
+}
+
+/// Returns the HTML message announcing `item`, without the label of `feed`.
+pub(crate) fn item_body(feed: &feed::Model, item: &Item) -> String {
+    let mut message = String::new();
+    let link = item.link().unwrap_or_default();
+    let title = escape(item.title().unwrap_or_default());
+    // Telegram can't open magnet links, so their title is not a link
+    if link.is_empty() || crate::torrents::is_magnet(link) {
+        message.push_str(&format!("{}\n", title));
+    } else {
+        let link = crate::instant_view::link(feed, link);
+        message.push_str(&format!("<a href='{}'>{}</a>\n", escape(&link), title));
+    }
+    if let Some(torrent) = crate::torrents::torrent_link(item) {
+        message.push_str(&format!("<code>{}</code>\n", escape(torrent)));
+    }
+    let tags = hashtags(item);
+    if feed.hashtags && !tags.is_empty() {
+        message.push_str(&format!("{}\n", tags.join(" ")));
+    }
+    let words = if feed.reading_time { word_count(item) } else { 0 };
+    if words > 0 {
+        let minutes = words.div_ceil(WORDS_PER_MINUTE);
+        message.push_str(&format!("📖 {} words, {} min\n", words, minutes));
+    }
+    message
+}
+
+/// A message of a digest, with the ids of the held items it lists.
+pub(crate) struct DigestPart {
+    pub(crate) message: String,
+    pub(crate) ids: Vec<i64>,
+}
+
+fn shorten(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let short: String = text.chars().take(max_chars).collect();
+    format!("{}…", short)
+}
+
+/// Returns the line listing `delivery` in a digest. Links too long to fit are left out.
+fn digest_line(delivery: &delivery::Model) -> String {
+    let title = escape(&shorten(&delivery.title, MAX_DIGEST_TITLE_CHARS));
+    if delivery.link.is_empty() || delivery.link.chars().count() > MAX_DIGEST_LINK_CHARS {
+        return format!("• {}\n", title);
+    }
+    format!("• <a href='{}'>{}</a>\n", escape(&delivery.link), title)
+}
+
+/// Appends the `lines` to `message` as long as it has at most `max_chars` characters, and
+/// returns the number of lines that didn't fit.
+fn append_lines(message: &mut String, lines: &[String], max_chars: usize) -> usize {
+    let mut chars = message.chars().count();
+    for (index, line) in lines.iter().enumerate() {
+        let line_chars = line.chars().count();
+        if chars + line_chars > max_chars {
+            return lines.len() - index;
+        }
+        message.push_str(line);
+        chars += line_chars;
+    }
+    0
+}
+
+/// Splits the digest made of `header` and `lines` into messages that Telegram accepts, the
+/// messages after the first starting with `continued`. Each line lists the held item with the
+/// id it comes with, if any.
+fn split_digest(
+    header: &str,
+    continued: &str,
+    lines: Vec<(String, Option<i64>)>,
+) -> Vec<DigestPart> {
+    let mut parts = Vec::new();
+    let mut part = DigestPart {
+        message: header.to_string(),
+        ids: Vec::new(),
+    };
+    let mut chars = header.chars().count();
+    for (line, id) in lines {
+        let line_chars = line.chars().count();
+        if chars + line_chars > MAX_MESSAGE_CHARS {
+            parts.push(part);
+            part = DigestPart {
+                message: continued.to_string(),
+                ids: Vec::new(),
+            };
+            chars = continued.chars().count();
+        }
+        part.message.push_str(&line);
+        part.ids.extend(id);
+        chars += line_chars;
+    }
+    parts.push(part);
+    parts
+}
+
+/// Returns the HTML messages listing the `held` items of `feed`.
+pub(crate) fn digest_messages(feed: &feed::Model, held: &[delivery::Model]) -> Vec<DigestPart> {
+    titled_digest(&feed_label(feed), held)
+}
+
+/// Returns the HTML messages listing the `held` items of the feeds of `folder`.
+pub(crate) fn folder_digest_messages(
+    folder: &folder::Model,
+    held: &[delivery::Model],
+) -> Vec<DigestPart> {
+    titled_digest(&format!("📁 {}", folder.name), held)
+}
+
+/// Returns the HTML message listing the first `top` of the `ranked` held items of the feeds of
+/// `folder`, followed by the others in a collapsed quote. The items that don't fit in a single
+/// message are only counted.
+pub(crate) fn top_digest_message(
+    folder: &folder::Model,
+    ranked: &[delivery::Model],
+    top: usize,
+) -> DigestPart {
+    let (best, others) = ranked.split_at(top.min(ranked.len()));
+    let mut message = format!(
+        "<i>📁 {}</i>\nTop {} of {} items:\n",
+        escape(&shorten(&folder.name, MAX_DIGEST_TITLE_CHARS)),
+        best.len(),
+        ranked.len()
+    );
+    let max_chars = MAX_MESSAGE_CHARS - RESERVED_CHARS;
+    let best_lines: Vec<String> = best.iter().map(digest_line).collect();
+    let mut left_out = append_lines(&mut message, &best_lines, max_chars);
+    if left_out > 0 {
+        left_out += others.len();
+    } else if !others.is_empty() {
+        message.push_str("<blockquote expandable>More:\n");
+        let other_lines: Vec<String> = others.iter().map(digest_line).collect();
+        left_out = append_lines(&mut message, &other_lines, max_chars);
+        message.push_str("</blockquote>");
+    }
+    if left_out > 0 {
+        message.push_str(&format!("\nand {} more", left_out));
+    }
+    DigestPart {
+        message,
+        ids: ranked.iter().map(|delivery| delivery.id).collect(),
+    }
+}
+
+/// Returns the lines of a digest of `held`, grouped by topic with the number of items of each.
+fn clustered_digest(held: &[delivery::Model]) -> Vec<(String, Option<i64>)> {
+    let titles: Vec<&str> = held.iter().map(|delivery| delivery.title.as_str()).collect();
+    let (clusters, alone) = crate::similarity::clusters(&titles);
+    let mut lines = Vec::new();
+    let groups = clusters
+        .iter()
+        .map(|cluster| (format!("{} ({})", cluster.topic, cluster.members.len()), &cluster.members))
+        .chain(std::iter::once((format!("Other ({})", alone.len()), &alone)));
+    for (topic, members) in groups {
+        if members.is_empty() {
+            continue;
+        }
+        let topic = escape(&shorten(&topic, MAX_DIGEST_TITLE_CHARS));
+        lines.push((format!("<b>{}</b>\n", topic), None));
+        for &member in members {
+            let delivery = &held[member];
+            lines.push((digest_line(delivery), Some(delivery.id)));
+        }
+    }
+    lines
+}
+
+fn titled_digest(title: &str, held: &[delivery::Model]) -> Vec<DigestPart> {
+    let title = escape(&shorten(title, MAX_DIGEST_TITLE_CHARS));
+    let header = format!("<i>{}</i>\nDigest of {} items:\n", title, held.len());
+    let continued = format!("<i>{}</i> (continued)\n", title);
+    let lines = if held.len() >= MIN_CLUSTERED_ITEMS {
+        clustered_digest(held)
+    } else {
+        held.iter()
+            .map(|delivery| (digest_line(delivery), Some(delivery.id)))
+            .collect()
+    };
+    split_digest(&header, &continued, lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the publication date and the message of each item of the feed `content`.
+    fn messages(content: &str) -> String {
+        let channel = crate::parse_channel(content.as_bytes()).unwrap();
+        let feed = crate::bench::sample_feed(channel.title());
+        let messages: Vec<String> = channel
+            .items()
+            .iter()
+            .map(|item| {
+                let published = match crate::dates::published(item) {
+                    Ok(Some(date)) => date.to_string(),
+                    Ok(None) => "undated".to_string(),
+                    Err(err) => err,
+                };
+                format!("{}\n{}", published, item_message(&feed, item).trim_end())
+            })
+            .collect();
+        messages.join("\n---\n")
+    }
+
+    #[test]
+    fn fixture_messages() {
+        for (name, content) in crate::fixtures::FEEDS {
+            insta::assert_snapshot!(name, messages(content));
+        }
+    }
+
+    #[test]
+    fn titles_are_escaped() {
+        let (_, content) = crate::fixtures::FEEDS
+            .iter()
+            .find(|(name, _)| *name == "edge_cases.xml")
+            .unwrap();
+        let messages = messages(content);
+        assert!(!messages.contains("<script>"));
+        assert!(!messages.contains("href=''"));
+    }
+
+    #[test]
+    fn labels_are_escaped() {
+        let mut feed = crate::bench::sample_feed("Q&A <live>");
+        feed.icon = Some("💬".to_string());
+        let message = item_message(&feed, &Item::default());
+        assert!(message.starts_with("<i>💬 Q&amp;A &lt;live&gt;</i>\n"));
+    }
+}
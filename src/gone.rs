@@ -0,0 +1,238 @@
+//! Replacement prompts for feeds that are gone.
+//!
+//! Feeds whose server answers 404 Not Found or 410 Gone record since when in `gone_since`, and
+//! a successful poll clears it. Once a day, feeds gone for [`GONE_DAYS`] days are reported to
+//! their chat, once per outage, with a feed found on the same site if there is one: the home
+//! page is searched for alternate RSS and Atom links, then common feed paths are tried. Buttons
+//! switch the subscription to the replacement, unsubscribe or keep the feed.
+
+use std::error::Error;
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use reqwest::{StatusCode, Url};
+use scraper::{Html, Selector};
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait,
+    QueryFilter, Set,
+};
+use teloxide::{
+    payloads::SendMessageSetters,
+    prelude::{Bot, Requester, ResponseResult},
+    types::{CallbackQuery, ChatId, InlineKeyboardButton, InlineKeyboardMarkup},
+};
+
+use crate::bots::{BotId, Bots};
+use crate::sources::normalize_link;
+use crate::undo::{self, Action};
+use entity::feed;
+
+const GONE_DAYS: i64 = 7;
+const PROMPT_PREFIX: &str = "gone:";
+/// Paths tried on the site of a gone feed when its home page links to no feed.
+const COMMON_PATHS: [&str; 7] = [
+    "/feed",
+    "/rss",
+    "/feed.xml",
+    "/rss.xml",
+    "/atom.xml",
+    "/index.xml",
+    "/feed/atom",
+];
+
+/// Returns whether `err` is a 404 Not Found or 410 Gone answer.
+pub(crate) fn is_gone(err: &(dyn Error + Send + Sync + 'static)) -> bool {
+    err.downcast_ref::<reqwest::Error>()
+        .and_then(reqwest::Error::status)
+        .is_some_and(|status| status == StatusCode::NOT_FOUND || status == StatusCode::GONE)
+}
+
+/// Returns the links of the feeds announced by the alternate links of the page `html` at `url`.
+fn alternate_links(url: &Url, html: &str) -> Vec<String> {
+    let selector = Selector::parse(
+        "link[rel~='alternate'][type='application/rss+xml'][href], \
+         link[rel~='alternate'][type='application/atom+xml'][href]",
+    )
+    .expect("valid selector");
+    Html::parse_document(html)
+        .select(&selector)
+        .filter_map(|link| link.value().attr("href"))
+        .filter_map(|href| url.join(href).ok())
+        .map(String::from)
+        .collect()
+}
+
+/// Returns a working feed on the site of `link` other than `link` itself, if there is one.
+async fn discover(link: &str) -> Option<String> {
+    let home = Url::parse(link).ok()?.join("/").ok()?;
+    let mut candidates = match crate::http::download(home.as_str()).await {
+        Ok(download) => alternate_links(&home, &String::from_utf8_lossy(&download.body)),
+        Err(_) => Vec::new(),
+    };
+    candidates.extend(
+        COMMON_PATHS
+            .iter()
+            .filter_map(|path| home.join(path).ok())
+            .map(String::from),
+    );
+    let gone = normalize_link(link);
+    for candidate in candidates {
+        if normalize_link(&candidate) == gone {
+            continue;
+        }
+        if crate::validate_feed(&candidate).await.is_ok() {
+            return Some(candidate);
+        }
+    }
+    None
+}
+
+fn prompt_keyboard(feed_id: i64, replacement: bool) -> InlineKeyboardMarkup {
+    let button = |text: &str, action: &str| {
+        let data = format!("{}{}:{}", PROMPT_PREFIX, action, feed_id);
+        InlineKeyboardButton::callback(text, data)
+    };
+    let mut buttons = Vec::new();
+    if replacement {
+        buttons.push(button("Switch", "switch"));
+    }
+    buttons.push(button("Unsubscribe", "unsubscribe"));
+    buttons.push(button("Keep", "keep"));
+    InlineKeyboardMarkup::new(vec![buttons])
+}
+
+/// Returns the feeds gone for [`GONE_DAYS`] days whose chat wasn't asked about it yet.
+async fn gone_feeds(db: &DatabaseConnection) -> Result<Vec<feed::Model>, DbErr> {
+    let since = Utc::now().naive_utc() - Duration::days(GONE_DAYS);
+    Ok(entity::prelude::Feed::find()
+        .filter(feed::Column::GoneSince.lt(since))
+        .filter(feed::Column::Paused.eq(false))
+        .all(db)
+        .await?
+        .into_iter()
+        .filter(|feed| match (feed.replacement_prompted_at, feed.gone_since) {
+            (Some(prompted_at), Some(gone_since)) => prompted_at < gone_since,
+            _ => true,
+        })
+        .collect())
+}
+
+/// Asks the chat of every feed that is gone whether to replace it.
+pub(crate) async fn prompt_replacements(bots: Bots, db: DatabaseConnection) {
+    let feeds = match gone_feeds(&db).await {
+        Ok(feeds) => feeds,
+        Err(err) => {
+            println!("Error looking for gone feeds: {:?}", err);
+            return;
+        }
+    };
+    for feed in feeds {
+        let Some(bot) = bots.get(feed.bot_id) else {
+            continue;
+        };
+        let replacement = discover(&feed.link).await;
+        let text = match &replacement {
+            Some(replacement) => format!(
+                "{} ({}) has been gone for {} days. I found another feed on the same site:\n{}\n\
+                 Do you want to switch to it?",
+                feed.title, feed.link, GONE_DAYS, replacement
+            ),
+            None => format!(
+                "{} ({}) has been gone for {} days, and I found no other feed on the same site. \
+                 Do you want to unsubscribe?",
+                feed.title, feed.link, GONE_DAYS
+            ),
+        };
+        let sent = bot
+            .send_message(ChatId(feed.chat_id), text)
+            .reply_markup(prompt_keyboard(feed.id, replacement.is_some()))
+            .await;
+        if let Err(err) = sent {
+            println!("Error sending replacement prompt: {:?}", err);
+            continue;
+        }
+        let mut prompted_feed: feed::ActiveModel = feed.into();
+        prompted_feed.replacement_link = Set(replacement);
+        prompted_feed.replacement_prompted_at = Set(Some(Utc::now().naive_utc()));
+        match prompted_feed.update(&db).await {
+            Ok(feed) => crate::registry::put(feed),
+            Err(err) => println!("Error updating feed: {:?}", err),
+        }
+    }
+}
+
+/// Moves feed `id` of `chat_id` to the replacement it was offered, and returns its new title.
+async fn switch(
+    db: &DatabaseConnection,
+    id: i64,
+    chat_id: i64,
+    bot_id: i64,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let feed = entity::prelude::Feed::find_by_id(id)
+        .filter(feed::Column::ChatId.eq(chat_id))
+        .filter(feed::Column::BotId.eq(bot_id))
+        .one(db)
+        .await?
+        .ok_or("Feed not found")?;
+    let replacement = feed.replacement_link.ok_or("No replacement was found")?;
+    if crate::moderation::is_blocked(db, &replacement).await? {
+        return Err(format!("{} is blocked", replacement).into());
+    }
+    let channel = crate::validate_feed(&replacement).await?;
+    let source = crate::sources::source_for(db, &replacement).await?;
+    // Items already published by the replacement are not sent
+    entity::prelude::Feed::update_many()
+        .col_expr(feed::Column::SourceId, Expr::value(source.id))
+        .col_expr(feed::Column::Link, Expr::value(replacement.clone()))
+        .col_expr(feed::Column::Title, Expr::value(channel.title()))
+        .col_expr(feed::Column::UpdatedAt, Expr::value(Utc::now().naive_utc()))
+        .col_expr(feed::Column::GoneSince, Expr::value(None::<NaiveDateTime>))
+        .col_expr(feed::Column::ReplacementLink, Expr::value(None::<String>))
+        .filter(feed::Column::Id.eq(id))
+        .exec(db)
+        .await?;
+    crate::registry::invalidate(id);
+    Ok(channel.title().to_string())
+}
+
+/// Returns whether the callback `data` belongs to a replacement prompt.
+pub(crate) fn is_prompt(data: &str) -> bool {
+    data.starts_with(PROMPT_PREFIX)
+}
+
+/// Handles the buttons of a replacement prompt, replacing it with the outcome.
+pub(crate) async fn handle_prompt(
+    bot: &Bot,
+    q: &CallbackQuery,
+    data: &str,
+    db: &DatabaseConnection,
+    bot_id: BotId,
+) -> ResponseResult<()> {
+    let Some(message) = &q.message else {
+        return Ok(());
+    };
+    let chat_id = message.chat.id;
+    let action = data
+        .strip_prefix(PROMPT_PREFIX)
+        .and_then(|action| action.split_once(':'))
+        .and_then(|(action, feed_id)| Some((action, feed_id.parse::<i64>().ok()?)));
+    let text = match action {
+        Some(("switch", feed_id)) => match switch(db, feed_id, chat_id.0, bot_id.0).await {
+            Ok(title) => format!("Switched to {}", title),
+            Err(error) => format!("Error: {}", error),
+        },
+        Some(("unsubscribe", feed_id)) => {
+            let deleted = async {
+                undo::record(db, chat_id.0, bot_id.0, Action::Unsubscribe, &[feed_id]).await?;
+                crate::delete_feed(db, feed_id, chat_id.0, bot_id.0).await
+            };
+            match deleted.await {
+                Ok(delete_result) => format!("Deleted {} feed", delete_result.rows_affected),
+                Err(error) => format!("Error: {}", error),
+            }
+        }
+        Some(("keep", _)) => "Keeping the feed.".to_string(),
+        _ => return Ok(()),
+    };
+    bot.edit_message_text(chat_id, message.id, text).await?;
+    Ok(())
+}
@@ -0,0 +1,69 @@
+//! Recent deliveries of a chat.
+//!
+//! Every item sent to a chat is recorded in the `delivery` table with its feed, title, link,
+//! time and message, which editing, deduplication and `/stats` read back. `/history [feed id]`
+//! lists the last [`LIMIT`] items sent to the chat, or by one of its feeds.
+
+use std::collections::HashMap;
+use std::error::Error;
+
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, QuerySelect,
+};
+
+use entity::{delivery, feed};
+
+const LIMIT: u64 = 20;
+
+/// Returns the last deliveries to `chat_id`, of feed `args` if it is a feed id.
+pub(crate) async fn history(
+    db: &DatabaseConnection,
+    chat_id: i64,
+    bot_id: i64,
+    args: &str,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let args = args.trim();
+    let mut feeds = entity::prelude::Feed::find()
+        .filter(feed::Column::ChatId.eq(chat_id))
+        .filter(feed::Column::BotId.eq(bot_id));
+    if !args.is_empty() {
+        let feed_id: i64 = args
+            .parse()
+            .map_err(|_| format!("Expected a feed id, got {}", args))?;
+        feeds = feeds.filter(feed::Column::Id.eq(feed_id));
+    }
+    let feeds: HashMap<i64, feed::Model> = feeds
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|feed| (feed.id, feed))
+        .collect();
+    if feeds.is_empty() && !args.is_empty() {
+        return Err(format!("Feed {} not found", args).into());
+    }
+    let deliveries = entity::prelude::Delivery::find()
+        .filter(delivery::Column::FeedId.is_in(feeds.keys().copied()))
+        .filter(delivery::Column::Held.eq(false))
+        .order_by_desc(delivery::Column::SentAt)
+        .limit(LIMIT)
+        .all(db)
+        .await?;
+    if deliveries.is_empty() {
+        return Ok("Nothing was sent yet.".to_string());
+    }
+    let mut lines = vec![format!("Last {} items sent:", deliveries.len())];
+    for delivery in &deliveries {
+        let label = feeds
+            .get(&delivery.feed_id)
+            .map(crate::format::feed_label)
+            .unwrap_or_default();
+        lines.push(format!(
+            "{} UTC - {} - {}\n{}",
+            delivery.sent_at.format("%Y-%m-%d %H:%M"),
+            label,
+            delivery.title,
+            delivery.link
+        ));
+    }
+    Ok(lines.join("\n"))
+}
@@ -0,0 +1,134 @@
+//! The HTTP client shared by the downloads of feeds.
+//!
+//! Feeds are requested with gzip, deflate and brotli compression, and decompressed here rather
+//! than by reqwest, so that both the bytes transferred and the size of the body are known. Large
+//! feeds served uncompressed are a real cost on small servers, and [`sources`] adds up both
+//! sizes per source for `/bandwidth`. Sources with [`crate::tls`] options are downloaded with a
+//! client of their own.
+//!
+//! [`sources`]: crate::sources
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::Read;
+use std::sync::{Mutex, OnceLock};
+
+use flate2::read::{DeflateDecoder, GzDecoder, ZlibDecoder};
+use reqwest::{
+    header::{ACCEPT_ENCODING, CONTENT_ENCODING},
+    Client, ClientBuilder, RequestBuilder, Response,
+};
+
+use crate::tls::TlsOptions;
+
+const ACCEPTED_ENCODINGS: &str = "gzip, deflate, br";
+/// Bodies that decompress to more than this many bytes are refused.
+const MAX_BODY_BYTES: u64 = 64 * 1024 * 1024;
+
+static CLIENT: OnceLock<Client> = OnceLock::new();
+/// The clients of the sources with TLS options, by options.
+static TLS_CLIENTS: Mutex<Option<HashMap<TlsOptions, Client>>> = Mutex::new(None);
+
+/// A downloaded body, with the number of bytes it took to transfer it.
+pub(crate) struct Download {
+    pub(crate) body: Vec<u8>,
+    /// 0 if the body didn't have to be downloaded, such as when it was cached.
+    pub(crate) transferred: u64,
+}
+
+impl Download {
+    /// Returns a body that was not downloaded.
+    pub(crate) fn local(body: Vec<u8>) -> Self {
+        Download {
+            body,
+            transferred: 0,
+        }
+    }
+}
+
+/// Returns a builder of clients resolving hosts with the [`crate::dns`] cache.
+fn builder() -> ClientBuilder {
+    match crate::dns::resolver() {
+        Ok(resolver) => Client::builder().dns_resolver(resolver),
+        Err(err) => {
+            println!("Error configuring DNS, using the system resolver: {:?}", err);
+            Client::builder()
+        }
+    }
+}
+
+/// Returns the shared client.
+pub(crate) fn client() -> &'static Client {
+    CLIENT.get_or_init(|| builder().build().expect("Can't build the HTTP client"))
+}
+
+/// Returns the client downloading `link`, which has TLS options of its own if its source has.
+fn client_for(link: &str) -> Result<Client, Box<dyn Error + Send + Sync>> {
+    let Some(options) = crate::tls::options(link) else {
+        return Ok(client().clone());
+    };
+    let mut clients = TLS_CLIENTS.lock().expect("TLS clients lock poisoned");
+    let clients = clients.get_or_insert_with(HashMap::new);
+    if let Some(client) = clients.get(&options) {
+        return Ok(client.clone());
+    }
+    let client = options.configure(builder())?.build()?;
+    clients.insert(options, client.clone());
+    Ok(client)
+}
+
+/// Drops the clients of the sources with TLS options, after the options changed.
+pub(crate) fn forget_clients() {
+    *TLS_CLIENTS.lock().expect("TLS clients lock poisoned") = None;
+}
+
+/// Returns a GET request of `link` accepting compressed bodies.
+pub(crate) fn get(link: &str) -> Result<RequestBuilder, Box<dyn Error + Send + Sync>> {
+    Ok(client_for(link)?
+        .get(link)
+        .header(ACCEPT_ENCODING, ACCEPTED_ENCODINGS))
+}
+
+/// Reads at most [`MAX_BODY_BYTES`] from `reader`.
+fn read_limited(reader: impl Read) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+    let mut body = Vec::new();
+    reader.take(MAX_BODY_BYTES + 1).read_to_end(&mut body)?;
+    if body.len() as u64 > MAX_BODY_BYTES {
+        return Err(format!("The body is larger than {} bytes", MAX_BODY_BYTES).into());
+    }
+    Ok(body)
+}
+
+/// Decompresses `bytes` sent with the `Content-Encoding` `encoding`.
+fn decode(encoding: &str, bytes: &[u8]) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+    match encoding.trim().to_lowercase().as_str() {
+        "" | "identity" => Ok(bytes.to_vec()),
+        "gzip" | "x-gzip" => read_limited(GzDecoder::new(bytes)),
+        // Some servers send raw deflate data instead of the zlib format of the standard
+        "deflate" => read_limited(ZlibDecoder::new(bytes))
+            .or_else(|_| read_limited(DeflateDecoder::new(bytes))),
+        "br" => read_limited(brotli::Decompressor::new(bytes, 4096)),
+        other => Err(format!("Unsupported content encoding {}", other).into()),
+    }
+}
+
+/// Reads and decompresses the body of `response`, failing on error statuses.
+pub(crate) async fn read(response: Response) -> Result<Download, Box<dyn Error + Send + Sync>> {
+    let response = response.error_for_status()?;
+    let encoding = response
+        .headers()
+        .get(CONTENT_ENCODING)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    let bytes = response.bytes().await?;
+    Ok(Download {
+        body: decode(&encoding, &bytes)?,
+        transferred: bytes.len() as u64,
+    })
+}
+
+/// Downloads the body at `link`.
+pub(crate) async fn download(link: &str) -> Result<Download, Box<dyn Error + Send + Sync>> {
+    read(get(link)?.send().await?).await
+}
@@ -0,0 +1,120 @@
+//! Inspection of a chat by the admin chat.
+//!
+//! `/inspect <chat id>` shows the settings of a chat, its feeds with their errors and its latest
+//! deliveries, so that operators can help users whose feed is broken without querying the
+//! database. Deliveries are listed by title only, and secrets such as the webhook secret and the
+//! shortener key are left out.
+
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder, QuerySelect,
+};
+
+use entity::{chat, delivery, feed};
+
+/// Number of deliveries listed.
+const DELIVERIES: u64 = 20;
+/// Errors are shortened to this many characters.
+const MAX_ERROR_CHARS: usize = 200;
+/// The report is cut to fit in a Telegram message.
+const MAX_CHARS: usize = 4000;
+
+fn shorten(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let short: String = text.chars().take(max_chars).collect();
+    format!("{}…", short)
+}
+
+fn format_chat(chat: &chat::Model) -> String {
+    let mut lines = vec![
+        format!("Chat {} on bot {}, since {}", chat.id, chat.bot_id, chat.created_at),
+        format!("Tier: {}", chat.subscription_tier),
+    ];
+    if let Some(until) = chat.premium_until {
+        lines.push(format!("Premium until {}", until));
+    }
+    lines.push(format!(
+        "NSFW: {}, weekly summary: {}, click tracking: {}, breaking news: {}",
+        chat.nsfw_mode, chat.weekly_summary, chat.track_clicks, chat.breaking
+    ));
+    if let Some(url) = &chat.webhook_url {
+        lines.push(format!("Webhook: {}", url));
+    }
+    if let (Some(shortener), Some(url)) = (&chat.shortener, &chat.shortener_url) {
+        lines.push(format!("Shortener: {} {}", shortener, url));
+    }
+    lines.join("\n")
+}
+
+fn format_feed(feed: &feed::Model) -> String {
+    let mut line = format!("{} - {} ({})", feed.id, feed.title, feed.link);
+    if feed.paused {
+        line.push_str(" paused");
+    }
+    if let Some(until) = feed.muted_until {
+        line.push_str(&format!(" muted until {}", until));
+    }
+    let checked = feed
+        .last_checked_at
+        .map_or("never".to_string(), |checked| checked.to_string());
+    line.push_str(&format!(
+        "\n  checked {}, {} failures in {} checks",
+        checked, feed.failure_count, feed.check_count
+    ));
+    if let Some(error) = &feed.last_error {
+        line.push_str(&format!(
+            "\n  {} errors in a row: {}",
+            feed.error_count,
+            shorten(error, MAX_ERROR_CHARS)
+        ));
+    }
+    line
+}
+
+fn format_delivery(delivery: &delivery::Model) -> String {
+    let held = if delivery.held { " (held)" } else { "" };
+    format!(
+        "{} feed {}{}: {}",
+        delivery.sent_at.format("%Y-%m-%d %H:%M"),
+        delivery.feed_id,
+        held,
+        delivery.title
+    )
+}
+
+/// Returns the report of the settings, feeds and latest deliveries of `chat_id`, on every bot.
+pub(crate) async fn inspect(db: &DatabaseConnection, chat_id: i64) -> Result<String, DbErr> {
+    let chats = entity::prelude::Chat::find()
+        .filter(chat::Column::Id.eq(chat_id))
+        .all(db)
+        .await?;
+    if chats.is_empty() {
+        return Ok(format!("No chat {}", chat_id));
+    }
+    let mut sections: Vec<String> = chats.iter().map(format_chat).collect();
+    let feeds = entity::prelude::Feed::find()
+        .filter(feed::Column::ChatId.eq(chat_id))
+        .order_by_asc(feed::Column::Id)
+        .all(db)
+        .await?;
+    sections.push(if feeds.is_empty() {
+        "No feeds.".to_string()
+    } else {
+        let feeds: Vec<String> = feeds.iter().map(format_feed).collect();
+        format!("Feeds:\n{}", feeds.join("\n"))
+    });
+    let deliveries = entity::prelude::Delivery::find()
+        .filter(delivery::Column::ChatId.eq(chat_id))
+        .order_by_desc(delivery::Column::SentAt)
+        .limit(DELIVERIES)
+        .all(db)
+        .await?;
+    sections.push(if deliveries.is_empty() {
+        "No deliveries.".to_string()
+    } else {
+        let deliveries: Vec<String> = deliveries.iter().map(format_delivery).collect();
+        format!("Latest deliveries:\n{}", deliveries.join("\n"))
+    });
+    Ok(shorten(&sections.join("\n\n"), MAX_CHARS))
+}
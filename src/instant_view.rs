@@ -0,0 +1,61 @@
+//! Instant View links.
+//!
+//! `/instantview <feed id> <rhash>` sends the links of a feed as
+//! `https://t.me/iv?url=<link>&rhash=<rhash>`, so that they open in Telegram's Instant View with
+//! the template `rhash`. Operators can list templates known to work for whole domains in the
+//! `IV_TEMPLATES` environment variable, as `example.com=<rhash>,other.org=<rhash>`, which apply to
+//! every feed without a template of its own.
+
+use std::env;
+use std::error::Error;
+
+use sea_orm::{DatabaseConnection, UpdateResult};
+
+use entity::feed;
+
+const IV_URL: &str = "https://t.me/iv";
+
+/// Returns the template of the domain of `link` listed in `IV_TEMPLATES`.
+fn known_template(link: &str) -> Option<String> {
+    let templates = env::var("IV_TEMPLATES").ok()?;
+    let url = reqwest::Url::parse(link).ok()?;
+    let host = url.host_str()?;
+    templates.split(',').find_map(|template| {
+        let (domain, rhash) = template.trim().split_once('=')?;
+        let matches = host == domain || host.ends_with(&format!(".{}", domain));
+        Some(rhash.to_string()).filter(|_| matches)
+    })
+}
+
+/// Returns `link` of an item of `feed`, opened in Instant View if there is a template for it.
+pub(crate) fn link(feed: &feed::Model, link: &str) -> String {
+    let rhash = feed.iv_rhash.clone().or_else(|| known_template(link));
+    match rhash {
+        Some(rhash) if !link.is_empty() => format!(
+            "{}?url={}&rhash={}",
+            IV_URL,
+            urlencoding::encode(link),
+            rhash
+        ),
+        _ => link.to_string(),
+    }
+}
+
+/// Sets the Instant View template of feed `id` to `rhash`. `off` removes it.
+pub(crate) async fn set_rhash(
+    db: &DatabaseConnection,
+    id: i64,
+    chat_id: i64,
+    bot_id: i64,
+    rhash: &str,
+) -> Result<UpdateResult, Box<dyn Error + Send + Sync>> {
+    let rhash = if rhash.eq_ignore_ascii_case("off") {
+        None
+    } else if !rhash.is_empty() && rhash.chars().all(|c| c.is_ascii_alphanumeric()) {
+        Some(rhash.to_string())
+    } else {
+        return Err(format!("Expected the rhash of a template, got {}", rhash).into());
+    };
+    let column = feed::Column::IvRhash;
+    crate::set_managed_feed_option(db, id, chat_id, bot_id, column, rhash).await
+}
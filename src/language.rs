@@ -0,0 +1,134 @@
+//! Per-feed language filters.
+//!
+//! Multilingual aggregators mix items in several languages. A feed with a language filter such
+//! as `en,de` only sends the items whose title and description are detected to be in one of
+//! those languages. Items whose language can't be detected reliably are always sent.
+
+use std::error::Error;
+
+use rss::Item;
+use sea_orm::{DatabaseConnection, UpdateResult};
+use whatlang::Lang;
+
+use entity::feed;
+
+/// ISO 639-1 codes of the languages that can be detected, with their ISO 639-3 codes.
+const CODES: [(&str, &str); 69] = [
+    ("af", "afr"),
+    ("ak", "aka"),
+    ("am", "amh"),
+    ("ar", "ara"),
+    ("az", "aze"),
+    ("be", "bel"),
+    ("bg", "bul"),
+    ("bn", "ben"),
+    ("ca", "cat"),
+    ("cs", "ces"),
+    ("da", "dan"),
+    ("de", "deu"),
+    ("el", "ell"),
+    ("en", "eng"),
+    ("eo", "epo"),
+    ("es", "spa"),
+    ("et", "est"),
+    ("fa", "pes"),
+    ("fi", "fin"),
+    ("fr", "fra"),
+    ("gu", "guj"),
+    ("he", "heb"),
+    ("hi", "hin"),
+    ("hr", "hrv"),
+    ("hu", "hun"),
+    ("hy", "hye"),
+    ("id", "ind"),
+    ("it", "ita"),
+    ("ja", "jpn"),
+    ("jv", "jav"),
+    ("ka", "kat"),
+    ("km", "khm"),
+    ("kn", "kan"),
+    ("ko", "kor"),
+    ("la", "lat"),
+    ("lt", "lit"),
+    ("lv", "lav"),
+    ("mk", "mkd"),
+    ("ml", "mal"),
+    ("mr", "mar"),
+    ("my", "mya"),
+    ("nb", "nob"),
+    ("ne", "nep"),
+    ("nl", "nld"),
+    ("or", "ori"),
+    ("pa", "pan"),
+    ("pl", "pol"),
+    ("pt", "por"),
+    ("ro", "ron"),
+    ("ru", "rus"),
+    ("si", "sin"),
+    ("sk", "slk"),
+    ("sl", "slv"),
+    ("sn", "sna"),
+    ("sr", "srp"),
+    ("sv", "swe"),
+    ("ta", "tam"),
+    ("te", "tel"),
+    ("th", "tha"),
+    ("tk", "tuk"),
+    ("tl", "tgl"),
+    ("tr", "tur"),
+    ("uk", "ukr"),
+    ("ur", "urd"),
+    ("uz", "uzb"),
+    ("vi", "vie"),
+    ("yi", "yid"),
+    ("zh", "cmn"),
+    ("zu", "zul"),
+];
+
+/// Returns the language with the ISO 639-1 or 639-3 `code`.
+fn language(code: &str) -> Option<Lang> {
+    let code = code.trim().to_lowercase();
+    let code = CODES
+        .iter()
+        .find(|(short, _)| *short == code)
+        .map_or(code.as_str(), |(_, long)| *long);
+    Lang::from_code(code)
+}
+
+/// Returns whether `item` is in one of the languages allowed by `feed`.
+pub(crate) fn allows_item(feed: &feed::Model, item: &Item) -> bool {
+    let Some(filter) = &feed.language_filter else {
+        return true;
+    };
+    let detected = whatlang::detect(&crate::format::plain_text(item));
+    let Some(info) = detected.filter(|info| info.is_reliable()) else {
+        return true;
+    };
+    filter
+        .split(',')
+        .filter_map(language)
+        .any(|allowed| allowed == info.lang())
+}
+
+/// Stores the comma separated `languages` allowed for feed `id`. `off` or no languages remove
+/// the filter.
+pub(crate) async fn set_language_filter(
+    db: &DatabaseConnection,
+    id: i64,
+    chat_id: i64,
+    bot_id: i64,
+    languages: &str,
+) -> Result<UpdateResult, Box<dyn Error + Send + Sync>> {
+    let languages = languages.trim().to_lowercase();
+    let filter = if languages.is_empty() || languages == "off" {
+        None
+    } else {
+        let codes: Vec<&str> = languages.split(',').map(str::trim).collect();
+        if let Some(unknown) = codes.iter().find(|code| language(code).is_none()) {
+            return Err(format!("Unknown language {}", unknown).into());
+        }
+        Some(codes.join(","))
+    };
+    let column = feed::Column::LanguageFilter;
+    crate::set_managed_feed_option(db, id, chat_id, bot_id, column, filter).await
+}
@@ -0,0 +1,2923 @@
+use std::collections::HashMap;
+use std::env;
+use std::error::Error;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::time::Instant;
+
+use chrono::{Utc, Weekday};
+use rss::validation::Validate;
+use rss::{Channel, Item};
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ActiveValue, ColumnTrait, Database, DatabaseConnection,
+    DbErr, DeleteResult, EntityTrait, PaginatorTrait, QueryFilter, Set, UpdateResult, Value,
+};
+use teloxide::{
+    dispatching::{HandlerExt, UpdateFilterExt},
+    dptree,
+    payloads::{AnswerCallbackQuerySetters, SendMessageSetters, SendPhotoSetters},
+    prelude::{Bot, Dispatcher, LoggingErrorHandler, Requester, ResponseResult, Update},
+    types::{CallbackQuery, ChatId, InputFile, Me, Message, MessageId, ParseMode},
+    utils::command::{BotCommands, ParseError},
+};
+use tokio::task::JoinSet;
+use tokio_schedule::{every, Job};
+use urlencoding::encode;
+
+use entity::{chat, delivery, feed, feed_source};
+use migration::{Migrator, MigratorTrait};
+
+use admin::AdminChat;
+use bots::{BotId, Bots};
+use catchup::CatchUp;
+use flags::Flag;
+use http::Download;
+use nsfw::NsfwMode;
+use pins::PinMode;
+use priority::Priority;
+use quota::Usage;
+use signups::Event;
+
+mod admin;
+mod albums;
+mod aliases;
+mod api;
+mod archive;
+mod backup;
+pub mod bench;
+mod bots;
+mod bridges;
+mod bulk;
+mod cache;
+mod bursts;
+mod calendar;
+mod catchup;
+mod chaos;
+mod churn;
+mod clicks;
+mod comics;
+mod config;
+mod content_hash;
+mod dashboard;
+mod dates;
+mod dedup;
+mod defaults;
+mod destinations;
+mod dns;
+mod edits;
+mod fediverse;
+mod filters;
+mod fixtures;
+mod flags;
+mod folders;
+mod follows;
+mod format;
+mod gone;
+mod history;
+mod http;
+mod inspect;
+mod instant_view;
+mod language;
+mod links;
+mod maintenance;
+mod moderation;
+mod mute;
+mod newsletters;
+mod noise;
+mod nsfw;
+mod notify;
+mod papers;
+mod partial;
+mod pins;
+mod pipeline;
+mod premium;
+mod priority;
+mod quota;
+mod ranking;
+mod rate;
+mod reconcile;
+mod recordings;
+mod registry;
+mod releases;
+mod schedule;
+mod schema;
+mod secrets;
+mod severity;
+mod short_ids;
+mod shorteners;
+mod signups;
+mod similarity;
+mod sitemaps;
+mod snooze;
+mod spam;
+mod sources;
+mod summary;
+mod syndication;
+mod thumbnails;
+mod tls;
+mod torrents;
+mod undo;
+mod user_view;
+mod videos;
+mod watches;
+mod webhooks;
+mod window;
+
+/// Placeholder for the `DB_PASSWORD` secret in `DATABASE_URL`.
+const PASSWORD_PLACEHOLDER: &str = "{password}";
+/// Feeds of at least this many bytes are parsed on a blocking thread.
+const BLOCKING_PARSE_BYTES: usize = 256 * 1024;
+/// Telegram refuses captions longer than this many characters.
+const MAX_CAPTION_CHARS: usize = 1024;
+
+async fn db_password() -> String {
+    let db_password = secrets::get("DB_PASSWORD")
+        .await
+        .unwrap_or_else(|err| panic!("{}", err));
+    // Encode the password to escape special characters
+    encode(&db_password).into_owned()
+}
+
+/// Returns the URL of the database.
+///
+/// `DATABASE_URL` is used as it is, except that a `{password}` placeholder is replaced by the
+/// `DB_PASSWORD` secret. Otherwise the URL is composed from `DB_USER`, `DB_PASSWORD`, `DB_HOST`,
+/// `DB_PORT` (5432 by default) and `DB_NAME`. A `DB_HOST` starting with `/` is the directory of
+/// a Unix socket.
+async fn db_url() -> String {
+    if let Ok(db_url) = env::var("DATABASE_URL") {
+        if db_url.contains(PASSWORD_PLACEHOLDER) {
+            return db_url.replace(PASSWORD_PLACEHOLDER, &db_password().await);
+        }
+        return db_url;
+    }
+    let db_user = env::var("DB_USER").expect("DB_USER environment variable not set");
+    let db_password = db_password().await;
+    let db_host = env::var("DB_HOST").expect("DB_HOST environment variable not set");
+    let db_port = env::var("DB_PORT").unwrap_or_else(|_| "5432".to_string());
+    let db_port: u16 = db_port.parse().expect("DB_PORT is not a port number");
+    let db_name = env::var("DB_NAME").expect("DB_NAME environment variable not set");
+    if db_host.starts_with('/') {
+        format!(
+            "postgres://{}:{}@localhost:{}/{}?host={}",
+            &db_user,
+            &db_password,
+            db_port,
+            &db_name,
+            encode(&db_host)
+        )
+    } else {
+        format!(
+            "postgres://{}:{}@{}:{}/{}",
+            &db_user, &db_password, &db_host, db_port, &db_name
+        )
+    }
+}
+
+/// TLS parameters of the connection and the environment variables setting them.
+const TLS_OPTIONS: [(&str, &str); 4] = [
+    ("sslmode", "DB_SSLMODE"),
+    ("sslrootcert", "DB_SSLROOTCERT"),
+    ("sslcert", "DB_SSLCERT"),
+    ("sslkey", "DB_SSLKEY"),
+];
+
+/// Adds the TLS parameters set in the environment to `db_url`, e.g. `DB_SSLMODE=verify-full`
+/// and `DB_SSLROOTCERT=/run/secrets/db_ca.pem` for managed databases that require TLS.
+fn with_tls_options(mut db_url: String) -> String {
+    for (param, var) in TLS_OPTIONS {
+        if let Ok(value) = env::var(var) {
+            let separator = if db_url.contains('?') { '&' } else { '?' };
+            db_url.push_str(&format!("{}{}={}", separator, param, encode(&value)));
+        }
+    }
+    db_url
+}
+
+async fn db_connect() -> Result<DatabaseConnection, DbErr> {
+    let db_url = with_tls_options(db_url().await);
+    let db = Database::connect(&db_url).await?;
+    Ok(db)
+}
+
+/// Runs the command given on the command line, or else the bots.
+pub async fn run() {
+    pretty_env_logger::init();
+
+    // Run a benchmark, which needs no database, if asked to
+    let args: Vec<String> = env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("bench") => {
+            let items = args.get(2).map_or(bench::DEFAULT_ITEMS, |items| {
+                items.parse().expect("Not a number")
+            });
+            bench::run(items).expect("Benchmark failed");
+            return;
+        }
+        Some("loadtest") => {
+            let feeds = args.get(2).map_or(bench::DEFAULT_FEEDS, |feeds| {
+                feeds.parse().expect("Not a number")
+            });
+            let concurrency = args
+                .get(3)
+                .map_or(bench::DEFAULT_CONCURRENCY, |concurrency| {
+                    concurrency.parse().expect("Not a number")
+                });
+            bench::load_test(feeds, concurrency)
+                .await
+                .expect("Load test failed");
+            return;
+        }
+        _ => {}
+    }
+
+    // Connect to database
+    log::info!("Connecting to database...");
+    let db = db_connect().await.expect("Can't connect to database");
+    assert!(db.ping().await.is_ok());
+
+    // List the new migrations without applying them if asked to
+    if args.get(1).map(String::as_str) == Some("migrate")
+        && args.get(2).map(String::as_str) == Some("--dry-run")
+    {
+        let pending = schema::pending_migrations(&db).await;
+        let pending = pending.expect("Can't read the applied migrations");
+        if pending.is_empty() {
+            println!("No migrations to apply");
+        }
+        for migration in pending {
+            println!("Would apply {}", migration);
+        }
+        return;
+    }
+
+    // Apply any new migrations to the database, and check that it matches the entities
+    Migrator::up(&db, None).await.expect("Migrations failed");
+    schema::check(&db)
+        .await
+        .unwrap_or_else(|err| panic!("{}", err));
+
+    // Run a maintenance command instead of the bots if one was given
+    match args.get(1).map(String::as_str) {
+        Some("backup") => {
+            let path = args.get(2).map(String::as_str);
+            backup::backup(&db, path).await.expect("Backup failed");
+            return;
+        }
+        Some("fixtures") => {
+            let addr = args.get(2).map_or(fixtures::DEFAULT_ADDRESS, String::as_str);
+            let addr: SocketAddr = addr.parse().expect("Not a valid address");
+            fixtures::serve(addr).await.expect("Fixture server failed");
+            return;
+        }
+        Some("seed") => {
+            let server = args.get(2).map_or(fixtures::DEFAULT_ADDRESS, String::as_str);
+            let copies = args.get(3).map_or(1, |copies| copies.parse().expect("Not a number"));
+            fixtures::seed(&db, server, copies).await.expect("Seeding failed");
+            return;
+        }
+        Some("replay") => {
+            let usage = "Usage: multitude_bot replay <feed id> [file]";
+            let feed_id: i64 = args.get(2).and_then(|id| id.parse().ok()).expect(usage);
+            let path = args.get(3).map(String::as_str);
+            recordings::replay(&db, feed_id, path).await.expect("Replay failed");
+            return;
+        }
+        Some("migrate") => return,
+        Some("restore") => {
+            let path = args.get(2).expect("Usage: multitude_bot restore <file>");
+            backup::restore(&db, path).await.expect("Restore failed");
+            log::info!("Restored {}", path);
+            return;
+        }
+        Some(command) => panic!("Unknown command {}", command),
+        None => {}
+    }
+
+    // Read the configuration file, and again on SIGHUP
+    let loaded = config::reload(&db).await;
+    log::info!("{}", loaded.unwrap_or_else(|err| panic!("{}", err)));
+    tokio::spawn(config::reload_on_hangup(db.clone()));
+
+    // Cache feed bodies in Redis if a server was configured
+    if let Ok(redis_url) = env::var("REDIS_URL") {
+        log::info!("Connecting to Redis...");
+        cache::connect(&redis_url)
+            .await
+            .expect("Can't connect to Redis");
+    }
+
+    // Relax the TLS checks of the sources configured with /tls
+    tls::load(&db).await.expect("Can't load the TLS options");
+
+    // Start the bots, one for every line of the token file
+    log::info!("Starting command bots...");
+    let teloxide_tokens = secrets::get("TELOXIDE_TOKEN")
+        .await
+        .unwrap_or_else(|err| panic!("{}", err));
+    let bots = Bots::from_tokens(&teloxide_tokens);
+    claim_unassigned_chats(&db, bots.default_id())
+        .await
+        .expect("Can't assign chats to the default bot");
+
+    // Fix inconsistencies before polling, reporting them to the admin chat if there is one
+    let admin = AdminChat::from_env();
+    reconcile::run(&db, bots.default_bot(), admin.0).await;
+
+    // Check the feeds whose last check was interrupted by a crash right away
+    match schedule::resume(&db).await {
+        Ok(0) => {}
+        Ok(resumed) => println!("Resuming {} feeds interrupted by a crash", resumed),
+        Err(err) => println!("Error resuming interrupted feeds: {:?}", err),
+    }
+
+    // Check the feeds that are due, then wait until the next one is or feeds are made due
+    let bots_clone = bots.clone();
+    let db_clone = db.clone();
+    tokio::spawn(async move {
+        loop {
+            check_for_updates(bots_clone.clone(), db_clone.clone()).await;
+            schedule::sleep().await;
+        }
+    });
+
+    // Offer replacements of the feeds that are gone once a day
+    let bots_clone = bots.clone();
+    let db_clone = db.clone();
+    let every_day = every(1)
+        .day()
+        .at(11, 0, 0)
+        .in_timezone(&Utc)
+        .perform(move || {
+            let job = gone::prompt_replacements(bots_clone.clone(), db_clone.clone());
+            unless_maintenance("the replacement prompts", job)
+        });
+    tokio::spawn(every_day);
+
+    // Move the subscriptions to links with an alias once a day
+    let bots_clone = bots.clone();
+    let db_clone = db.clone();
+    let every_day = every(1)
+        .day()
+        .at(4, 0, 0)
+        .in_timezone(&Utc)
+        .perform(move || {
+            let job = aliases::sweep_daily(bots_clone.clone(), db_clone.clone());
+            unless_maintenance("the sweep of the aliases", job)
+        });
+    tokio::spawn(every_day);
+
+    // Suggest muting, digesting or pausing noisy feeds once a day
+    let bots_clone = bots.clone();
+    let db_clone = db.clone();
+    let every_day = every(1)
+        .day()
+        .at(10, 0, 0)
+        .in_timezone(&Utc)
+        .perform(move || {
+            let job = noise::suggest_mutes(bots_clone.clone(), db_clone.clone());
+            unless_maintenance("the noise suggestions", job)
+        });
+    tokio::spawn(every_day);
+
+    // Report the new and deleted accounts of the day to the admin chat
+    let bots_clone = bots.clone();
+    let db_clone = db.clone();
+    let every_day = every(1)
+        .day()
+        .at(9, 0, 0)
+        .in_timezone(&Utc)
+        .perform(move || {
+            let job = signups::send_daily_counts(bots_clone.clone(), db_clone.clone(), admin);
+            unless_maintenance("the daily counts", job)
+        });
+    tokio::spawn(every_day);
+
+    // Send the weekly summaries on Monday mornings
+    let bots_clone = bots.clone();
+    let db_clone = db.clone();
+    let every_week = every(1)
+        .week()
+        .on(Weekday::Mon)
+        .at(10, 0, 0)
+        .in_timezone(&Utc)
+        .perform(move || {
+            let job = summary::send_summaries(bots_clone.clone(), db_clone.clone());
+            unless_maintenance("the weekly summaries", job)
+        });
+    tokio::spawn(every_week);
+
+    // Poll feeds on request of other systems
+    let db_clone = db.clone();
+    tokio::spawn(async move {
+        if let Err(err) = notify::listen(db_clone).await {
+            log::error!("Stopped listening for poll requests: {}", err);
+        }
+    });
+
+    // Serve the management API if an address to listen on was configured
+    if let Ok(api_addr) = env::var("API_ADDR") {
+        let api_addr: SocketAddr = api_addr.parse().expect("API_ADDR is not a valid address");
+        let api_token = secrets::get("API_TOKEN")
+            .await
+            .unwrap_or_else(|err| panic!("{}", err));
+        let api = api::serve(api_addr, api_token, db.clone(), bots.clone());
+        tokio::spawn(async move {
+            if let Err(err) = api.await {
+                log::error!("Management API stopped: {}", err);
+            }
+        });
+    }
+
+    let handler = dptree::entry()
+        .branch(
+            Update::filter_message()
+                .filter(|msg: Message| msg.successful_payment().is_some())
+                .endpoint(premium::process_payment),
+        )
+        .branch(Update::filter_pre_checkout_query().endpoint(premium::process_pre_checkout))
+        .branch(
+            // Filter messages from users who are not in the DB "logged out"
+            Update::filter_message()
+                .filter_async(is_not_subscribed)
+                .branch(
+                    Update::filter_message()
+                        .filter_command::<LoggedOutCommand>()
+                        .endpoint(process_logged_out_command),
+                )
+                .branch(dptree::entry().endpoint(ask_to_subscribe)),
+        )
+        .branch(
+            Update::filter_message()
+                .filter_map_async(parse_command)
+                .endpoint(process_command),
+        )
+        .branch(Update::filter_callback_query().endpoint(process_callback))
+        .branch(
+            // Handle other messages or actions here
+            dptree::filter(|msg: Message| msg.chat.is_group() || msg.chat.is_supergroup())
+                .endpoint(noop),
+        );
+
+    let mut dispatchers = JoinSet::new();
+    for (bot_id, bot) in bots.iter() {
+        let mut dispatcher = Dispatcher::builder(bot.clone(), handler.clone())
+            .dependencies(dptree::deps![db.clone(), bot_id, admin, bots.clone()])
+            .default_handler(|upd| async move {
+                log::warn!("Unhandled update: {:?}", upd);
+            })
+            .error_handler(LoggingErrorHandler::with_custom_text(
+                "An error has occurred in the dispatcher",
+            ))
+            .enable_ctrlc_handler()
+            .build();
+        dispatchers.spawn(async move { dispatcher.dispatch().await });
+    }
+    while dispatchers.join_next().await.is_some() {}
+}
+
+/// Assigns the chats created before multi-bot support, which have `bot_id` 0, to `bot_id`.
+///
+/// Their feeds follow through the cascading foreign key.
+async fn claim_unassigned_chats(
+    db: &DatabaseConnection,
+    bot_id: i64,
+) -> Result<UpdateResult, DbErr> {
+    entity::prelude::Chat::update_many()
+        .col_expr(chat::Column::BotId, Expr::value(bot_id))
+        .filter(chat::Column::BotId.eq(0))
+        .exec(db)
+        .await
+}
+
+/// Periodically checks for updates in RSS feeds and sends messages for new items.
+///
+/// This function takes the configured `Bots` and a database connection `DatabaseConnection`
+/// to fetch and process RSS feeds for updates. Every source is fetched once, and the parsed
+/// channel is handed to [`check_channel`] for each of its feeds together with the bot its chat
+/// talks to.
+/// If any errors occur during the process, they are logged to the console.
+///
+/// # Arguments
+///
+/// * `bots` - The `Bots` used for sending messages.
+/// * `db` - A `DatabaseConnection` for fetching feed and chat information.
+///
+/// # Example
+///
+/// ```ignore
+/// check_for_updates(bots, db).await;
+/// ```
+async fn check_for_updates(bots: Bots, db: DatabaseConnection) {
+    let started = Instant::now();
+    if maintenance::is_on() {
+        println!("Skipping polling during maintenance");
+        return;
+    }
+    let feeds = registry::due_feeds(&db, Utc::now().naive_utc(), schedule::BATCH).await;
+    if let Err(err) = feeds {
+        println!("Error fetching feeds: {:?}", err);
+        return;
+    }
+
+    let feeds = feeds.unwrap();
+    if let Err(err) = schedule::start(&db, &feeds).await {
+        println!("Error recording the feeds being checked: {:?}", err);
+    }
+    let due = feeds.len();
+    if due > 0 {
+        println!("Checking {} feeds for updates", due);
+    }
+    let sources = entity::prelude::FeedSource::find()
+        .filter(feed_source::Column::Id.is_in(feeds.iter().map(|feed| feed.source_id)))
+        .all(&db)
+        .await;
+    let sources = match sources {
+        Ok(sources) => sources,
+        Err(err) => {
+            println!("Error fetching sources: {:?}", err);
+            return;
+        }
+    };
+    let mut by_source: HashMap<i64, Vec<feed::Model>> = HashMap::new();
+    for feed in feeds {
+        by_source.entry(feed.source_id).or_default().push(feed);
+    }
+    // Up to `concurrency` sources are fetched and polled at the same time
+    let concurrency = config::get().concurrency;
+    let mut polls = JoinSet::new();
+    for source in sources {
+        let Some(feeds) = by_source.remove(&source.id) else {
+            continue;
+        };
+        if polls.len() >= concurrency {
+            polls.join_next().await;
+        }
+        polls.spawn(check_source(bots.clone(), db.clone(), source, feeds));
+    }
+    while polls.join_next().await.is_some() {}
+    rate::send_digests(&bots, &db).await;
+    snooze::resend_due(&bots, &db).await;
+    if due > 0 {
+        println!("Checked for updates in {:.1?}", started.elapsed());
+    }
+}
+
+/// Fetches `source` once and polls its `feeds` that are within their download quota.
+async fn check_source(
+    bots: Bots,
+    db: DatabaseConnection,
+    source: feed_source::Model,
+    feeds: Vec<feed::Model>,
+) {
+    let mut within_quota = Vec::new();
+    for feed in feeds {
+        if quota::is_exceeded(&db, feed.chat_id, feed.bot_id, Usage::Bytes).await {
+            println!("Skipping feed {} over its download quota", feed.id);
+            schedule::postpone(&db, feed).await;
+        } else {
+            within_quota.push(feed);
+        }
+    }
+    if within_quota.is_empty() {
+        return;
+    }
+    let download = fetch(&db, &source.link).await;
+    if let Ok(download) = &download {
+        let bytes = (download.body.len() / within_quota.len()) as i64;
+        for feed in &within_quota {
+            if let Some(bot) = bots.get(feed.bot_id) {
+                quota::use_quota(bot, &db, feed.chat_id, feed.bot_id, Usage::Bytes, bytes).await;
+            }
+        }
+        sources::record_transfer(&db, source.id, download).await;
+    }
+    // Calendars list different reminders over time from the same body
+    let body_hash = match &download {
+        Ok(download) if !calendar::is_calendar(&download.body) => {
+            Some(content_hash::body_hash(&download.body))
+        }
+        _ => None,
+    };
+    let channel = match download {
+        Ok(download) => partial::read_channel(&db, download.body, &within_quota).await,
+        Err(err) => Err(err),
+    };
+    let error = channel.as_ref().err().map(|err| err.to_string());
+    sources::record_fetch(&db, source.id, error).await;
+    for feed in within_quota {
+        match bots.get(feed.bot_id) {
+            Some(bot) => check_channel(bot, &db, feed, &channel, body_hash.as_deref()).await,
+            None => {
+                println!("No bot configured for feed {}", feed.id);
+                schedule::postpone(&db, feed).await;
+            }
+        }
+    }
+}
+
+/// Returns the body of the feed at `link`. Newsletter, sitemap and watch feeds are built from
+/// the database, checking the sitemap or the watched page first. Downloaded bodies are recorded
+/// if `RECORD_DIR` is set.
+async fn fetch(
+    db: &DatabaseConnection,
+    link: &str,
+) -> Result<Download, Box<dyn Error + Send + Sync>> {
+    let channel = if newsletters::is_newsletter(link) {
+        newsletters::channel(db, link).await?
+    } else if sitemaps::is_sitemap(link) {
+        sitemaps::channel(db, link).await?
+    } else if watches::is_watch(link) {
+        watches::channel(db, link).await?
+    } else {
+        chaos::fetch(link).await?;
+        let download = cache::fetch_download(link).await?;
+        recordings::record(link, &download.body);
+        return Ok(download);
+    };
+    Ok(Download::local(channel.to_string().into_bytes()))
+}
+
+/// Fetches and parses the feed at `link`.
+async fn fetch_channel(
+    db: &DatabaseConnection,
+    link: &str,
+) -> Result<Channel, Box<dyn Error + Send + Sync>> {
+    read_channel(fetch(db, link).await?.body).await
+}
+
+/// Parses `content` like [`parse_channel`], on a blocking thread if it is large enough for the
+/// parsing to hold up the other tasks of the runtime.
+async fn read_channel(content: Vec<u8>) -> Result<Channel, Box<dyn Error + Send + Sync>> {
+    if content.len() < BLOCKING_PARSE_BYTES {
+        return parse_channel(&content);
+    }
+    tokio::task::spawn_blocking(move || parse_channel(&content)).await?
+}
+
+/// Parses an RSS, Atom or JSON feed, or an iCalendar file into a feed of reminders.
+fn parse_channel(content: &[u8]) -> Result<Channel, Box<dyn Error + Send + Sync>> {
+    if calendar::is_calendar(content) {
+        return calendar::channel(content);
+    }
+    if syndication::is_json_feed(content) {
+        return syndication::json_feed_channel(content);
+    }
+    match Channel::read_from(content) {
+        // Atom feeds start with <feed> instead of <rss>
+        Err(rss::Error::InvalidStartTag) => syndication::atom_channel(content),
+        channel => Ok(channel?),
+    }
+}
+
+/// Runs the scheduled job `name`, unless in maintenance mode.
+async fn unless_maintenance(name: &str, job: impl std::future::Future<Output = ()>) {
+    if maintenance::is_on() {
+        println!("Skipping {} during maintenance", name);
+        return;
+    }
+    job.await;
+}
+
+/// Polls a single feed with its fetched `channel` and stores the outcome.
+///
+/// On success the feed's `updated_at` is advanced to the newest item that was sent and its
+/// error state is cleared. On failure the error is recorded in `last_error` and
+/// `error_count` is incremented, so that the status can be inspected later. `check_count`
+/// and `failure_count` keep the totals used to compute error rates. The items are not read
+/// again if `body_hash` is the hash of the body of the last successful poll. `gone_since`
+/// records since when the feed answers 404 or 410, for [`gone`] to offer a replacement.
+async fn check_channel(
+    bot: &Bot,
+    db: &DatabaseConnection,
+    feed: feed::Model,
+    channel: &Result<Channel, Box<dyn Error + Send + Sync>>,
+    body_hash: Option<&str>,
+) {
+    let unchanged = body_hash.is_some() && body_hash == feed.body_hash.as_deref();
+    let gone = channel
+        .as_ref()
+        .err()
+        .is_some_and(|err| gone::is_gone(err.as_ref()));
+    let result = match channel {
+        Ok(_) if unchanged => Ok(None),
+        Ok(channel) => poll_feed(bot, db, &feed, channel).await,
+        Err(err) => Err(err.to_string().into()),
+    };
+    let now = Utc::now().naive_utc();
+    let mut updated_feed: feed::ActiveModel = feed.clone().into();
+    updated_feed.last_checked_at = Set(Some(now));
+    let errors = if result.is_ok() { 0 } else { feed.error_count + 1 };
+    updated_feed.next_check_at = Set(Some(schedule::next_check_at(&feed, now, errors)));
+    updated_feed.checking_since = Set(None);
+    updated_feed.check_count = Set(feed.check_count + 1);
+    updated_feed.gone_since = Set(if gone {
+        feed.gone_since.or(Some(now))
+    } else {
+        None
+    });
+    match result {
+        Ok(max_update_time) => {
+            if let Some(max_time) = max_update_time {
+                if max_time > feed.updated_at {
+                    updated_feed.updated_at = Set(max_time);
+                }
+            }
+            updated_feed.last_error = Set(None);
+            updated_feed.error_count = Set(0);
+            if let Some(body_hash) = body_hash {
+                updated_feed.body_hash = Set(Some(body_hash.to_string()));
+            }
+        }
+        Err(err) => {
+            println!("Error polling feed {}: {:?}", feed.id, err);
+            updated_feed.last_error = Set(Some(err.to_string()));
+            updated_feed.error_count = Set(feed.error_count + 1);
+            updated_feed.failure_count = Set(feed.failure_count + 1);
+        }
+    }
+    let updated = match chaos::database() {
+        Ok(()) => updated_feed.update(db).await,
+        Err(err) => Err(err),
+    };
+    match updated {
+        Ok(feed) => registry::put(feed),
+        Err(err) => println!("Error updating feed: {:?}", err),
+    }
+}
+
+/// Sends a message for every item of `channel` published after `feed.updated_at`, or whose
+/// hash wasn't seen yet for feeds that use [`content_hash`].
+///
+/// The settings of the folder of the feed, if it is in one, override its own. Items first go
+/// through the pipeline of the feed, which may transform or drop them. Items that don't pass the
+/// feed's category, author, length or language filters, that its severity rules drop, that
+/// duplicate a recent item when deduplication is on, or that look like spam when the spam filter is
+/// on, are skipped, as are NSFW items if the chat drops them. Every message that was sent is
+/// recorded in the `delivery` table. When `edit_updates` is on, items that were already sent are
+/// recognized by their GUID and their message is edited if the title changed. When `thumbnail` is
+/// on, items are sent as photos of the feed's image or favicon. Items beyond the feed's maximum
+/// rate or the chat's message quota, outside its delivery window, or while it is muted with
+/// `buffer`, are held for a digest; other items published while it is muted are dropped. Items
+/// of high-priority feeds are never held and always notify, items of low-priority feeds are
+/// always held. Items mentioning a name followed by the chat are sent like those of high-priority
+/// feeds, highlighted with ⭐. Feeds whose new items were mostly sent already are switched to
+/// hashes by [`churn`] and send nothing. After a downtime, the chat's [`catchup`] preference
+/// may skip older items.
+/// Returns the publication date of the newest item that was sent, if any.
+async fn poll_feed(
+    bot: &Bot,
+    db: &DatabaseConnection,
+    feed: &feed::Model,
+    channel: &Channel,
+) -> Result<Option<sea_orm::prelude::DateTime>, Box<dyn Error + Send + Sync>> {
+    chaos::database()?;
+    let (resolved, folder) = folders::resolve(db, feed).await?;
+    let feed = &resolved;
+    let top_digest = folder.is_some_and(|folder| folder.top_items.is_some());
+    let mut max_update_time: Option<sea_orm::prelude::DateTime> = None;
+    let mut recent_titles = if feed.dedup {
+        dedup::recent_titles(db, feed).await?
+    } else {
+        Vec::new()
+    };
+    let sent_items = if feed.edit_updates {
+        edits::sent_items(db, feed, &channel.items).await?
+    } else {
+        HashMap::new()
+    };
+    let thumbnails_enabled = feed.thumbnail && flags::is_enabled(db, Flag::Thumbnails).await;
+    let thumbnail = match thumbnails::image_url(channel) {
+        Some(url) if thumbnails_enabled => thumbnails::thumbnail(db, &url).await,
+        _ => None,
+    };
+    let title_counts = spam::title_counts(&channel.items);
+    let followed = follows::followed(db, feed.chat_id, feed.bot_id).await?;
+    let cutoff = catchup::cutoff(db, feed, Utc::now().naive_utc()).await;
+    let known_hashes = if content_hash::uses_hashes(feed, channel) {
+        Some(content_hash::known(db, feed).await?)
+    } else {
+        None
+    };
+    // The first poll with hashes only records the items already in the feed
+    let first_hashes = known_hashes.as_ref().is_some_and(|known| known.is_empty());
+    if known_hashes.is_none() {
+        let new_items: Vec<&Item> = channel
+            .items
+            .iter()
+            .filter(|item| {
+                dates::published(item).ok().flatten().unwrap_or_default() > cutoff
+            })
+            .collect();
+        // Feeds that rewrite their items switch to hashes rather than sending them all again
+        if churn::is_churning(db, feed, &new_items).await? {
+            churn::switch_to_hashes(db, feed, new_items.len()).await?;
+            content_hash::record(db, feed, &channel.items).await?;
+            return Ok(None);
+        }
+    }
+
+    for item in &channel.items {
+        // Items without a readable date are never newer than the last poll
+        let published_date = dates::published(item).unwrap_or_else(|err| {
+            println!("Skipping item of feed {}: {}", feed.id, err);
+            None
+        });
+        let published_date = published_date.unwrap_or_default();
+        if let Some(delivery) = item.guid().and_then(|guid| sent_items.get(guid.value())) {
+            if let Some(item) = pipeline::apply(feed, item) {
+                edits::update_item(bot, db, feed, delivery, &item).await;
+            }
+            continue;
+        }
+        let is_new = match &known_hashes {
+            Some(known) => !first_hashes && !known.contains(&content_hash::item_hash(item)),
+            None => published_date > cutoff,
+        };
+        if is_new {
+            // Filtered items still advance `updated_at`, so that they are not checked again
+            if max_update_time.is_none() || published_date > max_update_time.unwrap() {
+                max_update_time = Some(published_date);
+            }
+            // Spam is recognized by the original title, before the pipeline rewrites it
+            let spam = if feed.spam_filter {
+                spam::reason(item, &title_counts)
+            } else {
+                None
+            };
+            let Some(item) = &pipeline::apply(feed, item) else {
+                println!("Skipping item of feed {} dropped by its pipeline", feed.id);
+                continue;
+            };
+            let action = severity::action(feed, item);
+            let allowed = filters::allows_item(feed, item)
+                && filters::allows_length(feed, item)
+                && language::allows_item(feed, item)
+                && fediverse::allows_item(feed, item)
+                && releases::allows_item(feed, item);
+            if allowed && action != severity::Action::Drop {
+                let title = item.title().unwrap_or_default();
+                if feed.dedup && dedup::is_duplicate(title, &recent_titles) {
+                    println!("Skipping duplicate item {} of feed {}", title, feed.id);
+                } else if let Some(reason) = spam {
+                    println!("Skipping spam item {} of feed {}: {}", title, feed.id, reason);
+                    spam::record(db, feed, item, reason).await;
+                } else if nsfw::is_nsfw(item)
+                    && nsfw::chat_mode(db, feed).await == NsfwMode::Drop
+                {
+                    println!("Skipping NSFW item {} of feed {}", title, feed.id);
+                } else if mute::is_muted(feed) && !feed.mute_buffer {
+                    println!("Skipping item {} of muted feed {}", title, feed.id);
+                } else {
+                    let highlighted = follows::highlight(&followed, item);
+                    let priority = if highlighted.is_some() {
+                        Priority::High
+                    } else {
+                        priority::of(feed)
+                    };
+                    let silent = match priority {
+                        Priority::High => false,
+                        Priority::Normal => action == severity::Action::Silent,
+                        Priority::Low => true,
+                    };
+                    let now = Utc::now().naive_utc();
+                    let hold = if priority == Priority::High {
+                        Ok(false)
+                    } else if priority == Priority::Low
+                        || top_digest
+                        || mute::is_muted(feed)
+                        || !window::is_open(feed, now)
+                    {
+                        Ok(true)
+                    } else {
+                        rate::is_exceeded(db, feed).await
+                    };
+                    let hold = match hold {
+                        Ok(false) => {
+                            let (chat_id, bot_id) = (feed.chat_id, feed.bot_id);
+                            Ok(!quota::use_quota(bot, db, chat_id, bot_id, Usage::Messages, 1)
+                                .await)
+                        }
+                        hold => hold,
+                    };
+                    match hold {
+                        Ok(true) => {
+                            if let Err(err) = rate::hold_item(db, feed, item).await {
+                                println!("Error holding item: {:?}", err);
+                            }
+                        }
+                        Ok(false) => {
+                            if !bursts::absorb(bot, db, feed, item).await {
+                                let item = highlighted.as_ref().unwrap_or(item);
+                                send_item(bot, db, feed, item, silent, thumbnail.as_deref()).await
+                            }
+                        }
+                        Err(err) => println!("Error checking rate of feed {}: {:?}", feed.id, err),
+                    }
+                    recent_titles.push(title.to_string());
+                }
+            }
+        }
+    }
+    if known_hashes.is_some() {
+        content_hash::record(db, feed, &channel.items).await?;
+    }
+    // Items skipped after a downtime are not new at the next poll either
+    if cutoff > feed.updated_at {
+        max_update_time = max_update_time.max(Some(cutoff));
+    }
+    Ok(max_update_time)
+}
+
+/// Sends an item of `feed` to its chat, without a notification if `silent` and as the caption
+/// of `thumbnail` if there is one, pins it if the feed asks for it and records the delivery.
+/// NSFW items are hidden behind a spoiler or a button if the chat asks for it, and items with
+/// several images are sent as an album if the feed asks for it. Items of comics are sent as
+/// the main image of their page. Video enclosures are sent as videos when they are small
+/// enough, or else linked with their duration.
+async fn send_item(
+    bot: &Bot,
+    db: &DatabaseConnection,
+    feed: &feed::Model,
+    item: &Item,
+    silent: bool,
+    thumbnail: Option<&[u8]>,
+) {
+    let nsfw_mode = if nsfw::is_nsfw(item) {
+        nsfw::chat_mode(db, feed).await
+    } else {
+        NsfwMode::Show
+    };
+    // Only the message has the tracked or short link, the delivery records the item as it is
+    let tracked = clicks::track(db, feed, item).await;
+    let shortened = match tracked {
+        Some(_) => None,
+        None => shorteners::shorten_item(db, feed, item).await,
+    };
+    let shown = match (&tracked, &shortened) {
+        (Some((_, tracked_item)), _) => tracked_item,
+        (None, Some(shortened_item)) => shortened_item,
+        (None, None) => item,
+    };
+    let (text, keyboard, thumbnail) = match nsfw_mode {
+        NsfwMode::Spoiler => (
+            nsfw::spoiler_message(feed, shown),
+            noise::item_keyboard(feed.id),
+            thumbnail,
+        ),
+        NsfwMode::Button => (nsfw::hidden_message(feed), nsfw::show_keyboard(), None),
+        NsfwMode::Show | NsfwMode::Drop => {
+            let mut text = format::item_message(feed, shown);
+            if let Some(details) = papers::details(feed, item).await {
+                text.push_str(&details);
+            }
+            if let Some(details) = releases::details(feed, item).await {
+                text.push_str(&details);
+            }
+            if let Some(details) = archive::details(feed, item).await {
+                text.push_str(&details);
+            }
+            (text, noise::item_keyboard(feed.id), thumbnail)
+        }
+    };
+    let hidden = matches!(nsfw_mode, NsfwMode::Spoiler | NsfwMode::Button)
+        || torrents::torrent_link(item).is_some();
+    let album = if hidden {
+        None
+    } else {
+        albums::album(feed, item)
+    };
+    let comic = if hidden || album.is_some() {
+        None
+    } else {
+        comics::image(feed, item).await
+    };
+    let video = if hidden || album.is_some() || comic.is_some() {
+        None
+    } else {
+        videos::download(db, item).await
+    };
+    let text = match &video {
+        None if !hidden => format!("{}{}", text, videos::video_link(item)),
+        _ => text,
+    };
+    // Texts too long for a caption follow the media in a message of their own
+    let (album, comic, video, thumbnail) = if text.chars().count() > MAX_CAPTION_CHARS {
+        let chat_id = ChatId(feed.chat_id);
+        let media = match (album, comic, video, thumbnail) {
+            (Some(photos), ..) => {
+                Some(albums::send(bot, feed, &photos, String::new(), silent).await)
+            }
+            (None, Some(url), ..) => Some(
+                bot.send_photo(chat_id, InputFile::url(url))
+                    .disable_notification(silent)
+                    .await,
+            ),
+            (None, None, Some(video), _) => Some(
+                bot.send_video(chat_id, InputFile::memory(video))
+                    .disable_notification(silent)
+                    .await,
+            ),
+            (None, None, None, Some(image)) => Some(
+                bot.send_photo(chat_id, InputFile::memory(image.to_vec()))
+                    .disable_notification(silent)
+                    .await,
+            ),
+            (None, None, None, None) => None,
+        };
+        if let Some(Err(err)) = media {
+            println!("Error sending the media of an item: {:?}", err);
+        }
+        (None, None, None, None)
+    } else {
+        (album, comic, video, thumbnail)
+    };
+    let sent = match (chaos::telegram(), album, comic, video, thumbnail) {
+        (Some(err), ..) => Err(err),
+        (None, Some(photos), ..) => albums::send(bot, feed, &photos, text, silent).await,
+        (None, None, Some(url), ..) => {
+            bot.send_photo(ChatId(feed.chat_id), InputFile::url(url))
+                .caption(text)
+                .parse_mode(ParseMode::Html)
+                .disable_notification(silent)
+                .reply_markup(keyboard)
+                .await
+        }
+        (None, None, None, Some(video), _) => {
+            bot.send_video(ChatId(feed.chat_id), InputFile::memory(video))
+                .caption(text)
+                .parse_mode(ParseMode::Html)
+                .disable_notification(silent)
+                .reply_markup(keyboard)
+                .await
+        }
+        (None, None, None, None, Some(image)) => {
+            bot.send_photo(ChatId(feed.chat_id), InputFile::memory(image.to_vec()))
+                .caption(text)
+                .parse_mode(ParseMode::Html)
+                .disable_notification(silent)
+                .reply_markup(keyboard)
+                .await
+        }
+        (None, None, None, None, None) => {
+            bot.send_message(ChatId(feed.chat_id), text)
+                .parse_mode(ParseMode::Html)
+                .disable_notification(silent)
+                .disable_web_page_preview(hidden)
+                .reply_markup(keyboard)
+                .await
+        }
+    };
+    match sent {
+        Ok(sent) => {
+            if feed.pin {
+                pins::pin_item(bot, db, feed, sent.id).await;
+            }
+            let delivery = match tracked {
+                Some((delivery, _)) => clicks::sent(db, delivery, sent.id).await,
+                None => create_delivery(db, feed, item, Some(sent.id)).await,
+            };
+            if let Err(err) = delivery {
+                println!("Error recording delivery: {:?}", err);
+            }
+            destinations::send_copies(bot, db, feed, sent.id, silent).await;
+            bridges::send_item(db, feed, item).await;
+            webhooks::send_item(db, feed, item).await;
+            torrents::send_item(feed, item).await;
+        }
+        Err(err) => {
+            println!("Error sending message: {:?}", err);
+            if let Some((delivery, _)) = tracked {
+                clicks::discard(db, delivery).await;
+            }
+        }
+    }
+}
+
+async fn ask_to_subscribe(bot: Bot, msg: Message) -> ResponseResult<()> {
+    bot.send_message(
+        msg.chat.id,
+        "type /start to create an account and chat with the bot. Only this chat id will be stored.",
+    )
+    .await?;
+    Ok(())
+}
+
+async fn noop(_bot: Bot, _msg: Message) -> ResponseResult<()> {
+    // no action on other messages
+    Ok(())
+}
+
+async fn process_callback(
+    bot: Bot,
+    q: CallbackQuery,
+    db: DatabaseConnection,
+    bot_id: BotId,
+    admin: AdminChat,
+) -> ResponseResult<()> {
+    let in_admin_chat = q
+        .message
+        .as_ref()
+        .is_some_and(|message| admin.is(message.chat.id.0));
+    if maintenance::is_on() && !in_admin_chat {
+        bot.answer_callback_query(q.id)
+            .text(maintenance::NOTICE)
+            .await?;
+        return Ok(());
+    }
+    let data = q.data.clone().unwrap_or_default();
+    if data == noise::LIKE {
+        if let Some(message) = &q.message {
+            let liked = noise::record_like(&db, message.chat.id.0, bot_id.0, message.id).await;
+            if let Err(err) = liked {
+                log::error!("Error recording like: {}", err);
+            }
+        }
+        bot.answer_callback_query(q.id).text("👍").await?;
+    } else if mute::is_mute_button(&data) {
+        let text = mute::handle_button(&q, &data, &db, bot_id).await;
+        bot.answer_callback_query(q.id).text(text).await?;
+    } else {
+        if noise::is_suggestion(&data) {
+            noise::handle_suggestion(&bot, &q, &data, &db, bot_id).await?;
+        } else if bulk::is_unsubscribe_button(&data) {
+            bulk::handle_unsubscribe(&bot, &q, &data, &db, bot_id).await?;
+        } else if gone::is_prompt(&data) {
+            gone::handle_prompt(&bot, &q, &data, &db, bot_id).await?;
+        } else if snooze::is_snooze(&data) {
+            snooze::handle_button(&bot, &q, &data, &db).await?;
+        } else if summary::is_summary_action(&data) {
+            summary::handle_action(&bot, &q, &data, &db, bot_id).await?;
+        } else if nsfw::is_show_button(&data) {
+            nsfw::handle_show_button(&bot, &q, &db).await?;
+        }
+        bot.answer_callback_query(q.id).await?;
+    }
+    Ok(())
+}
+
+async fn is_not_subscribed(msg: Message, db: DatabaseConnection, bot_id: BotId) -> bool {
+    // The database may be unavailable during maintenance, when the commands answer with the
+    // maintenance notice without it
+    if maintenance::is_on() {
+        return false;
+    }
+    // check if the chat is not in the database
+    let c = entity::prelude::Chat::find_by_id((msg.chat.id.0, bot_id.0))
+        .one(&db)
+        .await;
+    match c {
+        Ok(c) => c.is_none(),
+        Err(err) => {
+            log::error!("Error checking the subscription of chat {}: {}", msg.chat.id, err);
+            false
+        }
+    }
+}
+
+#[derive(BotCommands, Clone)]
+#[command(
+    rename_rule = "lowercase",
+    description = "These commands are supported:"
+)]
+enum LoggedOutCommand {
+    #[command(description = "display this text.")]
+    Help,
+    #[command(description = "create an account for your chat with the bot")]
+    Start,
+}
+
+/// Parses the arguments of commands taking a feed id followed by free text, such as rules.
+fn parse_feed_args(input: String) -> Result<(i64, String), ParseError> {
+    let (feed_id, rest) = input.trim().split_once(' ').unwrap_or((input.trim(), ""));
+    let feed_id = feed_id
+        .parse()
+        .map_err(|err| ParseError::IncorrectFormat(Box::new(err)))?;
+    Ok((feed_id, rest.trim().to_string()))
+}
+
+/// Parses `msg` as a command, in which the feed, if the command takes one, can be referred to
+/// by its short id or title, or by replying to one of its items.
+async fn parse_command(
+    bot: Bot,
+    msg: Message,
+    me: Me,
+    db: DatabaseConnection,
+    bot_id: BotId,
+) -> Option<LoggedInCommand> {
+    let text = msg.text()?;
+    let replied_to = msg.reply_to_message().map(|reply| reply.id.0);
+    let text = match short_ids::rewrite(&db, msg.chat.id.0, bot_id.0, text, replied_to).await {
+        Ok(Ok(text)) => text,
+        Ok(Err(reply)) => {
+            if let Err(err) = bot.send_message(msg.chat.id, reply).await {
+                println!("Error sending reply: {:?}", err);
+            }
+            return None;
+        }
+        Err(err) => {
+            println!("Error resolving the feed of {}: {:?}", text, err);
+            text.to_string()
+        }
+    };
+    LoggedInCommand::parse(&text, me.username()).ok()
+}
+
+/// An `on` or `off` command argument.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Switch(bool);
+
+impl FromStr for Switch {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "on" => Ok(Switch(true)),
+            "off" => Ok(Switch(false)),
+            _ => Err(format!("Expected on or off, got {}", s)),
+        }
+    }
+}
+
+#[derive(BotCommands, Clone)]
+#[command(
+    rename_rule = "lowercase",
+    description = "These commands are supported:"
+)]
+enum LoggedInCommand {
+    #[command(description = "display this text.")]
+    Help,
+    #[command(
+        description = "<RSS or iCalendar address>|@user@instance subscribe to an RSS feed, a \
+                       calendar or a Fediverse account, or to several separated by spaces or in \
+                       reply to a list"
+    )]
+    Subscribe { link: String },
+    #[command(description = "<sitemap address> - subscribe to the pages added to a site")]
+    Sitemap { url: String },
+    #[command(
+        description = "<url> <css selector> - get notified when the text of an element of a \
+                       page changes, such as a price"
+    )]
+    Watch { args: String },
+    #[command(
+        description = "<url> [percent] - get a diff of a page whenever at least percent of its \
+                       text changes, 1 by default"
+    )]
+    Monitor { args: String },
+    #[command(description = "list feeds")]
+    List,
+    #[command(
+        description = "<feed id>|all|#<folder>|matching <text> - unsubscribe from feed. Take the \
+                       ids from the list command"
+    )]
+    Unsubscribe { target: String },
+    #[command(description = "<feed id> - stop sending items from a feed")]
+    Pause { feed_id: i64 },
+    #[command(description = "<feed id> - send items from a paused feed again")]
+    Resume { feed_id: i64 },
+    #[command(
+        description = "get a code in a private chat, then send /link <code> in a group you \
+                       administer to manage its feeds from the private chat"
+    )]
+    Link { code: String },
+    #[command(description = "<chat id> - stop managing the feeds of a linked chat")]
+    Unlink { chat_id: i64 },
+    #[command(
+        description = "<feed id> <chat id> - move a feed to this chat or a linked chat",
+        parse_with = "split"
+    )]
+    Transfer { feed_id: i64, target: i64 },
+    #[command(
+        description = "<feed id> <chat id> - copy a feed to this chat or a linked chat",
+        parse_with = "split"
+    )]
+    Copy { feed_id: i64, target: i64 },
+    #[command(
+        description = "<feed id> <rules> - only send items in some categories, e.g. +Politics \
+                       -Sports. No rules removes the filter",
+        parse_with = parse_feed_args
+    )]
+    FilterCategory { feed_id: i64, rules: String },
+    #[command(
+        description = "<feed id> <rules> - only send items by some authors, e.g. +Alice -Bob. \
+                       No rules removes the filter",
+        parse_with = parse_feed_args
+    )]
+    FilterAuthor { feed_id: i64, rules: String },
+    #[command(
+        description = "<feed id> <languages> - only send items in some languages, e.g. en,de. \
+                       off removes the filter",
+        parse_with = parse_feed_args
+    )]
+    LangFilter { feed_id: i64, languages: String },
+    #[command(
+        description = "<feed id> <characters> - skip items whose title and description are \
+                       shorter. 0 removes the minimum",
+        parse_with = "split"
+    )]
+    MinLength { feed_id: i64, length: u16 },
+    #[command(description = "<feed id> - list the steps of the pipeline of a feed")]
+    Pipeline { feed_id: i64 },
+    #[command(
+        description = "<feed id> <step> - add a step to the pipeline of a feed: filter +word \
+                       -word, rewrite s/pattern/replacement/, strip_params [names], mirror \
+                       <domain> <mirror>, wrap <prefix>, truncate <characters> or template \
+                       <text with {feed} {title} {link}>",
+        parse_with = parse_feed_args
+    )]
+    AddStep { feed_id: i64, step: String },
+    #[command(
+        description = "<feed id> <position> - remove a step from the pipeline of a feed",
+        parse_with = "split"
+    )]
+    RemoveStep { feed_id: i64, position: usize },
+    #[command(
+        description = "<feed id> s/pattern/replacement/ - rewrite the titles of a feed with a \
+                       regular expression. off removes all rewrites",
+        parse_with = parse_feed_args
+    )]
+    Rewrite { feed_id: i64, substitution: String },
+    #[command(
+        description = "<feed id> mirror <domain> <mirror>|wrap <prefix> - rewrite the links of \
+                       a feed, e.g. mirror reddit.com old.reddit.com. off removes all link rules",
+        parse_with = parse_feed_args
+    )]
+    LinkRule { feed_id: i64, rule: String },
+    #[command(
+        description = "<feed id> on|off - skip items with the same title as a recent one",
+        parse_with = "split"
+    )]
+    Dedup { feed_id: i64, enabled: Switch },
+    #[command(
+        description = "<feed id> replies|boosts on|off - send the replies or boosts of a \
+                       Fediverse account",
+        parse_with = "split"
+    )]
+    Fediverse { feed_id: i64, option: String, enabled: Switch },
+    #[command(
+        description = "<feed id> <url>|off - POST the magnet or torrent links of a feed to a \
+                       download client",
+        parse_with = parse_feed_args
+    )]
+    Download { feed_id: i64, url: String },
+    #[command(
+        description = "<feed id> on|off - add the authors, abstract and PDF of arXiv papers and \
+                       DOIs to the items of a feed",
+        parse_with = "split"
+    )]
+    Papers { feed_id: i64, enabled: Switch },
+    #[command(
+        description = "<feed id> on|off - add the notes and assets of GitHub releases to the \
+                       items of a feed",
+        parse_with = "split"
+    )]
+    ReleaseNotes { feed_id: i64, enabled: Switch },
+    #[command(
+        description = "<feed id> major|minor|all [stable] - only send major or minor releases \
+                       of a release feed, stable skips pre-releases. off sends all items",
+        parse_with = parse_feed_args
+    )]
+    Versions { feed_id: i64, filter: String },
+    #[command(
+        description = "<feed id> on|off - send items with several images as albums",
+        parse_with = "split"
+    )]
+    Albums { feed_id: i64, enabled: Switch },
+    #[command(
+        description = "<feed id> on|off - send the main image of the page of each item, for \
+                       webcomics",
+        parse_with = "split"
+    )]
+    Comic { feed_id: i64, enabled: Switch },
+    #[command(
+        description = "<feed id> <rhash>|off - open the links of a feed in Instant View with \
+                       the template rhash",
+        parse_with = parse_feed_args
+    )]
+    InstantView { feed_id: i64, rhash: String },
+    #[command(
+        description = "<feed id> on|off - add archive links to the items of paywalled sites",
+        parse_with = "split"
+    )]
+    Archive { feed_id: i64, enabled: Switch },
+    #[command(
+        description = "<feed id> on|off - recognize new items of a feed by their title and link \
+                       rather than by their date, for feeds with wrong dates",
+        parse_with = "split"
+    )]
+    ContentHash { feed_id: i64, enabled: Switch },
+    #[command(
+        description = "<feed id> on|off - skip items that look like spam",
+        parse_with = "split"
+    )]
+    SpamFilter { feed_id: i64, enabled: Switch },
+    #[command(description = "<feed id> - list the latest items skipped as spam")]
+    Spam { feed_id: i64 },
+    #[command(
+        description = "<feed id> on|off - edit sent messages when the feed updates an item",
+        parse_with = "split"
+    )]
+    EditUpdates { feed_id: i64, enabled: Switch },
+    #[command(
+        description = "<feed id> on|off|replace - pin items of a feed, replace unpins the \
+                       previous one",
+        parse_with = "split"
+    )]
+    Pin { feed_id: i64, mode: PinMode },
+    #[command(
+        description = "<feed id> <rules> - deliver status page items by severity, e.g. \
+                       critical=loud resolved=silent maintenance=drop",
+        parse_with = parse_feed_args
+    )]
+    Severity { feed_id: i64, rules: String },
+    #[command(
+        description = "<feed id> <emoji> - show an emoji before the items of a feed. No emoji \
+                       removes it",
+        parse_with = parse_feed_args
+    )]
+    SetIcon { feed_id: i64, icon: String },
+    #[command(
+        description = "<feed id> on|off - send items with the image or favicon of the feed",
+        parse_with = "split"
+    )]
+    Thumbnail { feed_id: i64, enabled: Switch },
+    #[command(
+        description = "<feed id> on|off - add hashtags from the categories of the items",
+        parse_with = "split"
+    )]
+    Hashtags { feed_id: i64, enabled: Switch },
+    #[command(
+        description = "<feed id> on|off - show the word count and reading time of the items",
+        parse_with = "split"
+    )]
+    ReadingTime { feed_id: i64, enabled: Switch },
+    #[command(
+        description = "[<option> <value>|<option> default|reset] - list or set the settings \
+                       that new feeds start with, e.g. dedup on or priority low"
+    )]
+    Defaults { args: String },
+    #[command(description = "on|off - receive a summary of your feeds every Monday")]
+    Weekly { enabled: Switch },
+    #[command(
+        description = "<feed id> <rate> - send at most e.g. 5/day or 10/hour items of a feed, \
+                       and the rest as a digest. off removes the limit",
+        parse_with = "split"
+    )]
+    MaxRate { feed_id: i64, rate: String },
+    #[command(
+        description = "<feed id> <duration> [buffer] - mute a feed for e.g. 3h or 2d, sending \
+                       its items as a digest afterwards with buffer. off unmutes it",
+        parse_with = parse_feed_args
+    )]
+    Mute { feed_id: i64, args: String },
+    #[command(
+        description = "<feed id> <hours> [days] - deliver items only e.g. 09:00-18:00 Mon-Fri \
+                       (UTC), and the rest as a digest when it opens. off removes the window",
+        parse_with = parse_feed_args
+    )]
+    Window { feed_id: i64, window: String },
+    #[command(
+        description = "show|spoiler|button|drop - how to send items marked as NSFW, hidden \
+                       behind a spoiler by default"
+    )]
+    Nsfw { mode: String },
+    #[command(
+        description = "create|add|remove <name> <feed ids>, delete <name>, set <name> \
+                       rate|window|template|top|keywords <value> or list - group feeds in \
+                       folders whose settings apply to all their feeds"
+    )]
+    Folder { args: String },
+    #[command(
+        description = "<feed id> high|normal|low - send items of a feed right away with a \
+                       notification, or always silently in a digest",
+        parse_with = "split"
+    )]
+    Priority { feed_id: i64, priority: Priority },
+    #[command(
+        description = "<feed id> <duration> - check a feed every e.g. 15m or 6h. default goes \
+                       back to the interval of the bot",
+        parse_with = "split"
+    )]
+    Interval { feed_id: i64, interval: String },
+    #[command(
+        description = "<feed id> [add <chat id>|here [topic id], remove <destination id> or mute \
+                       <destination id> <duration>|off] - send the items of a feed to other \
+                       chats and topics as well",
+        parse_with = parse_feed_args
+    )]
+    Destinations { feed_id: i64, args: String },
+    #[command(
+        description = "<url>|off - also POST every item of this chat as JSON to an https \
+                       address, signed with a secret"
+    )]
+    Webhook { url: String },
+    #[command(
+        description = "shlink|yourls <address> <key>|off - shorten the links sent to this chat \
+                       with a Shlink or YOURLS server"
+    )]
+    Shortener { args: String },
+    #[command(
+        description = "on|off - count the clicks on the links sent to this chat",
+        parse_with = "split"
+    )]
+    TrackClicks { enabled: Switch },
+    #[command(description = "rank the feeds of this chat by clicks over the last 30 days")]
+    Stats,
+    #[command(description = "[feed id] - list the last 20 items sent to this chat, or by a feed")]
+    History { args: String },
+    #[command(
+        description = "reverse the last unsubscribe, rename or filter change of the last 10 \
+                       minutes"
+    )]
+    Undo,
+    #[command(
+        description = "all|skip|<hours> - after the bot was down, send every missed item, skip \
+                       them or send those of the last hours"
+    )]
+    CatchUp { value: String },
+    #[command(
+        description = "on|off - group the items of several feeds about the same breaking news \
+                       in a single message",
+        parse_with = "split"
+    )]
+    Breaking { enabled: Switch },
+    #[command(
+        description = "[\"name\"] - send the items of all feeds mentioning a name right away \
+                       and highlighted, or list the followed names and the names in the news"
+    )]
+    Follow { name: String },
+    #[command(description = "\"name\" - stop following a name")]
+    Unfollow { name: String },
+    #[command(description = "get an email address whose newsletters are sent to this chat")]
+    Newsletter,
+    #[command(description = "pay with Telegram Stars for premium, with more feeds")]
+    Premium,
+    #[command(
+        description = "<feed id> [reason] - report a feed that breaks the rules to the \
+                       operators of the bot",
+        parse_with = parse_feed_args
+    )]
+    Report { feed_id: i64, reason: String },
+    #[command(description = "admin chat only - list the feature flags")]
+    Flags,
+    #[command(
+        description = "<flag> <value> - admin chat only - set a feature flag",
+        parse_with = "split"
+    )]
+    SetFlag { flag: String, value: String },
+    #[command(description = "admin chat only - list the open reports")]
+    Reports,
+    #[command(description = "<report id> - admin chat only - close a report without action")]
+    Dismiss { report_id: i64 },
+    #[command(
+        description = "<domain or link> - admin chat only - unsubscribe everyone from matching \
+                       feeds and refuse new subscriptions"
+    )]
+    Block { pattern: String },
+    #[command(description = "<domain or link> - admin chat only - allow a blocked feed again")]
+    Unblock { pattern: String },
+    #[command(
+        description = "<link> <new link> - admin chat only - block a feed and move its \
+                       subscribers to another one",
+        parse_with = "split"
+    )]
+    Replace { link: String, replacement: String },
+    #[command(
+        description = "[<link or start of links*> <new link>|remove <pattern>] - admin chat only - \
+                       list the aliases of feeds that moved, or move every subscription to a link"
+    )]
+    Alias { args: String },
+    #[command(
+        description = "<chat id> [matrix <room id>|discord <webhook url>|off] - admin chat only - \
+                       post the items of a chat to Matrix or Discord as well"
+    )]
+    Bridge { args: String },
+    #[command(
+        description = "<chat id> - admin chat only - show the settings, feeds, errors and latest \
+                       deliveries of a chat",
+        parse_with = "split"
+    )]
+    Inspect { chat_id: i64 },
+    #[command(
+        description = "admin chat only - show the bytes downloaded for the feeds and the feeds \
+                       that download the most"
+    )]
+    Bandwidth,
+    #[command(
+        description = "<link> [legacy|insecure|ca <path>|default] - admin chat only - accept \
+                       old TLS versions, invalid certificates or another certificate authority \
+                       for a feed"
+    )]
+    Tls { args: String },
+    #[command(
+        description = "on|off - admin chat only - stop polling and answering commands while the \
+                       database is maintained",
+        parse_with = "split"
+    )]
+    Maintenance { enabled: Switch },
+    #[command(description = "admin chat only - reload the configuration file")]
+    Reload,
+    #[command(description = "delete my user account and all associated subscriptions")]
+    DeleteAccount,
+}
+
+async fn create_chat(
+    db: &DatabaseConnection,
+    chat_id: i64,
+    bot_id: i64,
+) -> Result<chat::Model, Box<dyn Error + Send + Sync>> {
+    let new_chat = chat::ActiveModel {
+        id: ActiveValue::Set(chat_id),
+        bot_id: ActiveValue::Set(bot_id),
+        ..Default::default()
+    };
+    Ok(new_chat.insert(db).await?)
+}
+
+/// Returns the title or username of the chat of `msg`, to tell it apart in the admin chat.
+fn chat_name(msg: &Message) -> String {
+    msg.chat
+        .title()
+        .or(msg.chat.username())
+        .unwrap_or("private chat")
+        .to_string()
+}
+
+async fn process_logged_out_command(
+    bot: Bot,
+    msg: Message,
+    cmd: LoggedOutCommand,
+    db: DatabaseConnection,
+    bot_id: BotId,
+    admin: AdminChat,
+    bots: Bots,
+) -> ResponseResult<()> {
+    // commands for logged out users:
+    // /help -> Send command list
+    // /start -> Add chat to database
+    if maintenance::is_on() && !matches!(cmd, LoggedOutCommand::Help) {
+        bot.send_message(msg.chat.id, maintenance::NOTICE).await?;
+        return Ok(());
+    }
+    match cmd {
+        LoggedOutCommand::Help => {
+            bot.send_message(msg.chat.id, LoggedOutCommand::descriptions().to_string())
+                .await?;
+        }
+        LoggedOutCommand::Start => match create_chat(&db, msg.chat.id.0, bot_id.0).await {
+            Ok(new_chat) => {
+                let name = chat_name(&msg);
+                let (chat_id, bot_id) = (new_chat.id, new_chat.bot_id);
+                signups::record(&db, &bots, admin, Event::Registered, chat_id, bot_id, &name)
+                    .await;
+                bot.send_message(
+                    msg.chat.id,
+                    format!(
+                        "[{}] Registering your chat with the bot...Done.",
+                        new_chat.created_at
+                    ),
+                )
+                .await?;
+            }
+            Err(err) => {
+                bot.send_message(
+                    msg.chat.id,
+                    format!("[{}] Error in registering new chat", err),
+                )
+                .await?;
+            }
+        },
+    }
+    Ok(())
+}
+
+/// Asynchronously validates and processes an RSS feed from a given URL.
+///
+/// This function fetches the content of the RSS feed from the specified URL, validates it,
+/// and returns the parsed and validated `Channel` if successful.
+///
+/// # Arguments
+///
+/// * `link` - A reference to a `String` containing the URL of the RSS feed to be validated.
+///
+/// # Returns
+///
+/// Returns a `Result` where `Ok` contains the validated `Channel` if successful,
+/// and `Err` contains an error implementing the `Error` trait in case of any issues.
+///
+/// # Errors
+///
+/// This function may return an error if:
+/// - The HTTP request to fetch the feed content fails.
+/// - The feed content cannot be parsed into a `Channel`.
+/// - The parsed `Channel` fails the validation.
+///
+/// # Example
+///
+/// ```ignore
+/// use std::error::Error;
+///
+/// async fn main() -> Result<(), Box<dyn Error>> {
+///     let url = "https://example.com/rss-feed.xml".to_string();
+///     match validate_feed(&url).await {
+///         Ok(channel) => {
+///             println!("Feed validation successful: {:?}", channel);
+///         }
+///         Err(err) => {
+///             eprintln!("Error while validating the feed: {}", err);
+///         }
+///     }
+///     Ok(())
+/// }
+/// ```
+///
+async fn validate_feed(link: &String) -> Result<Channel, Box<dyn Error + Send + Sync>> {
+    let download = http::download(link).await?;
+    let mut channel = read_channel(download.body).await?;
+    channel.set_link(link);
+    channel.validate()?;
+    Ok(channel)
+}
+
+/// Fails if `chat_id` already has as many feeds as it is allowed, and otherwise returns the chat
+/// if it is registered.
+async fn check_feed_quota(
+    db: &DatabaseConnection,
+    chat_id: i64,
+    bot_id: i64,
+) -> Result<Option<chat::Model>, Box<dyn Error + Send + Sync>> {
+    let chat = entity::prelude::Chat::find_by_id((chat_id, bot_id))
+        .one(db)
+        .await?;
+    if let Some(mut max_feeds) = flags::limit(db, Flag::MaxFeeds).await {
+        if chat.as_ref().is_some_and(premium::is_premium) {
+            max_feeds *= premium::FEEDS_FACTOR;
+        }
+        let feeds = entity::prelude::Feed::find()
+            .filter(feed::Column::ChatId.eq(chat_id))
+            .filter(feed::Column::BotId.eq(bot_id))
+            .count(db)
+            .await?;
+        if feeds >= max_feeds {
+            return Err(format!("This chat can subscribe to at most {} feeds", max_feeds).into());
+        }
+    }
+    Ok(chat)
+}
+
+async fn create_feed(
+    db: &DatabaseConnection,
+    channel: &Channel,
+    chat_id: i64,
+    bot_id: i64,
+) -> Result<feed::Model, Box<dyn Error + Send + Sync>> {
+    if !flags::is_enabled(db, Flag::Subscriptions).await {
+        return Err("New subscriptions are disabled".into());
+    }
+    let chat = check_feed_quota(db, chat_id, bot_id).await?;
+    if moderation::is_blocked(db, &channel.link).await? {
+        return Err("This feed is blocked on this bot".into());
+    }
+    let source = sources::source_for(db, &channel.link).await?;
+    let mut new_feed = feed::ActiveModel {
+        chat_id: ActiveValue::Set(chat_id),
+        bot_id: ActiveValue::Set(bot_id),
+        title: ActiveValue::Set(channel.title.clone()),
+        link: ActiveValue::Set(channel.link.clone()),
+        source_id: ActiveValue::Set(source.id),
+        short_id: ActiveValue::Set(short_ids::next(db, chat_id, bot_id).await?),
+        ..Default::default()
+    };
+    if let Some(chat) = &chat {
+        defaults::apply(chat, &mut new_feed);
+    }
+    let feed = new_feed.insert(db).await?;
+    registry::invalidate(feed.id);
+    Ok(feed)
+}
+
+async fn read_feed(
+    db: &DatabaseConnection,
+    chat_id: i64,
+    bot_id: i64,
+) -> Result<Vec<feed::Model>, Box<dyn Error + Send + Sync>> {
+    Ok(entity::prelude::Feed::find()
+        .filter(feed::Column::ChatId.eq(chat_id))
+        .filter(feed::Column::BotId.eq(bot_id))
+        .all(db)
+        .await?)
+}
+
+async fn delete_feed(
+    db: &DatabaseConnection,
+    id: i64,
+    chat_id: i64,
+    bot_id: i64,
+) -> Result<DeleteResult, Box<dyn Error + Send + Sync>> {
+    let deleted = entity::prelude::Feed::delete_many()
+        .filter(feed::Column::ChatId.eq(chat_id))
+        .filter(feed::Column::BotId.eq(bot_id))
+        .filter(feed::Column::Id.eq(id))
+        .exec(db)
+        .await?;
+    registry::invalidate(id);
+    Ok(deleted)
+}
+
+async fn delete_chat(
+    db: &DatabaseConnection,
+    id: i64,
+    bot_id: i64,
+) -> Result<DeleteResult, Box<dyn Error + Send + Sync>> {
+    let deleted = entity::prelude::Chat::delete_by_id((id, bot_id))
+        .exec(db)
+        .await?;
+    // The feeds of the chat are deleted with it
+    registry::invalidate_all();
+    Ok(deleted)
+}
+
+async fn create_delivery(
+    db: &DatabaseConnection,
+    feed: &feed::Model,
+    item: &Item,
+    message_id: Option<MessageId>,
+) -> Result<delivery::Model, Box<dyn Error + Send + Sync>> {
+    let new_delivery = delivery::ActiveModel {
+        feed_id: ActiveValue::Set(feed.id),
+        chat_id: ActiveValue::Set(feed.chat_id),
+        title: ActiveValue::Set(item.title().unwrap_or_default().to_string()),
+        link: ActiveValue::Set(item.link().unwrap_or_default().to_string()),
+        message_id: ActiveValue::Set(message_id.map(|message_id| message_id.0)),
+        guid: ActiveValue::Set(item.guid().map(|guid| guid.value().to_string())),
+        ..Default::default()
+    };
+    Ok(new_delivery.insert(db).await?)
+}
+
+async fn set_feed_paused(
+    db: &DatabaseConnection,
+    id: i64,
+    chat_id: i64,
+    bot_id: i64,
+    paused: bool,
+) -> Result<UpdateResult, Box<dyn Error + Send + Sync>> {
+    set_feed_option(db, id, chat_id, bot_id, feed::Column::Paused, paused).await
+}
+
+/// Sets `column` of feed `id` to `value`, if the feed belongs to `chat_id`.
+async fn set_feed_option(
+    db: &DatabaseConnection,
+    id: i64,
+    chat_id: i64,
+    bot_id: i64,
+    column: feed::Column,
+    value: impl Into<Value>,
+) -> Result<UpdateResult, Box<dyn Error + Send + Sync>> {
+    let updated = entity::prelude::Feed::update_many()
+        .col_expr(column, Expr::value(value))
+        .filter(feed::Column::ChatId.eq(chat_id))
+        .filter(feed::Column::BotId.eq(bot_id))
+        .filter(feed::Column::Id.eq(id))
+        .exec(db)
+        .await?;
+    registry::invalidate(id);
+    Ok(updated)
+}
+
+/// Like [`set_feed_option`], but also for feeds of chats linked to `chat_id`.
+async fn set_managed_feed_option(
+    db: &DatabaseConnection,
+    id: i64,
+    chat_id: i64,
+    bot_id: i64,
+    column: feed::Column,
+    value: impl Into<Value>,
+) -> Result<UpdateResult, Box<dyn Error + Send + Sync>> {
+    let chat_id = links::managed_chat(db, id, chat_id, bot_id).await?;
+    set_feed_option(db, id, chat_id, bot_id, column, value).await
+}
+
+/// Lists `feeds` with their short ids, or with their global ids if they are the feeds of a
+/// linked chat, which the short ids of this chat don't refer to.
+fn format_feed_list(feeds: &[feed::Model], linked: bool) -> String {
+    feeds
+        .iter()
+        .map(|feed| {
+            let id = if linked {
+                short_ids::global_reference(feed)
+            } else {
+                feed.short_id.to_string()
+            };
+            let status = match feed.muted_until {
+                _ if feed.paused => " (paused)".to_string(),
+                Some(until) if mute::is_muted(feed) => {
+                    format!(" (muted until {} UTC)", until.format("%Y-%m-%d %H:%M"))
+                }
+                _ => String::new(),
+            };
+            format!("{} - {}{}", id, format::feed_label(feed), status)
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+async fn send_transfer_result(
+    bot: &Bot,
+    msg: &Message,
+    transferred: Result<Option<feed::Model>, Box<dyn Error + Send + Sync>>,
+    verb: &str,
+) -> ResponseResult<()> {
+    match transferred {
+        Ok(Some(feed)) => {
+            bot.send_message(
+                msg.chat.id,
+                format!("{} feed {} to chat {}", verb, feed.title, feed.chat_id),
+            )
+            .await?;
+        }
+        Ok(None) => {
+            bot.send_message(
+                msg.chat.id,
+                "Both the feed and the target must belong to this chat or a linked chat.",
+            )
+            .await?;
+        }
+        Err(error) => {
+            bot.send_message(msg.chat.id, format!("Error: {}", error))
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Replies with the number of feeds changed by a command, e.g. "Paused 1 feed".
+async fn send_update_result(
+    bot: &Bot,
+    msg: &Message,
+    updated: Result<UpdateResult, Box<dyn Error + Send + Sync>>,
+    verb: &str,
+) -> ResponseResult<()> {
+    match updated {
+        Ok(update_result) => {
+            bot.send_message(
+                msg.chat.id,
+                format!("{} {} feed", verb, update_result.rows_affected),
+            )
+            .await?;
+        }
+        Err(error) => {
+            bot.send_message(msg.chat.id, format!("Error: {}", error))
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+async fn process_command(
+    bot: Bot,
+    msg: Message,
+    cmd: LoggedInCommand,
+    db: DatabaseConnection,
+    bot_id: BotId,
+    admin: AdminChat,
+    bots: Bots,
+) -> ResponseResult<()> {
+    if maintenance::is_on() && !admin.is(msg.chat.id.0) && !matches!(cmd, LoggedInCommand::Help)
+    {
+        bot.send_message(msg.chat.id, maintenance::NOTICE).await?;
+        return Ok(());
+    }
+    // Once a chat reached its quota, it was told so and further commands are ignored
+    let within_quota = admin.is(msg.chat.id.0)
+        || quota::use_quota(&bot, &db, msg.chat.id.0, bot_id.0, Usage::Commands, 1).await;
+    if !within_quota {
+        return Ok(());
+    }
+    let cmd_is_admin = matches!(
+        cmd,
+        LoggedInCommand::Flags
+            | LoggedInCommand::SetFlag { .. }
+            | LoggedInCommand::Reports
+            | LoggedInCommand::Dismiss { .. }
+            | LoggedInCommand::Block { .. }
+            | LoggedInCommand::Unblock { .. }
+            | LoggedInCommand::Replace { .. }
+            | LoggedInCommand::Alias { .. }
+            | LoggedInCommand::Bridge { .. }
+            | LoggedInCommand::Inspect { .. }
+            | LoggedInCommand::Bandwidth
+            | LoggedInCommand::Tls { .. }
+            | LoggedInCommand::Maintenance { .. }
+            | LoggedInCommand::Reload
+    );
+    match cmd {
+        LoggedInCommand::Help => {
+            bot.send_message(msg.chat.id, LoggedInCommand::descriptions().to_string())
+                .await?;
+        }
+        LoggedInCommand::Subscribe { link } if bulk::is_bulk(&link, &msg) => {
+            let links = bulk::links(&link, &msg);
+            let text = bulk::subscribe(&db, links, msg.chat.id.0, bot_id.0).await;
+            bot.send_message(msg.chat.id, text)
+                .disable_web_page_preview(true)
+                .await?;
+        }
+        LoggedInCommand::Subscribe { link } if fediverse::is_handle(&link) => {
+            let text = match fediverse::subscribe(&db, &link, msg.chat.id.0, bot_id.0).await {
+                Ok(f) => format!("Subscribed to feed:\n{}\n{}", f.title, f.link),
+                Err(error) => format!("Error: {}", error),
+            };
+            bot.send_message(msg.chat.id, text).await?;
+        }
+        LoggedInCommand::Subscribe { link } => {
+            let link = aliases::resolve(&db, &link).await;
+            let valid = validate_feed(&link).await;
+            match valid {
+                Ok(channel) => {
+                    let new_feed = create_feed(&db, &channel, msg.chat.id.0, bot_id.0).await;
+                    match new_feed {
+                        Ok(f) => {
+                            bot.send_message(
+                                msg.chat.id,
+                                format!("Subscribed to feed:\n{}\n{}", f.title, f.link),
+                            )
+                            .await?;
+                        }
+                        Err(error) => {
+                            bot.send_message(msg.chat.id, format!("Error: {}", error))
+                                .await?;
+                        }
+                    }
+                }
+                Err(error) => {
+                    bot.send_message(msg.chat.id, format!("Error: {}", error))
+                        .await?;
+                }
+            }
+        }
+        LoggedInCommand::Sitemap { url } => {
+            let text = match sitemaps::subscribe(&db, &url, msg.chat.id.0, bot_id.0).await {
+                Ok(f) => format!("Subscribed to feed:\n{}\n{}", f.title, f.link),
+                Err(error) => format!("Error: {}", error),
+            };
+            bot.send_message(msg.chat.id, text).await?;
+        }
+        LoggedInCommand::Watch { args } => {
+            let text = match args.trim().split_once(' ') {
+                Some((url, selector)) => {
+                    let selector = Some(selector.trim());
+                    match watches::subscribe(&db, url, selector, msg.chat.id.0, bot_id.0).await {
+                        Ok((f, value)) => {
+                            format!("Subscribed to feed:\n{}\nCurrent text: {}", f.title, value)
+                        }
+                        Err(error) => format!("Error: {}", error),
+                    }
+                }
+                None => "Expected /watch <url> <css selector>".to_string(),
+            };
+            bot.send_message(msg.chat.id, text).await?;
+        }
+        LoggedInCommand::Monitor { args } => {
+            let mut words = args.split_whitespace();
+            let url = words.next().unwrap_or_default();
+            let text = match words.next().map(str::parse::<i32>).unwrap_or(Ok(1)) {
+                Ok(threshold) => {
+                    match watches::monitor(&db, url, threshold, msg.chat.id.0, bot_id.0).await {
+                        Ok((f, value)) => format!(
+                            "Subscribed to feed:\n{}\nMonitoring {} lines of text",
+                            f.title,
+                            value.lines().count()
+                        ),
+                        Err(error) => format!("Error: {}", error),
+                    }
+                }
+                Err(error) => format!("Error: {}", error),
+            };
+            bot.send_message(msg.chat.id, text).await?;
+        }
+        LoggedInCommand::Unsubscribe { target } if target.trim().parse::<i64>().is_err() => {
+            bulk::confirm_unsubscribe(&bot, &msg, &db, bot_id, &target).await?;
+        }
+        LoggedInCommand::Unsubscribe { target } => {
+            let feed_id: i64 = target.trim().parse().unwrap_or_default();
+            let deleted = async {
+                let chat_id = links::managed_chat(&db, feed_id, msg.chat.id.0, bot_id.0).await?;
+                let action = undo::Action::Unsubscribe;
+                undo::record(&db, msg.chat.id.0, bot_id.0, action, &[feed_id]).await?;
+                delete_feed(&db, feed_id, chat_id, bot_id.0).await
+            };
+            match deleted.await {
+                Ok(delete_result) => {
+                    bot.send_message(
+                        msg.chat.id,
+                        format!("Deleted {} feed", delete_result.rows_affected),
+                    )
+                    .await?;
+                }
+                Err(error) => {
+                    bot.send_message(msg.chat.id, format!("Error: {}", error))
+                        .await?;
+                }
+            }
+        }
+        LoggedInCommand::Pause { feed_id } => {
+            let paused = match links::managed_chat(&db, feed_id, msg.chat.id.0, bot_id.0).await {
+                Ok(chat_id) => set_feed_paused(&db, feed_id, chat_id, bot_id.0, true).await,
+                Err(error) => Err(error),
+            };
+            match paused {
+                Ok(update_result) => {
+                    bot.send_message(
+                        msg.chat.id,
+                        format!("Paused {} feed", update_result.rows_affected),
+                    )
+                    .await?;
+                }
+                Err(error) => {
+                    bot.send_message(msg.chat.id, format!("Error: {}", error))
+                        .await?;
+                }
+            }
+        }
+        LoggedInCommand::Resume { feed_id } => {
+            let resumed = match links::managed_chat(&db, feed_id, msg.chat.id.0, bot_id.0).await {
+                Ok(chat_id) => set_feed_paused(&db, feed_id, chat_id, bot_id.0, false).await,
+                Err(error) => Err(error),
+            };
+            match resumed {
+                Ok(update_result) => {
+                    bot.send_message(
+                        msg.chat.id,
+                        format!("Resumed {} feed", update_result.rows_affected),
+                    )
+                    .await?;
+                }
+                Err(error) => {
+                    bot.send_message(msg.chat.id, format!("Error: {}", error))
+                        .await?;
+                }
+            }
+        }
+        LoggedInCommand::List => {
+            // Retrieve and list the user's subscribed RSS feeds, followed by the feeds of the
+            // chats linked to this one.
+            let feeds = read_feed(&db, msg.chat.id.0, bot_id.0).await;
+            let linked = links::linked_chats(&db, msg.chat.id.0, bot_id.0).await;
+            match (feeds, linked) {
+                (Ok(feeds), Ok(linked)) => {
+                    let mut feed_list = format_feed_list(&feeds, false);
+                    for chat_id in linked {
+                        let chat_feeds = match read_feed(&db, chat_id, bot_id.0).await {
+                            Ok(chat_feeds) => format_feed_list(&chat_feeds, true),
+                            Err(error) => format!("Error: {}", error),
+                        };
+                        let title = bot
+                            .get_chat(ChatId(chat_id))
+                            .await
+                            .ok()
+                            .and_then(|chat| chat.title().map(String::from))
+                            .unwrap_or_else(|| chat_id.to_string());
+                        feed_list.push_str(&format!(
+                            "\n\n{} ({}):\n{}",
+                            title, chat_id, chat_feeds
+                        ));
+                    }
+                    bot.send_message(msg.chat.id, feed_list).await?;
+                }
+                (Err(error), _) | (_, Err(error)) => {
+                    bot.send_message(msg.chat.id, format!("Error: {}", error))
+                        .await?;
+                }
+            }
+        }
+        LoggedInCommand::Link { code } => {
+            let code = code.trim();
+            if code.is_empty() {
+                if msg.chat.is_private() {
+                    match links::create_code(&db, msg.chat.id.0, bot_id.0).await {
+                        Ok(link_code) => {
+                            bot.send_message(
+                                msg.chat.id,
+                                format!(
+                                    "Send /link {} in the group you want to manage within {} \
+                                     minutes.",
+                                    link_code.code,
+                                    links::CODE_VALIDITY_MINUTES
+                                ),
+                            )
+                            .await?;
+                        }
+                        Err(error) => {
+                            bot.send_message(msg.chat.id, format!("Error: {}", error))
+                                .await?;
+                        }
+                    }
+                } else {
+                    bot.send_message(
+                        msg.chat.id,
+                        "Send /link in a private chat with the bot to get a code.",
+                    )
+                    .await?;
+                }
+            } else {
+                let user = msg.from().filter(|_| !msg.chat.is_private());
+                let is_admin = match user {
+                    Some(user) => bot
+                        .get_chat_member(msg.chat.id, user.id)
+                        .await?
+                        .is_privileged(),
+                    None => false,
+                };
+                match user.filter(|_| is_admin) {
+                    Some(user) => {
+                        let user_id = user.id.0 as i64;
+                        let linked =
+                            links::redeem_code(&db, code, msg.chat.id.0, user_id, bot_id.0).await;
+                        match linked {
+                            Ok(Some(_)) => {
+                                bot.send_message(
+                                    msg.chat.id,
+                                    "Linked this chat, you can now manage its feeds from your \
+                                     private chat with the bot.",
+                                )
+                                .await?;
+                            }
+                            Ok(None) => {
+                                bot.send_message(msg.chat.id, "Invalid or expired code.")
+                                    .await?;
+                            }
+                            Err(error) => {
+                                bot.send_message(msg.chat.id, format!("Error: {}", error))
+                                    .await?;
+                            }
+                        }
+                    }
+                    None => {
+                        bot.send_message(
+                            msg.chat.id,
+                            "Only administrators can link a group to their private chat.",
+                        )
+                        .await?;
+                    }
+                }
+            }
+        }
+        LoggedInCommand::Unlink { chat_id } => {
+            let deleted = links::delete_link(&db, msg.chat.id.0, chat_id, bot_id.0).await;
+            match deleted {
+                Ok(delete_result) => {
+                    bot.send_message(
+                        msg.chat.id,
+                        format!("Unlinked {} chat", delete_result.rows_affected),
+                    )
+                    .await?;
+                }
+                Err(error) => {
+                    bot.send_message(msg.chat.id, format!("Error: {}", error))
+                        .await?;
+                }
+            }
+        }
+        LoggedInCommand::Transfer { feed_id, target } => {
+            let transferred =
+                links::transfer_feed(&db, feed_id, target, msg.chat.id.0, bot_id.0, false).await;
+            send_transfer_result(&bot, &msg, transferred, "Moved").await?;
+        }
+        LoggedInCommand::Copy { feed_id, target } => {
+            let transferred =
+                links::transfer_feed(&db, feed_id, target, msg.chat.id.0, bot_id.0, true).await;
+            send_transfer_result(&bot, &msg, transferred, "Copied").await?;
+        }
+        LoggedInCommand::FilterCategory { feed_id, rules } => {
+            let column = feed::Column::CategoryFilter;
+            let filtered = async {
+                let chat_id = links::managed_chat(&db, feed_id, msg.chat.id.0, bot_id.0).await?;
+                let action = undo::Action::Filter;
+                undo::record(&db, msg.chat.id.0, bot_id.0, action, &[feed_id]).await?;
+                filters::set_feed_filter(&db, feed_id, chat_id, bot_id.0, column, &rules).await
+            };
+            send_update_result(&bot, &msg, filtered.await, "Set filter of").await?;
+        }
+        LoggedInCommand::FilterAuthor { feed_id, rules } => {
+            let column = feed::Column::AuthorFilter;
+            let filtered = async {
+                let chat_id = links::managed_chat(&db, feed_id, msg.chat.id.0, bot_id.0).await?;
+                let action = undo::Action::Filter;
+                undo::record(&db, msg.chat.id.0, bot_id.0, action, &[feed_id]).await?;
+                filters::set_feed_filter(&db, feed_id, chat_id, bot_id.0, column, &rules).await
+            };
+            send_update_result(&bot, &msg, filtered.await, "Set filter of").await?;
+        }
+        LoggedInCommand::LangFilter { feed_id, languages } => {
+            let updated = async {
+                links::managed_chat(&db, feed_id, msg.chat.id.0, bot_id.0).await?;
+                let action = undo::Action::Filter;
+                undo::record(&db, msg.chat.id.0, bot_id.0, action, &[feed_id]).await?;
+                language::set_language_filter(&db, feed_id, msg.chat.id.0, bot_id.0, &languages)
+                    .await
+            };
+            send_update_result(&bot, &msg, updated.await, "Set language filter of").await?;
+        }
+        LoggedInCommand::MinLength { feed_id, length } => {
+            let column = feed::Column::MinLength;
+            let updated = set_managed_feed_option(
+                &db,
+                feed_id,
+                msg.chat.id.0,
+                bot_id.0,
+                column,
+                i32::from(length),
+            )
+            .await;
+            send_update_result(&bot, &msg, updated, "Set minimum length of").await?;
+        }
+        LoggedInCommand::Pipeline { feed_id } => {
+            let text = match pipeline::describe(&db, feed_id, msg.chat.id.0, bot_id.0).await {
+                Ok(text) => text,
+                Err(error) => format!("Error: {}", error),
+            };
+            bot.send_message(msg.chat.id, text).await?;
+        }
+        LoggedInCommand::AddStep { feed_id, step } => {
+            let updated = match step.parse() {
+                Ok(step) => pipeline::add_step(&db, feed_id, msg.chat.id.0, bot_id.0, step).await,
+                Err(error) => Err(error.into()),
+            };
+            send_update_result(&bot, &msg, updated, "Added a step to the pipeline of").await?;
+        }
+        LoggedInCommand::RemoveStep { feed_id, position } => {
+            let updated =
+                pipeline::remove_step(&db, feed_id, msg.chat.id.0, bot_id.0, position).await;
+            send_update_result(&bot, &msg, updated, "Removed a step from the pipeline of").await?;
+        }
+        LoggedInCommand::Rewrite {
+            feed_id,
+            substitution,
+        } => {
+            let updated =
+                pipeline::set_rewrite(&db, feed_id, msg.chat.id.0, bot_id.0, &substitution).await;
+            send_update_result(&bot, &msg, updated, "Set title rewrites of").await?;
+        }
+        LoggedInCommand::LinkRule { feed_id, rule } => {
+            let updated =
+                pipeline::set_link_rule(&db, feed_id, msg.chat.id.0, bot_id.0, &rule).await;
+            send_update_result(&bot, &msg, updated, "Set link rules of").await?;
+        }
+        LoggedInCommand::Dedup { feed_id, enabled } => {
+            let column = feed::Column::Dedup;
+            let updated =
+                set_managed_feed_option(&db, feed_id, msg.chat.id.0, bot_id.0, column, enabled.0)
+                    .await;
+            let verb = if enabled.0 {
+                "Turned on deduplication for"
+            } else {
+                "Turned off deduplication for"
+            };
+            send_update_result(&bot, &msg, updated, verb).await?;
+        }
+        LoggedInCommand::Fediverse { feed_id, option, enabled } => {
+            let updated =
+                fediverse::set_option(&db, feed_id, msg.chat.id.0, bot_id.0, &option, enabled.0)
+                    .await;
+            let verb = if enabled.0 {
+                format!("Turned on the {} of", option.to_lowercase())
+            } else {
+                format!("Turned off the {} of", option.to_lowercase())
+            };
+            send_update_result(&bot, &msg, updated, &verb).await?;
+        }
+        LoggedInCommand::Download { feed_id, url } => {
+            let updated =
+                torrents::set_download_webhook(&db, feed_id, msg.chat.id.0, bot_id.0, &url).await;
+            let verb = if url.eq_ignore_ascii_case("off") {
+                "Removed the download client of"
+            } else {
+                "Set the download client of"
+            };
+            send_update_result(&bot, &msg, updated, verb).await?;
+        }
+        LoggedInCommand::Papers { feed_id, enabled } => {
+            let column = feed::Column::Papers;
+            let updated =
+                set_managed_feed_option(&db, feed_id, msg.chat.id.0, bot_id.0, column, enabled.0)
+                    .await;
+            let verb = if enabled.0 {
+                "Turned on the details of papers for"
+            } else {
+                "Turned off the details of papers for"
+            };
+            send_update_result(&bot, &msg, updated, verb).await?;
+        }
+        LoggedInCommand::ReleaseNotes { feed_id, enabled } => {
+            let column = feed::Column::ReleaseNotes;
+            let updated =
+                set_managed_feed_option(&db, feed_id, msg.chat.id.0, bot_id.0, column, enabled.0)
+                    .await;
+            let verb = if enabled.0 {
+                "Turned on the release notes for"
+            } else {
+                "Turned off the release notes for"
+            };
+            send_update_result(&bot, &msg, updated, verb).await?;
+        }
+        LoggedInCommand::Versions { feed_id, filter } => {
+            let updated =
+                releases::set_filter(&db, feed_id, msg.chat.id.0, bot_id.0, &filter).await;
+            let verb = if filter.eq_ignore_ascii_case("off") {
+                "Removed the version filter of"
+            } else {
+                "Set the version filter of"
+            };
+            send_update_result(&bot, &msg, updated, verb).await?;
+        }
+        LoggedInCommand::Albums { feed_id, enabled } => {
+            let column = feed::Column::Albums;
+            let updated =
+                set_managed_feed_option(&db, feed_id, msg.chat.id.0, bot_id.0, column, enabled.0)
+                    .await;
+            let verb = if enabled.0 {
+                "Turned on albums for"
+            } else {
+                "Turned off albums for"
+            };
+            send_update_result(&bot, &msg, updated, verb).await?;
+        }
+        LoggedInCommand::Comic { feed_id, enabled } => {
+            let column = feed::Column::Comic;
+            let updated =
+                set_managed_feed_option(&db, feed_id, msg.chat.id.0, bot_id.0, column, enabled.0)
+                    .await;
+            let verb = if enabled.0 {
+                "Turned on full images for"
+            } else {
+                "Turned off full images for"
+            };
+            send_update_result(&bot, &msg, updated, verb).await?;
+        }
+        LoggedInCommand::InstantView { feed_id, rhash } => {
+            let updated =
+                instant_view::set_rhash(&db, feed_id, msg.chat.id.0, bot_id.0, &rhash).await;
+            let verb = if rhash.eq_ignore_ascii_case("off") {
+                "Removed the Instant View template of"
+            } else {
+                "Set the Instant View template of"
+            };
+            send_update_result(&bot, &msg, updated, verb).await?;
+        }
+        LoggedInCommand::ContentHash { feed_id, enabled } => {
+            let column = feed::Column::ContentHash;
+            let updated =
+                set_managed_feed_option(&db, feed_id, msg.chat.id.0, bot_id.0, column, enabled.0)
+                    .await;
+            let verb = if enabled.0 {
+                "Turned on content hashes for"
+            } else {
+                "Turned off content hashes for"
+            };
+            send_update_result(&bot, &msg, updated, verb).await?;
+        }
+        LoggedInCommand::Archive { feed_id, enabled } => {
+            let column = feed::Column::Archive;
+            let updated =
+                set_managed_feed_option(&db, feed_id, msg.chat.id.0, bot_id.0, column, enabled.0)
+                    .await;
+            let verb = if enabled.0 {
+                "Turned on archive links for"
+            } else {
+                "Turned off archive links for"
+            };
+            send_update_result(&bot, &msg, updated, verb).await?;
+        }
+        LoggedInCommand::SpamFilter { feed_id, enabled } => {
+            let column = feed::Column::SpamFilter;
+            let updated =
+                set_managed_feed_option(&db, feed_id, msg.chat.id.0, bot_id.0, column, enabled.0)
+                    .await;
+            let verb = if enabled.0 {
+                "Turned on the spam filter for"
+            } else {
+                "Turned off the spam filter for"
+            };
+            send_update_result(&bot, &msg, updated, verb).await?;
+        }
+        LoggedInCommand::Spam { feed_id } => {
+            let text = match spam::review(&db, feed_id, msg.chat.id.0, bot_id.0).await {
+                Ok(text) => text,
+                Err(error) => format!("Error: {}", error),
+            };
+            bot.send_message(msg.chat.id, text).await?;
+        }
+        LoggedInCommand::EditUpdates { feed_id, enabled } => {
+            let column = feed::Column::EditUpdates;
+            let updated =
+                set_managed_feed_option(&db, feed_id, msg.chat.id.0, bot_id.0, column, enabled.0)
+                    .await;
+            let verb = if enabled.0 {
+                "Turned on editing of updated items for"
+            } else {
+                "Turned off editing of updated items for"
+            };
+            send_update_result(&bot, &msg, updated, verb).await?;
+        }
+        LoggedInCommand::Pin { feed_id, mode } => {
+            let updated = pins::set_pin_mode(&db, feed_id, msg.chat.id.0, bot_id.0, mode).await;
+            let verb = match mode {
+                PinMode::Off => "Stopped pinning items of",
+                PinMode::On => "Pinning items of",
+                PinMode::Replace => "Pinning only the latest item of",
+            };
+            send_update_result(&bot, &msg, updated, verb).await?;
+        }
+        LoggedInCommand::Severity { feed_id, rules } => {
+            let updated = severity::set_rules(&db, feed_id, msg.chat.id.0, bot_id.0, &rules).await;
+            send_update_result(&bot, &msg, updated, "Set severity rules of").await?;
+        }
+        LoggedInCommand::SetIcon { feed_id, icon } => {
+            let updated = if icon.chars().count() > format::MAX_ICON_CHARS
+                || icon.contains(char::is_whitespace)
+            {
+                Err(format!(
+                    "The icon must be a single emoji of at most {} characters",
+                    format::MAX_ICON_CHARS
+                )
+                .into())
+            } else {
+                let icon = Some(icon).filter(|icon| !icon.is_empty());
+                let column = feed::Column::Icon;
+                set_managed_feed_option(&db, feed_id, msg.chat.id.0, bot_id.0, column, icon).await
+            };
+            send_update_result(&bot, &msg, updated, "Set icon of").await?;
+        }
+        LoggedInCommand::Thumbnail { feed_id, enabled } => {
+            let column = feed::Column::Thumbnail;
+            let updated =
+                set_managed_feed_option(&db, feed_id, msg.chat.id.0, bot_id.0, column, enabled.0)
+                    .await;
+            let verb = if enabled.0 {
+                "Turned on thumbnails for"
+            } else {
+                "Turned off thumbnails for"
+            };
+            send_update_result(&bot, &msg, updated, verb).await?;
+        }
+        LoggedInCommand::Hashtags { feed_id, enabled } => {
+            let column = feed::Column::Hashtags;
+            let updated =
+                set_managed_feed_option(&db, feed_id, msg.chat.id.0, bot_id.0, column, enabled.0)
+                    .await;
+            let verb = if enabled.0 {
+                "Turned on hashtags for"
+            } else {
+                "Turned off hashtags for"
+            };
+            send_update_result(&bot, &msg, updated, verb).await?;
+        }
+        LoggedInCommand::ReadingTime { feed_id, enabled } => {
+            let column = feed::Column::ReadingTime;
+            let updated =
+                set_managed_feed_option(&db, feed_id, msg.chat.id.0, bot_id.0, column, enabled.0)
+                    .await;
+            let verb = if enabled.0 {
+                "Turned on reading time for"
+            } else {
+                "Turned off reading time for"
+            };
+            send_update_result(&bot, &msg, updated, verb).await?;
+        }
+        LoggedInCommand::Weekly { enabled } => {
+            let updated =
+                summary::set_weekly_summary(&db, msg.chat.id.0, bot_id.0, enabled.0).await;
+            let text = match updated {
+                Ok(_update_result) if enabled.0 => {
+                    "You will receive a summary of your feeds every Monday.".to_string()
+                }
+                Ok(_update_result) => "Turned off the weekly summary.".to_string(),
+                Err(error) => format!("Error: {}", error),
+            };
+            bot.send_message(msg.chat.id, text).await?;
+        }
+        LoggedInCommand::MaxRate { feed_id, rate } => {
+            let updated = rate::set_max_rate(&db, feed_id, msg.chat.id.0, bot_id.0, &rate).await;
+            send_update_result(&bot, &msg, updated, "Set maximum rate of").await?;
+        }
+        LoggedInCommand::Mute { feed_id, args } => {
+            let muted = mute::mute_feed(&db, feed_id, msg.chat.id.0, bot_id.0, &args).await;
+            let text = match muted {
+                Ok((update_result, Some(until))) => format!(
+                    "Muted {} feed until {} UTC",
+                    update_result.rows_affected,
+                    until.format("%Y-%m-%d %H:%M")
+                ),
+                Ok((update_result, None)) => {
+                    format!("Unmuted {} feed", update_result.rows_affected)
+                }
+                Err(error) => format!("Error: {}", error),
+            };
+            bot.send_message(msg.chat.id, text).await?;
+        }
+        LoggedInCommand::Window { feed_id, window } => {
+            let updated =
+                window::set_window(&db, feed_id, msg.chat.id.0, bot_id.0, &window).await;
+            send_update_result(&bot, &msg, updated, "Set delivery window of").await?;
+        }
+        LoggedInCommand::Nsfw { mode } => {
+            let text = match nsfw::set_mode(&db, msg.chat.id.0, bot_id.0, &mode).await {
+                Ok(NsfwMode::Show) => "NSFW items will be sent as they are.".to_string(),
+                Ok(NsfwMode::Spoiler) => "NSFW items will be hidden behind a spoiler.".to_string(),
+                Ok(NsfwMode::Button) => "NSFW items will be shown with a button.".to_string(),
+                Ok(NsfwMode::Drop) => "NSFW items will not be sent.".to_string(),
+                Err(error) => format!("Error: {}", error),
+            };
+            bot.send_message(msg.chat.id, text).await?;
+        }
+        LoggedInCommand::Folder { args } => {
+            let text = match folders::run(&db, msg.chat.id.0, bot_id.0, &args).await {
+                Ok(text) => text,
+                Err(error) => format!("Error: {}", error),
+            };
+            bot.send_message(msg.chat.id, text).await?;
+        }
+        LoggedInCommand::Priority { feed_id, priority } => {
+            let updated =
+                priority::set_priority(&db, feed_id, msg.chat.id.0, bot_id.0, priority).await;
+            let verb = format!("Set priority {} of", priority);
+            send_update_result(&bot, &msg, updated, &verb).await?;
+        }
+        LoggedInCommand::Interval { feed_id, interval } => {
+            let updated =
+                schedule::set_interval(&db, feed_id, msg.chat.id.0, bot_id.0, &interval).await;
+            send_update_result(&bot, &msg, updated, "Set check interval of").await?;
+        }
+        LoggedInCommand::Destinations { feed_id, args } => {
+            let chat_id = msg.chat.id.0;
+            let ran =
+                destinations::run(&db, feed_id, chat_id, msg.thread_id, bot_id.0, &args).await;
+            let text = match ran {
+                Ok(text) => text,
+                Err(error) => format!("Error: {}", error),
+            };
+            bot.send_message(msg.chat.id, text).await?;
+        }
+        LoggedInCommand::Webhook { url } => {
+            let text = match webhooks::set_webhook(&db, msg.chat.id.0, bot_id.0, &url).await {
+                Ok((_update_result, Some(secret))) => format!(
+                    "Items will be posted to {}. Check the {} header, the HMAC-SHA256 of the \
+                     body, with the secret {}",
+                    url,
+                    webhooks::SIGNATURE_HEADER,
+                    secret
+                ),
+                Ok((_update_result, None)) => "Removed the webhook.".to_string(),
+                Err(error) => format!("Error: {}", error),
+            };
+            bot.send_message(msg.chat.id, text).await?;
+        }
+        LoggedInCommand::Shortener { args } => {
+            let updated = shorteners::set_shortener(&db, msg.chat.id.0, bot_id.0, &args).await;
+            let text = match updated {
+                Ok(_) if args.trim().eq_ignore_ascii_case("off") => {
+                    "Links will be sent unchanged.".to_string()
+                }
+                Ok(_) => "Links will be shortened.".to_string(),
+                Err(error) => format!("Error: {}", error),
+            };
+            bot.send_message(msg.chat.id, text).await?;
+        }
+        LoggedInCommand::TrackClicks { enabled } => {
+            let text = match clicks::set_tracking(&db, msg.chat.id.0, bot_id.0, enabled.0).await {
+                Ok(_) if enabled.0 => "Clicks on links will be counted, see /stats.".to_string(),
+                Ok(_) => "Clicks on links won't be counted.".to_string(),
+                Err(error) => format!("Error: {}", error),
+            };
+            bot.send_message(msg.chat.id, text).await?;
+        }
+        LoggedInCommand::Stats => {
+            let text = match clicks::stats(&db, msg.chat.id.0, bot_id.0).await {
+                Ok(text) => text,
+                Err(error) => format!("Error: {}", error),
+            };
+            bot.send_message(msg.chat.id, text).await?;
+        }
+        LoggedInCommand::History { args } => {
+            let text = match history::history(&db, msg.chat.id.0, bot_id.0, &args).await {
+                Ok(text) => text,
+                Err(error) => format!("Error: {}", error),
+            };
+            bot.send_message(msg.chat.id, text)
+                .disable_web_page_preview(true)
+                .await?;
+        }
+        LoggedInCommand::Undo => {
+            let text = match undo::undo(&db, msg.chat.id.0, bot_id.0).await {
+                Ok(text) => text,
+                Err(error) => format!("Error: {}", error),
+            };
+            bot.send_message(msg.chat.id, text).await?;
+        }
+        LoggedInCommand::Defaults { args } => {
+            let text = match defaults::run(&db, msg.chat.id.0, bot_id.0, &args).await {
+                Ok(text) => text,
+                Err(error) => format!("Error: {}", error),
+            };
+            bot.send_message(msg.chat.id, text).await?;
+        }
+        LoggedInCommand::CatchUp { value } => {
+            let text = match catchup::set_catch_up(&db, msg.chat.id.0, bot_id.0, &value).await {
+                Ok((CatchUp::All, _)) => {
+                    "Every missed item will be sent after a downtime.".to_string()
+                }
+                Ok((CatchUp::Hours(hours), _)) => format!(
+                    "The missed items of the last {} hours will be sent after a downtime.",
+                    hours
+                ),
+                Ok((CatchUp::Skip, _)) => {
+                    "Missed items will be skipped after a downtime.".to_string()
+                }
+                Err(error) => format!("Error: {}", error),
+            };
+            bot.send_message(msg.chat.id, text).await?;
+        }
+        LoggedInCommand::Breaking { enabled } => {
+            let text = match bursts::set_breaking(&db, msg.chat.id.0, bot_id.0, enabled.0).await {
+                Ok(_) if enabled.0 => "Breaking news will be grouped.".to_string(),
+                Ok(_) => "Breaking news won't be grouped.".to_string(),
+                Err(error) => format!("Error: {}", error),
+            };
+            bot.send_message(msg.chat.id, text).await?;
+        }
+        LoggedInCommand::Follow { name } => {
+            let text = match follows::follow(&db, msg.chat.id.0, bot_id.0, &name).await {
+                Ok(text) => text,
+                Err(error) => format!("Error: {}", error),
+            };
+            bot.send_message(msg.chat.id, text).await?;
+        }
+        LoggedInCommand::Unfollow { name } => {
+            let text = match follows::unfollow(&db, msg.chat.id.0, bot_id.0, &name).await {
+                Ok(0) => format!("Not following {}", name.trim()),
+                Ok(_) => format!("Stopped following {}", name.trim()),
+                Err(error) => format!("Error: {}", error),
+            };
+            bot.send_message(msg.chat.id, text).await?;
+        }
+        LoggedInCommand::Newsletter => {
+            let text = match newsletters::subscribe(&db, msg.chat.id.0, bot_id.0).await {
+                Ok(address) => format!("Newsletters sent to {} will appear in this chat.", address),
+                Err(error) => format!("Error: {}", error),
+            };
+            bot.send_message(msg.chat.id, text).await?;
+        }
+        LoggedInCommand::Premium => {
+            premium::send_invoice(&bot, msg.chat.id).await?;
+        }
+        LoggedInCommand::Report { feed_id, reason } => {
+            let reason = if reason.is_empty() {
+                "no reason given".to_string()
+            } else {
+                reason
+            };
+            let reported =
+                moderation::report_feed(&db, feed_id, msg.chat.id.0, bot_id.0, &reason).await;
+            let text = match reported {
+                Ok(report) => {
+                    moderation::notify_admin(&bots, admin, &report).await;
+                    "Thank you, the operators of the bot will review the feed.".to_string()
+                }
+                Err(error) => format!("Error: {}", error),
+            };
+            bot.send_message(msg.chat.id, text).await?;
+        }
+        _ if cmd_is_admin && !admin.is(msg.chat.id.0) => {
+            bot.send_message(msg.chat.id, "Only the admin chat can use this command.")
+                .await?;
+        }
+        LoggedInCommand::Flags => {
+            let text = match flags::list(&db).await {
+                Ok(flags) => flags
+                    .iter()
+                    .map(|(flag, value)| format!("{}: {}", flag.name(), value))
+                    .collect::<Vec<String>>()
+                    .join("\n"),
+                Err(error) => format!("Error: {}", error),
+            };
+            bot.send_message(msg.chat.id, text).await?;
+        }
+        LoggedInCommand::SetFlag { flag, value } => {
+            let text = match flags::set(&db, &flag, &value).await {
+                Ok(flag) => format!("Set {} to {}", flag.name(), value.to_lowercase()),
+                Err(error) => format!("Error: {}", error),
+            };
+            bot.send_message(msg.chat.id, text).await?;
+        }
+        LoggedInCommand::Reports => {
+            let text = match moderation::open_reports(&db).await {
+                Ok(reports) => moderation::format_reports(&reports),
+                Err(error) => format!("Error: {}", error),
+            };
+            bot.send_message(msg.chat.id, text).await?;
+        }
+        LoggedInCommand::Dismiss { report_id } => {
+            let text = match moderation::dismiss(&db, report_id).await {
+                Ok(update_result) => format!("Dismissed {} report", update_result.rows_affected),
+                Err(error) => format!("Error: {}", error),
+            };
+            bot.send_message(msg.chat.id, text).await?;
+        }
+        LoggedInCommand::Block { pattern } => {
+            let text = match moderation::block(&bots, &db, &pattern).await {
+                Ok(removed) => format!("Blocked {} and removed {} feed", pattern, removed),
+                Err(error) => format!("Error: {}", error),
+            };
+            bot.send_message(msg.chat.id, text).await?;
+        }
+        LoggedInCommand::Unblock { pattern } => {
+            let text = match moderation::unblock(&db, &pattern).await {
+                Ok(delete_result) => format!("Unblocked {} pattern", delete_result.rows_affected),
+                Err(error) => format!("Error: {}", error),
+            };
+            bot.send_message(msg.chat.id, text).await?;
+        }
+        LoggedInCommand::Replace { link, replacement } => {
+            let replaced = moderation::replace(&bots, &db, &link, &replacement).await;
+            let text = match replaced {
+                Ok(moved) => {
+                    format!("Blocked {} and moved {} feed to {}", link, moved, replacement)
+                }
+                Err(error) => format!("Error: {}", error),
+            };
+            bot.send_message(msg.chat.id, text).await?;
+        }
+        LoggedInCommand::Alias { args } => {
+            let text = match aliases::run(&bots, &db, &args).await {
+                Ok(text) => text,
+                Err(error) => format!("Error: {}", error),
+            };
+            bot.send_message(msg.chat.id, text).await?;
+        }
+        LoggedInCommand::Bridge { args } => {
+            let text = match bridges::run(&db, bot_id.0, &args).await {
+                Ok(text) => text,
+                Err(error) => format!("Error: {}", error),
+            };
+            bot.send_message(msg.chat.id, text).await?;
+        }
+        LoggedInCommand::Inspect { chat_id } => {
+            let text = match inspect::inspect(&db, chat_id).await {
+                Ok(text) => text,
+                Err(error) => format!("Error: {}", error),
+            };
+            bot.send_message(msg.chat.id, text).await?;
+        }
+        LoggedInCommand::Bandwidth => {
+            let text = match sources::bandwidth(&db).await {
+                Ok(text) => text,
+                Err(error) => format!("Error: {}", error),
+            };
+            bot.send_message(msg.chat.id, text).await?;
+        }
+        LoggedInCommand::Tls { args } => {
+            let text = match tls::run(&db, &args).await {
+                Ok(text) => text,
+                Err(error) => format!("Error: {}", error),
+            };
+            bot.send_message(msg.chat.id, text).await?;
+        }
+        LoggedInCommand::Maintenance { enabled } => {
+            maintenance::set(enabled.0);
+            let text = if enabled.0 {
+                "Maintenance mode is on: feeds are not polled and commands are refused."
+            } else {
+                "Maintenance mode is off."
+            };
+            bot.send_message(msg.chat.id, text).await?;
+        }
+        LoggedInCommand::Reload => {
+            let text = match config::reload(&db).await {
+                Ok(description) => format!("Reloaded the configuration. {}", description),
+                Err(error) => format!("Error: {}", error),
+            };
+            bot.send_message(msg.chat.id, text).await?;
+        }
+        LoggedInCommand::DeleteAccount => {
+            let deleted = delete_chat(&db, msg.chat.id.0, bot_id.0).await;
+            match deleted {
+                Ok(_delete_result) => {
+                    let name = chat_name(&msg);
+                    let (chat_id, bot_id) = (msg.chat.id.0, bot_id.0);
+                    signups::record(&db, &bots, admin, Event::Deleted, chat_id, bot_id, &name)
+                        .await;
+                    bot.send_message(msg.chat.id, "Bye bye. Your account has been deleted.")
+                        .await?;
+                }
+                Err(error) => {
+                    bot.send_message(msg.chat.id, format!("Error: {}", error))
+                        .await?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,211 @@
+//! Links between a user's private chat and the group chats they administer.
+//!
+//! `/link` in the private chat creates a one-time code, which an administrator of the group
+//! sends back with `/link <code>` in the group. The private chat can then manage the feeds of
+//! the group and sees them in its `/list`.
+
+use std::error::Error;
+
+use chrono::{Duration, Utc};
+use rand::{distributions::Alphanumeric, Rng};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, DatabaseConnection, DeleteResult, EntityTrait,
+    QueryFilter, TransactionTrait,
+};
+
+use entity::{chat_link, feed, item_hash, link_code};
+
+const CODE_LENGTH: usize = 8;
+pub(crate) const CODE_VALIDITY_MINUTES: i64 = 10;
+
+/// Creates a one-time code that links `chat_id` to the group it is sent to.
+///
+/// Expired codes are removed at the same time.
+pub(crate) async fn create_code(
+    db: &DatabaseConnection,
+    chat_id: i64,
+    bot_id: i64,
+) -> Result<link_code::Model, Box<dyn Error + Send + Sync>> {
+    entity::prelude::LinkCode::delete_many()
+        .filter(link_code::Column::ExpiresAt.lt(Utc::now().naive_utc()))
+        .exec(db)
+        .await?;
+    let code: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(CODE_LENGTH)
+        .map(char::from)
+        .collect();
+    let new_code = link_code::ActiveModel {
+        code: ActiveValue::Set(code),
+        chat_id: ActiveValue::Set(chat_id),
+        bot_id: ActiveValue::Set(bot_id),
+        expires_at: ActiveValue::Set(
+            (Utc::now() + Duration::minutes(CODE_VALIDITY_MINUTES)).naive_utc(),
+        ),
+    };
+    Ok(new_code.insert(db).await?)
+}
+
+/// Consumes `code` and links the private chat that created it to `chat_id`.
+///
+/// Returns `None` if the code doesn't exist, has expired or was created by someone other than
+/// `user_id`, whose private chat has the same id.
+pub(crate) async fn redeem_code(
+    db: &DatabaseConnection,
+    code: &str,
+    chat_id: i64,
+    user_id: i64,
+    bot_id: i64,
+) -> Result<Option<chat_link::Model>, Box<dyn Error + Send + Sync>> {
+    let link_code = entity::prelude::LinkCode::find_by_id(code.to_string())
+        .filter(link_code::Column::BotId.eq(bot_id))
+        .one(db)
+        .await?;
+    let Some(link_code) = link_code else {
+        return Ok(None);
+    };
+    if link_code.chat_id != user_id {
+        return Ok(None);
+    }
+    entity::prelude::LinkCode::delete_by_id(link_code.code)
+        .exec(db)
+        .await?;
+    if link_code.expires_at < Utc::now().naive_utc() {
+        return Ok(None);
+    }
+    let new_link = chat_link::ActiveModel {
+        bot_id: ActiveValue::Set(bot_id),
+        owner_id: ActiveValue::Set(link_code.chat_id),
+        chat_id: ActiveValue::Set(chat_id),
+        ..Default::default()
+    };
+    Ok(Some(new_link.insert(db).await?))
+}
+
+/// Returns the ids of the chats linked to `owner_id`.
+pub(crate) async fn linked_chats(
+    db: &DatabaseConnection,
+    owner_id: i64,
+    bot_id: i64,
+) -> Result<Vec<i64>, Box<dyn Error + Send + Sync>> {
+    Ok(entity::prelude::ChatLink::find()
+        .filter(chat_link::Column::BotId.eq(bot_id))
+        .filter(chat_link::Column::OwnerId.eq(owner_id))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|link| link.chat_id)
+        .collect())
+}
+
+pub(crate) async fn delete_link(
+    db: &DatabaseConnection,
+    owner_id: i64,
+    chat_id: i64,
+    bot_id: i64,
+) -> Result<DeleteResult, Box<dyn Error + Send + Sync>> {
+    Ok(entity::prelude::ChatLink::delete_many()
+        .filter(chat_link::Column::BotId.eq(bot_id))
+        .filter(chat_link::Column::OwnerId.eq(owner_id))
+        .filter(chat_link::Column::ChatId.eq(chat_id))
+        .exec(db)
+        .await?)
+}
+
+/// Returns the chat owning feed `feed_id` if `chat_id` may manage it, that is if it is
+/// `chat_id` itself or a chat linked to it. Returns `chat_id` otherwise, so that the feed
+/// commands keep matching nothing for feeds of other chats.
+pub(crate) async fn managed_chat(
+    db: &DatabaseConnection,
+    feed_id: i64,
+    chat_id: i64,
+    bot_id: i64,
+) -> Result<i64, Box<dyn Error + Send + Sync>> {
+    let feed = entity::prelude::Feed::find_by_id(feed_id)
+        .filter(feed::Column::BotId.eq(bot_id))
+        .one(db)
+        .await?;
+    let Some(feed) = feed else {
+        return Ok(chat_id);
+    };
+    if feed.chat_id == chat_id {
+        return Ok(chat_id);
+    }
+    let linked = linked_chats(db, chat_id, bot_id).await?;
+    if linked.contains(&feed.chat_id) {
+        Ok(feed.chat_id)
+    } else {
+        Ok(chat_id)
+    }
+}
+
+/// Moves feed `feed_id` to chat `target`, or copies it if `copy` is set, keeping its settings
+/// and the date of the last item that was sent.
+///
+/// Both the chat owning the feed and `target` must be `chat_id` or linked to it, otherwise
+/// nothing happens and `None` is returned. Fails if `target` already has as many feeds as it is
+/// allowed or is already subscribed to the source of the feed. Copies keep the hashes of the
+/// items already seen, so that they aren't sent again.
+pub(crate) async fn transfer_feed(
+    db: &DatabaseConnection,
+    feed_id: i64,
+    target: i64,
+    chat_id: i64,
+    bot_id: i64,
+    copy: bool,
+) -> Result<Option<feed::Model>, Box<dyn Error + Send + Sync>> {
+    let linked = linked_chats(db, chat_id, bot_id).await?;
+    let controls = |id: i64| id == chat_id || linked.contains(&id);
+    let feed = entity::prelude::Feed::find_by_id(feed_id)
+        .filter(feed::Column::BotId.eq(bot_id))
+        .one(db)
+        .await?;
+    let Some(feed) = feed.filter(|feed| controls(feed.chat_id) && controls(target)) else {
+        return Ok(None);
+    };
+    let subscribed = entity::prelude::Feed::find()
+        .filter(feed::Column::ChatId.eq(target))
+        .filter(feed::Column::BotId.eq(bot_id))
+        .filter(feed::Column::SourceId.eq(feed.source_id))
+        .filter(feed::Column::Id.ne(feed.id))
+        .one(db)
+        .await?;
+    if subscribed.is_some() {
+        return Err(format!("Chat {} is already subscribed to this feed", target).into());
+    }
+    if copy || target != feed.chat_id {
+        crate::check_feed_quota(db, target, bot_id).await?;
+    }
+    if copy {
+        let hashes = entity::prelude::ItemHash::find()
+            .filter(item_hash::Column::FeedId.eq(feed.id))
+            .all(db)
+            .await?;
+        let mut new_feed: feed::ActiveModel = feed.into();
+        new_feed = new_feed.reset_all();
+        new_feed.id = ActiveValue::NotSet;
+        new_feed.chat_id = ActiveValue::Set(target);
+        let txn = db.begin().await?;
+        new_feed.short_id = ActiveValue::Set(crate::short_ids::next(&txn, target, bot_id).await?);
+        let new_feed = new_feed.insert(&txn).await?;
+        for hash in hashes {
+            let new_hash = item_hash::ActiveModel {
+                feed_id: ActiveValue::Set(new_feed.id),
+                hash: ActiveValue::Set(hash.hash),
+                seen_at: ActiveValue::Set(hash.seen_at),
+                ..Default::default()
+            };
+            new_hash.insert(&txn).await?;
+        }
+        txn.commit().await?;
+        crate::registry::invalidate(new_feed.id);
+        Ok(Some(new_feed))
+    } else {
+        let mut moved_feed: feed::ActiveModel = feed.into();
+        moved_feed.chat_id = ActiveValue::Set(target);
+        moved_feed.short_id = ActiveValue::Set(crate::short_ids::next(db, target, bot_id).await?);
+        let moved_feed = moved_feed.update(db).await?;
+        crate::registry::invalidate(moved_feed.id);
+        Ok(Some(moved_feed))
+    }
+}
@@ -0,0 +1,25 @@
+//! Maintenance mode.
+//!
+//! `/maintenance on` in the admin chat stops polling feeds and the daily and weekly jobs, and
+//! makes the bots answer every command but `/help` and every button with [`NOTICE`], as do the
+//! management API and the web views, so that the database can be migrated or repaired without
+//! stopping the process. Feeds keep their state, so polling resumes where it stopped with
+//! `/maintenance off`. The mode is kept in memory and doesn't touch the database, which may be
+//! unavailable meanwhile, and a restart turns it off.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+pub(crate) const NOTICE: &str =
+    "The bot is under maintenance, please try again later. Your feeds will be sent afterwards.";
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Returns whether maintenance mode is on.
+pub(crate) fn is_on() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Turns maintenance mode on or off.
+pub(crate) fn set(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
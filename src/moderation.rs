@@ -0,0 +1,290 @@
+//! Abuse reports and the blocklist.
+//!
+//! Users send `/report <feed id> [reason]` about feeds that break the rules of the instance,
+//! and the report is forwarded to the admin chat. The admin chat reviews the open reports with
+//! `/reports` and either dismisses them or blocks the feed with `/block <domain or link>`.
+//! Blocking removes every subscription to a matching feed, telling its chat why, and refuses
+//! new ones. A domain also blocks its subdomains. `/replace <link> <new link>` blocks a feed
+//! as well but moves its subscriptions to another feed instead of removing them, for feeds that
+//! started redirecting to malware or moved to a new address.
+
+use std::error::Error;
+
+use chrono::Utc;
+use sea_orm::{
+    sea_query::{Expr, OnConflict},
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, DeleteResult, EntityTrait,
+    QueryFilter, QueryOrder, Set, UpdateResult,
+};
+use teloxide::{
+    prelude::{Bot, Requester},
+    types::ChatId,
+};
+
+use crate::admin::AdminChat;
+use crate::bots::Bots;
+use crate::sources::normalize_link;
+use entity::{blocklist, feed, feed_source, report};
+
+/// Returns the lowercase host of `link`.
+pub(crate) fn host(link: &str) -> Option<String> {
+    let url = reqwest::Url::parse(link.trim()).ok()?;
+    Some(url.host_str()?.to_lowercase())
+}
+
+/// Returns `pattern` as it is stored: a normalized link, or a lowercase domain.
+fn normalize_pattern(pattern: &str) -> String {
+    if pattern.contains("://") {
+        normalize_link(pattern)
+    } else {
+        pattern.trim().trim_end_matches('/').to_lowercase()
+    }
+}
+
+/// Returns whether the blocklist `pattern` matches `link`.
+fn matches(pattern: &str, link: &str) -> bool {
+    if pattern.contains("://") {
+        return normalize_link(link) == pattern;
+    }
+    host(link).is_some_and(|host| host == pattern || host.ends_with(&format!(".{}", pattern)))
+}
+
+/// Returns whether `link` is blocked.
+pub(crate) async fn is_blocked(db: &DatabaseConnection, link: &str) -> Result<bool, DbErr> {
+    Ok(entity::prelude::Blocklist::find()
+        .all(db)
+        .await?
+        .iter()
+        .any(|blocked| matches(&blocked.pattern, link)))
+}
+
+/// Records a report of chat `chat_id` about feed `feed_id`, which it must be able to manage.
+pub(crate) async fn report_feed(
+    db: &DatabaseConnection,
+    feed_id: i64,
+    chat_id: i64,
+    bot_id: i64,
+    reason: &str,
+) -> Result<report::Model, Box<dyn Error + Send + Sync>> {
+    let feed_chat_id = crate::links::managed_chat(db, feed_id, chat_id, bot_id).await?;
+    let feed = entity::prelude::Feed::find_by_id(feed_id)
+        .filter(feed::Column::ChatId.eq(feed_chat_id))
+        .filter(feed::Column::BotId.eq(bot_id))
+        .one(db)
+        .await?
+        .ok_or_else(|| format!("No feed {}", feed_id))?;
+    let new_report = report::ActiveModel {
+        chat_id: Set(chat_id),
+        bot_id: Set(bot_id),
+        feed_id: Set(feed.id),
+        link: Set(feed.link),
+        reason: Set(reason.to_string()),
+        ..Default::default()
+    };
+    Ok(new_report.insert(db).await?)
+}
+
+/// Forwards `report` to the admin chat, if there is one.
+pub(crate) async fn notify_admin(bots: &Bots, admin: AdminChat, report: &report::Model) {
+    let Some(admin_chat) = admin.0 else {
+        return;
+    };
+    let pattern = host(&report.link).unwrap_or_else(|| report.link.clone());
+    let text = format!(
+        "Report {}: feed {} ({}) reported by chat {}: {}\n/block {} or /dismiss {}",
+        report.id, report.feed_id, report.link, report.chat_id, report.reason, pattern, report.id
+    );
+    if let Err(err) = bots.default_bot().send_message(ChatId(admin_chat), text).await {
+        log::error!("Error sending report to the admin chat: {}", err);
+    }
+}
+
+/// Returns the reports that were neither dismissed nor resolved by a block, oldest first.
+pub(crate) async fn open_reports(db: &DatabaseConnection) -> Result<Vec<report::Model>, DbErr> {
+    entity::prelude::Report::find()
+        .filter(report::Column::Resolved.eq(false))
+        .order_by_asc(report::Column::CreatedAt)
+        .all(db)
+        .await
+}
+
+/// Formats `reports` for the admin chat.
+pub(crate) fn format_reports(reports: &[report::Model]) -> String {
+    if reports.is_empty() {
+        return "No open reports.".to_string();
+    }
+    reports
+        .iter()
+        .map(|report| {
+            format!(
+                "{}: feed {} ({}) by chat {} on {}: {}",
+                report.id,
+                report.feed_id,
+                report.link,
+                report.chat_id,
+                report.created_at.format("%Y-%m-%d"),
+                report.reason
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Closes report `id` without action.
+pub(crate) async fn dismiss(db: &DatabaseConnection, id: i64) -> Result<UpdateResult, DbErr> {
+    entity::prelude::Report::update_many()
+        .col_expr(report::Column::Resolved, Expr::value(true))
+        .filter(report::Column::Id.eq(id))
+        .exec(db)
+        .await
+}
+
+/// Adds the normalized `pattern` to the blocklist.
+async fn add_to_blocklist(db: &DatabaseConnection, pattern: &str) -> Result<(), DbErr> {
+    let blocked = blocklist::ActiveModel {
+        pattern: Set(pattern.to_string()),
+        ..Default::default()
+    };
+    entity::prelude::Blocklist::insert(blocked)
+        .on_conflict(
+            OnConflict::column(blocklist::Column::Pattern)
+                .do_nothing()
+                .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await?;
+    Ok(())
+}
+
+/// Returns the sources matching the normalized `pattern` and the feeds subscribed to them, and
+/// resolves the reports about these feeds.
+async fn blocked_feeds(
+    db: &DatabaseConnection,
+    pattern: &str,
+) -> Result<(Vec<i64>, Vec<feed::Model>), DbErr> {
+    let sources: Vec<i64> = entity::prelude::FeedSource::find()
+        .all(db)
+        .await?
+        .into_iter()
+        .filter(|source| matches(pattern, &source.link))
+        .map(|source| source.id)
+        .collect();
+    let feeds = entity::prelude::Feed::find()
+        .filter(feed::Column::SourceId.is_in(sources.clone()))
+        .all(db)
+        .await?;
+    let feed_ids: Vec<i64> = feeds.iter().map(|feed| feed.id).collect();
+    entity::prelude::Report::update_many()
+        .col_expr(report::Column::Resolved, Expr::value(true))
+        .filter(report::Column::FeedId.is_in(feed_ids))
+        .exec(db)
+        .await?;
+    Ok((sources, feeds))
+}
+
+/// Blocks `pattern`, removes the feeds it matches, resolves their reports and tells their chats.
+/// Returns the number of feeds that were removed.
+pub(crate) async fn block(
+    bots: &Bots,
+    db: &DatabaseConnection,
+    pattern: &str,
+) -> Result<u64, Box<dyn Error + Send + Sync>> {
+    let pattern = normalize_pattern(pattern);
+    if pattern.is_empty() {
+        return Err("Expected a domain or a link".into());
+    }
+    add_to_blocklist(db, &pattern).await?;
+    let (sources, feeds) = blocked_feeds(db, &pattern).await?;
+    let feed_ids: Vec<i64> = feeds.iter().map(|feed| feed.id).collect();
+    let deleted = entity::prelude::Feed::delete_many()
+        .filter(feed::Column::Id.is_in(feed_ids.clone()))
+        .exec(db)
+        .await?;
+    feed_ids.into_iter().for_each(crate::registry::invalidate);
+    entity::prelude::FeedSource::delete_many()
+        .filter(feed_source::Column::Id.is_in(sources))
+        .exec(db)
+        .await?;
+
+    for feed in feeds {
+        let Some(bot) = bots.get(feed.bot_id) else {
+            continue;
+        };
+        let text = format!(
+            "The operators of this bot blocked {} ({}), so you are no longer subscribed to it.",
+            feed.title, feed.link
+        );
+        notify_subscriber(bot, &feed, text).await;
+    }
+    Ok(deleted.rows_affected)
+}
+
+/// Blocks the feed at `link` and moves its subscriptions to the feed at `replacement`, telling
+/// their chats. Returns the number of feeds that were moved.
+pub(crate) async fn replace(
+    bots: &Bots,
+    db: &DatabaseConnection,
+    link: &str,
+    replacement: &str,
+) -> Result<u64, Box<dyn Error + Send + Sync>> {
+    let pattern = normalize_pattern(link);
+    if !pattern.contains("://") {
+        return Err(format!("Expected the link of a feed, got {}", link).into());
+    }
+    if normalize_link(replacement) == pattern {
+        return Err("Expected a different feed as replacement".into());
+    }
+    if is_blocked(db, replacement).await? {
+        return Err(format!("{} is blocked", replacement).into());
+    }
+    let channel = crate::validate_feed(&replacement.to_string()).await?;
+    let source = crate::sources::source_for(db, replacement).await?;
+    add_to_blocklist(db, &pattern).await?;
+    let (sources, feeds) = blocked_feeds(db, &pattern).await?;
+    // Items already published by the replacement are not sent
+    let moved = entity::prelude::Feed::update_many()
+        .col_expr(feed::Column::SourceId, Expr::value(source.id))
+        .col_expr(feed::Column::Link, Expr::value(replacement.trim()))
+        .col_expr(feed::Column::Title, Expr::value(channel.title()))
+        .col_expr(feed::Column::UpdatedAt, Expr::value(Utc::now().naive_utc()))
+        .filter(feed::Column::SourceId.is_in(sources.clone()))
+        .exec(db)
+        .await?;
+    for feed in &feeds {
+        crate::registry::invalidate(feed.id);
+    }
+    entity::prelude::FeedSource::delete_many()
+        .filter(feed_source::Column::Id.is_in(sources))
+        .exec(db)
+        .await?;
+
+    for feed in feeds {
+        let Some(bot) = bots.get(feed.bot_id) else {
+            continue;
+        };
+        let text = format!(
+            "The operators of this bot disabled {} ({}) and replaced it with {} ({}) in your \
+             subscriptions.",
+            feed.title,
+            feed.link,
+            channel.title(),
+            replacement.trim()
+        );
+        notify_subscriber(bot, &feed, text).await;
+    }
+    Ok(moved.rows_affected)
+}
+
+async fn notify_subscriber(bot: &Bot, feed: &feed::Model, text: String) {
+    if let Err(err) = bot.send_message(ChatId(feed.chat_id), text).await {
+        log::error!("Error telling chat {} about a blocked feed: {}", feed.chat_id, err);
+    }
+}
+
+/// Removes `pattern` from the blocklist. Feeds that were removed are not restored.
+pub(crate) async fn unblock(db: &DatabaseConnection, pattern: &str) -> Result<DeleteResult, DbErr> {
+    entity::prelude::Blocklist::delete_many()
+        .filter(blocklist::Column::Pattern.eq(normalize_pattern(pattern)))
+        .exec(db)
+        .await
+}
@@ -0,0 +1,118 @@
+//! Temporary muting of feeds.
+//!
+//! `/mute <feed id> 3h` suppresses the items of a feed for the given duration, after which the
+//! feed resumes by itself. Items published meanwhile are dropped, or held and sent as a single
+//! digest when the mute ends if `buffer` is given. Every delivered item also carries a button
+//! muting its feed for [`BUTTON_HOURS`] hours.
+
+use std::error::Error;
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use sea_orm::{
+    sea_query::Expr, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, UpdateResult,
+};
+use teloxide::types::{CallbackQuery, InlineKeyboardButton};
+
+use crate::bots::BotId;
+use entity::feed;
+
+const BUTTON_HOURS: i64 = 24;
+const MUTE_PREFIX: &str = "mute:";
+
+/// Parses a duration such as `30m`, `3h`, `2d` or `1w`.
+pub(crate) fn parse_duration(s: &str) -> Result<Duration, String> {
+    let error = || format!("Expected a duration such as 3h or 2d, got {}", s);
+    let split = s.find(|c: char| !c.is_ascii_digit()).ok_or_else(error)?;
+    let (amount, unit) = s.split_at(split);
+    let amount: i64 = amount.parse().map_err(|_| error())?;
+    match unit.to_lowercase().as_str() {
+        "m" | "min" => Ok(Duration::minutes(amount)),
+        "h" => Ok(Duration::hours(amount)),
+        "d" => Ok(Duration::days(amount)),
+        "w" => Ok(Duration::weeks(amount)),
+        _ => Err(error()),
+    }
+}
+
+/// Returns whether `feed` is muted now.
+pub(crate) fn is_muted(feed: &feed::Model) -> bool {
+    feed.muted_until.is_some_and(|until| until > Utc::now().naive_utc())
+}
+
+/// The button muting feed `feed_id`, attached to its items.
+pub(crate) fn button(feed_id: i64) -> InlineKeyboardButton {
+    InlineKeyboardButton::callback(
+        format!("🔕 {}h", BUTTON_HOURS),
+        format!("{}{}", MUTE_PREFIX, feed_id),
+    )
+}
+
+async fn set_muted_until(
+    db: &DatabaseConnection,
+    id: i64,
+    chat_id: i64,
+    bot_id: i64,
+    until: Option<NaiveDateTime>,
+    buffer: bool,
+) -> Result<UpdateResult, Box<dyn Error + Send + Sync>> {
+    let chat_id = crate::links::managed_chat(db, id, chat_id, bot_id).await?;
+    let updated = entity::prelude::Feed::update_many()
+        .col_expr(feed::Column::MutedUntil, Expr::value(until))
+        .col_expr(feed::Column::MuteBuffer, Expr::value(buffer))
+        .filter(feed::Column::ChatId.eq(chat_id))
+        .filter(feed::Column::BotId.eq(bot_id))
+        .filter(feed::Column::Id.eq(id))
+        .exec(db)
+        .await?;
+    crate::registry::invalidate(id);
+    Ok(updated)
+}
+
+/// Mutes feed `id` as described by `args`, which is `<duration> [buffer|drop]`, or `off` to
+/// unmute it. Returns the end of the mute, if any.
+pub(crate) async fn mute_feed(
+    db: &DatabaseConnection,
+    id: i64,
+    chat_id: i64,
+    bot_id: i64,
+    args: &str,
+) -> Result<(UpdateResult, Option<NaiveDateTime>), Box<dyn Error + Send + Sync>> {
+    let mut words = args.split_whitespace();
+    let until = match words.next() {
+        Some(off) if off.eq_ignore_ascii_case("off") => None,
+        Some(duration) => Some(Utc::now().naive_utc() + parse_duration(duration)?),
+        None => return Err("Expected a duration such as 3h or 2d, or off".into()),
+    };
+    let buffer = match words.next().map(str::to_lowercase).as_deref() {
+        Some("buffer") => true,
+        Some("drop") | None => false,
+        Some(other) => return Err(format!("Expected buffer or drop, got {}", other).into()),
+    };
+    let updated = set_muted_until(db, id, chat_id, bot_id, until, buffer).await?;
+    Ok((updated, until))
+}
+
+/// Returns whether the callback `data` belongs to a mute button.
+pub(crate) fn is_mute_button(data: &str) -> bool {
+    data.starts_with(MUTE_PREFIX)
+}
+
+/// Mutes the feed of a mute button, dropping its items, and returns the answer to show.
+pub(crate) async fn handle_button(
+    q: &CallbackQuery,
+    data: &str,
+    db: &DatabaseConnection,
+    bot_id: BotId,
+) -> String {
+    let feed_id = data
+        .strip_prefix(MUTE_PREFIX)
+        .and_then(|feed_id| feed_id.parse::<i64>().ok());
+    let (Some(message), Some(feed_id)) = (&q.message, feed_id) else {
+        return String::new();
+    };
+    let until = Utc::now().naive_utc() + Duration::hours(BUTTON_HOURS);
+    match set_muted_until(db, feed_id, message.chat.id.0, bot_id.0, Some(until), false).await {
+        Ok(_update_result) => format!("Muted for {} hours", BUTTON_HOURS),
+        Err(error) => format!("Error: {}", error),
+    }
+}
@@ -0,0 +1,239 @@
+//! Newsletters received by email, as a feed.
+//!
+//! `/newsletter` gives a chat an address such as `x7k2m9q4p1ab@<NEWSLETTER_DOMAIN>` and
+//! subscribes it to a virtual feed with the link `newsletter:x7k2m9q4p1ab`. Emails sent to the
+//! address are posted to `/newsletters/inbound` by a [Mailgun](https://www.mailgun.com) route,
+//! stored, and read by the poller like any other feed, so that they go through the same
+//! filters, pipeline and delivery settings. Items link to a copy of the email served at
+//! `<PUBLIC_URL>/newsletters/<inbox>/<id>` if `PUBLIC_URL` is set.
+
+use std::env;
+use std::error::Error;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+    routing::{get, post},
+    Form, Router,
+};
+use chrono::{Duration, Utc};
+use hmac::{Hmac, Mac};
+use rand::{distributions::Alphanumeric, Rng};
+use rss::{Channel, Guid, Item};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder,
+    QuerySelect, Set,
+};
+use serde::Deserialize;
+use sha2::Sha256;
+
+use crate::api::ApiState;
+use entity::{feed, newsletter_item};
+
+const LINK_PREFIX: &str = "newsletter:";
+const INBOX_LENGTH: usize = 12;
+const TITLE: &str = "Newsletters";
+/// Number of emails in the virtual feed.
+const ITEMS: u64 = 20;
+/// Emails are deleted after this many days.
+const RETENTION_DAYS: i64 = 30;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// The fields of a message posted by Mailgun that are used here.
+#[derive(Deserialize)]
+struct Inbound {
+    recipient: String,
+    from: String,
+    subject: Option<String>,
+    #[serde(rename = "body-plain")]
+    body_plain: Option<String>,
+    #[serde(rename = "body-html")]
+    body_html: Option<String>,
+    timestamp: String,
+    token: String,
+    signature: String,
+}
+
+pub(crate) fn routes() -> Router<ApiState> {
+    Router::new()
+        .route("/newsletters/inbound", post(receive))
+        .route("/newsletters/{inbox}/{id}", get(show))
+}
+
+/// Returns whether `link` is the link of a newsletter feed.
+pub(crate) fn is_newsletter(link: &str) -> bool {
+    link.starts_with(LINK_PREFIX)
+}
+
+fn address(inbox: &str, domain: &str) -> String {
+    format!("{}@{}", inbox, domain)
+}
+
+fn web_link(email: &newsletter_item::Model) -> Option<String> {
+    let public_url = env::var("PUBLIC_URL").ok()?;
+    Some(format!(
+        "{}/newsletters/{}/{}",
+        public_url.trim_end_matches('/'),
+        email.inbox,
+        email.id
+    ))
+}
+
+fn item(email: &newsletter_item::Model) -> Item {
+    let mut item = Item::default();
+    item.set_title(email.subject.clone());
+    item.set_author(email.sender.clone());
+    item.set_description(email.text.clone());
+    item.set_link(web_link(email));
+    item.set_pub_date(crate::dates::rfc2822(email.received_at));
+    let mut guid = Guid::default();
+    guid.set_value(format!("{}{}", LINK_PREFIX, email.id));
+    guid.set_permalink(false);
+    item.set_guid(guid);
+    item
+}
+
+/// Returns the virtual feed at `link` with the latest emails it received.
+pub(crate) async fn channel(
+    db: &DatabaseConnection,
+    link: &str,
+) -> Result<Channel, Box<dyn Error + Send + Sync>> {
+    let inbox = link
+        .strip_prefix(LINK_PREFIX)
+        .ok_or_else(|| format!("{} is not a newsletter feed", link))?;
+    let emails = entity::prelude::NewsletterItem::find()
+        .filter(newsletter_item::Column::Inbox.eq(inbox))
+        .order_by_desc(newsletter_item::Column::ReceivedAt)
+        .limit(ITEMS)
+        .all(db)
+        .await?;
+    let mut channel = Channel::default();
+    channel.set_title(TITLE);
+    channel.set_link(link);
+    channel.set_items(emails.iter().map(item).collect::<Vec<Item>>());
+    Ok(channel)
+}
+
+/// Subscribes `chat_id` to its newsletter feed, creating it if needed, and returns the email
+/// address of the feed.
+pub(crate) async fn subscribe(
+    db: &DatabaseConnection,
+    chat_id: i64,
+    bot_id: i64,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let domain =
+        env::var("NEWSLETTER_DOMAIN").map_err(|_| "Newsletters are not enabled on this bot")?;
+    let existing = entity::prelude::Feed::find()
+        .filter(feed::Column::ChatId.eq(chat_id))
+        .filter(feed::Column::BotId.eq(bot_id))
+        .filter(feed::Column::Link.starts_with(LINK_PREFIX))
+        .one(db)
+        .await?;
+    if let Some(feed) = existing {
+        return Ok(address(&feed.link[LINK_PREFIX.len()..], &domain));
+    }
+    // Email addresses are case insensitive in practice
+    let inbox: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(INBOX_LENGTH)
+        .map(|c| char::from(c).to_ascii_lowercase())
+        .collect();
+    let mut channel = Channel::default();
+    channel.set_title(TITLE);
+    channel.set_link(format!("{}{}", LINK_PREFIX, inbox));
+    crate::create_feed(db, &channel, chat_id, bot_id).await?;
+    Ok(address(&inbox, &domain))
+}
+
+/// Checks the signature of a message posted by Mailgun with the webhook signing key.
+fn verify(signing_key: &str, message: &Inbound) -> bool {
+    let Ok(signature) = hex::decode(&message.signature) else {
+        return false;
+    };
+    let mut mac =
+        HmacSha256::new_from_slice(signing_key.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(message.timestamp.as_bytes());
+    mac.update(message.token.as_bytes());
+    mac.verify_slice(&signature).is_ok()
+}
+
+/// Stores an email posted by Mailgun. Emails to unknown addresses are refused with
+/// `406 Not Acceptable`, which Mailgun doesn't retry.
+async fn receive(State(state): State<ApiState>, Form(message): Form<Inbound>) -> Response {
+    let signing_key = match crate::secrets::get("MAILGUN_SIGNING_KEY").await {
+        Ok(signing_key) => signing_key,
+        Err(err) => {
+            log::error!("Error receiving newsletter: {}", err);
+            return StatusCode::SERVICE_UNAVAILABLE.into_response();
+        }
+    };
+    if !verify(&signing_key, &message) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    let Some((inbox, _)) = message.recipient.trim().split_once('@') else {
+        return StatusCode::NOT_ACCEPTABLE.into_response();
+    };
+    let inbox = inbox.to_lowercase();
+    match store(&state.db, &inbox, message).await {
+        Ok(true) => StatusCode::OK.into_response(),
+        Ok(false) => StatusCode::NOT_ACCEPTABLE.into_response(),
+        Err(err) => {
+            log::error!("Error storing newsletter: {}", err);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Stores `message` in `inbox`, and deletes the emails past the retention period. Returns
+/// whether a feed reads the inbox.
+async fn store(
+    db: &DatabaseConnection,
+    inbox: &str,
+    message: Inbound,
+) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    let feeds = entity::prelude::Feed::find()
+        .filter(feed::Column::Link.eq(format!("{}{}", LINK_PREFIX, inbox)))
+        .all(db)
+        .await?;
+    if feeds.is_empty() {
+        return Ok(false);
+    }
+    let email = newsletter_item::ActiveModel {
+        inbox: Set(inbox.to_string()),
+        sender: Set(message.from),
+        subject: Set(message.subject.unwrap_or_default()),
+        text: Set(message.body_plain.unwrap_or_default()),
+        html: Set(message.body_html),
+        ..Default::default()
+    };
+    email.insert(db).await?;
+    entity::prelude::NewsletterItem::delete_many()
+        .filter(
+            newsletter_item::Column::ReceivedAt
+                .lt(Utc::now().naive_utc() - Duration::days(RETENTION_DAYS)),
+        )
+        .exec(db)
+        .await?;
+    Ok(true)
+}
+
+/// Shows the copy of an email. The inbox, which is known only to its chat, is required.
+async fn show(
+    State(state): State<ApiState>,
+    Path((inbox, id)): Path<(String, i64)>,
+) -> Response {
+    let email = entity::prelude::NewsletterItem::find_by_id(id)
+        .filter(newsletter_item::Column::Inbox.eq(inbox))
+        .one(&state.db)
+        .await;
+    match email {
+        Ok(Some(email)) => match email.html {
+            Some(html) => Html(html).into_response(),
+            None => email.text.into_response(),
+        },
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response(),
+    }
+}
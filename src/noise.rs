@@ -0,0 +1,292 @@
+//! Noise scoring and mute suggestions.
+//!
+//! Every delivered item carries a 👍 button whose clicks are counted in the `delivery` table.
+//! Once a day, feeds that sent more than [`NOISY_ITEMS_PER_DAY`] items as their own messages in
+//! the last day without a single click in the last [`ENGAGEMENT_DAYS`] days are reported to their
+//! chat, with buttons to mute the feed for [`MUTE_DURATION`], get its items in a daily digest,
+//! pause it, unsubscribe from it or keep it as it is.
+
+use std::collections::HashMap;
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use sea_orm::{
+    sea_query::{Expr, Query},
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, Set,
+    UpdateResult,
+};
+use teloxide::{
+    payloads::SendMessageSetters,
+    prelude::{Bot, Requester, ResponseResult},
+    types::{CallbackQuery, ChatId, InlineKeyboardButton, InlineKeyboardMarkup, MessageId},
+};
+
+use crate::bots::{BotId, Bots};
+use crate::undo::{self, Action};
+use entity::{delivery, feed};
+
+pub(crate) const NOISY_ITEMS_PER_DAY: usize = 20;
+const ENGAGEMENT_DAYS: i64 = 7;
+/// A feed is suggested again at most once in this many days.
+const SUGGESTION_INTERVAL_DAYS: i64 = 7;
+/// How long the Mute button of a suggestion mutes the feed.
+const MUTE_DURATION: &str = "1w";
+/// The rate set by the Digest button of a suggestion, past which items are held for the digest.
+const DIGEST_RATE: &str = "1/day";
+
+pub(crate) const LIKE: &str = "like";
+const SUGGESTION_PREFIX: &str = "noise:";
+
+/// The keyboard attached to every delivered item of feed `feed_id`.
+pub(crate) fn item_keyboard(feed_id: i64) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(vec![vec![
+        InlineKeyboardButton::callback("👍", LIKE),
+        crate::snooze::button(),
+        crate::mute::button(feed_id),
+    ]])
+}
+
+fn suggestion_keyboard(feed_id: i64) -> InlineKeyboardMarkup {
+    let button = |text: &str, action: &str| {
+        let data = format!("{}{}:{}", SUGGESTION_PREFIX, action, feed_id);
+        InlineKeyboardButton::callback(text, data)
+    };
+    InlineKeyboardMarkup::new(vec![
+        vec![
+            button(&format!("Mute {}", MUTE_DURATION), "mute"),
+            button("Daily digest", "digest"),
+        ],
+        vec![
+            button("Pause", "pause"),
+            button("Unsubscribe", "unsubscribe"),
+            button("Keep", "keep"),
+        ],
+    ])
+}
+
+/// Returns the feeds that deserve a suggestion, with the number of items they sent in the last
+/// day.
+/// Returns the number of items that each noisy feed among `deliveries` sent in the day before
+/// `now`. Only the items sent as their own message count, not those held for a digest or sent in
+/// one, which already spare the chat.
+fn noisy_counts(deliveries: &[delivery::Model], now: NaiveDateTime) -> HashMap<i64, usize> {
+    let mut last_day: HashMap<i64, usize> = HashMap::new();
+    let mut clicks: HashMap<i64, i32> = HashMap::new();
+    for delivery in deliveries {
+        let own_message = !delivery.held && delivery.message_id.is_some();
+        if own_message && delivery.sent_at > now - Duration::days(1) {
+            *last_day.entry(delivery.feed_id).or_default() += 1;
+        }
+        *clicks.entry(delivery.feed_id).or_default() += delivery.clicks;
+    }
+    last_day.retain(|feed_id, count| {
+        *count > NOISY_ITEMS_PER_DAY && clicks.get(feed_id).copied().unwrap_or(0) == 0
+    });
+    last_day
+}
+
+async fn noisy_feeds(db: &DatabaseConnection) -> Result<Vec<(feed::Model, usize)>, DbErr> {
+    let now = Utc::now().naive_utc();
+    let deliveries = entity::prelude::Delivery::find()
+        .filter(delivery::Column::SentAt.gt(now - Duration::days(ENGAGEMENT_DAYS)))
+        .all(db)
+        .await?;
+    let last_day = noisy_counts(&deliveries, now);
+    let feeds = entity::prelude::Feed::find()
+        .filter(feed::Column::Id.is_in(last_day.keys().copied()))
+        .filter(feed::Column::Paused.eq(false))
+        .all(db)
+        .await?;
+    Ok(feeds
+        .into_iter()
+        .filter(|feed| match feed.noise_suggested_at {
+            Some(date) => date < now - Duration::days(SUGGESTION_INTERVAL_DAYS),
+            None => true,
+        })
+        .map(|feed| {
+            let count = last_day[&feed.id];
+            (feed, count)
+        })
+        .collect())
+}
+
+/// Sends a suggestion to mute every noisy feed to its chat.
+pub(crate) async fn suggest_mutes(bots: Bots, db: DatabaseConnection) {
+    let feeds = match noisy_feeds(&db).await {
+        Ok(feeds) => feeds,
+        Err(err) => {
+            println!("Error looking for noisy feeds: {:?}", err);
+            return;
+        }
+    };
+    for (feed, count) in feeds {
+        let Some(bot) = bots.get(feed.bot_id) else {
+            continue;
+        };
+        let sent = bot
+            .send_message(
+                ChatId(feed.chat_id),
+                format!(
+                    "{} sent {} items in the last day, and you haven't liked any in the last {} \
+                     days. Do you want to mute it, get it as a daily digest or pause it?",
+                    feed.title, count, ENGAGEMENT_DAYS
+                ),
+            )
+            .reply_markup(suggestion_keyboard(feed.id))
+            .await;
+        if let Err(err) = sent {
+            println!("Error sending suggestion: {:?}", err);
+            continue;
+        }
+        let mut suggested_feed: feed::ActiveModel = feed.into();
+        suggested_feed.noise_suggested_at = Set(Some(Utc::now().naive_utc()));
+        match suggested_feed.update(&db).await {
+            Ok(feed) => crate::registry::put(feed),
+            Err(err) => println!("Error updating feed: {:?}", err),
+        }
+    }
+}
+
+/// Counts a click on the 👍 button of the item sent as `message_id` in `chat_id` by the bot
+/// `bot_id`.
+pub(crate) async fn record_like(
+    db: &DatabaseConnection,
+    chat_id: i64,
+    bot_id: i64,
+    message_id: MessageId,
+) -> Result<UpdateResult, DbErr> {
+    // Every bot numbers its messages in the chat on its own
+    let feeds = Query::select()
+        .column(feed::Column::Id)
+        .from(feed::Entity)
+        .and_where(feed::Column::BotId.eq(bot_id))
+        .to_owned();
+    entity::prelude::Delivery::update_many()
+        .col_expr(
+            delivery::Column::Clicks,
+            Expr::col(delivery::Column::Clicks).add(1),
+        )
+        .filter(delivery::Column::ChatId.eq(chat_id))
+        .filter(delivery::Column::MessageId.eq(message_id.0))
+        .filter(delivery::Column::FeedId.in_subquery(feeds))
+        .exec(db)
+        .await
+}
+
+/// Returns whether the callback `data` belongs to a suggestion.
+pub(crate) fn is_suggestion(data: &str) -> bool {
+    data.starts_with(SUGGESTION_PREFIX)
+}
+
+/// Handles the buttons of a suggestion, replacing it with the outcome.
+pub(crate) async fn handle_suggestion(
+    bot: &Bot,
+    q: &CallbackQuery,
+    data: &str,
+    db: &DatabaseConnection,
+    bot_id: BotId,
+) -> ResponseResult<()> {
+    let Some(message) = &q.message else {
+        return Ok(());
+    };
+    let chat_id = message.chat.id;
+    let action = data
+        .strip_prefix(SUGGESTION_PREFIX)
+        .and_then(|action| action.split_once(':'))
+        .and_then(|(action, feed_id)| Some((action, feed_id.parse::<i64>().ok()?)));
+    let text = match action {
+        Some(("mute", feed_id)) => {
+            match crate::mute::mute_feed(db, feed_id, chat_id.0, bot_id.0, MUTE_DURATION).await {
+                Ok((update_result, _)) => {
+                    format!(
+                        "Muted {} feed for {}",
+                        update_result.rows_affected, MUTE_DURATION
+                    )
+                }
+                Err(error) => format!("Error: {}", error),
+            }
+        }
+        Some(("digest", feed_id)) => {
+            match crate::rate::set_max_rate(db, feed_id, chat_id.0, bot_id.0, DIGEST_RATE).await {
+                Ok(update_result) => format!(
+                    "Limited {} feed to {}, the other items are sent in a digest",
+                    update_result.rows_affected, DIGEST_RATE
+                ),
+                Err(error) => format!("Error: {}", error),
+            }
+        }
+        Some(("pause", feed_id)) => {
+            match crate::set_feed_paused(db, feed_id, chat_id.0, bot_id.0, true).await {
+                Ok(update_result) => format!("Paused {} feed", update_result.rows_affected),
+                Err(error) => format!("Error: {}", error),
+            }
+        }
+        Some(("unsubscribe", feed_id)) => {
+            let deleted = async {
+                undo::record(db, chat_id.0, bot_id.0, Action::Unsubscribe, &[feed_id]).await?;
+                crate::delete_feed(db, feed_id, chat_id.0, bot_id.0).await
+            };
+            match deleted.await {
+                Ok(delete_result) => format!("Deleted {} feed", delete_result.rows_affected),
+                Err(error) => format!("Error: {}", error),
+            }
+        }
+        Some(("keep", _)) => "Keeping the feed.".to_string(),
+        _ => return Ok(()),
+    };
+    bot.edit_message_text(chat_id, message.id, text).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: &str = "2026-10-15T10:00:00";
+
+    fn deliveries(
+        feed_id: i64,
+        count: usize,
+        held: bool,
+        message_id: Option<i32>,
+    ) -> Vec<delivery::Model> {
+        let now: NaiveDateTime = NOW.parse().unwrap();
+        (0..count)
+            .map(|i| delivery::Model {
+                id: feed_id * 1000 + i as i64,
+                feed_id,
+                chat_id: 1001,
+                title: format!("Item {}", i),
+                link: format!("https://example.com/{}", i),
+                sent_at: now - Duration::minutes(i as i64 + 1),
+                message_id,
+                clicks: 0,
+                guid: None,
+                held,
+                link_clicks: 0,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn counts_items_sent_on_their_own() {
+        let items = NOISY_ITEMS_PER_DAY + 5;
+        let sent = deliveries(1, items, false, Some(1));
+        let counts = noisy_counts(&sent, NOW.parse().unwrap());
+        assert_eq!(counts.get(&1), Some(&items));
+    }
+
+    #[test]
+    fn ignores_feeds_with_clicks() {
+        let mut sent = deliveries(1, NOISY_ITEMS_PER_DAY + 5, false, Some(1));
+        sent[3].clicks = 1;
+        assert!(noisy_counts(&sent, NOW.parse().unwrap()).is_empty());
+    }
+
+    #[test]
+    fn ignores_items_held_or_sent_in_digests() {
+        let items = NOISY_ITEMS_PER_DAY + 5;
+        let mut digested = deliveries(1, items, true, None);
+        digested.extend(deliveries(2, items, false, None));
+        assert!(noisy_counts(&digested, NOW.parse().unwrap()).is_empty());
+    }
+}
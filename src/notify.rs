@@ -0,0 +1,34 @@
+//! Instant polls requested through Postgres notifications.
+//!
+//! Sending `NOTIFY poll_feed, '<feed id>'` to the database makes the poller check that feed
+//! right away, so that webhook bridges and scripts can refresh a feed without waiting for the
+//! next cycle or restarting the bot. Like with the management API, paused feeds and feeds being
+//! checked are not polled again.
+
+use sea_orm::{
+    sqlx::{self, postgres::PgListener},
+    DatabaseConnection,
+};
+
+pub(crate) const CHANNEL: &str = "poll_feed";
+
+async fn poll(db: &DatabaseConnection, feed_id: i64) {
+    match crate::schedule::make_due(db, feed_id).await {
+        Ok(true) => {}
+        Ok(false) => println!("Poll requested for unknown or busy feed {}", feed_id),
+        Err(err) => println!("Error making feed {} due: {:?}", feed_id, err),
+    }
+}
+
+/// Polls the feeds whose ids are notified on [`CHANNEL`], until the connection fails for good.
+pub(crate) async fn listen(db: DatabaseConnection) -> Result<(), sqlx::Error> {
+    let mut listener = PgListener::connect_with(db.get_postgres_connection_pool()).await?;
+    listener.listen(CHANNEL).await?;
+    loop {
+        let notification = listener.recv().await?;
+        match notification.payload().trim().parse::<i64>() {
+            Ok(feed_id) => poll(&db, feed_id).await,
+            Err(_) => println!("Invalid feed id {} on {}", notification.payload(), CHANNEL),
+        }
+    }
+}
@@ -0,0 +1,184 @@
+//! Handling of sensitive items.
+//!
+//! An item is NSFW if its iTunes `explicit` tag is set, if one of its categories is an NSFW
+//! marker such as `nsfw` or `adult`, or if its title contains one of those markers or one of the
+//! comma separated keywords in `NSFW_KEYWORDS`. Every chat chooses with `/nsfw` how such items
+//! are delivered: as they are, hidden behind a spoiler, behind a button that reveals them, or not
+//! at all.
+
+use std::env;
+use std::error::Error;
+use std::str::FromStr;
+
+use rss::Item;
+use sea_orm::{sea_query::Expr, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use teloxide::{
+    payloads::EditMessageTextSetters,
+    prelude::{Bot, Requester, ResponseResult},
+    types::{CallbackQuery, InlineKeyboardButton, InlineKeyboardMarkup, ParseMode},
+    utils::html::escape,
+};
+
+use entity::{chat, delivery, feed};
+
+const MARKERS: [&str; 6] = ["nsfw", "explicit", "adult", "18+", "porn", "xxx"];
+const SHOW: &str = "nsfw:show";
+
+/// How a chat receives NSFW items, set with `/nsfw`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum NsfwMode {
+    Show,
+    Spoiler,
+    Button,
+    Drop,
+}
+
+impl FromStr for NsfwMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "show" => Ok(NsfwMode::Show),
+            "spoiler" => Ok(NsfwMode::Spoiler),
+            "button" => Ok(NsfwMode::Button),
+            "drop" => Ok(NsfwMode::Drop),
+            _ => Err(format!("Expected show, spoiler, button or drop, got {}", s)),
+        }
+    }
+}
+
+impl NsfwMode {
+    fn name(self) -> &'static str {
+        match self {
+            NsfwMode::Show => "show",
+            NsfwMode::Spoiler => "spoiler",
+            NsfwMode::Button => "button",
+            NsfwMode::Drop => "drop",
+        }
+    }
+}
+
+fn keywords() -> Vec<String> {
+    let extra = env::var("NSFW_KEYWORDS").unwrap_or_default();
+    MARKERS
+        .iter()
+        .map(|marker| marker.to_string())
+        .chain(extra.split(',').map(|keyword| keyword.trim().to_lowercase()))
+        .filter(|keyword| !keyword.is_empty())
+        .collect()
+}
+
+/// Returns whether `item` is marked or looks NSFW.
+pub(crate) fn is_nsfw(item: &Item) -> bool {
+    let explicit = item
+        .itunes_ext()
+        .and_then(|itunes| itunes.explicit())
+        .is_some_and(|explicit| {
+            matches!(explicit.to_lowercase().as_str(), "yes" | "true" | "explicit")
+        });
+    let keywords = keywords();
+    let category = item
+        .categories()
+        .iter()
+        .any(|category| keywords.contains(&category.name().trim().to_lowercase()));
+    let title: Vec<String> = item
+        .title()
+        .unwrap_or_default()
+        .split(|c: char| c.is_whitespace() || "[](){}:,.!?".contains(c))
+        .map(str::to_lowercase)
+        .collect();
+    explicit || category || title.iter().any(|word| keywords.contains(word))
+}
+
+/// Returns how the chat of `feed` receives NSFW items.
+pub(crate) async fn chat_mode(db: &DatabaseConnection, feed: &feed::Model) -> NsfwMode {
+    let chat = entity::prelude::Chat::find_by_id((feed.chat_id, feed.bot_id))
+        .one(db)
+        .await;
+    match chat {
+        Ok(Some(chat)) => chat.nsfw_mode.parse().unwrap_or(NsfwMode::Spoiler),
+        Ok(None) => NsfwMode::Spoiler,
+        Err(err) => {
+            println!("Error reading chat {}: {:?}", feed.chat_id, err);
+            NsfwMode::Spoiler
+        }
+    }
+}
+
+/// Sets how chat `chat_id` receives NSFW items.
+pub(crate) async fn set_mode(
+    db: &DatabaseConnection,
+    chat_id: i64,
+    bot_id: i64,
+    mode: &str,
+) -> Result<NsfwMode, Box<dyn Error + Send + Sync>> {
+    let mode: NsfwMode = mode.parse()?;
+    entity::prelude::Chat::update_many()
+        .col_expr(chat::Column::NsfwMode, Expr::value(mode.name()))
+        .filter(chat::Column::Id.eq(chat_id))
+        .filter(chat::Column::BotId.eq(bot_id))
+        .exec(db)
+        .await?;
+    Ok(mode)
+}
+
+/// Returns the HTML message of an NSFW `item` hidden behind a spoiler.
+pub(crate) fn spoiler_message(feed: &feed::Model, item: &Item) -> String {
+    format!(
+        "<i>{}</i>\n🔞 <tg-spoiler>{}</tg-spoiler>",
+        escape(&crate::format::feed_label(feed)),
+        crate::format::item_body(feed, item).trim_end()
+    )
+}
+
+/// Returns the HTML message of an NSFW item of `feed` that is revealed by a button.
+pub(crate) fn hidden_message(feed: &feed::Model) -> String {
+    format!(
+        "<i>{}</i>\n🔞 Sensitive content",
+        escape(&crate::format::feed_label(feed))
+    )
+}
+
+pub(crate) fn show_keyboard() -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(vec![vec![InlineKeyboardButton::callback("Show", SHOW)]])
+}
+
+pub(crate) fn is_show_button(data: &str) -> bool {
+    data == SHOW
+}
+
+/// Replaces a hidden item with its title and link, which are recorded in its delivery.
+pub(crate) async fn handle_show_button(
+    bot: &Bot,
+    q: &CallbackQuery,
+    db: &DatabaseConnection,
+) -> ResponseResult<()> {
+    let Some(message) = &q.message else {
+        return Ok(());
+    };
+    let found = entity::prelude::Delivery::find()
+        .filter(delivery::Column::ChatId.eq(message.chat.id.0))
+        .filter(delivery::Column::MessageId.eq(message.id.0))
+        .find_also_related(entity::prelude::Feed)
+        .one(db)
+        .await;
+    let (delivery, feed) = match found {
+        Ok(Some((delivery, Some(feed)))) => (delivery, feed),
+        Ok(_) => return Ok(()),
+        Err(err) => {
+            log::error!("Error fetching hidden item: {}", err);
+            return Ok(());
+        }
+    };
+    let text = format!(
+        "<i>{}</i>\n<a href='{}'>{}</a>",
+        escape(&crate::format::feed_label(&feed)),
+        escape(&delivery.link),
+        escape(&delivery.title)
+    );
+    bot.edit_message_text(message.chat.id, message.id, text)
+        .parse_mode(ParseMode::Html)
+        .reply_markup(crate::noise::item_keyboard(feed.id))
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,192 @@
+//! Details of scientific papers.
+//!
+//! With `/papers <feed id> on`, items linking to an arXiv paper or to a DOI are sent with their
+//! authors, the beginning of their abstract in an expandable quote and a link to their PDF,
+//! read from the arXiv API or from Crossref.
+
+use std::error::Error;
+use std::sync::OnceLock;
+
+use regex::Regex;
+use rss::Item;
+use serde::Deserialize;
+use teloxide::utils::html::escape;
+
+use entity::feed;
+
+const ARXIV_API: &str = "http://export.arxiv.org/api/query";
+const CROSSREF_API: &str = "https://api.crossref.org/works";
+/// Abstracts are shortened to this many characters.
+const MAX_ABSTRACT_CHARS: usize = 400;
+/// Authors listed before `et al.`
+const MAX_AUTHORS: usize = 5;
+
+static DOI: OnceLock<Regex> = OnceLock::new();
+
+#[derive(Debug, Default)]
+struct Paper {
+    authors: Vec<String>,
+    summary: Option<String>,
+    pdf: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CrossrefResponse {
+    message: CrossrefWork,
+}
+
+#[derive(Deserialize)]
+struct CrossrefWork {
+    #[serde(default)]
+    author: Vec<CrossrefAuthor>,
+    #[serde(rename = "abstract")]
+    summary: Option<String>,
+    #[serde(default)]
+    link: Vec<CrossrefLink>,
+}
+
+#[derive(Deserialize)]
+struct CrossrefAuthor {
+    given: Option<String>,
+    family: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CrossrefLink {
+    #[serde(rename = "URL")]
+    url: String,
+    #[serde(rename = "content-type")]
+    content_type: Option<String>,
+}
+
+/// Returns the arXiv identifier of `item`, from its link or its guid.
+fn arxiv_id(item: &Item) -> Option<String> {
+    let from_link = item.link().and_then(|link| {
+        let (_, id) = link
+            .split_once("arxiv.org/abs/")
+            .or_else(|| link.split_once("arxiv.org/pdf/"))?;
+        Some(id.trim_end_matches(".pdf").to_string())
+    });
+    let from_guid = || {
+        let guid = item.guid()?.value();
+        guid.strip_prefix("oai:arXiv.org:").map(str::to_string)
+    };
+    from_link.or_else(from_guid).filter(|id| !id.is_empty())
+}
+
+/// Returns the DOI of `item`, from its link or its guid.
+fn doi(item: &Item) -> Option<String> {
+    let doi = DOI.get_or_init(|| Regex::new(r#"10\.\d{4,9}/[^\s"<>?#]+"#).expect("valid regex"));
+    [item.link(), item.guid().map(|guid| guid.value())]
+        .into_iter()
+        .flatten()
+        .find_map(|text| doi.find(text))
+        .map(|found| found.as_str().to_string())
+}
+
+fn strip_tags(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut in_tag = false;
+    for c in text.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => stripped.push(c),
+            _ => {}
+        }
+    }
+    stripped.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+async fn arxiv_paper(id: &str) -> Result<Paper, Box<dyn Error + Send + Sync>> {
+    let url = format!("{}?id_list={}", ARXIV_API, urlencoding::encode(id));
+    let atom = reqwest::get(&url).await?.error_for_status()?.text().await?;
+    let entry = atom
+        .split_once("<entry>")
+        .map(|(_, entry)| entry)
+        .ok_or_else(|| format!("No arXiv paper {}", id))?;
+    Ok(Paper {
+        authors: crate::sitemaps::tag_values(entry, "name"),
+        summary: crate::sitemaps::tag_values(entry, "summary")
+            .into_iter()
+            .next()
+            .map(|summary| strip_tags(&summary)),
+        pdf: Some(format!("https://arxiv.org/pdf/{}", id)),
+    })
+}
+
+async fn crossref_paper(doi: &str) -> Result<Paper, Box<dyn Error + Send + Sync>> {
+    let url = format!("{}/{}", CROSSREF_API, doi);
+    let response: CrossrefResponse = reqwest::get(&url)
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    let work = response.message;
+    let authors = work
+        .author
+        .into_iter()
+        .filter_map(|author| match (author.given, author.family) {
+            (Some(given), Some(family)) => Some(format!("{} {}", given, family)),
+            (given, family) => family.or(given),
+        })
+        .collect();
+    let pdf = work
+        .link
+        .into_iter()
+        .find(|link| link.content_type.as_deref() == Some("application/pdf"))
+        .map(|link| link.url);
+    Ok(Paper {
+        authors,
+        summary: work.summary.map(|summary| strip_tags(&summary)),
+        pdf,
+    })
+}
+
+fn shorten(text: &str) -> String {
+    if text.chars().count() <= MAX_ABSTRACT_CHARS {
+        return text.to_string();
+    }
+    let short: String = text.chars().take(MAX_ABSTRACT_CHARS).collect();
+    format!("{}…", short.trim_end())
+}
+
+fn paper_message(paper: &Paper) -> String {
+    let mut message = String::new();
+    if !paper.authors.is_empty() {
+        let mut authors = paper.authors[..paper.authors.len().min(MAX_AUTHORS)].join(", ");
+        if paper.authors.len() > MAX_AUTHORS {
+            authors.push_str(" et al.");
+        }
+        message.push_str(&format!("✍️ {}\n", escape(&authors)));
+    }
+    if let Some(summary) = paper.summary.as_ref().filter(|summary| !summary.is_empty()) {
+        message.push_str(&format!(
+            "<blockquote expandable>{}</blockquote>\n",
+            escape(&shorten(summary))
+        ));
+    }
+    if let Some(pdf) = &paper.pdf {
+        message.push_str(&format!("📄 <a href='{}'>PDF</a>\n", escape(pdf)));
+    }
+    message
+}
+
+/// Returns the HTML details of the paper `item` links to, if `feed` asks for them.
+pub(crate) async fn details(feed: &feed::Model, item: &Item) -> Option<String> {
+    if !feed.papers {
+        return None;
+    }
+    let paper = match (arxiv_id(item), doi(item)) {
+        (Some(id), _) => arxiv_paper(&id).await,
+        (None, Some(doi)) => crossref_paper(&doi).await,
+        (None, None) => return None,
+    };
+    match paper {
+        Ok(paper) => Some(paper_message(&paper)),
+        Err(err) => {
+            println!("Error fetching the paper of an item of feed {}: {:?}", feed.id, err);
+            None
+        }
+    }
+}
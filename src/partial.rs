@@ -0,0 +1,129 @@
+//! Partial parsing of large feeds.
+//!
+//! Some feeds list thousands of items, of which only the first few are new at every poll.
+//! Feeds of at least [`MIN_BYTES`] bytes are scanned with a streaming XML reader that stops
+//! after [`MAX_ITEMS`] items, or as soon as it passed the last item delivered to every feed
+//! polled, and only that start of the document is parsed. Stopping early assumes that the
+//! newest items come first, so the whole document is parsed instead when the items that were
+//! read are not in reverse chronological order.
+
+use std::collections::HashSet;
+use std::error::Error;
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use rss::Channel;
+use sea_orm::{ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder};
+
+use entity::{delivery, feed};
+
+/// Smaller feeds are parsed whole.
+const MIN_BYTES: usize = 1024 * 1024;
+/// Maximum number of items read from a large feed.
+const MAX_ITEMS: usize = 200;
+
+/// Returns the GUID of the last item delivered to each of `feeds`, or nothing if one of them
+/// has none.
+async fn last_guids(
+    db: &DatabaseConnection,
+    feeds: &[feed::Model],
+) -> Result<HashSet<String>, DbErr> {
+    let mut guids = HashSet::new();
+    for feed in feeds {
+        let last = entity::prelude::Delivery::find()
+            .filter(delivery::Column::FeedId.eq(feed.id))
+            .order_by_desc(delivery::Column::Id)
+            .one(db)
+            .await?;
+        match last.and_then(|last| last.guid) {
+            Some(guid) => guids.insert(guid),
+            None => return Ok(HashSet::new()),
+        };
+    }
+    Ok(guids)
+}
+
+/// Returns the start of the XML document `content` up to the end of its `limit`th item, or of
+/// the item after which every GUID of `stop_after` was seen, with the elements still open at
+/// that point closed. Returns `None` if the document ends first or can't be read.
+fn truncate(content: &[u8], limit: usize, stop_after: &HashSet<String>) -> Option<Vec<u8>> {
+    let mut reader = Reader::from_reader(content);
+    reader.check_end_names(false);
+    let mut open: Vec<Vec<u8>> = Vec::new();
+    let mut remaining = stop_after.clone();
+    let mut items = 0;
+    let mut in_guid = false;
+    loop {
+        match reader.read_event().ok()? {
+            Event::Start(element) => {
+                in_guid = element.local_name().as_ref() == b"guid";
+                open.push(element.name().as_ref().to_vec());
+            }
+            Event::End(element) => {
+                open.pop();
+                in_guid = false;
+                if element.local_name().as_ref() != b"item" {
+                    continue;
+                }
+                items += 1;
+                if items >= limit || (!stop_after.is_empty() && remaining.is_empty()) {
+                    let mut start = content[..reader.buffer_position()].to_vec();
+                    for name in open.iter().rev() {
+                        start.extend_from_slice(b"</");
+                        start.extend_from_slice(name);
+                        start.push(b'>');
+                    }
+                    return Some(start);
+                }
+            }
+            Event::Text(text) if in_guid => {
+                if let Ok(guid) = text.unescape() {
+                    remaining.remove(guid.trim());
+                }
+            }
+            Event::CData(guid) if in_guid => {
+                remaining.remove(String::from_utf8_lossy(&guid).trim());
+            }
+            Event::Eof => return None,
+            _ => {}
+        }
+    }
+}
+
+/// Returns whether every item of `channel` has a date and is older than the one before.
+fn is_newest_first(channel: &Channel) -> bool {
+    let dates: Option<Vec<_>> = channel
+        .items()
+        .iter()
+        .map(|item| crate::dates::published(item).ok().flatten())
+        .collect();
+    dates.is_some_and(|dates| dates.windows(2).all(|pair| pair[0] >= pair[1]))
+}
+
+/// Parses the feed `content` fetched for `feeds`, only reading the newest items if it is large.
+pub(crate) async fn read_channel(
+    db: &DatabaseConnection,
+    content: Vec<u8>,
+    feeds: &[feed::Model],
+) -> Result<Channel, Box<dyn Error + Send + Sync>> {
+    if content.len() < MIN_BYTES || crate::calendar::is_calendar(&content) {
+        return crate::read_channel(content).await;
+    }
+    let stop_after = match last_guids(db, feeds).await {
+        Ok(guids) => guids,
+        Err(err) => {
+            println!("Error fetching the last deliveries: {:?}", err);
+            HashSet::new()
+        }
+    };
+    let parsed = tokio::task::spawn_blocking(move || {
+        let channel = truncate(&content, MAX_ITEMS, &stop_after)
+            .and_then(|start| crate::parse_channel(&start).ok())
+            .filter(is_newest_first);
+        match channel {
+            Some(channel) => Ok(channel),
+            None => crate::parse_channel(&content),
+        }
+    });
+    parsed.await?
+}
@@ -0,0 +1,101 @@
+//! Pinning of items from high-priority feeds.
+//!
+//! Feeds with `pin` on have every item pinned in their chat after it is sent. With
+//! `unpin_previous` on as well, the item sent before is unpinned, so that only the latest one
+//! stays pinned.
+
+use std::error::Error;
+use std::str::FromStr;
+
+use sea_orm::{
+    sea_query::Expr, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder,
+    UpdateResult,
+};
+use teloxide::{
+    payloads::UnpinChatMessageSetters,
+    prelude::{Bot, Requester},
+    types::{ChatId, MessageId},
+};
+
+use entity::{delivery, feed};
+
+/// The argument of the `/pin` command.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum PinMode {
+    Off,
+    On,
+    Replace,
+}
+
+impl FromStr for PinMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "off" => Ok(PinMode::Off),
+            "on" => Ok(PinMode::On),
+            "replace" => Ok(PinMode::Replace),
+            _ => Err(format!("Expected on, off or replace, got {}", s)),
+        }
+    }
+}
+
+pub(crate) async fn set_pin_mode(
+    db: &DatabaseConnection,
+    id: i64,
+    chat_id: i64,
+    bot_id: i64,
+    mode: PinMode,
+) -> Result<UpdateResult, Box<dyn Error + Send + Sync>> {
+    let chat_id = crate::links::managed_chat(db, id, chat_id, bot_id).await?;
+    let updated = entity::prelude::Feed::update_many()
+        .col_expr(feed::Column::Pin, Expr::value(mode != PinMode::Off))
+        .col_expr(
+            feed::Column::UnpinPrevious,
+            Expr::value(mode == PinMode::Replace),
+        )
+        .filter(feed::Column::ChatId.eq(chat_id))
+        .filter(feed::Column::BotId.eq(bot_id))
+        .filter(feed::Column::Id.eq(id))
+        .exec(db)
+        .await?;
+    crate::registry::invalidate(id);
+    Ok(updated)
+}
+
+/// Pins the item just sent as `message_id`, unpinning the previous item of the feed if the
+/// feed asks for it. Must be called before the new delivery is recorded.
+pub(crate) async fn pin_item(
+    bot: &Bot,
+    db: &DatabaseConnection,
+    feed: &feed::Model,
+    message_id: MessageId,
+) {
+    let chat_id = ChatId(feed.chat_id);
+    if feed.unpin_previous {
+        let previous = entity::prelude::Delivery::find()
+            .filter(delivery::Column::FeedId.eq(feed.id))
+            .order_by_desc(delivery::Column::SentAt)
+            .one(db)
+            .await;
+        match previous {
+            Ok(Some(delivery::Model {
+                message_id: Some(previous_id),
+                ..
+            })) => {
+                let unpinned = bot
+                    .unpin_chat_message(chat_id)
+                    .message_id(MessageId(previous_id))
+                    .await;
+                if let Err(err) = unpinned {
+                    println!("Error unpinning message: {:?}", err);
+                }
+            }
+            Ok(_) => {}
+            Err(err) => println!("Error finding previous delivery: {:?}", err),
+        }
+    }
+    if let Err(err) = bot.pin_chat_message(chat_id, message_id).await {
+        println!("Error pinning message: {:?}", err);
+    }
+}
@@ -0,0 +1,384 @@
+//! Per-feed pipelines of item transformations.
+//!
+//! A pipeline is an ordered list of steps, stored as JSON in the feed's `pipeline` column and
+//! edited with `/addstep`, `/removestep` and `/pipeline`. Every new item goes through the steps
+//! in order before it is delivered:
+//!
+//! * `filter +Word -Other` drops the item unless its title contains a `+` word, if there are
+//!   any, and none of the `-` words;
+//! * `rewrite s/pattern/replacement/` replaces the regular expression `pattern` in the title;
+//! * `strip_params [names]` removes the named query parameters from the link, or all of them;
+//! * `mirror <domain> <mirror>` replaces the domain of the link, or its `www.` subdomain, for
+//!   example `reddit.com` with `old.reddit.com`;
+//! * `wrap <prefix>` prepends a prefix to the link, such as an archive service;
+//! * `truncate <characters>` shortens the title;
+//! * `template <text>` formats the message, replacing `{feed}`, `{title}` and `{link}`, escaped.
+//!
+//! `/rewrite <feed id> s/pattern/replacement/` is a shortcut for adding a `rewrite` step, for
+//! example to strip `[Sponsored]` prefixes or the name of the site from titles, and
+//! `/linkrule <feed id> <step>` for adding a `mirror` or `wrap` step.
+
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use regex::Regex;
+use rss::Item;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, UpdateResult};
+use serde::{Deserialize, Serialize};
+use teloxide::utils::html::escape;
+
+use crate::filters::Filter;
+use entity::feed;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "step", rename_all = "snake_case")]
+pub(crate) enum Step {
+    Filter { rules: String },
+    Rewrite { pattern: String, replacement: String },
+    StripParams { params: Vec<String> },
+    Mirror { domain: String, mirror: String },
+    Wrap { prefix: String },
+    Truncate { length: usize },
+    Template { template: String },
+}
+
+/// Splits a substitution of the form `s/pattern/replacement/`. Slashes in the pattern or the
+/// replacement are escaped as `\/`.
+pub(crate) fn parse_substitution(s: &str) -> Result<(String, String), String> {
+    let error = || format!("Expected s/pattern/replacement/, got {}", s);
+    let body = s
+        .trim()
+        .strip_prefix("s/")
+        .and_then(|body| body.strip_suffix('/'))
+        .ok_or_else(error)?;
+    let mut parts = vec![String::new()];
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.clone().next() == Some('/') => {
+                chars.next();
+                parts.last_mut().unwrap().push('/');
+            }
+            '/' => parts.push(String::new()),
+            c => parts.last_mut().unwrap().push(c),
+        }
+    }
+    match <[String; 2]>::try_from(parts) {
+        Ok([pattern, replacement]) => {
+            Regex::new(&pattern).map_err(|err| err.to_string())?;
+            Ok((pattern, replacement))
+        }
+        Err(_) => Err(error()),
+    }
+}
+
+impl FromStr for Step {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, args) = s.trim().split_once(' ').unwrap_or((s.trim(), ""));
+        let args = args.trim();
+        match name.to_lowercase().as_str() {
+            "filter" => {
+                Filter::parse(args)?;
+                Ok(Step::Filter {
+                    rules: args.to_string(),
+                })
+            }
+            "rewrite" => {
+                let (pattern, replacement) = parse_substitution(args)?;
+                Ok(Step::Rewrite {
+                    pattern,
+                    replacement,
+                })
+            }
+            "strip_params" => Ok(Step::StripParams {
+                params: args.split_whitespace().map(str::to_string).collect(),
+            }),
+            "mirror" => match args.split_whitespace().collect::<Vec<&str>>()[..] {
+                [domain, mirror] => Ok(Step::Mirror {
+                    domain: domain.to_lowercase(),
+                    mirror: mirror.to_lowercase(),
+                }),
+                _ => Err(format!("Expected a domain and its mirror, got {}", args)),
+            },
+            "wrap" if reqwest::Url::parse(args).is_ok() => Ok(Step::Wrap {
+                prefix: args.to_string(),
+            }),
+            "truncate" => match args.parse() {
+                Ok(length) if length > 0 => Ok(Step::Truncate { length }),
+                _ => Err(format!("Expected a number of characters, got {}", args)),
+            },
+            "template" if !args.is_empty() => Ok(Step::Template {
+                template: args.replace("\\n", "\n"),
+            }),
+            _ => Err(format!(
+                "Unknown step {}, expected filter, rewrite, strip_params, mirror, wrap, truncate \
+                 or template",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Step::Filter { rules } => write!(f, "filter {}", rules),
+            Step::Rewrite {
+                pattern,
+                replacement,
+            } => write!(
+                f,
+                "rewrite s/{}/{}/",
+                pattern.replace('/', "\\/"),
+                replacement.replace('/', "\\/")
+            ),
+            Step::StripParams { params } => write!(f, "strip_params {}", params.join(" ")),
+            Step::Mirror { domain, mirror } => write!(f, "mirror {} {}", domain, mirror),
+            Step::Wrap { prefix } => write!(f, "wrap {}", prefix),
+            Step::Truncate { length } => write!(f, "truncate {}", length),
+            Step::Template { template } => write!(f, "template {}", template.replace('\n', "\\n")),
+        }
+    }
+}
+
+/// Returns the steps of the pipeline of `feed`.
+pub(crate) fn steps(feed: &feed::Model) -> Vec<Step> {
+    let Some(pipeline) = &feed.pipeline else {
+        return Vec::new();
+    };
+    serde_json::from_str(pipeline).unwrap_or_else(|err| {
+        println!("Invalid pipeline of feed {}: {:?}", feed.id, err);
+        Vec::new()
+    })
+}
+
+fn strip_params(link: &str, params: &[String]) -> String {
+    let Ok(mut url) = reqwest::Url::parse(link) else {
+        return link.to_string();
+    };
+    let kept: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(name, _)| !params.is_empty() && !params.iter().any(|param| param == name))
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect();
+    if kept.is_empty() {
+        url.set_query(None);
+    } else {
+        url.query_pairs_mut().clear().extend_pairs(kept);
+    }
+    url.to_string()
+}
+
+fn mirror(link: &str, domain: &str, mirror: &str) -> String {
+    let Ok(mut url) = reqwest::Url::parse(link) else {
+        return link.to_string();
+    };
+    let host = url.host_str().unwrap_or_default().to_lowercase();
+    if host != domain && host.strip_prefix("www.") != Some(domain) {
+        return link.to_string();
+    }
+    match url.set_host(Some(mirror)) {
+        Ok(()) => url.to_string(),
+        Err(_) => link.to_string(),
+    }
+}
+
+/// Runs `item` through the pipeline of `feed`, returning the transformed item or `None` if a
+/// filter drops it. Templates only apply when the message is formatted.
+pub(crate) fn apply(feed: &feed::Model, item: &Item) -> Option<Item> {
+    let mut item = item.clone();
+    for step in steps(feed) {
+        let title = item.title().unwrap_or_default().to_string();
+        match step {
+            Step::Filter { rules } => match Filter::parse(&rules) {
+                Ok(filter) if !filter.allows_text(&title) => return None,
+                _ => {}
+            },
+            Step::Rewrite {
+                pattern,
+                replacement,
+            } => {
+                if let Ok(regex) = Regex::new(&pattern) {
+                    let title = regex.replace_all(&title, replacement.as_str());
+                    item.set_title(title.trim().to_string());
+                }
+            }
+            Step::StripParams { params } => {
+                let link = item.link().map(|link| strip_params(link, &params));
+                item.set_link(link);
+            }
+            Step::Mirror { domain, mirror: to } => {
+                let link = item.link().map(|link| mirror(link, &domain, &to));
+                item.set_link(link);
+            }
+            Step::Wrap { prefix } => {
+                let link = item.link().map(|link| format!("{}{}", prefix, link));
+                item.set_link(link);
+            }
+            Step::Truncate { length } => {
+                if title.chars().count() > length {
+                    let truncated: String = title.chars().take(length).collect();
+                    item.set_title(format!("{}…", truncated.trim_end()));
+                }
+            }
+            Step::Template { .. } => {}
+        }
+    }
+    Some(item)
+}
+
+/// Returns the last template of the pipeline of `feed`, if there is one.
+pub(crate) fn template(feed: &feed::Model) -> Option<String> {
+    steps(feed).into_iter().rev().find_map(|step| match step {
+        Step::Template { template } => Some(template),
+        _ => None,
+    })
+}
+
+/// Returns the pipeline of `feed` as JSON with `template` as its last step, which overrides the
+/// templates of the feed.
+pub(crate) fn with_template(feed: &feed::Model, template: &str) -> Option<String> {
+    let mut steps = steps(feed);
+    steps.push(Step::Template {
+        template: template.to_string(),
+    });
+    serde_json::to_string(&steps).ok()
+}
+
+/// Returns the HTML message of `item` formatted with `template`.
+pub(crate) fn render(template: &str, feed: &feed::Model, item: &Item) -> String {
+    template
+        .replace("{feed}", &escape(&crate::format::feed_label(feed)))
+        .replace("{title}", &escape(item.title().unwrap_or_default()))
+        .replace("{link}", &escape(item.link().unwrap_or_default()))
+}
+
+/// Returns the pipeline of feed `id` of chat `chat_id`, listing its numbered steps.
+pub(crate) async fn describe(
+    db: &DatabaseConnection,
+    id: i64,
+    chat_id: i64,
+    bot_id: i64,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let feed = managed_feed(db, id, chat_id, bot_id).await?;
+    let steps = steps(&feed);
+    if steps.is_empty() {
+        return Ok(format!("The pipeline of {} is empty.", feed.title));
+    }
+    Ok(steps
+        .iter()
+        .enumerate()
+        .map(|(position, step)| format!("{}. {}", position + 1, step))
+        .collect::<Vec<String>>()
+        .join("\n"))
+}
+
+/// Returns feed `id` if `chat_id` may manage it.
+pub(crate) async fn managed_feed(
+    db: &DatabaseConnection,
+    id: i64,
+    chat_id: i64,
+    bot_id: i64,
+) -> Result<feed::Model, Box<dyn Error + Send + Sync>> {
+    let chat_id = crate::links::managed_chat(db, id, chat_id, bot_id).await?;
+    Ok(entity::prelude::Feed::find_by_id(id)
+        .filter(feed::Column::ChatId.eq(chat_id))
+        .filter(feed::Column::BotId.eq(bot_id))
+        .one(db)
+        .await?
+        .ok_or_else(|| format!("No feed {}", id))?)
+}
+
+async fn store(
+    db: &DatabaseConnection,
+    feed: &feed::Model,
+    steps: &[Step],
+) -> Result<UpdateResult, Box<dyn Error + Send + Sync>> {
+    let pipeline = if steps.is_empty() {
+        None
+    } else {
+        Some(serde_json::to_string(steps)?)
+    };
+    let column = feed::Column::Pipeline;
+    crate::set_feed_option(db, feed.id, feed.chat_id, feed.bot_id, column, pipeline).await
+}
+
+/// Appends `step` to the pipeline of feed `id`.
+pub(crate) async fn add_step(
+    db: &DatabaseConnection,
+    id: i64,
+    chat_id: i64,
+    bot_id: i64,
+    step: Step,
+) -> Result<UpdateResult, Box<dyn Error + Send + Sync>> {
+    let feed = managed_feed(db, id, chat_id, bot_id).await?;
+    let mut steps = steps(&feed);
+    steps.push(step);
+    store(db, &feed, &steps).await
+}
+
+/// Removes the step at `position`, starting from 1, from the pipeline of feed `id`.
+pub(crate) async fn remove_step(
+    db: &DatabaseConnection,
+    id: i64,
+    chat_id: i64,
+    bot_id: i64,
+    position: usize,
+) -> Result<UpdateResult, Box<dyn Error + Send + Sync>> {
+    let feed = managed_feed(db, id, chat_id, bot_id).await?;
+    let mut steps = steps(&feed);
+    if position == 0 || position > steps.len() {
+        return Err(format!("No step {}", position).into());
+    }
+    steps.remove(position - 1);
+    store(db, &feed, &steps).await
+}
+
+/// Adds a title rewrite for `substitution` to the pipeline of feed `id`, or removes all its
+/// rewrites if `substitution` is `off`.
+pub(crate) async fn set_rewrite(
+    db: &DatabaseConnection,
+    id: i64,
+    chat_id: i64,
+    bot_id: i64,
+    substitution: &str,
+) -> Result<UpdateResult, Box<dyn Error + Send + Sync>> {
+    if !substitution.trim().eq_ignore_ascii_case("off") {
+        let (pattern, replacement) = parse_substitution(substitution)?;
+        let step = Step::Rewrite {
+            pattern,
+            replacement,
+        };
+        return add_step(db, id, chat_id, bot_id, step).await;
+    }
+    let feed = managed_feed(db, id, chat_id, bot_id).await?;
+    let mut steps = steps(&feed);
+    steps.retain(|step| !matches!(step, Step::Rewrite { .. }));
+    store(db, &feed, &steps).await
+}
+
+/// Adds the link rewrite `rule`, a `mirror` or `wrap` step, to the pipeline of feed `id`, or
+/// removes all its link rewrites if `rule` is `off`.
+pub(crate) async fn set_link_rule(
+    db: &DatabaseConnection,
+    id: i64,
+    chat_id: i64,
+    bot_id: i64,
+    rule: &str,
+) -> Result<UpdateResult, Box<dyn Error + Send + Sync>> {
+    if !rule.trim().eq_ignore_ascii_case("off") {
+        return match rule.parse()? {
+            step @ (Step::Mirror { .. } | Step::Wrap { .. }) => {
+                add_step(db, id, chat_id, bot_id, step).await
+            }
+            _ => Err("Expected mirror <domain> <mirror> or wrap <prefix>".into()),
+        };
+    }
+    let feed = managed_feed(db, id, chat_id, bot_id).await?;
+    let mut steps = steps(&feed);
+    steps.retain(|step| !matches!(step, Step::Mirror { .. } | Step::Wrap { .. }));
+    store(db, &feed, &steps).await
+}
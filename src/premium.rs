@@ -0,0 +1,120 @@
+//! Premium tier paid with Telegram Stars.
+//!
+//! `/premium` sends an invoice in Stars for [`PERIOD_DAYS`] days of premium for the chat. Once
+//! paid, the chat's `subscription_tier` is `premium` until `premium_until`, which raises its
+//! feed quota [`FEEDS_FACTOR`] times. Paying again extends the period.
+
+use std::env;
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use sea_orm::{ActiveModelTrait, DatabaseConnection, DbErr, EntityTrait, Set};
+use teloxide::{
+    prelude::{Bot, Requester, ResponseResult},
+    types::{ChatId, LabeledPrice, Message, PreCheckoutQuery},
+};
+
+use crate::bots::BotId;
+use entity::chat;
+
+const PREMIUM: &str = "premium";
+const PERIOD_DAYS: i64 = 30;
+/// Premium chats may subscribe to this many times the feeds of free chats.
+pub(crate) const FEEDS_FACTOR: u64 = 10;
+/// Price in Stars, unless `PREMIUM_PRICE_STARS` is set.
+const DEFAULT_PRICE_STARS: u32 = 100;
+/// Currency code of Telegram Stars.
+const STARS: &str = "XTR";
+const PAYLOAD_PREFIX: &str = "premium:";
+
+/// Returns whether `chat` is premium now.
+pub(crate) fn is_premium(chat: &chat::Model) -> bool {
+    chat.subscription_tier == PREMIUM
+        && chat
+            .premium_until
+            .is_some_and(|until| until > Utc::now().naive_utc())
+}
+
+fn price() -> u32 {
+    env::var("PREMIUM_PRICE_STARS")
+        .ok()
+        .and_then(|price| price.parse().ok())
+        .unwrap_or(DEFAULT_PRICE_STARS)
+}
+
+/// Sends the invoice for premium to `chat_id`.
+pub(crate) async fn send_invoice(bot: &Bot, chat_id: ChatId) -> ResponseResult<()> {
+    bot.send_invoice(
+        chat_id,
+        "Premium",
+        format!(
+            "{} days of premium for this chat, with {} times more feeds",
+            PERIOD_DAYS, FEEDS_FACTOR
+        ),
+        format!("{}{}", PAYLOAD_PREFIX, chat_id.0),
+        "",
+        STARS,
+        vec![LabeledPrice::new("Premium", price())],
+    )
+    .await?;
+    Ok(())
+}
+
+/// Accepts the checkout of invoices for premium.
+pub(crate) async fn process_pre_checkout(bot: Bot, q: PreCheckoutQuery) -> ResponseResult<()> {
+    let valid = q.invoice_payload.starts_with(PAYLOAD_PREFIX);
+    bot.answer_pre_checkout_query(q.id, valid).await?;
+    Ok(())
+}
+
+/// Extends the premium period of chat `chat_id` and returns its new end, or `None` if the chat
+/// doesn't exist.
+async fn extend(
+    db: &DatabaseConnection,
+    chat_id: i64,
+    bot_id: i64,
+) -> Result<Option<NaiveDateTime>, DbErr> {
+    let Some(chat) = entity::prelude::Chat::find_by_id((chat_id, bot_id))
+        .one(db)
+        .await?
+    else {
+        return Ok(None);
+    };
+    let now = Utc::now().naive_utc();
+    let start = chat.premium_until.filter(|until| *until > now).unwrap_or(now);
+    let until = start + Duration::days(PERIOD_DAYS);
+    let mut premium_chat: chat::ActiveModel = chat.into();
+    premium_chat.subscription_tier = Set(PREMIUM.to_string());
+    premium_chat.premium_until = Set(Some(until));
+    premium_chat.update(db).await?;
+    Ok(Some(until))
+}
+
+/// Handles a successful payment, upgrading the chat named in the payload.
+pub(crate) async fn process_payment(
+    bot: Bot,
+    msg: Message,
+    db: DatabaseConnection,
+    bot_id: BotId,
+) -> ResponseResult<()> {
+    let Some(payment) = msg.successful_payment() else {
+        return Ok(());
+    };
+    let chat_id = payment
+        .invoice_payload
+        .strip_prefix(PAYLOAD_PREFIX)
+        .and_then(|chat_id| chat_id.parse::<i64>().ok())
+        .unwrap_or(msg.chat.id.0);
+    let text = match extend(&db, chat_id, bot_id.0).await {
+        Ok(Some(until)) => format!(
+            "Thank you! This chat is premium until {} UTC.",
+            until.format("%Y-%m-%d %H:%M")
+        ),
+        Ok(None) => "Thank you! Send /start to create an account first.".to_string(),
+        Err(error) => {
+            log::error!("Error recording payment of chat {}: {}", chat_id, error);
+            format!("Error: {}", error)
+        }
+    };
+    bot.send_message(msg.chat.id, text).await?;
+    Ok(())
+}
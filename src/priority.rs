@@ -0,0 +1,61 @@
+//! Priority levels of feeds.
+//!
+//! `/priority <feed id> high|normal|low` changes how the items of a feed are timed. Items of
+//! high-priority feeds are sent right away with a notification, bypassing delivery windows,
+//! maximum rates and mute buffers. Items of low-priority feeds are always held and sent
+//! silently as a digest, once per window of their maximum rate or every hour.
+
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use sea_orm::{DatabaseConnection, UpdateResult};
+
+use entity::feed;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Priority {
+    High,
+    Normal,
+    Low,
+}
+
+impl FromStr for Priority {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "high" => Ok(Priority::High),
+            "normal" => Ok(Priority::Normal),
+            "low" => Ok(Priority::Low),
+            _ => Err(format!("Expected high, normal or low, got {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Priority::High => "high",
+            Priority::Normal => "normal",
+            Priority::Low => "low",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Returns the priority of `feed`, normal if it can't be parsed.
+pub(crate) fn of(feed: &feed::Model) -> Priority {
+    feed.priority.parse().unwrap_or(Priority::Normal)
+}
+
+pub(crate) async fn set_priority(
+    db: &DatabaseConnection,
+    id: i64,
+    chat_id: i64,
+    bot_id: i64,
+    priority: Priority,
+) -> Result<UpdateResult, Box<dyn Error + Send + Sync>> {
+    let column = feed::Column::Priority;
+    crate::set_managed_feed_option(db, id, chat_id, bot_id, column, priority.to_string()).await
+}
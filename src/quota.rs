@@ -0,0 +1,181 @@
+//! Daily usage quotas of chats.
+//!
+//! The commands a chat sends, the bytes downloaded for its feeds and the messages it receives
+//! are counted per day (UTC) in the `usage` table. The `quota_*` feature flags set the daily
+//! limits, 0 meaning no limit. A chat is warned once it uses [`SOFT_LIMIT_PERCENT`]% of a
+//! quota and is throttled when it reaches it: its commands are refused, its feeds are not
+//! polled, and its items are held for a digest sent the next day.
+
+use chrono::Utc;
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait,
+    QueryFilter, Set,
+};
+use teloxide::{
+    prelude::{Bot, Requester},
+    types::ChatId,
+};
+
+use crate::flags::{self, Flag};
+use entity::usage;
+
+const SOFT_LIMIT_PERCENT: u64 = 80;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Usage {
+    Commands,
+    Bytes,
+    Messages,
+}
+
+impl Usage {
+    fn column(self) -> usage::Column {
+        match self {
+            Usage::Commands => usage::Column::Commands,
+            Usage::Bytes => usage::Column::Bytes,
+            Usage::Messages => usage::Column::Messages,
+        }
+    }
+
+    fn flag(self) -> Flag {
+        match self {
+            Usage::Commands => Flag::QuotaCommands,
+            Usage::Bytes => Flag::QuotaBytes,
+            Usage::Messages => Flag::QuotaMessages,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Usage::Commands => "commands",
+            Usage::Bytes => "downloaded bytes",
+            Usage::Messages => "messages",
+        }
+    }
+
+    fn of(self, usage: &usage::Model) -> u64 {
+        let used = match self {
+            Usage::Commands => usage.commands,
+            Usage::Bytes => usage.bytes,
+            Usage::Messages => usage.messages,
+        };
+        used.max(0) as u64
+    }
+}
+
+async fn today(
+    db: &DatabaseConnection,
+    chat_id: i64,
+    bot_id: i64,
+) -> Result<Option<usage::Model>, DbErr> {
+    entity::prelude::Usage::find_by_id((chat_id, bot_id, Utc::now().date_naive()))
+        .one(db)
+        .await
+}
+
+/// Adds `amount` to the usage of `chat_id` today and returns the updated usage.
+async fn record(
+    db: &DatabaseConnection,
+    chat_id: i64,
+    bot_id: i64,
+    kind: Usage,
+    amount: i64,
+) -> Result<usage::Model, DbErr> {
+    if today(db, chat_id, bot_id).await?.is_none() {
+        let new_usage = usage::ActiveModel {
+            chat_id: Set(chat_id),
+            bot_id: Set(bot_id),
+            day: Set(Utc::now().date_naive()),
+            ..Default::default()
+        };
+        new_usage.insert(db).await?;
+    }
+    entity::prelude::Usage::update_many()
+        .col_expr(kind.column(), Expr::col(kind.column()).add(amount))
+        .filter(usage::Column::ChatId.eq(chat_id))
+        .filter(usage::Column::BotId.eq(bot_id))
+        .filter(usage::Column::Day.eq(Utc::now().date_naive()))
+        .exec(db)
+        .await?;
+    today(db, chat_id, bot_id)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("usage".to_string()))
+}
+
+/// Returns whether `chat_id` reached its daily quota of `kind`.
+pub(crate) async fn is_exceeded(
+    db: &DatabaseConnection,
+    chat_id: i64,
+    bot_id: i64,
+    kind: Usage,
+) -> bool {
+    let Some(limit) = flags::limit(db, kind.flag()).await else {
+        return false;
+    };
+    match today(db, chat_id, bot_id).await {
+        Ok(Some(usage)) => kind.of(&usage) >= limit,
+        Ok(None) => false,
+        Err(err) => {
+            println!("Error reading usage of chat {}: {:?}", chat_id, err);
+            false
+        }
+    }
+}
+
+/// Records that `chat_id` uses `amount` of `kind`, and returns whether it is still within its
+/// quota. The chat is told when it is close to the quota and when it reaches it, once a day.
+pub(crate) async fn use_quota(
+    bot: &Bot,
+    db: &DatabaseConnection,
+    chat_id: i64,
+    bot_id: i64,
+    kind: Usage,
+    amount: i64,
+) -> bool {
+    let usage = match record(db, chat_id, bot_id, kind, amount).await {
+        Ok(usage) => usage,
+        Err(err) => {
+            println!("Error recording usage of chat {}: {:?}", chat_id, err);
+            return true;
+        }
+    };
+    let Some(limit) = flags::limit(db, kind.flag()).await else {
+        return true;
+    };
+    let used = kind.of(&usage);
+    let (column, text) = if used > limit {
+        if usage.limited {
+            return false;
+        }
+        let text = format!(
+            "This chat reached its daily quota of {} {}. It is throttled until tomorrow (UTC).",
+            limit,
+            kind.name()
+        );
+        (usage::Column::Limited, text)
+    } else if used * 100 >= limit * SOFT_LIMIT_PERCENT && !usage.warned {
+        let text = format!(
+            "This chat used {} of its daily quota of {} {}.",
+            used,
+            limit,
+            kind.name()
+        );
+        (usage::Column::Warned, text)
+    } else {
+        return true;
+    };
+    let notified = entity::prelude::Usage::update_many()
+        .col_expr(column, Expr::value(true))
+        .filter(usage::Column::ChatId.eq(chat_id))
+        .filter(usage::Column::BotId.eq(bot_id))
+        .filter(usage::Column::Day.eq(usage.day))
+        .exec(db)
+        .await;
+    if let Err(err) = notified {
+        println!("Error recording quota notification: {:?}", err);
+    }
+    if let Err(err) = bot.send_message(ChatId(chat_id), text).await {
+        println!("Error sending quota notification: {:?}", err);
+    }
+    used <= limit
+}
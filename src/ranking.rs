@@ -0,0 +1,92 @@
+//! Top items digests of folders.
+//!
+//! A folder with `/folder set <name> top <N>` holds the items of its feeds for a daily digest
+//! that lists its `N` best items first and the others in a collapsed quote. Items are scored by
+//! how recent they are, how many of the `keywords` of the folder their title contains, and how
+//! often the chat interacted with the items of their feed in the last [`ENGAGEMENT_DAYS`] days.
+
+use std::collections::HashMap;
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use sea_orm::{ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter};
+
+use entity::{delivery, folder};
+
+const ENGAGEMENT_DAYS: i64 = 30;
+/// Score of an item matching a keyword, per keyword.
+const KEYWORD_SCORE: f64 = 2.0;
+/// Engagement counts up to this score, so that a single favourite feed can't take all the top.
+const MAX_ENGAGEMENT_SCORE: f64 = 3.0;
+
+/// Returns the keywords of `folder`, in lowercase.
+fn keywords(folder: &folder::Model) -> Vec<String> {
+    folder
+        .keywords
+        .as_deref()
+        .unwrap_or_default()
+        .split(',')
+        .map(|keyword| keyword.trim().to_lowercase())
+        .filter(|keyword| !keyword.is_empty())
+        .collect()
+}
+
+/// Returns the interactions per item of each of the feeds of `held`.
+async fn engagement(
+    db: &DatabaseConnection,
+    held: &[delivery::Model],
+) -> Result<HashMap<i64, f64>, DbErr> {
+    let since = Utc::now().naive_utc() - Duration::days(ENGAGEMENT_DAYS);
+    let deliveries = entity::prelude::Delivery::find()
+        .filter(delivery::Column::FeedId.is_in(held.iter().map(|delivery| delivery.feed_id)))
+        .filter(delivery::Column::SentAt.gt(since))
+        .filter(delivery::Column::Held.eq(false))
+        .all(db)
+        .await?;
+    let mut counts: HashMap<i64, (f64, f64)> = HashMap::new();
+    for delivery in deliveries {
+        let (items, interactions) = counts.entry(delivery.feed_id).or_default();
+        *items += 1.0;
+        *interactions += f64::from(delivery.clicks + delivery.link_clicks);
+    }
+    Ok(counts
+        .into_iter()
+        .map(|(feed_id, (items, interactions))| (feed_id, interactions / items))
+        .collect())
+}
+
+fn score(
+    delivery: &delivery::Model,
+    keywords: &[String],
+    engagement: &HashMap<i64, f64>,
+    now: NaiveDateTime,
+) -> f64 {
+    let age_hours = (now - delivery.sent_at).num_minutes() as f64 / 60.0;
+    let recency = (1.0 - age_hours / 24.0).clamp(0.0, 1.0);
+    let title = delivery.title.to_lowercase();
+    let matches = keywords.iter().filter(|keyword| title.contains(*keyword)).count();
+    let engagement = engagement.get(&delivery.feed_id).copied().unwrap_or_default();
+    recency + KEYWORD_SCORE * matches as f64 + engagement.min(MAX_ENGAGEMENT_SCORE)
+}
+
+/// Returns the `held` items of `folder` from the best to the worst.
+pub(crate) async fn rank(
+    db: &DatabaseConnection,
+    folder: &folder::Model,
+    mut held: Vec<delivery::Model>,
+) -> Vec<delivery::Model> {
+    let engagement = match engagement(db, &held).await {
+        Ok(engagement) => engagement,
+        Err(err) => {
+            println!("Error reading the engagement of folder {}: {:?}", folder.id, err);
+            HashMap::new()
+        }
+    };
+    let keywords = keywords(folder);
+    let now = Utc::now().naive_utc();
+    held.sort_by(|a, b| {
+        let a = score(a, &keywords, &engagement, now);
+        let b = score(b, &keywords, &engagement, now);
+        b.total_cmp(&a)
+    });
+    held
+}
@@ -0,0 +1,284 @@
+//! Per-feed maximum message rate.
+//!
+//! A feed with a rate such as `5/day` sends at most 5 items per calendar day (UTC). Further items
+//! are held, recorded as deliveries with `held` set, and sent together in a digest, split into
+//! several messages if it is too long for one, once the window is over. Feeds muted with `buffer`
+//! hold their items the same way until the mute ends, feeds with a delivery window until it opens,
+//! and chats over their daily message quota until the next day. Held items keep no message id, so
+//! that the digest is never edited to show a single updated item. Low-priority feeds hold all their
+//! items, and send them silently once the window of their rate, or the hour, is over. Folders with
+//! a number of top items hold all the items of their feeds for a daily digest of their best ones.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::str::FromStr;
+
+use chrono::{Datelike, Duration, NaiveDateTime, NaiveTime, Timelike, Utc};
+use rss::Item;
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ActiveValue, ColumnTrait, DatabaseConnection, DbErr,
+    EntityTrait, PaginatorTrait, QueryFilter, QueryOrder, UpdateResult,
+};
+use teloxide::{
+    payloads::SendMessageSetters,
+    prelude::Requester,
+    types::{ChatId, ParseMode},
+    RequestError,
+};
+
+use crate::bots::Bots;
+use crate::format::DigestPart;
+use crate::priority::Priority;
+use crate::quota::Usage;
+use entity::{delivery, feed, folder};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Window {
+    Hour,
+    Day,
+    Week,
+}
+
+impl Window {
+    /// Returns the start of the window containing `time`.
+    fn start(self, time: NaiveDateTime) -> NaiveDateTime {
+        let day = time.date().and_time(NaiveTime::MIN);
+        match self {
+            Window::Hour => day + Duration::hours(time.hour().into()),
+            Window::Day => day,
+            Window::Week => day - Duration::days(time.weekday().num_days_from_monday().into()),
+        }
+    }
+}
+
+/// A maximum number of items per window, written as `5/day`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Rate {
+    items: u64,
+    window: Window,
+}
+
+impl FromStr for Rate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || format!("Expected a rate such as 5/day, got {}", s);
+        let (items, window) = s.trim().split_once('/').ok_or_else(error)?;
+        let items = items.parse::<u64>().ok().filter(|&items| items > 0);
+        let window = match window.to_lowercase().as_str() {
+            "hour" | "h" => Window::Hour,
+            "day" | "d" => Window::Day,
+            "week" | "w" => Window::Week,
+            _ => return Err(error()),
+        };
+        Ok(Rate {
+            items: items.ok_or_else(error)?,
+            window,
+        })
+    }
+}
+
+fn feed_rate(feed: &feed::Model) -> Option<Rate> {
+    feed.max_rate.as_deref().and_then(|rate| rate.parse().ok())
+}
+
+/// Returns whether `feed`, or its folder if the rate is the folder's, already sent as many
+/// items as its rate allows in the current window.
+pub(crate) async fn is_exceeded(
+    db: &DatabaseConnection,
+    feed: &feed::Model,
+) -> Result<bool, DbErr> {
+    let Some(rate) = feed_rate(feed) else {
+        return Ok(false);
+    };
+    let feed_ids = crate::folders::rate_feed_ids(db, feed).await?;
+    let sent = entity::prelude::Delivery::find()
+        .filter(delivery::Column::FeedId.is_in(feed_ids))
+        .filter(delivery::Column::SentAt.gte(rate.window.start(Utc::now().naive_utc())))
+        .count(db)
+        .await?;
+    Ok(sent >= rate.items)
+}
+
+/// Holds `item` until the digest at the end of the current window.
+pub(crate) async fn hold_item(
+    db: &DatabaseConnection,
+    feed: &feed::Model,
+    item: &Item,
+) -> Result<delivery::Model, DbErr> {
+    let held_delivery = delivery::ActiveModel {
+        feed_id: ActiveValue::Set(feed.id),
+        chat_id: ActiveValue::Set(feed.chat_id),
+        title: ActiveValue::Set(item.title().unwrap_or_default().to_string()),
+        link: ActiveValue::Set(item.link().unwrap_or_default().to_string()),
+        guid: ActiveValue::Set(item.guid().map(|guid| guid.value().to_string())),
+        held: ActiveValue::Set(true),
+        ..Default::default()
+    };
+    held_delivery.insert(db).await
+}
+
+/// Sends a digest of the held items of every feed whose window is over, or whose rate was
+/// removed. The items of the feeds of a folder are sent in a single digest, with the settings
+/// of the folder.
+pub(crate) async fn send_digests(bots: &Bots, db: &DatabaseConnection) {
+    let held = entity::prelude::Delivery::find()
+        .filter(delivery::Column::Held.eq(true))
+        .order_by_asc(delivery::Column::SentAt)
+        .all(db)
+        .await;
+    let held = match held {
+        Ok(held) => held,
+        Err(err) => {
+            println!("Error fetching held items: {:?}", err);
+            return;
+        }
+    };
+    let mut by_feed: HashMap<i64, Vec<delivery::Model>> = HashMap::new();
+    for delivery in held {
+        by_feed.entry(delivery.feed_id).or_default().push(delivery);
+    }
+    let now = Utc::now().naive_utc();
+    let mut by_folder: HashMap<i64, (feed::Model, folder::Model, Vec<delivery::Model>)> =
+        HashMap::new();
+    for (feed_id, deliveries) in by_feed {
+        let feed = match entity::prelude::Feed::find_by_id(feed_id).one(db).await {
+            Ok(Some(feed)) => feed,
+            Ok(None) => continue,
+            Err(err) => {
+                println!("Error fetching feed {}: {:?}", feed_id, err);
+                continue;
+            }
+        };
+        let (feed, folder) = match crate::folders::resolve(db, &feed).await {
+            Ok(resolved) => resolved,
+            Err(err) => {
+                println!("Error fetching the folder of feed {}: {:?}", feed_id, err);
+                continue;
+            }
+        };
+        if crate::mute::is_muted(&feed) || !crate::window::is_open(&feed, now) {
+            continue;
+        }
+        let low_priority = crate::priority::of(&feed) == Priority::Low;
+        let top = folder.as_ref().is_some_and(|folder| folder.top_items.is_some());
+        let window = match feed_rate(&feed) {
+            Some(rate) => Some(rate.window),
+            None if top => Some(Window::Day),
+            None if low_priority => Some(Window::Hour),
+            None => None,
+        };
+        let due: Vec<delivery::Model> = match window {
+            Some(window) => {
+                let start = window.start(now);
+                deliveries
+                    .into_iter()
+                    .filter(|delivery| delivery.sent_at < start)
+                    .collect()
+            }
+            None => deliveries,
+        };
+        if due.is_empty() {
+            continue;
+        }
+        match folder {
+            Some(folder) => {
+                let (_, _, folder_due) = by_folder
+                    .entry(folder.id)
+                    .or_insert_with(|| (feed, folder, Vec::new()));
+                folder_due.extend(due);
+            }
+            None => {
+                let parts = crate::format::digest_messages(&feed, &due);
+                send_digest(bots, db, &feed, parts, &due, low_priority).await;
+            }
+        }
+    }
+    for (feed, folder, mut due) in by_folder.into_values() {
+        due.sort_by_key(|delivery| delivery.sent_at);
+        let parts = match folder.top_items {
+            Some(top) => {
+                let due = crate::ranking::rank(db, &folder, due.clone()).await;
+                vec![crate::format::top_digest_message(&folder, &due, top as usize)]
+            }
+            None => crate::format::folder_digest_messages(&folder, &due),
+        };
+        let silent = crate::priority::of(&feed) == Priority::Low;
+        send_digest(bots, db, &feed, parts, &due, silent).await;
+    }
+}
+
+/// Marks the held items with `ids` as sent.
+async fn release(db: &DatabaseConnection, ids: &[i64]) {
+    let released = entity::prelude::Delivery::update_many()
+        .col_expr(delivery::Column::Held, Expr::value(false))
+        .filter(delivery::Column::Id.is_in(ids.iter().copied()))
+        .exec(db)
+        .await;
+    if let Err(err) = released {
+        println!("Error releasing held items: {:?}", err);
+    }
+}
+
+/// Sends the `parts` of a digest of the `due` items to the chat of `feed`, without a
+/// notification if `silent`, and releases the items of each part sent. The items of a part that
+/// Telegram refuses are released too, since the same digest would be refused again, and the
+/// parts after a network error or a flood wait are left for the next poll.
+async fn send_digest(
+    bots: &Bots,
+    db: &DatabaseConnection,
+    feed: &feed::Model,
+    parts: Vec<DigestPart>,
+    due: &[delivery::Model],
+    silent: bool,
+) {
+    let Some(bot) = bots.get(feed.bot_id) else {
+        return;
+    };
+    let single_feed = due.iter().all(|delivery| delivery.feed_id == feed.id);
+    for part in parts {
+        if crate::quota::is_exceeded(db, feed.chat_id, feed.bot_id, Usage::Messages).await {
+            return;
+        }
+        let sent = bot
+            .send_message(ChatId(feed.chat_id), part.message)
+            .parse_mode(ParseMode::Html)
+            .disable_web_page_preview(true)
+            .disable_notification(silent)
+            .await;
+        match sent {
+            Ok(sent) => {
+                if single_feed {
+                    crate::destinations::send_copies(bot, db, feed, sent.id, silent).await;
+                }
+                crate::quota::use_quota(bot, db, feed.chat_id, feed.bot_id, Usage::Messages, 1)
+                    .await;
+            }
+            Err(RequestError::Api(err)) => {
+                println!("Dropping a digest part refused by Telegram: {:?}", err);
+            }
+            Err(err) => {
+                println!("Error sending digest: {:?}", err);
+                return;
+            }
+        }
+        release(db, &part.ids).await;
+    }
+}
+
+/// Sets the maximum rate of feed `id`. `off` removes it.
+pub(crate) async fn set_max_rate(
+    db: &DatabaseConnection,
+    id: i64,
+    chat_id: i64,
+    bot_id: i64,
+    rate: &str,
+) -> Result<UpdateResult, Box<dyn Error + Send + Sync>> {
+    let rate = if rate.eq_ignore_ascii_case("off") {
+        None
+    } else {
+        rate.parse::<Rate>()?;
+        Some(rate.to_lowercase())
+    };
+    crate::set_managed_feed_option(db, id, chat_id, bot_id, feed::Column::MaxRate, rate).await
+}
@@ -0,0 +1,133 @@
+//! Reconciliation of the database on startup.
+//!
+//! Long-lived databases accumulate inconsistencies, e.g. rows written by older versions or by
+//! hand. Before polling starts, feeds without a chat are reported, timestamps in the future are
+//! reset, source links are normalized again, merging duplicate sources, and sources without
+//! feeds are removed. Every anomaly is logged and sent to the admin chat, if one is configured
+//! with `ADMIN_CHAT_ID`.
+
+use std::collections::HashMap;
+
+use chrono::{NaiveDateTime, Utc};
+use sea_orm::{
+    sea_query::Expr, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, ModelTrait,
+    QueryFilter,
+};
+use teloxide::{
+    prelude::{Bot, Requester},
+    types::ChatId,
+};
+
+use crate::sources::normalize_link;
+use entity::{feed, feed_source};
+
+/// Fixes what can be fixed and returns a description of every anomaly.
+async fn reconcile(db: &DatabaseConnection) -> Result<Vec<String>, DbErr> {
+    let mut anomalies = Vec::new();
+    let now = Utc::now().naive_utc();
+
+    let feeds = entity::prelude::Feed::find()
+        .find_also_related(entity::prelude::Chat)
+        .all(db)
+        .await?;
+    for (feed, chat) in &feeds {
+        if chat.is_none() {
+            anomalies.push(format!(
+                "Feed {} belongs to the missing chat {} of bot {}",
+                feed.id, feed.chat_id, feed.bot_id
+            ));
+        }
+    }
+
+    let future = entity::prelude::Feed::update_many()
+        .col_expr(feed::Column::UpdatedAt, Expr::value(now))
+        .filter(feed::Column::UpdatedAt.gt(now))
+        .exec(db)
+        .await?;
+    if future.rows_affected > 0 {
+        anomalies.push(format!(
+            "Reset the date of the last item of {} feeds, which was in the future",
+            future.rows_affected
+        ));
+    }
+    let future = entity::prelude::Feed::update_many()
+        .col_expr(feed::Column::LastCheckedAt, Expr::value(None::<NaiveDateTime>))
+        .filter(feed::Column::LastCheckedAt.gt(now))
+        .exec(db)
+        .await?;
+    if future.rows_affected > 0 {
+        anomalies.push(format!(
+            "Cleared the last check of {} feeds, which was in the future",
+            future.rows_affected
+        ));
+    }
+
+    let sources = entity::prelude::FeedSource::find().all(db).await?;
+    let mut by_link: HashMap<String, i64> = sources
+        .iter()
+        .filter(|source| normalize_link(&source.link) == source.link)
+        .map(|source| (source.link.clone(), source.id))
+        .collect();
+    for source in &sources {
+        let link = normalize_link(&source.link);
+        if link == source.link {
+            continue;
+        }
+        match by_link.get(&link) {
+            Some(&target) => {
+                entity::prelude::Feed::update_many()
+                    .col_expr(feed::Column::SourceId, Expr::value(target))
+                    .filter(feed::Column::SourceId.eq(source.id))
+                    .exec(db)
+                    .await?;
+                source.clone().delete(db).await?;
+                anomalies.push(format!("Merged source {} into {}", source.link, link));
+            }
+            None => {
+                entity::prelude::FeedSource::update_many()
+                    .col_expr(feed_source::Column::Link, Expr::value(link.clone()))
+                    .filter(feed_source::Column::Id.eq(source.id))
+                    .exec(db)
+                    .await?;
+                anomalies.push(format!("Normalized source {} to {}", source.link, link));
+                by_link.insert(link, source.id);
+            }
+        }
+    }
+
+    let used: Vec<i64> = entity::prelude::Feed::find()
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|feed| feed.source_id)
+        .collect();
+    let unused = entity::prelude::FeedSource::delete_many()
+        .filter(feed_source::Column::Id.is_not_in(used))
+        .exec(db)
+        .await?;
+    if unused.rows_affected > 0 {
+        anomalies.push(format!("Removed {} sources without feeds", unused.rows_affected));
+    }
+
+    Ok(anomalies)
+}
+
+/// Reconciles the database, reporting the anomalies with `bot` to `admin_chat`.
+pub(crate) async fn run(db: &DatabaseConnection, bot: &Bot, admin_chat: Option<i64>) {
+    let anomalies = match reconcile(db).await {
+        Ok(anomalies) => anomalies,
+        Err(err) => {
+            log::error!("Error reconciling the database: {}", err);
+            return;
+        }
+    };
+    for anomaly in &anomalies {
+        log::warn!("{}", anomaly);
+    }
+    if let (Some(admin_chat), false) = (admin_chat, anomalies.is_empty()) {
+        let text = format!("Startup reconciliation:\n{}", anomalies.join("\n"));
+        if let Err(err) = bot.send_message(ChatId(admin_chat), text).await {
+            log::error!("Error reporting anomalies: {}", err);
+        }
+    }
+}
@@ -0,0 +1,87 @@
+//! Recording and replay of feed fetches.
+//!
+//! When `RECORD_DIR` is set, the body of every feed downloaded by the poller is written to
+//! `<RECORD_DIR>/<encoded link>/<timestamp>.body`. `multitude_bot replay <feed id> [file]` reads
+//! a recording, the latest one of the feed's link by default, and prints the message of every
+//! item as the feed's settings would send it, or why it would be skipped, without fetching the
+//! feed or sending anything. Reports of feeds that render wrong can then be reproduced from the
+//! exact body the bot saw.
+
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+use chrono::Utc;
+use sea_orm::{DatabaseConnection, EntityTrait};
+
+use crate::{fediverse, filters, folders, format, language, pipeline, releases};
+
+/// Returns the directory of the recordings of `link`, if recording is enabled.
+fn directory(link: &str) -> Option<PathBuf> {
+    let root = env::var("RECORD_DIR").ok()?;
+    Some(PathBuf::from(root).join(urlencoding::encode(link).as_ref()))
+}
+
+/// Records `content` fetched from `link`, if recording is enabled.
+pub(crate) fn record(link: &str, content: &[u8]) {
+    let Some(directory) = directory(link) else {
+        return;
+    };
+    let path = directory.join(format!("{}.body", Utc::now().format("%Y%m%dT%H%M%S%.3f")));
+    if let Err(err) = fs::create_dir_all(&directory).and_then(|_| fs::write(&path, content)) {
+        println!("Error recording {} to {}: {:?}", link, path.display(), err);
+    }
+}
+
+/// Returns the latest recording of `link`.
+fn latest(link: &str) -> Result<PathBuf, Box<dyn Error + Send + Sync>> {
+    let directory = directory(link).ok_or("RECORD_DIR is not set")?;
+    let mut recordings: Vec<PathBuf> = fs::read_dir(&directory)
+        .map_err(|err| format!("No recordings in {}: {}", directory.display(), err))?
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .collect();
+    // Timestamps sort in chronological order
+    recordings.sort();
+    recordings
+        .pop()
+        .ok_or_else(|| format!("No recordings in {}", directory.display()).into())
+}
+
+/// Prints the messages feed `feed_id` would send for the items of the recording at `path`, or
+/// of its latest recording.
+pub(crate) async fn replay(
+    db: &DatabaseConnection,
+    feed_id: i64,
+    path: Option<&str>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let feed = entity::prelude::Feed::find_by_id(feed_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| format!("No feed {}", feed_id))?;
+    let path = match path {
+        Some(path) => PathBuf::from(path),
+        None => latest(&feed.link)?,
+    };
+    println!("Replaying {} for feed {}", path.display(), feed.id);
+    let channel = crate::parse_channel(&fs::read(&path)?)?;
+    let (feed, _) = folders::resolve(db, &feed).await?;
+    for item in &channel.items {
+        let title = item.title().unwrap_or_default();
+        let Some(item) = pipeline::apply(&feed, item) else {
+            println!("---\nDropped by the pipeline: {}", title);
+            continue;
+        };
+        let allowed = filters::allows_item(&feed, &item)
+            && filters::allows_length(&feed, &item)
+            && language::allows_item(&feed, &item)
+            && fediverse::allows_item(&feed, &item)
+            && releases::allows_item(&feed, &item);
+        if allowed {
+            println!("---\n{}", format::item_message(&feed, &item));
+        } else {
+            println!("---\nFiltered out: {}", title);
+        }
+    }
+    Ok(())
+}
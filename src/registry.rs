@@ -0,0 +1,154 @@
+//! In-memory registry of the feeds.
+//!
+//! The poller used to load every feed from the database every cycle. The registry loads them
+//! once and then only reloads the feeds that were invalidated since the last cycle: every change
+//! of a feed calls [`invalidate`], or [`invalidate_all`] for changes of many feeds at once such
+//! as deleting a chat, and the poller stores the feeds it updates with [`put`].
+//!
+//! The feeds that are not paused are also kept in a queue ordered by their `next_check_at`, so
+//! that the poller only goes through the feeds that are due.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::Mutex;
+
+use chrono::NaiveDateTime;
+use sea_orm::{ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter};
+
+use entity::feed;
+
+#[derive(Default)]
+struct Registry {
+    /// Every feed, by id, if they were loaded.
+    feeds: Option<HashMap<i64, feed::Model>>,
+    /// The time of the next check and the id of the feeds that are not paused, earliest first.
+    queue: BTreeSet<(NaiveDateTime, i64)>,
+    /// The feeds that changed since they were loaded.
+    invalid: HashSet<i64>,
+    /// Incremented by [`invalidate_all`], so that a load that started before is not kept.
+    generation: u64,
+}
+
+/// The position of `feed` in the queue. Feeds that were never checked come first.
+fn queue_entry(feed: &feed::Model) -> (NaiveDateTime, i64) {
+    (feed.next_check_at.unwrap_or(NaiveDateTime::MIN), feed.id)
+}
+
+impl Registry {
+    fn remove(&mut self, id: i64) {
+        if let Some(feed) = self.feeds.as_mut().and_then(|feeds| feeds.remove(&id)) {
+            self.queue.remove(&queue_entry(&feed));
+        }
+    }
+
+    fn insert(&mut self, feed: feed::Model) {
+        self.remove(feed.id);
+        if let Some(feeds) = &mut self.feeds {
+            if !feed.paused {
+                self.queue.insert(queue_entry(&feed));
+            }
+            feeds.insert(feed.id, feed);
+        }
+    }
+
+    /// Replaces every feed with `feeds`.
+    fn load(&mut self, feeds: Vec<feed::Model>) {
+        self.feeds = Some(HashMap::new());
+        self.queue.clear();
+        for feed in feeds {
+            self.insert(feed);
+        }
+    }
+}
+
+static REGISTRY: Mutex<Option<Registry>> = Mutex::new(None);
+
+fn with_registry<T>(f: impl FnOnce(&mut Registry) -> T) -> T {
+    let mut registry = REGISTRY.lock().expect("registry lock poisoned");
+    f(registry.get_or_insert_with(Registry::default))
+}
+
+/// Reloads feed `id` on the next poll, because it was created, changed or deleted.
+pub(crate) fn invalidate(id: i64) {
+    with_registry(|registry| registry.invalid.insert(id));
+}
+
+/// Reloads every feed on the next poll.
+pub(crate) fn invalidate_all() {
+    with_registry(|registry| {
+        registry.feeds = None;
+        registry.queue.clear();
+        registry.generation += 1;
+    });
+}
+
+/// Stores `feed` as it was just updated in the database.
+pub(crate) fn put(feed: feed::Model) {
+    with_registry(|registry| registry.insert(feed));
+}
+
+/// Loads the feeds if they weren't loaded yet, or reloads those that changed since the last
+/// call.
+async fn refresh(db: &DatabaseConnection) -> Result<(), DbErr> {
+    let (loaded, invalid, generation) = with_registry(|registry| {
+        let invalid: Vec<i64> = registry.invalid.drain().collect();
+        (registry.feeds.is_some(), invalid, registry.generation)
+    });
+    if !loaded {
+        let feeds = entity::prelude::Feed::find().all(db).await?;
+        with_registry(|registry| {
+            if registry.generation == generation {
+                registry.load(feeds);
+            }
+        });
+    } else if !invalid.is_empty() {
+        let reloaded = entity::prelude::Feed::find()
+            .filter(feed::Column::Id.is_in(invalid.clone()))
+            .all(db)
+            .await;
+        let reloaded = match reloaded {
+            Ok(reloaded) => reloaded,
+            Err(err) => {
+                // Try again on the next poll
+                with_registry(|registry| registry.invalid.extend(invalid));
+                return Err(err);
+            }
+        };
+        with_registry(|registry| {
+            // Deleted feeds are not reloaded
+            for id in invalid {
+                registry.remove(id);
+            }
+            for feed in reloaded {
+                registry.insert(feed);
+            }
+        });
+    }
+    Ok(())
+}
+
+/// Returns up to `limit` feeds that are not paused and are due to be checked at `now`, the
+/// longest overdue first.
+pub(crate) async fn due_feeds(
+    db: &DatabaseConnection,
+    now: NaiveDateTime,
+    limit: usize,
+) -> Result<Vec<feed::Model>, DbErr> {
+    refresh(db).await?;
+    Ok(with_registry(|registry| {
+        let Some(feeds) = &registry.feeds else {
+            return Vec::new();
+        };
+        registry
+            .queue
+            .iter()
+            .take_while(|(next_check_at, _)| *next_check_at <= now)
+            .take(limit)
+            .filter_map(|(_, id)| feeds.get(id).cloned())
+            .collect()
+    }))
+}
+
+/// Returns when the next feed is due to be checked, if any is loaded.
+pub(crate) fn next_check_at() -> Option<NaiveDateTime> {
+    with_registry(|registry| registry.queue.first().map(|(next_check_at, _)| *next_check_at))
+}
@@ -0,0 +1,224 @@
+//! GitHub release feeds.
+//!
+//! With `/releasenotes <feed id> on`, items of a GitHub releases feed
+//! (`https://github.com/<owner>/<repo>/releases.atom`) are sent with the notes of the release,
+//! converted from Markdown and shortened, and links to its assets, read from the GitHub API. The
+//! optional secret `GITHUB_TOKEN` raises the rate limit of the API.
+//!
+//! `/versions <feed id> major|minor|all [stable]` filters the items of any release feed by the
+//! version in their title: `major` keeps versions such as `2.0.0`, `minor` also `2.1.0`, and
+//! `stable` skips pre-releases such as `2.1.0-rc.1` or nightly tags without a version.
+
+use std::error::Error;
+use std::sync::OnceLock;
+
+use regex::Regex;
+use rss::Item;
+use sea_orm::{DatabaseConnection, UpdateResult};
+use serde::Deserialize;
+use teloxide::utils::html::escape;
+
+use entity::feed;
+
+const GITHUB_API: &str = "https://api.github.com";
+/// Release notes are cut after this many characters of Markdown, at the end of a line.
+const MAX_NOTES_CHARS: usize = 1000;
+const MAX_ASSETS: usize = 5;
+
+static BOLD: OnceLock<Regex> = OnceLock::new();
+static CODE: OnceLock<Regex> = OnceLock::new();
+static LINK: OnceLock<Regex> = OnceLock::new();
+static VERSION: OnceLock<Regex> = OnceLock::new();
+
+/// The parts of a version number such as `1.2.3-rc.1` that filters look at, with missing parts
+/// read as 0.
+#[derive(Debug, PartialEq, Eq)]
+struct Version {
+    minor: u64,
+    patch: u64,
+    prerelease: bool,
+}
+
+#[derive(Deserialize)]
+struct Release {
+    body: Option<String>,
+    #[serde(default)]
+    assets: Vec<Asset>,
+}
+
+#[derive(Deserialize)]
+struct Asset {
+    name: String,
+    browser_download_url: String,
+}
+
+/// Returns the owner, repository and tag of the GitHub release `link`, such as
+/// `https://github.com/owner/repo/releases/tag/v1.0`.
+fn release_path(link: &str) -> Option<(&str, &str, &str)> {
+    let path = link.strip_prefix("https://github.com/")?;
+    let (repository, tag) = path.split_once("/releases/tag/")?;
+    let (owner, repo) = repository.split_once('/')?;
+    Some((owner, repo, tag))
+}
+
+async fn release(
+    owner: &str,
+    repo: &str,
+    tag: &str,
+) -> Result<Release, Box<dyn Error + Send + Sync>> {
+    let url = format!("{}/repos/{}/{}/releases/tags/{}", GITHUB_API, owner, repo, tag);
+    let mut request = reqwest::Client::new()
+        .get(&url)
+        .header(reqwest::header::USER_AGENT, "multitude_bot")
+        .header(reqwest::header::ACCEPT, "application/vnd.github+json");
+    if let Ok(token) = crate::secrets::get("GITHUB_TOKEN").await {
+        request = request.bearer_auth(token);
+    }
+    Ok(request.send().await?.error_for_status()?.json().await?)
+}
+
+/// Cuts `markdown` at the end of the last line that fits in [`MAX_NOTES_CHARS`].
+fn shorten(markdown: &str) -> String {
+    let mut short = String::new();
+    for line in markdown.lines() {
+        if short.len() + line.len() > MAX_NOTES_CHARS {
+            short.push('…');
+            break;
+        }
+        short.push_str(line);
+        short.push('\n');
+    }
+    short
+}
+
+/// Converts the Markdown of release notes to Telegram HTML: headings and bold text become bold,
+/// list items bullets, and code spans and links keep their meaning. Other Markdown is left as
+/// text.
+fn markdown_to_html(markdown: &str) -> String {
+    let bold = BOLD.get_or_init(|| Regex::new(r"\*\*([^*]+)\*\*").expect("valid regex"));
+    let code = CODE.get_or_init(|| Regex::new(r"`([^`]+)`").expect("valid regex"));
+    let link = LINK.get_or_init(|| Regex::new(r"\[([^\]]+)\]\(([^)\s]+)\)").expect("valid regex"));
+    markdown
+        .lines()
+        .map(|line| {
+            let line = escape(line.trim_end());
+            let line = bold.replace_all(&line, "<b>$1</b>");
+            let line = code.replace_all(&line, "<code>$1</code>");
+            let line = link.replace_all(&line, "<a href='$2'>$1</a>");
+            let trimmed = line.trim_start();
+            if trimmed.starts_with('#') {
+                format!("<b>{}</b>", trimmed.trim_start_matches('#').trim())
+            } else if let Some(entry) = trimmed.strip_prefix("- ").or(trimmed.strip_prefix("* ")) {
+                format!("• {}", entry)
+            } else {
+                line.to_string()
+            }
+        })
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn release_message(release: &Release) -> String {
+    let mut message = String::new();
+    if let Some(body) = release.body.as_ref().filter(|body| !body.trim().is_empty()) {
+        message.push_str(&format!(
+            "<blockquote expandable>{}</blockquote>\n",
+            markdown_to_html(&shorten(body))
+        ));
+    }
+    for asset in release.assets.iter().take(MAX_ASSETS) {
+        message.push_str(&format!(
+            "📦 <a href='{}'>{}</a>\n",
+            escape(&asset.browser_download_url),
+            escape(&asset.name)
+        ));
+    }
+    if release.assets.len() > MAX_ASSETS {
+        message.push_str(&format!("and {} more assets\n", release.assets.len() - MAX_ASSETS));
+    }
+    message
+}
+
+/// Returns the HTML notes and assets of the GitHub release `item` links to, if `feed` asks for
+/// them.
+pub(crate) async fn details(feed: &feed::Model, item: &Item) -> Option<String> {
+    if !feed.release_notes {
+        return None;
+    }
+    let (owner, repo, tag) = release_path(item.link()?)?;
+    match release(owner, repo, tag).await {
+        Ok(release) => Some(release_message(&release)),
+        Err(err) => {
+            println!("Error fetching the release of an item of feed {}: {:?}", feed.id, err);
+            None
+        }
+    }
+}
+
+/// Returns the first version number in `title`.
+fn parse_version(title: &str) -> Option<Version> {
+    let version = VERSION.get_or_init(|| {
+        Regex::new(r"(?i)\bv?(\d+)\.(\d+)(?:\.(\d+))?(-[0-9a-z.-]+)?").expect("valid regex")
+    });
+    let captures = version.captures(title)?;
+    let number = |index: usize| {
+        captures
+            .get(index)
+            .and_then(|part| part.as_str().parse().ok())
+            .unwrap_or_default()
+    };
+    let lowercase = title.to_lowercase();
+    let prerelease = captures.get(4).is_some()
+        || ["alpha", "beta", "nightly", "preview"]
+            .iter()
+            .any(|word| lowercase.contains(word));
+    Some(Version {
+        minor: number(2),
+        patch: number(3),
+        prerelease,
+    })
+}
+
+/// Returns whether `item` of `feed` is a release that passes the version filter of `feed`.
+pub(crate) fn allows_item(feed: &feed::Model, item: &Item) -> bool {
+    if feed.release_level.is_none() && !feed.stable_releases {
+        return true;
+    }
+    let Some(version) = parse_version(item.title().unwrap_or_default()) else {
+        return false;
+    };
+    let level = match feed.release_level.as_deref() {
+        Some("major") => version.minor == 0 && version.patch == 0,
+        Some("minor") => version.patch == 0,
+        _ => true,
+    };
+    level && !(feed.stable_releases && version.prerelease)
+}
+
+/// Sets the version filter of feed `id` from `args`, such as `minor stable`. `off` removes it.
+pub(crate) async fn set_filter(
+    db: &DatabaseConnection,
+    id: i64,
+    chat_id: i64,
+    bot_id: i64,
+    args: &str,
+) -> Result<UpdateResult, Box<dyn Error + Send + Sync>> {
+    let mut words = args.split_whitespace().map(str::to_lowercase);
+    let level = words.next().unwrap_or_default();
+    let stable = match words.next().as_deref() {
+        None => false,
+        Some("stable") => true,
+        Some(other) => return Err(format!("Expected stable, got {}", other).into()),
+    };
+    let (level, stable) = match level.as_str() {
+        "major" | "minor" => (Some(level), stable),
+        "all" => (None, stable),
+        "off" => (None, false),
+        _ => return Err(format!("Expected major, minor, all or off, got {}", level).into()),
+    };
+    let column = feed::Column::ReleaseLevel;
+    crate::set_managed_feed_option(db, id, chat_id, bot_id, column, level).await?;
+    let column = feed::Column::StableReleases;
+    crate::set_managed_feed_option(db, id, chat_id, bot_id, column, stable).await
+}
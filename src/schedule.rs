@@ -0,0 +1,185 @@
+//! When each feed is checked.
+//!
+//! Every feed has a `next_check_at`, and the poller only checks the feeds that are due, at most
+//! [`BATCH`] at a time, then sleeps until the next one is. A feed is checked every
+//! `poll_interval_seconds` of the configuration file unless `/interval <feed id> <duration>`
+//! gives it its own interval. Feeds that fail back off, doubling their interval after every
+//! failure in a row up to [`MAX_BACKOFF_HOURS`] hours.
+//!
+//! The schedule lives in the database, so that restarts keep the backoffs and the spread of
+//! the checks. Feeds being checked have `checking_since` set until their outcome is stored, and
+//! the feeds that still have it on startup were interrupted by a crash: [`resume`] reports them
+//! and makes them due again right away, rather than waiting for their next interval. Polls
+//! requested through the API or a notification only make the feed due and wake the poller, so
+//! that a feed is never checked twice at the same time.
+
+use std::error::Error;
+use std::sync::OnceLock;
+use std::time::Duration as StdDuration;
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait,
+    QueryFilter, Set, UpdateResult,
+};
+use tokio::sync::Notify;
+
+use crate::{config, maintenance, mute, registry};
+use entity::feed;
+
+/// Maximum number of feeds checked per poll. The others are checked by the next one.
+pub(crate) const BATCH: usize = 1000;
+/// Shortest interval that can be set for a feed, in minutes.
+const MIN_INTERVAL_MINUTES: i64 = 1;
+/// Longest interval that can be set for a feed, in days.
+const MAX_INTERVAL_DAYS: i64 = 7;
+/// Shortest sleep between two polls, so that feeds that stay due aren't polled in a busy loop.
+const MIN_WAIT: StdDuration = StdDuration::from_secs(1);
+/// Longest interval of a failing feed, in hours, unless its own interval is longer.
+const MAX_BACKOFF_HOURS: i64 = 24;
+/// The interval of a failing feed stops doubling after this many failures.
+const MAX_BACKOFF_STEPS: i32 = 10;
+
+/// Returns the interval between two checks of `feed`.
+pub(crate) fn interval(feed: &feed::Model) -> Duration {
+    match feed.check_interval {
+        Some(seconds) => Duration::seconds(seconds.into()),
+        None => Duration::seconds(config::get().poll_interval().as_secs() as i64),
+    }
+}
+
+/// Returns when `feed` is due again if it is checked at `now` after failing its last `errors`
+/// checks in a row.
+pub(crate) fn next_check_at(feed: &feed::Model, now: NaiveDateTime, errors: i32) -> NaiveDateTime {
+    let interval = interval(feed);
+    let backoff = interval * 2_i32.pow(errors.clamp(0, MAX_BACKOFF_STEPS) as u32);
+    now + backoff.min(Duration::hours(MAX_BACKOFF_HOURS)).max(interval)
+}
+
+/// Returns how long the poller should sleep before the next poll: until the next feed is due,
+/// but at least [`MIN_WAIT`] and at most the default interval.
+pub(crate) fn wait() -> StdDuration {
+    let default = config::get().poll_interval();
+    if maintenance::is_on() {
+        return default;
+    }
+    let Some(next_check_at) = registry::next_check_at() else {
+        return default;
+    };
+    (next_check_at - Utc::now().naive_utc())
+        .to_std()
+        .unwrap_or(MIN_WAIT)
+        .clamp(MIN_WAIT, default.max(MIN_WAIT))
+}
+
+static WAKE_UP: OnceLock<Notify> = OnceLock::new();
+
+/// Returns the signal waking the poller up when feeds are made due.
+fn wake_up() -> &'static Notify {
+    WAKE_UP.get_or_init(Notify::new)
+}
+
+/// Sleeps for [`wait`], or until feeds are made due.
+pub(crate) async fn sleep() {
+    tokio::select! {
+        _ = tokio::time::sleep(wait()) => {}
+        _ = wake_up().notified() => {}
+    }
+}
+
+/// Moves `feed` to its next check without checking it, such as when it is over its quota.
+pub(crate) async fn postpone(db: &DatabaseConnection, feed: feed::Model) {
+    let next_check_at = next_check_at(&feed, Utc::now().naive_utc(), feed.error_count);
+    let mut postponed_feed: feed::ActiveModel = feed.into();
+    postponed_feed.next_check_at = Set(Some(next_check_at));
+    postponed_feed.checking_since = Set(None);
+    match postponed_feed.update(db).await {
+        Ok(feed) => registry::put(feed),
+        Err(err) => println!("Error updating feed: {:?}", err),
+    }
+}
+
+/// Records that `feeds` are being checked.
+pub(crate) async fn start(db: &DatabaseConnection, feeds: &[feed::Model]) -> Result<(), DbErr> {
+    // The registry doesn't read `checking_since`, so its copies of the feeds stay valid
+    entity::prelude::Feed::update_many()
+        .col_expr(feed::Column::CheckingSince, Expr::value(Utc::now().naive_utc()))
+        .filter(feed::Column::Id.is_in(feeds.iter().map(|feed| feed.id)))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+/// Makes the feeds whose check was interrupted by a crash due again, and returns how many.
+pub(crate) async fn resume(db: &DatabaseConnection) -> Result<u64, DbErr> {
+    let none: Option<NaiveDateTime> = None;
+    let resumed = entity::prelude::Feed::update_many()
+        .col_expr(feed::Column::NextCheckAt, Expr::value(none))
+        .col_expr(feed::Column::CheckingSince, Expr::value(none))
+        .filter(feed::Column::CheckingSince.is_not_null())
+        .exec(db)
+        .await?;
+    registry::invalidate_all();
+    Ok(resumed.rows_affected)
+}
+
+/// Makes feed `id` due and wakes the poller up, unless the feed is being checked. Returns
+/// whether it was made due.
+pub(crate) async fn make_due(db: &DatabaseConnection, id: i64) -> Result<bool, DbErr> {
+    let none: Option<NaiveDateTime> = None;
+    let updated = entity::prelude::Feed::update_many()
+        .col_expr(feed::Column::NextCheckAt, Expr::value(none))
+        .filter(feed::Column::Id.eq(id))
+        .filter(feed::Column::CheckingSince.is_null())
+        .exec(db)
+        .await?;
+    registry::invalidate(id);
+    wake_up().notify_one();
+    Ok(updated.rows_affected > 0)
+}
+
+/// Makes every feed that is not being checked due and wakes the poller up, so that the next
+/// polls check all of them.
+pub(crate) async fn make_all_due(db: &DatabaseConnection) -> Result<(), DbErr> {
+    let none: Option<NaiveDateTime> = None;
+    entity::prelude::Feed::update_many()
+        .col_expr(feed::Column::NextCheckAt, Expr::value(none))
+        .filter(feed::Column::CheckingSince.is_null())
+        .exec(db)
+        .await?;
+    registry::invalidate_all();
+    wake_up().notify_one();
+    Ok(())
+}
+
+/// Sets the interval of feed `id` to `value`, such as `15m` or `6h`, or back to the default
+/// with `default`. The feed is checked again right away.
+pub(crate) async fn set_interval(
+    db: &DatabaseConnection,
+    id: i64,
+    chat_id: i64,
+    bot_id: i64,
+    value: &str,
+) -> Result<UpdateResult, Box<dyn Error + Send + Sync>> {
+    let seconds = if value.eq_ignore_ascii_case("default") {
+        None
+    } else {
+        let interval = mute::parse_duration(value)?;
+        if interval < Duration::minutes(MIN_INTERVAL_MINUTES)
+            || interval > Duration::days(MAX_INTERVAL_DAYS)
+        {
+            return Err(format!(
+                "Expected an interval between {}m and {}d",
+                MIN_INTERVAL_MINUTES, MAX_INTERVAL_DAYS
+            )
+            .into());
+        }
+        Some(interval.num_seconds() as i32)
+    };
+    let column = feed::Column::CheckInterval;
+    let updated = crate::set_managed_feed_option(db, id, chat_id, bot_id, column, seconds).await?;
+    let column = feed::Column::NextCheckAt;
+    let none: Option<NaiveDateTime> = None;
+    crate::set_managed_feed_option(db, id, chat_id, bot_id, column, none).await?;
+    Ok(updated)
+}
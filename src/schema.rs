@@ -0,0 +1,113 @@
+//! Checks of the database schema against the entities.
+//!
+//! `multitude_bot migrate --dry-run` lists the migrations that would be applied without applying
+//! them, and `multitude_bot migrate` applies them and exits. On startup, after the migrations,
+//! every column of every entity is looked up in the live schema, and the bot refuses to start
+//! with the list of the missing tables and columns instead of failing later with query errors,
+//! e.g. if a column was renamed by hand.
+
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+
+use sea_orm::{
+    ConnectionTrait, DatabaseConnection, DbErr, EntityName, EntityTrait, IdenStatic, Iterable,
+    Statement,
+};
+
+use entity::prelude::*;
+use migration::{Migrator, MigratorTrait};
+
+/// Returns the table of `entity` and its columns.
+fn columns<E: EntityTrait>(entity: E) -> (String, Vec<&'static str>) {
+    let columns = E::Column::iter().map(|column| column.as_str()).collect();
+    (entity.table_name().to_string(), columns)
+}
+
+/// Returns the tables and columns of every entity.
+fn entities() -> Vec<(String, Vec<&'static str>)> {
+    vec![
+        columns(AccountEvent),
+        columns(Blocklist),
+        columns(Bridge),
+        columns(Burst),
+        columns(Chat),
+        columns(ChatLink),
+        columns(Delivery),
+        columns(Destination),
+        columns(FeatureFlag),
+        columns(Feed),
+        columns(FeedAlias),
+        columns(FeedSource),
+        columns(Folder),
+        columns(FolderFeed),
+        columns(Follow),
+        columns(ImageCache),
+        columns(ItemHash),
+        columns(LinkCode),
+        columns(NewsletterItem),
+        columns(Report),
+        columns(SitemapPage),
+        columns(Snooze),
+        columns(SpamItem),
+        columns(UndoAction),
+        columns(Usage),
+        columns(Watch),
+        columns(WatchChange),
+    ]
+}
+
+/// Returns the columns of every table of the live schema.
+async fn live_columns(db: &DatabaseConnection) -> Result<HashMap<String, HashSet<String>>, DbErr> {
+    let sql = "SELECT table_name, column_name FROM information_schema.columns \
+               WHERE table_schema = current_schema()";
+    let rows = db
+        .query_all(Statement::from_string(db.get_database_backend(), sql))
+        .await?;
+    let mut tables: HashMap<String, HashSet<String>> = HashMap::new();
+    for row in rows {
+        let table: String = row.try_get("", "table_name")?;
+        let column: String = row.try_get("", "column_name")?;
+        tables.entry(table).or_default().insert(column);
+    }
+    Ok(tables)
+}
+
+/// Returns the tables and columns of the entities that are missing from the live schema.
+async fn drift(db: &DatabaseConnection) -> Result<Vec<String>, DbErr> {
+    let live = live_columns(db).await?;
+    let mut missing = Vec::new();
+    for (table, columns) in entities() {
+        let Some(live_columns) = live.get(&table) else {
+            missing.push(format!("table {}", table));
+            continue;
+        };
+        for column in columns {
+            if !live_columns.contains(column) {
+                missing.push(format!("column {}.{}", table, column));
+            }
+        }
+    }
+    Ok(missing)
+}
+
+/// Fails with the list of the tables and columns missing from the live schema, if any.
+pub(crate) async fn check(db: &DatabaseConnection) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let missing = drift(db).await?;
+    if missing.is_empty() {
+        return Ok(());
+    }
+    Err(format!(
+        "The database schema doesn't match the entities, missing:\n{}",
+        missing.join("\n")
+    )
+    .into())
+}
+
+/// Returns the names of the migrations that were not applied yet.
+pub(crate) async fn pending_migrations(db: &DatabaseConnection) -> Result<Vec<String>, DbErr> {
+    Ok(Migrator::get_pending_migrations(db)
+        .await?
+        .iter()
+        .map(|migration| migration.name().to_string())
+        .collect())
+}
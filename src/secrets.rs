@@ -0,0 +1,58 @@
+//! Loading of secrets.
+//!
+//! A secret such as `DB_PASSWORD` is looked up, in order, in
+//! - the environment variable `DB_PASSWORD`,
+//! - the file named by the environment variable `DB_PASSWORD_FILE`,
+//! - the Docker secret `/run/secrets/db_password`,
+//! - the key `db_password` of a HashiCorp Vault KV v2 secret, if `VAULT_ADDR` and `VAULT_TOKEN`
+//!   are set. The secret path defaults to `secret/data/multitude_bot` and can be changed with
+//!   `VAULT_SECRET_PATH`.
+
+use std::env;
+use std::error::Error;
+use std::fs;
+
+const DOCKER_SECRETS_DIR: &str = "/run/secrets";
+const DEFAULT_VAULT_PATH: &str = "secret/data/multitude_bot";
+
+async fn from_vault(key: &str) -> Result<Option<String>, Box<dyn Error + Send + Sync>> {
+    let (Ok(addr), Ok(token)) = (env::var("VAULT_ADDR"), env::var("VAULT_TOKEN")) else {
+        return Ok(None);
+    };
+    let path = env::var("VAULT_SECRET_PATH").unwrap_or_else(|_| DEFAULT_VAULT_PATH.to_string());
+    let url = format!("{}/v1/{}", addr.trim_end_matches('/'), path);
+    let response: serde_json::Value = reqwest::Client::new()
+        .get(&url)
+        .header("X-Vault-Token", token)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    Ok(response["data"]["data"][key].as_str().map(str::to_string))
+}
+
+/// Returns the secret `name`, without surrounding whitespace.
+pub(crate) async fn get(name: &str) -> Result<String, Box<dyn Error + Send + Sync>> {
+    if let Ok(value) = env::var(name) {
+        return Ok(value.trim().to_string());
+    }
+    let file_var = format!("{}_FILE", name);
+    if let Ok(path) = env::var(&file_var) {
+        let value = fs::read_to_string(&path)
+            .map_err(|err| format!("Couldn't read file {} of {}: {}", path, file_var, err))?;
+        return Ok(value.trim().to_string());
+    }
+    let key = name.to_lowercase();
+    if let Ok(value) = fs::read_to_string(format!("{}/{}", DOCKER_SECRETS_DIR, key)) {
+        return Ok(value.trim().to_string());
+    }
+    if let Some(value) = from_vault(&key).await? {
+        return Ok(value.trim().to_string());
+    }
+    Err(format!(
+        "Secret {} not found: set {} or {}, or add the Docker secret {}",
+        name, name, file_var, key
+    )
+    .into())
+}
@@ -0,0 +1,139 @@
+//! Severity routing for status-page feeds.
+//!
+//! Items are classified by the keywords status pages conventionally use in their titles and
+//! descriptions, and per-feed rules such as `critical=loud resolved=silent` decide whether
+//! items of each severity are sent with a notification, silently or not at all.
+
+use std::collections::HashMap;
+use std::error::Error;
+
+use rss::Item;
+use sea_orm::{DatabaseConnection, UpdateResult};
+
+use entity::feed;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum Severity {
+    Critical,
+    Incident,
+    Maintenance,
+    Resolved,
+    Other,
+}
+
+/// Keywords of each severity, checked in order: "Resolved: major outage" is resolved.
+const KEYWORDS: [(Severity, &[&str]); 4] = [
+    (
+        Severity::Resolved,
+        &["resolved", "completed", "recovered", "operational"],
+    ),
+    (Severity::Maintenance, &["maintenance", "scheduled"]),
+    (
+        Severity::Critical,
+        &["critical", "outage", "down", "emergency"],
+    ),
+    (
+        Severity::Incident,
+        &[
+            "investigating",
+            "identified",
+            "monitoring",
+            "degraded",
+            "partial",
+            "incident",
+        ],
+    ),
+];
+
+impl Severity {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "critical" => Some(Severity::Critical),
+            "incident" => Some(Severity::Incident),
+            "maintenance" => Some(Severity::Maintenance),
+            "resolved" => Some(Severity::Resolved),
+            "other" => Some(Severity::Other),
+            _ => None,
+        }
+    }
+
+    /// Classifies `item` by the keywords in its title and description.
+    pub(crate) fn of(item: &Item) -> Self {
+        let text = format!(
+            "{} {}",
+            item.title().unwrap_or_default(),
+            item.description().unwrap_or_default()
+        )
+        .to_lowercase();
+        KEYWORDS
+            .iter()
+            .find(|(_, keywords)| keywords.iter().any(|keyword| text.contains(keyword)))
+            .map_or(Severity::Other, |(severity, _)| *severity)
+    }
+}
+
+/// How the items of a severity are delivered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Action {
+    Loud,
+    Silent,
+    Drop,
+}
+
+impl Action {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "loud" => Some(Action::Loud),
+            "silent" => Some(Action::Silent),
+            "drop" => Some(Action::Drop),
+            _ => None,
+        }
+    }
+}
+
+/// Parses rules of the form `critical=loud resolved=silent`.
+pub(crate) fn parse_rules(rules: &str) -> Result<HashMap<Severity, Action>, String> {
+    rules
+        .split_whitespace()
+        .map(|rule| {
+            let (severity, action) = rule.to_lowercase().split_once('=').map_or(
+                (None, None),
+                |(severity, action)| (Severity::parse(severity), Action::parse(action)),
+            );
+            match (severity, action) {
+                (Some(severity), Some(action)) => Ok((severity, action)),
+                _ => Err(format!(
+                    "Invalid rule {}, use <critical|incident|maintenance|resolved|other>=\
+                     <loud|silent|drop>",
+                    rule
+                )),
+            }
+        })
+        .collect()
+}
+
+/// Returns how `item` of `feed` should be delivered. Items are loud unless a rule says
+/// otherwise.
+pub(crate) fn action(feed: &feed::Model, item: &Item) -> Action {
+    let Some(Ok(rules)) = feed.severity_rules.as_deref().map(parse_rules) else {
+        return Action::Loud;
+    };
+    rules
+        .get(&Severity::of(item))
+        .copied()
+        .unwrap_or(Action::Loud)
+}
+
+/// Stores the severity `rules` of feed `id`. Empty rules remove them.
+pub(crate) async fn set_rules(
+    db: &DatabaseConnection,
+    id: i64,
+    chat_id: i64,
+    bot_id: i64,
+    rules: &str,
+) -> Result<UpdateResult, Box<dyn Error + Send + Sync>> {
+    parse_rules(rules)?;
+    let rules = Some(rules.to_string()).filter(|rules| !rules.is_empty());
+    crate::set_managed_feed_option(db, id, chat_id, bot_id, feed::Column::SeverityRules, rules)
+        .await
+}
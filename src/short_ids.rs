@@ -0,0 +1,234 @@
+//! Short references to the feeds of a chat.
+//!
+//! The feeds of each chat are numbered from 1 in the order they were subscribed to, and keep
+//! their number for as long as they exist. Commands taking a feed accept its number, or the start
+//! of its title when no other feed of the chat has a title starting the same, such as `/pause
+//! hacker` or `/mute "hacker news" 3h`. The feeds of linked chats are managed by their global
+//! id, written with a marker such as `@123` so that it is never mistaken for the number of a
+//! feed. The commands taking a feed are those whose description starts with `<feed id>`.
+//!
+//! Sent in reply to an item, these commands take the feed of the item and no feed argument, such
+//! as `/mute 3h` or `/unsubscribe`, unless they name a feed by its title or global id, or a
+//! selection such as `/unsubscribe all`. Numbers in replies are taken as arguments, not feeds.
+
+use std::sync::OnceLock;
+
+use sea_orm::{ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter};
+use teloxide::utils::command::BotCommands;
+
+use entity::{delivery, feed};
+
+/// Marks the global id of a feed, such as `@123`, as opposed to its number in the chat.
+const GLOBAL_MARKER: char = '@';
+/// The descriptions of the commands whose first argument is a feed start with one of these.
+const FEED_PLACEHOLDERS: [&str; 2] = ["<feed id>", "[feed id]"];
+
+static FEED_COMMANDS: OnceLock<Vec<String>> = OnceLock::new();
+
+/// Returns the names of the commands whose first argument is a feed.
+fn feed_commands() -> &'static [String] {
+    FEED_COMMANDS.get_or_init(|| {
+        crate::LoggedInCommand::bot_commands()
+            .into_iter()
+            .filter(|command| {
+                FEED_PLACEHOLDERS
+                    .iter()
+                    .any(|placeholder| command.description.starts_with(placeholder))
+            })
+            .map(|command| command.command.trim_start_matches('/').to_lowercase())
+            .collect()
+    })
+}
+
+/// Returns the global id of `feed` as commands accept it, such as `@123`.
+pub(crate) fn global_reference(feed: &feed::Model) -> String {
+    format!("{}{}", GLOBAL_MARKER, feed.id)
+}
+
+/// Returns the number of the next feed of `chat_id`.
+pub(crate) async fn next<C: ConnectionTrait>(
+    db: &C,
+    chat_id: i64,
+    bot_id: i64,
+) -> Result<i32, DbErr> {
+    let feeds = entity::prelude::Feed::find()
+        .filter(feed::Column::ChatId.eq(chat_id))
+        .filter(feed::Column::BotId.eq(bot_id))
+        .all(db)
+        .await?;
+    Ok(feeds.iter().map(|feed| feed.short_id).max().unwrap_or(0) + 1)
+}
+
+/// Returns whether a feed of `chat_id` has the number `short_id`.
+pub(crate) async fn is_taken<C: ConnectionTrait>(
+    db: &C,
+    chat_id: i64,
+    bot_id: i64,
+    short_id: i32,
+) -> Result<bool, DbErr> {
+    let feed = entity::prelude::Feed::find()
+        .filter(feed::Column::ChatId.eq(chat_id))
+        .filter(feed::Column::BotId.eq(bot_id))
+        .filter(feed::Column::ShortId.eq(short_id))
+        .one(db)
+        .await?;
+    Ok(feed.is_some())
+}
+
+/// Returns the id of the feed of `chat_id` that `reference` refers to: the number of the feed,
+/// the start of its title or its global id marked with [`GLOBAL_MARKER`].
+pub(crate) async fn resolve(
+    db: &DatabaseConnection,
+    chat_id: i64,
+    bot_id: i64,
+    reference: &str,
+) -> Result<Option<i64>, DbErr> {
+    if let Some(id) = reference.strip_prefix(GLOBAL_MARKER) {
+        return Ok(id.parse().ok());
+    }
+    let feeds = entity::prelude::Feed::find()
+        .filter(feed::Column::ChatId.eq(chat_id))
+        .filter(feed::Column::BotId.eq(bot_id));
+    if let Ok(id) = reference.parse::<i64>() {
+        let Ok(short_id) = i32::try_from(id) else {
+            return Ok(None);
+        };
+        let feed = feeds
+            .filter(feed::Column::ShortId.eq(short_id))
+            .one(db)
+            .await?;
+        return Ok(feed.map(|feed| feed.id));
+    }
+    let prefix = reference.to_lowercase();
+    let matches: Vec<feed::Model> = feeds
+        .all(db)
+        .await?
+        .into_iter()
+        .filter(|feed| feed.title.to_lowercase().starts_with(&prefix))
+        .collect();
+    match matches.as_slice() {
+        [feed] => Ok(Some(feed.id)),
+        _ => Ok(None),
+    }
+}
+
+/// Returns the id of the feed whose item was sent to `chat_id` as `message_id`.
+async fn feed_of_item(
+    db: &DatabaseConnection,
+    chat_id: i64,
+    bot_id: i64,
+    message_id: i32,
+) -> Result<Option<i64>, DbErr> {
+    let deliveries = entity::prelude::Delivery::find()
+        .filter(delivery::Column::ChatId.eq(chat_id))
+        .filter(delivery::Column::MessageId.eq(message_id))
+        .all(db)
+        .await?;
+    let feeds = entity::prelude::Feed::find()
+        .filter(feed::Column::Id.is_in(deliveries.iter().map(|delivery| delivery.feed_id)))
+        .filter(feed::Column::BotId.eq(bot_id))
+        .all(db)
+        .await?;
+    // Digests hold the items of several feeds
+    match feeds.as_slice() {
+        [feed] => Ok(Some(feed.id)),
+        _ => Ok(None),
+    }
+}
+
+/// Splits the feed reference at the start of `args`, a word or a quoted title, from the rest.
+fn split_reference(args: &str) -> (&str, &str) {
+    let args = args.trim_start();
+    if let Some(quoted) = args.strip_prefix('"') {
+        if let Some((reference, rest)) = quoted.split_once('"') {
+            return (reference, rest);
+        }
+    }
+    let end = args.find(char::is_whitespace).unwrap_or(args.len());
+    args.split_at(end)
+}
+
+/// Returns the command `text` sent to `chat_id` with its feed, if it takes one, replaced by
+/// the id of the feed, or, if it replies to the item sent as `replied_to` and doesn't name a
+/// feed itself, preceded by the id of the feed of the item. Fails with the reply to send if the
+/// feed is a number or a global id that doesn't refer to a feed.
+pub(crate) async fn rewrite(
+    db: &DatabaseConnection,
+    chat_id: i64,
+    bot_id: i64,
+    text: &str,
+    replied_to: Option<i32>,
+) -> Result<Result<String, String>, DbErr> {
+    let (command, args) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+    let name = command
+        .trim_start_matches('/')
+        .split('@')
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+    if !feed_commands().contains(&name) {
+        return Ok(Ok(text.to_string()));
+    }
+    let (reference, rest) = split_reference(args);
+    // The selections of /unsubscribe are not titles
+    let is_selection = name == "unsubscribe"
+        && (reference.eq_ignore_ascii_case("all")
+            || reference.starts_with('#')
+            || reference == "matching");
+    if let Some(message_id) = replied_to {
+        // In a reply, numbers are arguments such as `/minlength 100`, but titles and global ids
+        // name a feed, such as `/mute hacker 3h`
+        let names_feed = is_selection
+            || (!reference.is_empty()
+                && reference.parse::<i64>().is_err()
+                && resolve(db, chat_id, bot_id, reference).await?.is_some());
+        if !names_feed {
+            if let Some(id) = feed_of_item(db, chat_id, bot_id, message_id).await? {
+                return Ok(Ok(format!("{} {} {}", command, id, args.trim())
+                    .trim_end()
+                    .to_string()));
+            }
+        }
+    }
+    if reference.is_empty() || is_selection {
+        return Ok(Ok(text.to_string()));
+    }
+    let is_id = reference.starts_with(GLOBAL_MARKER) || reference.parse::<i64>().is_ok();
+    Ok(match resolve(db, chat_id, bot_id, reference).await? {
+        Some(id) => Ok(format!("{} {}{}", command, id, rest)),
+        // Numbers are never taken for the global id of a feed, which needs its marker
+        None if is_id => Err(format!(
+            "No feed {} in this chat, see /list. The feeds of linked chats are referred to by \
+             their global id, such as {}123.",
+            reference, GLOBAL_MARKER
+        )),
+        None => Ok(text.to_string()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_the_commands_taking_a_feed() {
+        let commands = feed_commands();
+        for name in ["pause", "mute", "unsubscribe", "history", "destinations"] {
+            assert!(commands.iter().any(|command| command == name), "{}", name);
+        }
+        for name in ["list", "subscribe", "folder", "unlink", "dismiss"] {
+            assert!(!commands.iter().any(|command| command == name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn splits_quoted_titles() {
+        assert_eq!(split_reference(" hacker 3h"), ("hacker", " 3h"));
+        assert_eq!(
+            split_reference("\"hacker news\" 3h"),
+            ("hacker news", " 3h")
+        );
+        assert_eq!(split_reference("@123"), ("@123", ""));
+        assert_eq!(split_reference(""), ("", ""));
+    }
+}
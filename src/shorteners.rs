@@ -0,0 +1,155 @@
+//! Link shorteners of chats.
+//!
+//! `/shortener shlink|yourls <address> <key>` makes the links of the items sent to a chat go
+//! through a self-hosted Shlink or YOURLS server, which counts their clicks. Links are sent
+//! unchanged if the shortener fails.
+
+use std::error::Error;
+use std::str::FromStr;
+
+use rss::Item;
+use sea_orm::{
+    sea_query::Expr, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, UpdateResult,
+};
+use serde::Deserialize;
+use serde_json::json;
+
+use entity::{chat, feed};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Shortener {
+    Shlink,
+    Yourls,
+}
+
+impl Shortener {
+    fn name(self) -> &'static str {
+        match self {
+            Shortener::Shlink => "shlink",
+            Shortener::Yourls => "yourls",
+        }
+    }
+}
+
+impl FromStr for Shortener {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "shlink" => Ok(Shortener::Shlink),
+            "yourls" => Ok(Shortener::Yourls),
+            _ => Err(format!("Expected shlink or yourls, got {}", s)),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ShlinkUrl {
+    short_url: String,
+}
+
+#[derive(Deserialize)]
+struct YourlsUrl {
+    shorturl: String,
+}
+
+/// Returns the short link of `link` from the `shortener` server at `address`.
+async fn shorten(
+    shortener: Shortener,
+    address: &str,
+    key: &str,
+    link: &str,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let address = address.trim_end_matches('/');
+    let client = reqwest::Client::new();
+    match shortener {
+        Shortener::Shlink => {
+            let short: ShlinkUrl = client
+                .post(format!("{}/rest/v3/short-urls", address))
+                .header("X-Api-Key", key)
+                .json(&json!({ "longUrl": link, "findIfExists": true }))
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+            Ok(short.short_url)
+        }
+        Shortener::Yourls => {
+            let short: YourlsUrl = client
+                .get(format!("{}/yourls-api.php", address))
+                .query(&[
+                    ("signature", key),
+                    ("action", "shorturl"),
+                    ("format", "json"),
+                    ("url", link),
+                ])
+                .send()
+                .await?
+                .json()
+                .await?;
+            Ok(short.shorturl)
+        }
+    }
+}
+
+/// Returns `item` of `feed` with its link shortened, if its chat has a shortener.
+pub(crate) async fn shorten_item(
+    db: &DatabaseConnection,
+    feed: &feed::Model,
+    item: &Item,
+) -> Option<Item> {
+    let link = item.link()?;
+    let chat = entity::prelude::Chat::find_by_id((feed.chat_id, feed.bot_id))
+        .one(db)
+        .await;
+    let chat = match chat {
+        Ok(chat) => chat?,
+        Err(err) => {
+            println!("Error fetching chat {}: {:?}", feed.chat_id, err);
+            return None;
+        }
+    };
+    let shortener: Shortener = chat.shortener.as_deref()?.parse().ok()?;
+    let (address, key) = (chat.shortener_url?, chat.shortener_key?);
+    match shorten(shortener, &address, &key, link).await {
+        Ok(short) => {
+            let mut shortened = item.clone();
+            shortened.set_link(short);
+            Some(shortened)
+        }
+        Err(err) => {
+            println!("Error shortening {} with {}: {:?}", link, shortener.name(), err);
+            None
+        }
+    }
+}
+
+/// Sets the shortener of `chat_id` from `args`, such as `shlink https://s.example.com <key>`.
+/// `off` removes it.
+pub(crate) async fn set_shortener(
+    db: &DatabaseConnection,
+    chat_id: i64,
+    bot_id: i64,
+    args: &str,
+) -> Result<UpdateResult, Box<dyn Error + Send + Sync>> {
+    let words: Vec<&str> = args.split_whitespace().collect();
+    let (shortener, address, key) = match words[..] {
+        [off] if off.eq_ignore_ascii_case("off") => (None, None, None),
+        [shortener, address, key] => {
+            let shortener: Shortener = shortener.parse()?;
+            reqwest::Url::parse(address)?;
+            (Some(shortener.name()), Some(address), Some(key))
+        }
+        _ => return Err("Expected shlink|yourls <address> <key>, or off".into()),
+    };
+    Ok(entity::prelude::Chat::update_many()
+        .col_expr(chat::Column::Shortener, Expr::value(shortener))
+        .col_expr(chat::Column::ShortenerUrl, Expr::value(address))
+        .col_expr(chat::Column::ShortenerKey, Expr::value(key))
+        .filter(chat::Column::Id.eq(chat_id))
+        .filter(chat::Column::BotId.eq(bot_id))
+        .exec(db)
+        .await?)
+}
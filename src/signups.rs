@@ -0,0 +1,105 @@
+//! Notifications of new and deleted accounts to the admin chat.
+//!
+//! Every `/start` and `/deleteaccount` is recorded in the `account_event` table and, unless the
+//! `signup_alerts` flag is off, reported to the admin chat right away. Once a day the admin chat
+//! also gets the number of chats that registered and deleted their account in the last day, and
+//! the total number of chats, so that operators of public instances can follow their growth.
+
+use chrono::{Duration, Utc};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, PaginatorTrait,
+    QueryFilter, Set,
+};
+use teloxide::{prelude::Requester, types::ChatId};
+
+use crate::admin::AdminChat;
+use crate::bots::Bots;
+use crate::flags::{self, Flag};
+use entity::account_event;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Event {
+    Registered,
+    Deleted,
+}
+
+impl Event {
+    fn kind(self) -> &'static str {
+        match self {
+            Event::Registered => "registered",
+            Event::Deleted => "deleted",
+        }
+    }
+}
+
+/// Records `event` of chat `chat_id` and reports it to the admin chat. `name` describes the
+/// chat, such as its title or username.
+pub(crate) async fn record(
+    db: &DatabaseConnection,
+    bots: &Bots,
+    admin: AdminChat,
+    event: Event,
+    chat_id: i64,
+    bot_id: i64,
+    name: &str,
+) {
+    let new_event = account_event::ActiveModel {
+        chat_id: Set(chat_id),
+        bot_id: Set(bot_id),
+        kind: Set(event.kind().to_string()),
+        ..Default::default()
+    };
+    if let Err(err) = new_event.insert(db).await {
+        println!("Error recording account event of chat {}: {:?}", chat_id, err);
+    }
+    let Some(admin_chat) = admin.0 else {
+        return;
+    };
+    if !flags::is_enabled(db, Flag::SignupAlerts).await {
+        return;
+    }
+    let text = match event {
+        Event::Registered => format!("New chat {} ({}) on bot {}", chat_id, name, bot_id),
+        Event::Deleted => {
+            format!("Chat {} ({}) deleted its account on bot {}", chat_id, name, bot_id)
+        }
+    };
+    if let Err(err) = bots.default_bot().send_message(ChatId(admin_chat), text).await {
+        log::error!("Error sending account event to the admin chat: {}", err);
+    }
+}
+
+/// Returns the report of the account events of the last day.
+async fn daily_counts(db: &DatabaseConnection) -> Result<String, DbErr> {
+    let since = Utc::now().naive_utc() - Duration::days(1);
+    let count = |event: Event| {
+        entity::prelude::AccountEvent::find()
+            .filter(account_event::Column::Kind.eq(event.kind()))
+            .filter(account_event::Column::CreatedAt.gt(since))
+            .count(db)
+    };
+    let registered = count(Event::Registered).await?;
+    let deleted = count(Event::Deleted).await?;
+    let chats = entity::prelude::Chat::find().count(db).await?;
+    Ok(format!(
+        "Last 24 hours: {} new chats, {} deleted accounts. {} chats in total.",
+        registered, deleted, chats
+    ))
+}
+
+/// Sends the account events of the last day to the admin chat, if there is one.
+pub(crate) async fn send_daily_counts(bots: Bots, db: DatabaseConnection, admin: AdminChat) {
+    let Some(admin_chat) = admin.0 else {
+        return;
+    };
+    let text = match daily_counts(&db).await {
+        Ok(text) => text,
+        Err(err) => {
+            println!("Error counting account events: {:?}", err);
+            return;
+        }
+    };
+    if let Err(err) = bots.default_bot().send_message(ChatId(admin_chat), text).await {
+        log::error!("Error sending the daily counts to the admin chat: {}", err);
+    }
+}
@@ -0,0 +1,90 @@
+//! Lightweight text similarity.
+//!
+//! Texts are compared by their sets of lowercase words with the Jaccard similarity, and by
+//! their keywords, the words of at least [`MIN_KEYWORD_CHARS`] characters that are not common
+//! English words. Lists of texts are clustered by the keywords they share.
+
+use std::collections::{HashMap, HashSet};
+
+const MIN_KEYWORD_CHARS: usize = 4;
+const STOP_WORDS: [&str; 24] = [
+    "about", "after", "again", "also", "been", "before", "from", "have", "into", "just", "more",
+    "news", "over", "says", "than", "that", "their", "there", "they", "this", "what", "when",
+    "will", "with",
+];
+
+/// Returns the set of lowercase alphanumeric words in `text`.
+pub(crate) fn words(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Returns the words of `text` that say what it is about.
+pub(crate) fn keywords(text: &str) -> HashSet<String> {
+    words(text)
+        .into_iter()
+        .filter(|word| word.chars().count() >= MIN_KEYWORD_CHARS)
+        .filter(|word| !STOP_WORDS.contains(&word.as_str()))
+        .collect()
+}
+
+/// Returns the Jaccard similarity of `a` and `b`, 1 if both are empty.
+pub(crate) fn jaccard(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 1.0;
+    }
+    a.intersection(b).count() as f64 / union as f64
+}
+
+/// Returns the number of words shared by `a` and `b`.
+pub(crate) fn shared(a: &HashSet<String>, b: &HashSet<String>) -> usize {
+    a.intersection(b).count()
+}
+
+/// Texts sharing a keyword.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Cluster {
+    /// The keyword shared by most texts of the cluster.
+    pub(crate) topic: String,
+    /// The indices of the texts of the cluster.
+    pub(crate) members: Vec<usize>,
+}
+
+/// Groups `texts` by the keywords they share, from the largest group to the smallest. Each text
+/// joins the first group with a keyword of the text that started it. Texts that share no
+/// keyword with others are returned in no cluster, as the second value.
+pub(crate) fn clusters(texts: &[&str]) -> (Vec<Cluster>, Vec<usize>) {
+    let text_keywords: Vec<HashSet<String>> = texts.iter().map(|text| keywords(text)).collect();
+    let mut groups: Vec<(&HashSet<String>, Vec<usize>)> = Vec::new();
+    for (index, keywords) in text_keywords.iter().enumerate() {
+        match groups.iter_mut().find(|(seed, _)| shared(seed, keywords) > 0) {
+            Some((_, members)) => members.push(index),
+            None => groups.push((keywords, vec![index])),
+        }
+    }
+    let (groups, alone): (Vec<_>, Vec<_>) =
+        groups.into_iter().partition(|(_, members)| members.len() > 1);
+    let mut clusters: Vec<Cluster> = groups
+        .into_iter()
+        .map(|(_, members)| {
+            let mut counts: HashMap<&str, usize> = HashMap::new();
+            for &member in &members {
+                for keyword in &text_keywords[member] {
+                    *counts.entry(keyword).or_default() += 1;
+                }
+            }
+            let topic = counts
+                .into_iter()
+                .max_by(|(a, a_count), (b, b_count)| a_count.cmp(b_count).then(b.cmp(a)))
+                .map(|(topic, _)| topic.to_string())
+                .unwrap_or_default();
+            Cluster { topic, members }
+        })
+        .collect();
+    clusters.sort_by_key(|cluster| std::cmp::Reverse(cluster.members.len()));
+    let alone = alone.into_iter().flat_map(|(_, members)| members).collect();
+    (clusters, alone)
+}
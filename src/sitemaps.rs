@@ -0,0 +1,182 @@
+//! Feeds of the pages added to the sitemap of a site.
+//!
+//! `/sitemap <url>` subscribes to a virtual feed with the link `sitemap:<url>`. Every poll reads
+//! the sitemap, following one level of sitemap index, and records the pages it didn't list
+//! before, whose titles are fetched from the pages themselves. The feed lists the pages in the
+//! order they appeared. The pages already listed when the sitemap is first read are recorded
+//! as old, so that only later additions are sent.
+
+use std::collections::HashSet;
+use std::error::Error;
+
+use chrono::{DateTime, Utc};
+use rss::{Channel, Item};
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, QuerySelect, Set,
+};
+
+use entity::{feed, sitemap_page};
+
+const LINK_PREFIX: &str = "sitemap:";
+/// Number of pages in the virtual feed.
+const ITEMS: u64 = 50;
+/// Maximum number of child sitemaps read from a sitemap index.
+const MAX_SITEMAPS: usize = 20;
+/// Maximum number of page titles fetched per poll. Other new pages are titled by their address.
+const MAX_TITLES: usize = 10;
+/// Large sitemaps are recorded this many pages at a time.
+const INSERT_CHUNK: usize = 1000;
+
+/// Returns whether `link` is the link of a sitemap feed.
+pub(crate) fn is_sitemap(link: &str) -> bool {
+    link.starts_with(LINK_PREFIX)
+}
+
+fn feed_link(url: &str) -> String {
+    format!("{}{}", LINK_PREFIX, url)
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Returns the text of every `<tag>` element of `xml`.
+pub(crate) fn tag_values(xml: &str, tag: &str) -> Vec<String> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    let mut values = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        rest = &rest[start + open.len()..];
+        let Some(end) = rest.find(&close) else {
+            break;
+        };
+        values.push(unescape(rest[..end].trim()));
+        rest = &rest[end + close.len()..];
+    }
+    values
+}
+
+/// Returns the pages listed by the sitemap at `url`, and by its children if it is an index.
+async fn pages(url: &str) -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
+    let xml = String::from_utf8(crate::cache::fetch(url).await?)?;
+    if !xml.contains("<sitemapindex") {
+        return Ok(tag_values(&xml, "loc"));
+    }
+    let mut pages = Vec::new();
+    for child in tag_values(&xml, "loc").iter().take(MAX_SITEMAPS) {
+        match crate::cache::fetch(child).await.map(String::from_utf8) {
+            Ok(Ok(xml)) => pages.extend(tag_values(&xml, "loc")),
+            Ok(Err(err)) => println!("Error reading sitemap {}: {:?}", child, err),
+            Err(err) => println!("Error fetching sitemap {}: {:?}", child, err),
+        }
+    }
+    Ok(pages)
+}
+
+/// Returns the `<title>` of the page at `url`.
+async fn title(url: &str) -> Option<String> {
+    let html = reqwest::get(url).await.ok()?.text().await.ok()?;
+    let lowercase = html.to_lowercase();
+    let start = lowercase.find("<title")?;
+    let start = start + lowercase[start..].find('>')? + 1;
+    let end = start + lowercase[start..].find("</title>")?;
+    let title = unescape(html.get(start..end)?.trim());
+    Some(title).filter(|title| !title.is_empty())
+}
+
+/// Records the pages of the sitemap at `url` that weren't known yet.
+async fn record_new_pages(
+    db: &DatabaseConnection,
+    url: &str,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut known: HashSet<String> = entity::prelude::SitemapPage::find()
+        .filter(sitemap_page::Column::Sitemap.eq(url))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|page| page.url)
+        .collect();
+    let first_read = known.is_empty();
+    let mut new_pages: Vec<String> = pages(url).await?;
+    new_pages.retain(|page| known.insert(page.clone()));
+    let mut titles_left = MAX_TITLES;
+    let mut records = Vec::new();
+    for page in new_pages {
+        let (title, first_seen_at) = if first_read {
+            (page.clone(), DateTime::UNIX_EPOCH.naive_utc())
+        } else if titles_left > 0 {
+            titles_left -= 1;
+            (title(&page).await.unwrap_or_else(|| page.clone()), Utc::now().naive_utc())
+        } else {
+            (page.clone(), Utc::now().naive_utc())
+        };
+        records.push(sitemap_page::ActiveModel {
+            sitemap: Set(url.to_string()),
+            url: Set(page),
+            title: Set(title),
+            first_seen_at: Set(first_seen_at),
+            ..Default::default()
+        });
+    }
+    for chunk in records.chunks(INSERT_CHUNK) {
+        entity::prelude::SitemapPage::insert_many(chunk.to_vec())
+            .exec(db)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Subscribes `chat_id` to the pages added to the sitemap at `url`.
+pub(crate) async fn subscribe(
+    db: &DatabaseConnection,
+    url: &str,
+    chat_id: i64,
+    bot_id: i64,
+) -> Result<feed::Model, Box<dyn Error + Send + Sync>> {
+    reqwest::Url::parse(url)?;
+    let channel = channel(db, &feed_link(url)).await?;
+    crate::create_feed(db, &channel, chat_id, bot_id).await
+}
+
+/// Reads the sitemap of the feed at `link` and returns the feed of its latest pages.
+pub(crate) async fn channel(
+    db: &DatabaseConnection,
+    link: &str,
+) -> Result<Channel, Box<dyn Error + Send + Sync>> {
+    let url = link
+        .strip_prefix(LINK_PREFIX)
+        .ok_or_else(|| format!("{} is not a sitemap feed", link))?;
+    record_new_pages(db, url).await?;
+    let pages = entity::prelude::SitemapPage::find()
+        .filter(sitemap_page::Column::Sitemap.eq(url))
+        .order_by_desc(sitemap_page::Column::FirstSeenAt)
+        .limit(ITEMS)
+        .all(db)
+        .await?;
+    let host = reqwest::Url::parse(url)?
+        .host_str()
+        .unwrap_or(url)
+        .to_string();
+    let mut channel = Channel::default();
+    channel.set_title(format!("New pages of {}", host));
+    channel.set_link(link);
+    channel.set_items(
+        pages
+            .into_iter()
+            .map(|page| {
+                let mut item = Item::default();
+                item.set_title(page.title);
+                item.set_link(page.url);
+                item.set_pub_date(crate::dates::rfc2822(page.first_seen_at));
+                item
+            })
+            .collect::<Vec<Item>>(),
+    );
+    Ok(channel)
+}
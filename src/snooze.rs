@@ -0,0 +1,171 @@
+//! Snoozing of delivered items.
+//!
+//! The ⏰ button of an item offers to send it again in an hour, tonight or tomorrow morning
+//! (UTC). The message is deleted and the item is stored in the `snooze` table until it is due,
+//! when it is sent again as a new item.
+
+use chrono::{Duration, NaiveDateTime, NaiveTime, Utc};
+use rss::{Guid, Item};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, ModelTrait,
+    QueryFilter,
+};
+use teloxide::{
+    payloads::EditMessageReplyMarkupSetters,
+    prelude::{Bot, Requester, ResponseResult},
+    types::{CallbackQuery, InlineKeyboardButton, InlineKeyboardMarkup},
+};
+
+use crate::bots::Bots;
+use entity::{delivery, snooze};
+
+const SNOOZE: &str = "snooze";
+const SNOOZE_PREFIX: &str = "snooze:";
+/// Hour of the day, UTC, of "tonight" and "tomorrow".
+const TONIGHT_HOUR: u32 = 20;
+const MORNING_HOUR: u32 = 8;
+
+/// The button offering to snooze an item.
+pub(crate) fn button() -> InlineKeyboardButton {
+    InlineKeyboardButton::callback("⏰", SNOOZE)
+}
+
+fn choices_keyboard() -> InlineKeyboardMarkup {
+    let button = |text: &str, choice: &str| {
+        InlineKeyboardButton::callback(text, format!("{}{}", SNOOZE_PREFIX, choice))
+    };
+    InlineKeyboardMarkup::new(vec![vec![
+        button("1h", "hour"),
+        button("Tonight", "tonight"),
+        button("Tomorrow", "tomorrow"),
+        button("Cancel", "cancel"),
+    ]])
+}
+
+/// Returns when an item snoozed at `now` with `choice` is sent again.
+fn resend_at(choice: &str, now: NaiveDateTime) -> Option<NaiveDateTime> {
+    let today = now.date();
+    let at = |hour: u32| NaiveTime::from_hms_opt(hour, 0, 0).unwrap_or(NaiveTime::MIN);
+    match choice {
+        "hour" => Some(now + Duration::hours(1)),
+        "tonight" => {
+            let tonight = today.and_time(at(TONIGHT_HOUR));
+            Some(if tonight > now {
+                tonight
+            } else {
+                tonight + Duration::days(1)
+            })
+        }
+        "tomorrow" => Some(today.and_time(at(MORNING_HOUR)) + Duration::days(1)),
+        _ => None,
+    }
+}
+
+/// Returns whether the callback `data` belongs to the snooze buttons.
+pub(crate) fn is_snooze(data: &str) -> bool {
+    data == SNOOZE || data.starts_with(SNOOZE_PREFIX)
+}
+
+/// Handles the snooze buttons of an item: the ⏰ button shows the choices, a choice schedules
+/// the item and deletes its message, and cancel restores the item keyboard.
+pub(crate) async fn handle_button(
+    bot: &Bot,
+    q: &CallbackQuery,
+    data: &str,
+    db: &DatabaseConnection,
+) -> ResponseResult<()> {
+    let Some(message) = &q.message else {
+        return Ok(());
+    };
+    let delivery = entity::prelude::Delivery::find()
+        .filter(delivery::Column::ChatId.eq(message.chat.id.0))
+        .filter(delivery::Column::MessageId.eq(message.id.0))
+        .one(db)
+        .await;
+    let delivery = match delivery {
+        Ok(Some(delivery)) => delivery,
+        Ok(None) => return Ok(()),
+        Err(err) => {
+            log::error!("Error finding snoozed item: {}", err);
+            return Ok(());
+        }
+    };
+    let choice = data.strip_prefix(SNOOZE_PREFIX);
+    let keyboard = match choice {
+        None => choices_keyboard(),
+        Some("cancel") => crate::noise::item_keyboard(delivery.feed_id),
+        Some(choice) => {
+            let Some(resend_at) = resend_at(choice, Utc::now().naive_utc()) else {
+                return Ok(());
+            };
+            let new_snooze = snooze::ActiveModel {
+                delivery_id: ActiveValue::Set(delivery.id),
+                resend_at: ActiveValue::Set(resend_at),
+                ..Default::default()
+            };
+            if let Err(err) = new_snooze.insert(db).await {
+                log::error!("Error snoozing item: {}", err);
+                return Ok(());
+            }
+            // Messages older than two days can't be deleted, their buttons are removed instead
+            if bot.delete_message(message.chat.id, message.id).await.is_err() {
+                bot.edit_message_reply_markup(message.chat.id, message.id).await?;
+            }
+            return Ok(());
+        }
+    };
+    bot.edit_message_reply_markup(message.chat.id, message.id)
+        .reply_markup(keyboard)
+        .await?;
+    Ok(())
+}
+
+/// Returns the item that was recorded as `delivery`.
+fn delivered_item(delivery: &delivery::Model) -> Item {
+    let mut item = Item::default();
+    item.set_title(delivery.title.clone());
+    item.set_link(delivery.link.clone());
+    if let Some(value) = &delivery.guid {
+        let mut guid = Guid::default();
+        guid.set_value(value.clone());
+        item.set_guid(guid);
+    }
+    item
+}
+
+/// Sends again the snoozed items that are due.
+pub(crate) async fn resend_due(bots: &Bots, db: &DatabaseConnection) {
+    let due = entity::prelude::Snooze::find()
+        .filter(snooze::Column::ResendAt.lte(Utc::now().naive_utc()))
+        .find_also_related(entity::prelude::Delivery)
+        .all(db)
+        .await;
+    let due = match due {
+        Ok(due) => due,
+        Err(err) => {
+            println!("Error fetching snoozed items: {:?}", err);
+            return;
+        }
+    };
+    for (snooze, delivery) in due {
+        if let Err(err) = snooze.clone().delete(db).await {
+            println!("Error deleting snooze: {:?}", err);
+            continue;
+        }
+        let Some(delivery) = delivery else {
+            continue;
+        };
+        let feed = match delivery.find_related(entity::prelude::Feed).one(db).await {
+            Ok(Some(feed)) => feed,
+            Ok(None) => continue,
+            Err(err) => {
+                println!("Error fetching feed of snoozed item: {:?}", err);
+                continue;
+            }
+        };
+        let Some(bot) = bots.get(feed.bot_id) else {
+            continue;
+        };
+        crate::send_item(bot, db, &feed, &delivered_item(&delivery), false, None).await;
+    }
+}
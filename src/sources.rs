@@ -0,0 +1,146 @@
+//! Feed sources shared by subscriptions.
+//!
+//! A `feed_source` is a feed link with its fetch state, and every `feed` row is the
+//! subscription of a chat to a source, with the chat's own settings. The poller fetches every
+//! source once per cycle and hands the result to all its subscriptions.
+//!
+//! Every source also adds up the bytes downloaded for it and the size they decompressed to, so
+//! that `/bandwidth` shows which feeds cost the most and which are served uncompressed.
+
+use chrono::Utc;
+use sea_orm::{
+    sea_query::{Expr, OnConflict},
+    ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder,
+    QuerySelect, Set,
+};
+
+use crate::http::Download;
+use entity::feed_source;
+
+/// Number of sources listed by `/bandwidth`.
+const TOP_SOURCES: u64 = 10;
+
+/// Returns `link` without fragment and trailing slash, with lowercase scheme and host, so that
+/// subscriptions to the same feed share a single source.
+pub(crate) fn normalize_link(link: &str) -> String {
+    match reqwest::Url::parse(link.trim()) {
+        Ok(mut url) => {
+            url.set_fragment(None);
+            url.to_string().trim_end_matches('/').to_string()
+        }
+        Err(_) => link.trim().trim_end_matches('/').to_string(),
+    }
+}
+
+/// Returns the source of `link`, if it exists.
+pub(crate) async fn find_source<C: ConnectionTrait>(
+    db: &C,
+    link: &str,
+) -> Result<Option<feed_source::Model>, DbErr> {
+    entity::prelude::FeedSource::find()
+        .filter(feed_source::Column::Link.eq(normalize_link(link)))
+        .one(db)
+        .await
+}
+
+/// Returns the source of `link`, creating it if it doesn't exist yet.
+pub(crate) async fn source_for<C: ConnectionTrait>(
+    db: &C,
+    link: &str,
+) -> Result<feed_source::Model, DbErr> {
+    let link = normalize_link(link);
+    let new_source = feed_source::ActiveModel {
+        link: Set(link.clone()),
+        ..Default::default()
+    };
+    entity::prelude::FeedSource::insert(new_source)
+        .on_conflict(
+            OnConflict::column(feed_source::Column::Link)
+                .do_nothing()
+                .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await?;
+    find_source(db, &link)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("feed_source".to_string()))
+}
+
+/// Records the outcome of fetching source `id`.
+pub(crate) async fn record_fetch(db: &DatabaseConnection, id: i64, error: Option<String>) {
+    let updated = entity::prelude::FeedSource::update_many()
+        .col_expr(
+            feed_source::Column::LastFetchedAt,
+            Expr::value(Utc::now().naive_utc()),
+        )
+        .col_expr(feed_source::Column::LastError, Expr::value(error))
+        .filter(feed_source::Column::Id.eq(id))
+        .exec(db)
+        .await;
+    if let Err(err) = updated {
+        println!("Error updating source {}: {:?}", id, err);
+    }
+}
+
+/// Adds the sizes of `download` to the totals of source `id`, if it was downloaded.
+pub(crate) async fn record_transfer(db: &DatabaseConnection, id: i64, download: &Download) {
+    if download.transferred == 0 {
+        return;
+    }
+    let updated = entity::prelude::FeedSource::update_many()
+        .col_expr(
+            feed_source::Column::DownloadedBytes,
+            Expr::col(feed_source::Column::DownloadedBytes).add(download.transferred as i64),
+        )
+        .col_expr(
+            feed_source::Column::DecodedBytes,
+            Expr::col(feed_source::Column::DecodedBytes).add(download.body.len() as i64),
+        )
+        .filter(feed_source::Column::Id.eq(id))
+        .exec(db)
+        .await;
+    if let Err(err) = updated {
+        println!("Error updating source {}: {:?}", id, err);
+    }
+}
+
+/// Returns `bytes` in megabytes.
+fn megabytes(bytes: i64) -> String {
+    format!("{:.1} MB", bytes as f64 / 1_000_000.0)
+}
+
+/// Returns the share of the `decoded` bytes that compression saved, in percent.
+fn saved_percent(downloaded: i64, decoded: i64) -> i64 {
+    if decoded == 0 {
+        return 0;
+    }
+    100 - downloaded * 100 / decoded
+}
+
+/// Returns the total bytes downloaded for the feeds, and the sources that downloaded the most.
+pub(crate) async fn bandwidth(db: &DatabaseConnection) -> Result<String, DbErr> {
+    let sources = entity::prelude::FeedSource::find().all(db).await?;
+    let downloaded: i64 = sources.iter().map(|source| source.downloaded_bytes).sum();
+    let decoded: i64 = sources.iter().map(|source| source.decoded_bytes).sum();
+    let mut lines = vec![format!(
+        "Downloaded {} for {} of feeds, {}% saved by compression",
+        megabytes(downloaded),
+        megabytes(decoded),
+        saved_percent(downloaded, decoded)
+    )];
+    let top = entity::prelude::FeedSource::find()
+        .filter(feed_source::Column::DownloadedBytes.gt(0))
+        .order_by_desc(feed_source::Column::DownloadedBytes)
+        .limit(TOP_SOURCES)
+        .all(db)
+        .await?;
+    for source in top {
+        lines.push(format!(
+            "{} - {} ({}% saved)",
+            megabytes(source.downloaded_bytes),
+            source.link,
+            saved_percent(source.downloaded_bytes, source.decoded_bytes)
+        ));
+    }
+    Ok(lines.join("\n"))
+}
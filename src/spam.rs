@@ -0,0 +1,126 @@
+//! Heuristic spam filtering of items.
+//!
+//! Aggregated feeds sometimes inject promotional junk. When a feed has `spam_filter` on, an item
+//! is spam if its title appears [`MAX_REPEATS`] times or more in the feed, if its title is mostly
+//! emoji or capital letters, or if it links to one of the comma separated domains in
+//! `SPAM_DOMAINS`, or their subdomains. Spam is not sent, but recorded in the `spam_item` table
+//! for review with `/spam <feed id>`.
+
+use std::collections::HashMap;
+use std::env;
+use std::error::Error;
+
+use rss::Item;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder,
+    QuerySelect, Set,
+};
+
+use entity::{feed, spam_item};
+
+const MAX_REPEATS: usize = 3;
+/// Titles with at least this many emoji, or more emoji than words, are spam.
+const MAX_EMOJI: usize = 5;
+/// Titles with at least this many letters are spam if nearly all are capitals.
+const MIN_SHOUTING_LETTERS: usize = 12;
+const MAX_CAPITALS_PERCENT: usize = 80;
+/// Number of filtered items listed by `/spam`.
+const REVIEW_ITEMS: u64 = 20;
+
+/// Counts how many times every title appears among `items`.
+pub(crate) fn title_counts(items: &[Item]) -> HashMap<&str, usize> {
+    let mut counts = HashMap::new();
+    for title in items.iter().filter_map(|item| item.title()) {
+        *counts.entry(title.trim()).or_default() += 1;
+    }
+    counts
+}
+
+fn is_emoji(c: char) -> bool {
+    matches!(c as u32, 0x1F000..=0x1FAFF | 0x2600..=0x27BF | 0x2B00..=0x2BFF)
+}
+
+fn spam_domains() -> Vec<String> {
+    env::var("SPAM_DOMAINS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|domain| domain.trim().to_lowercase())
+        .filter(|domain| !domain.is_empty())
+        .collect()
+}
+
+/// Returns why `item` is spam, or `None` if it doesn't look like spam.
+pub(crate) fn reason(item: &Item, title_counts: &HashMap<&str, usize>) -> Option<&'static str> {
+    let title = item.title().unwrap_or_default().trim();
+    if title_counts.get(title).copied().unwrap_or(0) >= MAX_REPEATS {
+        return Some("repeated title");
+    }
+    let emoji = title.chars().filter(|&c| is_emoji(c)).count();
+    if emoji >= MAX_EMOJI || emoji > title.split_whitespace().count() {
+        return Some("too many emoji");
+    }
+    let letters: Vec<char> = title.chars().filter(|c| c.is_alphabetic()).collect();
+    let capitals = letters.iter().filter(|c| c.is_uppercase()).count();
+    if letters.len() >= MIN_SHOUTING_LETTERS
+        && capitals * 100 >= letters.len() * MAX_CAPITALS_PERCENT
+    {
+        return Some("too many capitals");
+    }
+    let host = crate::moderation::host(item.link().unwrap_or_default())?;
+    spam_domains()
+        .iter()
+        .any(|domain| host == *domain || host.ends_with(&format!(".{}", domain)))
+        .then_some("spam domain")
+}
+
+/// Records that `item` of `feed` was filtered as spam.
+pub(crate) async fn record(db: &DatabaseConnection, feed: &feed::Model, item: &Item, reason: &str) {
+    let spam = spam_item::ActiveModel {
+        feed_id: Set(feed.id),
+        title: Set(item.title().unwrap_or_default().to_string()),
+        link: Set(item.link().unwrap_or_default().to_string()),
+        reason: Set(reason.to_string()),
+        ..Default::default()
+    };
+    if let Err(err) = spam.insert(db).await {
+        println!("Error recording spam item: {:?}", err);
+    }
+}
+
+/// Lists the latest items of feed `feed_id` that were filtered as spam.
+pub(crate) async fn review(
+    db: &DatabaseConnection,
+    feed_id: i64,
+    chat_id: i64,
+    bot_id: i64,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let chat_id = crate::links::managed_chat(db, feed_id, chat_id, bot_id).await?;
+    entity::prelude::Feed::find_by_id(feed_id)
+        .filter(feed::Column::ChatId.eq(chat_id))
+        .filter(feed::Column::BotId.eq(bot_id))
+        .one(db)
+        .await?
+        .ok_or_else(|| format!("No feed {}", feed_id))?;
+    let items = entity::prelude::SpamItem::find()
+        .filter(spam_item::Column::FeedId.eq(feed_id))
+        .order_by_desc(spam_item::Column::FilteredAt)
+        .limit(REVIEW_ITEMS)
+        .all(db)
+        .await?;
+    if items.is_empty() {
+        return Ok("No items were filtered as spam.".to_string());
+    }
+    Ok(items
+        .iter()
+        .map(|item| {
+            format!(
+                "{} {} ({}): {}",
+                item.filtered_at.format("%Y-%m-%d %H:%M"),
+                item.title,
+                item.reason,
+                item.link
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n"))
+}
@@ -0,0 +1,228 @@
+//! Weekly summary of the feeds of a chat.
+//!
+//! Chats that turn it on with `/weekly on` receive every Monday the number of items every feed
+//! sent in the last week, the domains most items linked to, the feeds that sent nothing and
+//! the feeds that fail to update, with buttons to pause or unsubscribe from the last two.
+
+use std::collections::HashMap;
+
+use chrono::{Duration, Utc};
+use sea_orm::{
+    sea_query::Expr, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter,
+    UpdateResult,
+};
+use teloxide::{
+    payloads::SendMessageSetters,
+    prelude::{Bot, Requester, ResponseResult},
+    types::{CallbackQuery, ChatId, InlineKeyboardButton, InlineKeyboardMarkup},
+};
+
+use crate::bots::{BotId, Bots};
+use crate::flags::{self, Flag};
+use crate::undo::{self, Action};
+use entity::{chat, delivery, feed};
+
+const WEEK_DAYS: i64 = 7;
+const TOP_DOMAINS: usize = 3;
+/// Feed titles are shortened to this many characters in the buttons.
+const BUTTON_TITLE_CHARS: usize = 24;
+
+const ACTION_PREFIX: &str = "summary:";
+
+/// Turns the weekly summary of `chat_id` on or off.
+pub(crate) async fn set_weekly_summary(
+    db: &DatabaseConnection,
+    chat_id: i64,
+    bot_id: i64,
+    enabled: bool,
+) -> Result<UpdateResult, DbErr> {
+    entity::prelude::Chat::update_many()
+        .col_expr(chat::Column::WeeklySummary, Expr::value(enabled))
+        .filter(chat::Column::Id.eq(chat_id))
+        .filter(chat::Column::BotId.eq(bot_id))
+        .exec(db)
+        .await
+}
+
+/// Returns the host of `link` without its `www.` prefix.
+fn domain(link: &str) -> Option<String> {
+    let url = reqwest::Url::parse(link).ok()?;
+    let host = url.host_str()?;
+    Some(host.strip_prefix("www.").unwrap_or(host).to_string())
+}
+
+fn short_title(title: &str) -> String {
+    if title.chars().count() <= BUTTON_TITLE_CHARS {
+        title.to_string()
+    } else {
+        let short: String = title.chars().take(BUTTON_TITLE_CHARS - 1).collect();
+        format!("{}…", short)
+    }
+}
+
+fn action_keyboard(problems: &[&feed::Model]) -> InlineKeyboardMarkup {
+    let button = |text: String, action: &str, feed_id: i64| {
+        InlineKeyboardButton::callback(text, format!("{}{}:{}", ACTION_PREFIX, action, feed_id))
+    };
+    InlineKeyboardMarkup::new(problems.iter().map(|feed| {
+        let title = short_title(&feed.title);
+        vec![
+            button(format!("Pause {}", title), "pause", feed.id),
+            button(format!("Unsubscribe {}", title), "unsubscribe", feed.id),
+        ]
+    }))
+}
+
+/// Returns the summary of the last week of `chat` with its keyboard, or `None` if the chat has
+/// no feeds.
+async fn summarize(
+    db: &DatabaseConnection,
+    chat: &chat::Model,
+) -> Result<Option<(String, InlineKeyboardMarkup)>, DbErr> {
+    let week_ago = Utc::now().naive_utc() - Duration::days(WEEK_DAYS);
+    let feeds = entity::prelude::Feed::find()
+        .filter(feed::Column::ChatId.eq(chat.id))
+        .filter(feed::Column::BotId.eq(chat.bot_id))
+        .all(db)
+        .await?;
+    if feeds.is_empty() {
+        return Ok(None);
+    }
+    let deliveries = entity::prelude::Delivery::find()
+        .filter(delivery::Column::FeedId.is_in(feeds.iter().map(|feed| feed.id)))
+        .filter(delivery::Column::SentAt.gt(week_ago))
+        .all(db)
+        .await?;
+    let mut items: HashMap<i64, usize> = HashMap::new();
+    let mut domains: HashMap<String, usize> = HashMap::new();
+    for delivery in &deliveries {
+        *items.entry(delivery.feed_id).or_default() += 1;
+        if let Some(domain) = domain(&delivery.link) {
+            *domains.entry(domain).or_default() += 1;
+        }
+    }
+
+    let mut text = format!("Your feeds in the last {} days:\n", WEEK_DAYS);
+    for feed in &feeds {
+        let count = items.get(&feed.id).copied().unwrap_or(0);
+        text.push_str(&format!("• {}: {} items\n", feed.title, count));
+    }
+    let mut domains: Vec<(String, usize)> = domains.into_iter().collect();
+    domains.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    if !domains.is_empty() {
+        let top: Vec<String> = domains
+            .iter()
+            .take(TOP_DOMAINS)
+            .map(|(domain, count)| format!("{} ({})", domain, count))
+            .collect();
+        text.push_str(&format!("\nTop domains: {}\n", top.join(", ")));
+    }
+    let is_silent = |feed: &feed::Model| {
+        !feed.paused && feed.created_at < week_ago && !items.contains_key(&feed.id)
+    };
+    let silent: Vec<&feed::Model> = feeds.iter().filter(|feed| is_silent(feed)).collect();
+    if !silent.is_empty() {
+        text.push_str("\nSilent feeds:\n");
+        for feed in &silent {
+            text.push_str(&format!("• {} ({})\n", feed.title, feed.id));
+        }
+    }
+    let failing: Vec<&feed::Model> = feeds.iter().filter(|feed| feed.error_count > 0).collect();
+    if !failing.is_empty() {
+        text.push_str("\nFeeds with errors:\n");
+        for feed in &failing {
+            let error = feed.last_error.as_deref().unwrap_or_default();
+            text.push_str(&format!("• {} ({}): {}\n", feed.title, feed.id, error));
+        }
+    }
+    let mut problems = silent;
+    for feed in failing {
+        if !problems.iter().any(|problem| problem.id == feed.id) {
+            problems.push(feed);
+        }
+    }
+    Ok(Some((text, action_keyboard(&problems))))
+}
+
+/// Sends the weekly summary to every chat that turned it on.
+pub(crate) async fn send_summaries(bots: Bots, db: DatabaseConnection) {
+    if !flags::is_enabled(&db, Flag::WeeklySummary).await {
+        return;
+    }
+    let chats = entity::prelude::Chat::find()
+        .filter(chat::Column::WeeklySummary.eq(true))
+        .all(&db)
+        .await;
+    let chats = match chats {
+        Ok(chats) => chats,
+        Err(err) => {
+            println!("Error looking for chats with a weekly summary: {:?}", err);
+            return;
+        }
+    };
+    for chat in chats {
+        let Some(bot) = bots.get(chat.bot_id) else {
+            continue;
+        };
+        let (text, keyboard) = match summarize(&db, &chat).await {
+            Ok(Some(summary)) => summary,
+            Ok(None) => continue,
+            Err(err) => {
+                println!("Error summarizing chat {}: {:?}", chat.id, err);
+                continue;
+            }
+        };
+        let sent = bot
+            .send_message(ChatId(chat.id), text)
+            .reply_markup(keyboard)
+            .await;
+        if let Err(err) = sent {
+            println!("Error sending summary: {:?}", err);
+        }
+    }
+}
+
+/// Returns whether the callback `data` belongs to a summary.
+pub(crate) fn is_summary_action(data: &str) -> bool {
+    data.starts_with(ACTION_PREFIX)
+}
+
+/// Handles the buttons of a summary. The outcome is sent as a new message so that the summary
+/// and its other buttons stay available.
+pub(crate) async fn handle_action(
+    bot: &Bot,
+    q: &CallbackQuery,
+    data: &str,
+    db: &DatabaseConnection,
+    bot_id: BotId,
+) -> ResponseResult<()> {
+    let Some(message) = &q.message else {
+        return Ok(());
+    };
+    let chat_id = message.chat.id;
+    let action = data
+        .strip_prefix(ACTION_PREFIX)
+        .and_then(|action| action.split_once(':'))
+        .and_then(|(action, feed_id)| Some((action, feed_id.parse::<i64>().ok()?)));
+    let text = match action {
+        Some(("pause", feed_id)) => {
+            match crate::set_feed_paused(db, feed_id, chat_id.0, bot_id.0, true).await {
+                Ok(update_result) => format!("Paused {} feed", update_result.rows_affected),
+                Err(error) => format!("Error: {}", error),
+            }
+        }
+        Some(("unsubscribe", feed_id)) => {
+            let deleted = async {
+                undo::record(db, chat_id.0, bot_id.0, Action::Unsubscribe, &[feed_id]).await?;
+                crate::delete_feed(db, feed_id, chat_id.0, bot_id.0).await
+            };
+            match deleted.await {
+                Ok(delete_result) => format!("Deleted {} feed", delete_result.rows_affected),
+                Err(error) => format!("Error: {}", error),
+            }
+        }
+        _ => return Ok(()),
+    };
+    bot.send_message(chat_id, text).await?;
+    Ok(())
+}
@@ -0,0 +1,188 @@
+//! Atom and JSON Feed feeds.
+//!
+//! Both are turned into an RSS channel, so that the rest of the bot handles them like any other
+//! feed. Entries keep their id as their guid, their alternate link, their summary as their
+//! description and their first attachment or enclosure link as their enclosure. Dates are
+//! converted to RFC 2822, except those that can't be read, which are kept as they are so that
+//! they are reported as malformed.
+
+use std::error::Error;
+
+use rss::{Category, Channel, Enclosure, Guid, Item};
+use serde::Deserialize;
+
+use crate::dates;
+
+#[derive(Debug, Deserialize)]
+struct JsonFeed {
+    title: String,
+    home_page_url: Option<String>,
+    description: Option<String>,
+    #[serde(default)]
+    items: Vec<JsonItem>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonItem {
+    /// A string, but some feeds use numbers.
+    id: Option<serde_json::Value>,
+    url: Option<String>,
+    external_url: Option<String>,
+    title: Option<String>,
+    content_html: Option<String>,
+    content_text: Option<String>,
+    summary: Option<String>,
+    date_published: Option<String>,
+    date_modified: Option<String>,
+    /// The single author of JSON Feed 1.0, replaced by `authors` in 1.1.
+    author: Option<JsonAuthor>,
+    #[serde(default)]
+    authors: Vec<JsonAuthor>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    attachments: Vec<JsonAttachment>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonAuthor {
+    name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonAttachment {
+    url: String,
+    mime_type: String,
+    size_in_bytes: Option<u64>,
+}
+
+/// Returns whether `content` is a JSON Feed rather than an XML feed.
+pub(crate) fn is_json_feed(content: &[u8]) -> bool {
+    let start = &content[..content.len().min(64)];
+    String::from_utf8_lossy(start)
+        .trim_start_matches('\u{feff}')
+        .trim_start()
+        .starts_with('{')
+}
+
+/// Returns `value` as an RFC 2822 date, or as it is if it can't be read.
+fn pub_date(value: &str) -> String {
+    dates::parse(value).map_or_else(|| value.to_string(), dates::rfc2822)
+}
+
+fn guid(id: &str) -> Guid {
+    let mut guid = Guid::default();
+    guid.set_value(id);
+    // Atom ids and JSON Feed ids are often URNs rather than links
+    guid.set_permalink(id.starts_with("http://") || id.starts_with("https://"));
+    guid
+}
+
+fn category(name: &str) -> Category {
+    let mut category = Category::default();
+    category.set_name(name);
+    category
+}
+
+fn enclosure(url: &str, mime_type: &str, length: Option<u64>) -> Enclosure {
+    let mut enclosure = Enclosure::default();
+    enclosure.set_url(url);
+    enclosure.set_mime_type(mime_type);
+    enclosure.set_length(length.unwrap_or_default().to_string());
+    enclosure
+}
+
+fn atom_item(entry: &atom_syndication::Entry) -> Item {
+    let mut item = Item::default();
+    item.set_title(entry.title().value.clone());
+    let alternate = entry.links().iter().find(|link| link.rel() == "alternate");
+    item.set_link(alternate.map(|link| link.href().to_string()));
+    item.set_guid(guid(entry.id()));
+    let date = entry.published().unwrap_or(entry.updated());
+    item.set_pub_date(dates::rfc2822(date.naive_utc()));
+    item.set_description(entry.summary().map(|summary| summary.value.clone()));
+    item.set_content(
+        entry
+            .content()
+            .and_then(|content| content.value())
+            .map(String::from),
+    );
+    item.set_author(
+        entry
+            .authors()
+            .first()
+            .map(|author| author.name().to_string()),
+    );
+    item.set_categories(
+        entry
+            .categories()
+            .iter()
+            .map(|atom_category| category(atom_category.term()))
+            .collect::<Vec<Category>>(),
+    );
+    let enclosure_link = entry.links().iter().find(|link| link.rel() == "enclosure");
+    item.set_enclosure(enclosure_link.map(|link| {
+        let length = link.length().and_then(|length| length.parse().ok());
+        enclosure(link.href(), link.mime_type().unwrap_or_default(), length)
+    }));
+    item
+}
+
+/// Parses the Atom feed `content` into a channel.
+pub(crate) fn atom_channel(content: &[u8]) -> Result<Channel, Box<dyn Error + Send + Sync>> {
+    let feed = atom_syndication::Feed::read_from(content)?;
+    let mut channel = Channel::default();
+    channel.set_title(feed.title().value.clone());
+    let alternate = feed.links().iter().find(|link| link.rel() == "alternate");
+    channel.set_link(alternate.map(|link| link.href()).unwrap_or_default());
+    channel.set_description(
+        feed.subtitle()
+            .map(|subtitle| subtitle.value.clone())
+            .unwrap_or_default(),
+    );
+    channel.set_items(feed.entries().iter().map(atom_item).collect::<Vec<Item>>());
+    Ok(channel)
+}
+
+fn json_item(json_item: JsonItem) -> Item {
+    let mut item = Item::default();
+    item.set_title(json_item.title);
+    item.set_link(json_item.url.or(json_item.external_url));
+    let id = json_item.id.map(|id| match id {
+        serde_json::Value::String(id) => id,
+        id => id.to_string(),
+    });
+    item.set_guid(id.as_deref().map(guid));
+    let date = json_item.date_published.or(json_item.date_modified);
+    item.set_pub_date(date.as_deref().map(pub_date));
+    item.set_description(json_item.summary);
+    item.set_content(json_item.content_html.or(json_item.content_text));
+    let author = json_item.authors.into_iter().next().or(json_item.author);
+    item.set_author(author.and_then(|author| author.name));
+    item.set_categories(
+        json_item
+            .tags
+            .iter()
+            .map(|tag| category(tag))
+            .collect::<Vec<Category>>(),
+    );
+    item.set_enclosure(json_item.attachments.first().map(|attachment| {
+        enclosure(
+            &attachment.url,
+            &attachment.mime_type,
+            attachment.size_in_bytes,
+        )
+    }));
+    item
+}
+
+/// Parses the JSON Feed `content` into a channel.
+pub(crate) fn json_feed_channel(content: &[u8]) -> Result<Channel, Box<dyn Error + Send + Sync>> {
+    let feed: JsonFeed = serde_json::from_slice(content)?;
+    let mut channel = Channel::default();
+    channel.set_title(feed.title);
+    channel.set_link(feed.home_page_url.unwrap_or_default());
+    channel.set_description(feed.description.unwrap_or_default());
+    channel.set_items(feed.items.into_iter().map(json_item).collect::<Vec<Item>>());
+    Ok(channel)
+}
@@ -0,0 +1,81 @@
+//! Feed thumbnails shown with delivered items.
+//!
+//! The image of a feed is its channel `<image>`, or the favicon of its site. Images are cached
+//! in the `image_cache` table and fetched again after [`MAX_AGE_DAYS`] days.
+
+use chrono::{Duration, Utc};
+use reqwest::header::CONTENT_TYPE;
+use rss::Channel;
+use sea_orm::{sea_query::OnConflict, DatabaseConnection, EntityTrait, Set};
+
+use entity::image_cache;
+
+const MAX_AGE_DAYS: i64 = 7;
+/// Larger images are not cached nor sent.
+const MAX_BYTES: usize = 1024 * 1024;
+
+/// Returns the URL of the image representing `channel`.
+pub(crate) fn image_url(channel: &Channel) -> Option<String> {
+    if let Some(image) = channel.image() {
+        return Some(image.url().to_string());
+    }
+    let site = reqwest::Url::parse(channel.link()).ok()?;
+    Some(format!("{}://{}/favicon.ico", site.scheme(), site.host_str()?))
+}
+
+async fn fetch(url: &str) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+    let response = reqwest::get(url).await?.error_for_status()?;
+    let is_image = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("image/"));
+    if !is_image {
+        return Err(format!("{} is not an image", url).into());
+    }
+    let data = response.bytes().await?;
+    if data.len() > MAX_BYTES {
+        return Err(format!("{} is larger than {} bytes", url, MAX_BYTES).into());
+    }
+    Ok(data.to_vec())
+}
+
+/// Returns the image at `url`, from the cache if it is fresh enough.
+pub(crate) async fn thumbnail(db: &DatabaseConnection, url: &str) -> Option<Vec<u8>> {
+    let cached = entity::prelude::ImageCache::find_by_id(url.to_string())
+        .one(db)
+        .await;
+    match cached {
+        Ok(Some(image))
+            if image.fetched_at > Utc::now().naive_utc() - Duration::days(MAX_AGE_DAYS) =>
+        {
+            return Some(image.data);
+        }
+        Ok(_) => {}
+        Err(err) => println!("Error reading image cache: {:?}", err),
+    }
+    let data = match fetch(url).await {
+        Ok(data) => data,
+        Err(err) => {
+            println!("Error fetching image {}: {:?}", url, err);
+            return None;
+        }
+    };
+    let image = image_cache::ActiveModel {
+        url: Set(url.to_string()),
+        data: Set(data.clone()),
+        fetched_at: Set(Utc::now().naive_utc()),
+    };
+    let stored = entity::prelude::ImageCache::insert(image)
+        .on_conflict(
+            OnConflict::column(image_cache::Column::Url)
+                .update_columns([image_cache::Column::Data, image_cache::Column::FetchedAt])
+                .to_owned(),
+        )
+        .exec(db)
+        .await;
+    if let Err(err) = stored {
+        println!("Error caching image: {:?}", err);
+    }
+    Some(data)
+}
@@ -0,0 +1,164 @@
+//! TLS options of feed sources on broken or internal hosts.
+//!
+//! `/tls <link> legacy|insecure|ca <path>` in the admin chat relaxes the TLS checks of the
+//! downloads of one feed source, rather than letting an otherwise useful feed fail forever:
+//!
+//! - `legacy` accepts servers that only speak TLS 1.0 or 1.1;
+//! - `insecure` accepts invalid certificates, e.g. expired or self-signed ones;
+//! - `ca <path>` also trusts the PEM certificate authority at `path`, for intranet feeds.
+//!
+//! `/tls <link> default` removes every option and `/tls <link>` shows them. The options of
+//! every source are kept in memory, and [`crate::http`] downloads the sources that have some
+//! with a client of their own.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::sync::RwLock;
+
+use reqwest::{tls::Version, Certificate, ClientBuilder};
+use sea_orm::{
+    sea_query::Expr, ColumnTrait, Condition, DatabaseConnection, DbErr, EntityTrait, QueryFilter,
+};
+
+use crate::sources::normalize_link;
+use entity::feed_source;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub(crate) struct TlsOptions {
+    legacy: bool,
+    insecure: bool,
+    ca: Option<String>,
+}
+
+impl TlsOptions {
+    fn of(source: &feed_source::Model) -> Self {
+        TlsOptions {
+            legacy: source.tls_legacy,
+            insecure: source.tls_insecure,
+            ca: source.tls_ca.clone(),
+        }
+    }
+
+    fn describe(&self) -> String {
+        let mut options = Vec::new();
+        if self.legacy {
+            options.push("legacy".to_string());
+        }
+        if self.insecure {
+            options.push("insecure".to_string());
+        }
+        if let Some(ca) = &self.ca {
+            options.push(format!("ca {}", ca));
+        }
+        if options.is_empty() {
+            return "default".to_string();
+        }
+        options.join(", ")
+    }
+
+    /// Applies the options to `builder`.
+    pub(crate) fn configure(
+        &self,
+        mut builder: ClientBuilder,
+    ) -> Result<ClientBuilder, Box<dyn Error + Send + Sync>> {
+        if self.legacy {
+            builder = builder.min_tls_version(Version::TLS_1_0);
+        }
+        if self.insecure {
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+        if let Some(ca) = &self.ca {
+            builder = builder.add_root_certificate(read_ca(ca)?);
+        }
+        Ok(builder)
+    }
+}
+
+/// The options of the sources that have some, by link.
+static OPTIONS: RwLock<Option<HashMap<String, TlsOptions>>> = RwLock::new(None);
+
+fn read_ca(path: &str) -> Result<Certificate, Box<dyn Error + Send + Sync>> {
+    let pem = fs::read(path).map_err(|err| format!("Can't read {}: {}", path, err))?;
+    Ok(Certificate::from_pem(&pem)?)
+}
+
+fn store(link: String, options: TlsOptions) {
+    let mut stored = OPTIONS.write().expect("TLS options lock poisoned");
+    let stored = stored.get_or_insert_with(HashMap::new);
+    if options == TlsOptions::default() {
+        stored.remove(&link);
+    } else {
+        stored.insert(link, options);
+    }
+}
+
+/// Loads the options of every source.
+pub(crate) async fn load(db: &DatabaseConnection) -> Result<(), DbErr> {
+    let sources = entity::prelude::FeedSource::find()
+        .filter(
+            Condition::any()
+                .add(feed_source::Column::TlsLegacy.eq(true))
+                .add(feed_source::Column::TlsInsecure.eq(true))
+                .add(feed_source::Column::TlsCa.is_not_null()),
+        )
+        .all(db)
+        .await?;
+    for source in sources {
+        store(source.link.clone(), TlsOptions::of(&source));
+    }
+    Ok(())
+}
+
+/// Returns the options of the source of `link`, if it has some.
+pub(crate) fn options(link: &str) -> Option<TlsOptions> {
+    let stored = OPTIONS.read().expect("TLS options lock poisoned");
+    stored.as_ref()?.get(&normalize_link(link)).cloned()
+}
+
+/// Runs `/tls` with `args`, which are `<link> [legacy|insecure|ca <path>|default]`, and returns
+/// the options of the source.
+pub(crate) async fn run(
+    db: &DatabaseConnection,
+    args: &str,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let mut words = args.split_whitespace();
+    let link = words
+        .next()
+        .ok_or("Expected a link and legacy, insecure, ca <path> or default")?;
+    let source = entity::prelude::FeedSource::find()
+        .filter(feed_source::Column::Link.eq(normalize_link(link)))
+        .one(db)
+        .await?
+        .ok_or_else(|| format!("No feed is subscribed to {}", link))?;
+    let mut options = TlsOptions::of(&source);
+    match words.next().map(str::to_lowercase).as_deref() {
+        None => return Ok(format!("TLS options of {}: {}", source.link, options.describe())),
+        Some("legacy") => options.legacy = true,
+        Some("insecure") => options.insecure = true,
+        Some("ca") => {
+            let path = words.next().ok_or("Expected the path of a PEM certificate")?;
+            read_ca(path)?;
+            options.ca = Some(path.to_string());
+        }
+        Some("default") => options = TlsOptions::default(),
+        Some(other) => {
+            return Err(format!(
+                "Expected legacy, insecure, ca <path> or default, got {}",
+                other
+            )
+            .into())
+        }
+    }
+    entity::prelude::FeedSource::update_many()
+        .col_expr(feed_source::Column::TlsLegacy, Expr::value(options.legacy))
+        .col_expr(feed_source::Column::TlsInsecure, Expr::value(options.insecure))
+        .col_expr(feed_source::Column::TlsCa, Expr::value(options.ca.clone()))
+        .filter(feed_source::Column::Id.eq(source.id))
+        .exec(db)
+        .await?;
+    let description = options.describe();
+    store(source.link.clone(), options);
+    crate::http::forget_clients();
+    Ok(format!("TLS options of {}: {}", source.link, description))
+}
@@ -0,0 +1,84 @@
+//! Items of torrent release feeds.
+//!
+//! Items whose link or enclosure is a magnet URI or a `.torrent` file are sent with the link in a
+//! code block, which Telegram lets users copy, because it can't open magnet links. With
+//! `/download <feed id> <url>` the torrent link of every item of a feed is also POSTed as JSON to
+//! `url`, such as an automation that adds it to a download client.
+
+use std::error::Error;
+
+use rss::Item;
+use sea_orm::{DatabaseConnection, UpdateResult};
+use serde::Serialize;
+
+use entity::feed;
+
+const TORRENT_TYPE: &str = "application/x-bittorrent";
+
+/// The JSON body posted to the download client.
+#[derive(Serialize)]
+struct Download<'a> {
+    feed_id: i64,
+    title: Option<&'a str>,
+    link: &'a str,
+}
+
+pub(crate) fn is_magnet(link: &str) -> bool {
+    link.starts_with("magnet:")
+}
+
+fn is_torrent(link: &str) -> bool {
+    let path = reqwest::Url::parse(link)
+        .map(|url| url.path().to_lowercase())
+        .unwrap_or_default();
+    is_magnet(link) || path.ends_with(".torrent")
+}
+
+/// Returns the magnet URI or `.torrent` link of `item`, if it has one.
+pub(crate) fn torrent_link(item: &Item) -> Option<&str> {
+    let link = item.link().filter(|link| is_torrent(link));
+    link.or_else(|| {
+        let enclosure = item.enclosure()?;
+        let torrent = enclosure.mime_type() == TORRENT_TYPE || is_torrent(enclosure.url());
+        Some(enclosure.url()).filter(|_| torrent)
+    })
+}
+
+/// Posts the torrent link of `item` of `feed` to its download client, if both exist.
+pub(crate) async fn send_item(feed: &feed::Model, item: &Item) {
+    let (Some(url), Some(link)) = (&feed.download_webhook, torrent_link(item)) else {
+        return;
+    };
+    let download = Download {
+        feed_id: feed.id,
+        title: item.title(),
+        link,
+    };
+    let posted = reqwest::Client::new()
+        .post(url)
+        .json(&download)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status());
+    if let Err(err) = posted {
+        println!("Error sending a torrent of feed {} to its download client: {:?}", feed.id, err);
+    }
+}
+
+/// Sets the download client of feed `id` to `url`. `off` removes it.
+pub(crate) async fn set_download_webhook(
+    db: &DatabaseConnection,
+    id: i64,
+    chat_id: i64,
+    bot_id: i64,
+    url: &str,
+) -> Result<UpdateResult, Box<dyn Error + Send + Sync>> {
+    let url = if url.eq_ignore_ascii_case("off") {
+        None
+    } else {
+        reqwest::Url::parse(url)?;
+        Some(url.to_string())
+    };
+    let column = feed::Column::DownloadWebhook;
+    crate::set_managed_feed_option(db, id, chat_id, bot_id, column, url).await
+}
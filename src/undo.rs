@@ -0,0 +1,193 @@
+//! Undo of the last destructive action of a chat.
+//!
+//! Before an unsubscribe, a rename or a change of the filters, the feeds it changes are stored
+//! as they were. For [`WINDOW_MINUTES`] minutes, `/undo` reverses the last such action of the
+//! chat: it restores the deleted feeds with their id and settings, or the previous title or
+//! filters. Deleted feeds whose link the chat subscribed to again meanwhile are skipped. The
+//! items published meanwhile by restored feeds are sent at their next poll, but their folders
+//! and the history of their deliveries are not restored.
+
+use std::error::Error;
+
+use chrono::{Duration, Utc};
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait,
+    ModelTrait, QueryFilter, QueryOrder, Set, TransactionTrait,
+};
+
+use entity::{feed, undo_action};
+
+/// How long an action can be undone, in minutes.
+const WINDOW_MINUTES: i64 = 10;
+const FILTER_COLUMNS: [feed::Column; 3] = [
+    feed::Column::CategoryFilter,
+    feed::Column::AuthorFilter,
+    feed::Column::LanguageFilter,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Action {
+    Unsubscribe,
+    Rename,
+    Filter,
+}
+
+impl Action {
+    fn name(self) -> &'static str {
+        match self {
+            Action::Unsubscribe => "unsubscribe",
+            Action::Rename => "rename",
+            Action::Filter => "filter",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        [Action::Unsubscribe, Action::Rename, Action::Filter]
+            .into_iter()
+            .find(|action| action.name() == name)
+    }
+}
+
+/// Stores the feeds with `feed_ids` before `action` changes them on behalf of `chat_id`, and
+/// forgets the actions that can't be undone anymore.
+pub(crate) async fn record(
+    db: &DatabaseConnection,
+    chat_id: i64,
+    bot_id: i64,
+    action: Action,
+    feed_ids: &[i64],
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let now = Utc::now().naive_utc();
+    entity::prelude::UndoAction::delete_many()
+        .filter(undo_action::Column::CreatedAt.lt(now - Duration::minutes(WINDOW_MINUTES)))
+        .exec(db)
+        .await?;
+    let feeds = entity::prelude::Feed::find()
+        .filter(feed::Column::Id.is_in(feed_ids.iter().copied()))
+        .all(db)
+        .await?;
+    if feeds.is_empty() {
+        return Ok(());
+    }
+    let undo_action = undo_action::ActiveModel {
+        chat_id: Set(chat_id),
+        bot_id: Set(bot_id),
+        kind: Set(action.name().to_string()),
+        feeds: Set(serde_json::to_string(&feeds)?),
+        created_at: Set(now),
+        ..Default::default()
+    };
+    undo_action.insert(db).await?;
+    Ok(())
+}
+
+/// Returns `count` feeds, such as `1 feed` or `3 feeds`.
+fn feed_count(count: u64) -> String {
+    format!("{} feed{}", count, if count == 1 { "" } else { "s" })
+}
+
+/// Recreates the deleted `feeds`, with their id and settings, except those whose source their
+/// chat is subscribed to again. Returns how many were restored.
+async fn restore_feeds(
+    db: &DatabaseConnection,
+    feeds: Vec<feed::Model>,
+) -> Result<u64, Box<dyn Error + Send + Sync>> {
+    let mut restored = Vec::new();
+    let txn = db.begin().await?;
+    for mut feed in feeds {
+        if let Some(source) = crate::sources::find_source(&txn, &feed.link).await? {
+            let subscribed_again = entity::prelude::Feed::find()
+                .filter(feed::Column::ChatId.eq(feed.chat_id))
+                .filter(feed::Column::BotId.eq(feed.bot_id))
+                .filter(feed::Column::SourceId.eq(source.id))
+                .one(&txn)
+                .await?;
+            if subscribed_again.is_some() {
+                continue;
+            }
+        }
+        // The source of the feed is forgotten once no feed uses it
+        feed.source_id = crate::sources::source_for(&txn, &feed.link).await?.id;
+        // Feeds subscribed to meanwhile may have taken the short id
+        if crate::short_ids::is_taken(&txn, feed.chat_id, feed.bot_id, feed.short_id).await? {
+            feed.short_id = crate::short_ids::next(&txn, feed.chat_id, feed.bot_id).await?;
+        }
+        feed::ActiveModel::from(feed.clone())
+            .reset_all()
+            .insert(&txn)
+            .await?;
+        restored.push(feed);
+    }
+    txn.commit().await?;
+    for feed in &restored {
+        crate::registry::invalidate(feed.id);
+    }
+    Ok(restored.len() as u64)
+}
+
+/// Sets the `columns` of the feeds that still exist back to their values in `feeds`.
+async fn restore_columns(
+    db: &DatabaseConnection,
+    feeds: &[feed::Model],
+    columns: &[feed::Column],
+) -> Result<u64, DbErr> {
+    let mut restored = 0;
+    for feed in feeds {
+        let mut update = entity::prelude::Feed::update_many();
+        for column in columns {
+            update = update.col_expr(*column, Expr::value(feed.get(*column)));
+        }
+        restored += update
+            .filter(feed::Column::Id.eq(feed.id))
+            .exec(db)
+            .await?
+            .rows_affected;
+        crate::registry::invalidate(feed.id);
+    }
+    Ok(restored)
+}
+
+/// Reverses the last action of `chat_id` of the last [`WINDOW_MINUTES`] minutes.
+pub(crate) async fn undo(
+    db: &DatabaseConnection,
+    chat_id: i64,
+    bot_id: i64,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let since = Utc::now().naive_utc() - Duration::minutes(WINDOW_MINUTES);
+    let last = entity::prelude::UndoAction::find()
+        .filter(undo_action::Column::ChatId.eq(chat_id))
+        .filter(undo_action::Column::BotId.eq(bot_id))
+        .filter(undo_action::Column::CreatedAt.gte(since))
+        .order_by_desc(undo_action::Column::Id)
+        .one(db)
+        .await?
+        .ok_or_else(|| format!("Nothing to undo from the last {} minutes", WINDOW_MINUTES))?;
+    let action = Action::parse(&last.kind).ok_or("Unknown action")?;
+    let feeds: Vec<feed::Model> = serde_json::from_str(&last.feeds)?;
+    let count = feeds.len() as u64;
+    let text = match action {
+        Action::Unsubscribe => {
+            let restored = restore_feeds(db, feeds).await?;
+            match count - restored {
+                0 => format!("Restored {}", feed_count(restored)),
+                skipped => format!(
+                    "Restored {}, skipped {} subscribed to again",
+                    feed_count(restored),
+                    feed_count(skipped)
+                ),
+            }
+        }
+        Action::Rename => {
+            let restored = restore_columns(db, &feeds, &[feed::Column::Title]).await?;
+            format!("Restored the title of {}", feed_count(restored))
+        }
+        Action::Filter => {
+            let restored = restore_columns(db, &feeds, &FILTER_COLUMNS).await?;
+            format!("Restored the filters of {}", feed_count(restored))
+        }
+    };
+    entity::prelude::UndoAction::delete_by_id(last.id)
+        .exec(db)
+        .await?;
+    Ok(text)
+}
@@ -0,0 +1,357 @@
+//! Per-user web view, authenticated with the Telegram Login Widget.
+//!
+//! End users log in with their Telegram account and manage the subscriptions of their private
+//! chat with the default bot, whose id is the same as their user id. The login data is verified
+//! with the bot token as described in <https://core.telegram.org/widgets/login>, and the session
+//! is kept in a cookie signed with another key derived from the bot token, so that neither
+//! signature is accepted for the other, with the time of the login so that it expires after
+//! [`MAX_SESSION_AGE`] seconds.
+
+use std::collections::BTreeMap;
+
+use axum::{
+    extract::{Path, Query, Request, State},
+    http::header,
+    middleware::{self, Next},
+    response::{Html, IntoResponse, Redirect, Response},
+    routing::{get, post},
+    Extension, Form, Router,
+};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use sea_orm::EntityTrait;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use teloxide::utils::html::escape;
+
+use crate::api::ApiState;
+use crate::dashboard::{cookie, link, page};
+use entity::feed;
+
+const COOKIE_NAME: &str = "user_session";
+/// Login data older than this many seconds is rejected.
+const MAX_AUTH_AGE: i64 = 86400;
+/// Sessions expire this many seconds after the login.
+const MAX_SESSION_AGE: i64 = 7 * 86400;
+/// Derives the key of the sessions from the bot token.
+const SESSION_LABEL: &[u8] = b"session";
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// The id of the logged in Telegram user, which is also the id of their private chat.
+#[derive(Clone, Copy)]
+struct UserId(i64);
+
+#[derive(Deserialize)]
+struct NewSubscription {
+    link: String,
+}
+
+pub(crate) fn routes(state: ApiState) -> Router<ApiState> {
+    Router::new()
+        .route("/me", get(show))
+        .route("/me/subscribe", post(subscribe))
+        .route("/me/feeds/{id}/pause", post(pause))
+        .route("/me/feeds/{id}/resume", post(resume))
+        .route("/me/feeds/{id}/delete", post(remove))
+        .layer(middleware::from_fn_with_state(state, require_login))
+        .route("/me/login", get(login_form))
+        .route("/me/auth", get(auth))
+        .route("/me/logout", post(logout))
+}
+
+fn mac(token: &str) -> HmacSha256 {
+    let secret_key = Sha256::digest(token.as_bytes());
+    HmacSha256::new_from_slice(&secret_key).expect("HMAC accepts keys of any size")
+}
+
+/// Returns the MAC of the sessions, whose key is the HMAC of the bot token with
+/// [`SESSION_LABEL`] rather than the key of the login data.
+fn session_mac(token: &str) -> HmacSha256 {
+    let mut key = HmacSha256::new_from_slice(SESSION_LABEL).expect("HMAC accepts keys of any size");
+    key.update(token.as_bytes());
+    HmacSha256::new_from_slice(&key.finalize().into_bytes()).expect("HMAC accepts keys of any size")
+}
+
+/// Checks the `hash` of the data sent by the Telegram Login Widget and returns the user id.
+fn verify_login(token: &str, mut data: BTreeMap<String, String>) -> Option<i64> {
+    let hash = hex::decode(data.remove("hash")?).ok()?;
+    let auth_date: i64 = data.get("auth_date")?.parse().ok()?;
+    if Utc::now().timestamp() - auth_date > MAX_AUTH_AGE {
+        return None;
+    }
+    let data_check_string = data
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<String>>()
+        .join("\n");
+    let mut mac = mac(token);
+    mac.update(data_check_string.as_bytes());
+    mac.verify_slice(&hash).ok()?;
+    data.get("id")?.parse().ok()
+}
+
+/// Returns the session of `user_id` logged in at `issued_at`, a Unix timestamp.
+fn sign_session(token: &str, user_id: i64, issued_at: i64) -> String {
+    let payload = format!("{}:{}", user_id, issued_at);
+    let mut mac = session_mac(token);
+    mac.update(payload.as_bytes());
+    format!("{}:{}", payload, hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Returns the user id of `session` if it is signed and at most [`MAX_SESSION_AGE`] seconds old
+/// at `now`.
+fn verify_session(token: &str, session: &str, now: i64) -> Option<i64> {
+    let (payload, signature) = session.rsplit_once(':')?;
+    let mut mac = session_mac(token);
+    mac.update(payload.as_bytes());
+    mac.verify_slice(&hex::decode(signature).ok()?).ok()?;
+    let (user_id, issued_at) = payload.split_once(':')?;
+    let issued_at: i64 = issued_at.parse().ok()?;
+    if !(0..=MAX_SESSION_AGE).contains(&(now - issued_at)) {
+        return None;
+    }
+    user_id.parse().ok()
+}
+
+async fn require_login(
+    State(state): State<ApiState>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let user_id = cookie(request.headers(), COOKIE_NAME).and_then(|session| {
+        verify_session(
+            state.bots.default_bot().token(),
+            &session,
+            Utc::now().timestamp(),
+        )
+    });
+    match user_id {
+        Some(user_id) => {
+            request.extensions_mut().insert(UserId(user_id));
+            next.run(request).await
+        }
+        None => Redirect::to("/me/login").into_response(),
+    }
+}
+
+async fn login_form(State(state): State<ApiState>) -> Html<String> {
+    page(&format!(
+        "<h1>multitude_bot</h1>\
+         <script async src='https://telegram.org/js/telegram-widget.js?22' \
+         data-telegram-login='{}' data-size='large' data-onauth='onTelegramAuth(user)'></script>\
+         <script>function onTelegramAuth(user) {{\
+         window.location.href = '/me/auth?' + new URLSearchParams(user).toString();\
+         }}</script>",
+        escape(&state.bot_username)
+    ))
+}
+
+async fn auth(
+    State(state): State<ApiState>,
+    Query(data): Query<BTreeMap<String, String>>,
+) -> Response {
+    let Some(user_id) = verify_login(state.bots.default_bot().token(), data) else {
+        return Redirect::to("/me/login").into_response();
+    };
+    let session = sign_session(
+        state.bots.default_bot().token(),
+        user_id,
+        Utc::now().timestamp(),
+    );
+    let cookie = format!(
+        "{}={}; Path=/me; HttpOnly; SameSite=Lax; Max-Age={}",
+        COOKIE_NAME,
+        urlencoding::encode(&session),
+        MAX_SESSION_AGE
+    );
+    ([(header::SET_COOKIE, cookie)], Redirect::to("/me")).into_response()
+}
+
+async fn logout() -> Response {
+    let cookie = format!("{}=; Path=/me; HttpOnly; Max-Age=0", COOKIE_NAME);
+    ([(header::SET_COOKIE, cookie)], Redirect::to("/me/login")).into_response()
+}
+
+fn feed_row(feed: &feed::Model) -> String {
+    let status = if feed.paused {
+        "paused".to_string()
+    } else if let Some(error) = &feed.last_error {
+        format!("error: {}", escape(error))
+    } else {
+        "ok".to_string()
+    };
+    let toggle = if feed.paused { "resume" } else { "pause" };
+    format!(
+        "<tr><td>{id}</td><td>{link}</td><td>{status}</td><td>\
+         <form method='post' action='/me/feeds/{id}/{toggle}'>\
+         <button type='submit'>{toggle}</button></form> \
+         <form method='post' action='/me/feeds/{id}/delete' \
+         onsubmit=\"return confirm('Unsubscribe from this feed?')\">\
+         <button type='submit'>unsubscribe</button></form></td></tr>",
+        id = feed.id,
+        link = link(&feed.link, &feed.title),
+        status = status,
+        toggle = toggle,
+    )
+}
+
+async fn show(
+    State(state): State<ApiState>,
+    Extension(UserId(user_id)): Extension<UserId>,
+) -> Response {
+    let bot_id = state.bots.default_id();
+    let registered = entity::prelude::Chat::find_by_id((user_id, bot_id))
+        .one(&state.db)
+        .await;
+    let feeds = crate::read_feed(&state.db, user_id, bot_id).await;
+    let mut body = String::from("<h1>My subscriptions</h1>");
+    match (registered, feeds) {
+        (Ok(None), _) => {
+            body.push_str(&format!(
+                "<p>Send /start to <a href='https://t.me/{0}'>@{0}</a> to create an account.</p>",
+                escape(&state.bot_username)
+            ));
+        }
+        (Ok(Some(_)), Ok(feeds)) => {
+            body.push_str("<table><tr><th>id</th><th>title</th><th>status</th><th></th></tr>");
+            for feed in &feeds {
+                body.push_str(&feed_row(feed));
+            }
+            body.push_str(
+                "</table><form method='post' action='/me/subscribe'>\
+                 <input type='url' name='link' placeholder='RSS address' required>\
+                 <button type='submit'>Subscribe</button></form>",
+            );
+        }
+        (Err(err), _) => body.push_str(&format!("<p>Error: {}</p>", escape(&err.to_string()))),
+        (_, Err(err)) => body.push_str(&format!("<p>Error: {}</p>", escape(&err.to_string()))),
+    }
+    body.push_str(
+        "<p><form method='post' action='/me/logout'>\
+         <button type='submit'>Log out</button></form></p>",
+    );
+    page(&body).into_response()
+}
+
+async fn subscribe(
+    State(state): State<ApiState>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Form(subscription): Form<NewSubscription>,
+) -> Response {
+    let link = crate::aliases::resolve(&state.db, &subscription.link).await;
+    let created = match crate::validate_feed(&link).await {
+        Ok(channel) => {
+            crate::create_feed(&state.db, &channel, user_id, state.bots.default_id()).await
+        }
+        Err(err) => Err(err),
+    };
+    if let Err(err) = created {
+        return page(&format!(
+            "<p>Error: {}</p><p><a href='/me'>Back</a></p>",
+            escape(&err.to_string())
+        ))
+        .into_response();
+    }
+    Redirect::to("/me").into_response()
+}
+
+async fn set_paused(state: &ApiState, user_id: i64, id: i64, paused: bool) -> Response {
+    let bot_id = state.bots.default_id();
+    if let Err(err) = crate::set_feed_paused(&state.db, id, user_id, bot_id, paused).await {
+        log::error!("Error updating feed {}: {}", id, err);
+    }
+    Redirect::to("/me").into_response()
+}
+
+async fn pause(
+    State(state): State<ApiState>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Path(id): Path<i64>,
+) -> Response {
+    set_paused(&state, user_id, id, true).await
+}
+
+async fn resume(
+    State(state): State<ApiState>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Path(id): Path<i64>,
+) -> Response {
+    set_paused(&state, user_id, id, false).await
+}
+
+async fn remove(
+    State(state): State<ApiState>,
+    Extension(UserId(user_id)): Extension<UserId>,
+    Path(id): Path<i64>,
+) -> Response {
+    let bot_id = state.bots.default_id();
+    if let Err(err) = crate::delete_feed(&state.db, id, user_id, bot_id).await {
+        log::error!("Error removing feed {}: {}", id, err);
+    }
+    Redirect::to("/me").into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOKEN: &str = "123456:secret";
+    const NOW: i64 = 1_792_000_000;
+
+    #[test]
+    fn verifies_fresh_sessions() {
+        let session = sign_session(TOKEN, 42, NOW);
+        assert_eq!(verify_session(TOKEN, &session, NOW), Some(42));
+        assert_eq!(
+            verify_session(TOKEN, &session, NOW + MAX_SESSION_AGE),
+            Some(42)
+        );
+    }
+
+    #[test]
+    fn rejects_expired_sessions() {
+        let session = sign_session(TOKEN, 42, NOW);
+        assert_eq!(
+            verify_session(TOKEN, &session, NOW + MAX_SESSION_AGE + 1),
+            None
+        );
+        assert_eq!(verify_session(TOKEN, &session, NOW - 1), None);
+    }
+
+    #[test]
+    fn rejects_forged_sessions() {
+        let session = sign_session(TOKEN, 42, NOW);
+        let (_, signature) = session.rsplit_once(':').unwrap();
+        let other_user = format!("43:{}:{}", NOW, signature);
+        let later = format!("42:{}:{}", NOW + MAX_SESSION_AGE, signature);
+        assert_eq!(verify_session(TOKEN, &other_user, NOW), None);
+        assert_eq!(verify_session(TOKEN, &later, NOW + MAX_SESSION_AGE), None);
+        assert_eq!(verify_session("654321:other", &session, NOW), None);
+        // Sessions signed before they carried the time of the login
+        assert_eq!(verify_session(TOKEN, "42:abcdef", NOW), None);
+    }
+
+    #[test]
+    fn escapes_links() {
+        let mut feed = crate::bench::sample_feed("Feed");
+        feed.link = "https://example.com/'><script>alert(1)</script>".to_string();
+        assert!(feed_row(&feed).contains(
+            "<a href=\"https://example.com/&#39;&gt;&lt;script&gt;alert(1)&lt;/script&gt;\">\
+             Feed</a>"
+        ));
+        feed.link = "javascript:alert(1)".to_string();
+        assert!(!feed_row(&feed).contains("href"));
+    }
+
+    #[test]
+    fn rejects_sessions_signed_with_the_login_key() {
+        let payload = format!("42:{}", NOW);
+        let mut login_mac = mac(TOKEN);
+        login_mac.update(payload.as_bytes());
+        let signature = hex::encode(login_mac.finalize().into_bytes());
+        let session = format!("{}:{}", payload, signature);
+        assert_eq!(verify_session(TOKEN, &session, NOW), None);
+        assert_ne!(sign_session(TOKEN, 42, NOW), session);
+    }
+}
@@ -0,0 +1,80 @@
+//! Items with a video enclosure, such as video podcasts and PeerTube channels.
+//!
+//! Videos up to [`MAX_BYTES`] are downloaded and sent as Telegram videos with the message as
+//! caption, as long as the `videos` flag is on. Larger videos are sent as a link with their
+//! duration.
+
+use std::error::Error;
+
+use rss::{Enclosure, Item};
+use sea_orm::DatabaseConnection;
+use teloxide::utils::html::escape;
+
+use crate::flags::{self, Flag};
+
+/// Largest video a bot may upload.
+const MAX_BYTES: u64 = 50 * 1024 * 1024;
+
+fn video(item: &Item) -> Option<&Enclosure> {
+    item.enclosure().filter(|enclosure| enclosure.mime_type().starts_with("video/"))
+}
+
+/// Returns the duration of the video of `item`, as written by the feed, such as `1:02:03`.
+fn duration(item: &Item) -> Option<String> {
+    let itunes = item.itunes_ext().and_then(|itunes| itunes.duration());
+    let media = || {
+        let contents = item.extensions().get("media")?.get("content")?;
+        let seconds: u64 = contents
+            .iter()
+            .find_map(|content| content.attrs().get("duration"))?
+            .parse()
+            .ok()?;
+        let (hours, minutes) = (seconds / 3600, seconds / 60 % 60);
+        Some(match hours {
+            0 => format!("{}:{:02}", minutes, seconds % 60),
+            _ => format!("{}:{:02}:{:02}", hours, minutes, seconds % 60),
+        })
+    };
+    itunes.map(str::to_string).or_else(media)
+}
+
+async fn fetch(url: &str) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+    let response = reqwest::get(url).await?.error_for_status()?;
+    if response.content_length().is_some_and(|length| length > MAX_BYTES) {
+        return Err(format!("{} is larger than {} bytes", url, MAX_BYTES).into());
+    }
+    let data = response.bytes().await?;
+    if data.len() as u64 > MAX_BYTES {
+        return Err(format!("{} is larger than {} bytes", url, MAX_BYTES).into());
+    }
+    Ok(data.to_vec())
+}
+
+/// Returns the video of `item`, if it has one small enough to be sent.
+pub(crate) async fn download(db: &DatabaseConnection, item: &Item) -> Option<Vec<u8>> {
+    let enclosure = video(item)?;
+    let length: u64 = enclosure.length().parse().unwrap_or_default();
+    if length > MAX_BYTES || !flags::is_enabled(db, Flag::Videos).await {
+        return None;
+    }
+    match fetch(enclosure.url()).await {
+        Ok(data) => Some(data),
+        Err(err) => {
+            println!("Error fetching video {}: {:?}", enclosure.url(), err);
+            None
+        }
+    }
+}
+
+/// Returns the HTML link to the video of `item` with its duration, or nothing if it has no
+/// video.
+pub(crate) fn video_link(item: &Item) -> String {
+    let Some(enclosure) = video(item) else {
+        return String::new();
+    };
+    let link = format!("🎬 <a href='{}'>Video</a>", escape(enclosure.url()));
+    match duration(item) {
+        Some(duration) => format!("{} ({})\n", link, escape(&duration)),
+        None => format!("{}\n", link),
+    }
+}
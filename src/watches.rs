@@ -0,0 +1,279 @@
+//! Feeds of the changes of an element of a web page.
+//!
+//! `/watch <url> <css selector>` creates a watch of the text of the first element matching the
+//! selector, such as a price or an availability label, and subscribes to a virtual feed with the
+//! link `watch:<watch id>`. Every poll reads the page again, and each change of the text becomes
+//! an item titled `old → new`.
+//!
+//! `/monitor <url> [percent]` creates a watch of all the text of a page instead, kept one line
+//! per text node, and each change of at least `percent` of it becomes an item with an excerpt of
+//! the lines added and removed. Changes of a monitored page are sent at most once every
+//! [`MONITOR_HOURS`] hours: smaller or more frequent changes are left in the snapshot, so that
+//! the next excerpt includes them.
+
+use std::error::Error;
+
+use chrono::{Duration, Utc};
+use rss::{Channel, Item};
+use scraper::{Html, Selector};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder,
+    QuerySelect, Set,
+};
+use similar::{ChangeTag, TextDiff};
+use teloxide::utils::html::escape;
+
+use entity::{feed, watch, watch_change};
+
+const LINK_PREFIX: &str = "watch:";
+/// Number of changes in the virtual feed.
+const ITEMS: u64 = 20;
+/// Values are shortened to this many characters in the items.
+const MAX_VALUE_CHARS: usize = 200;
+/// Minimum number of hours between two changes of a monitored page.
+const MONITOR_HOURS: i64 = 6;
+/// Number of lines added or removed shown in the excerpt of a change of a monitored page.
+const EXCERPT_LINES: usize = 10;
+
+/// Returns whether `link` is the link of a watch feed.
+pub(crate) fn is_watch(link: &str) -> bool {
+    link.starts_with(LINK_PREFIX)
+}
+
+fn parse_selector(selector: &str) -> Result<Selector, String> {
+    Selector::parse(selector).map_err(|err| format!("Invalid selector {}: {}", selector, err))
+}
+
+/// Returns the text of the first element of `html` matching `selector`, with its whitespace
+/// collapsed.
+fn element_text(html: &str, selector: &Selector) -> Option<String> {
+    let document = Html::parse_document(html);
+    let element = document.select(selector).next()?;
+    let text = element.text().collect::<Vec<&str>>().join(" ");
+    Some(text.split_whitespace().collect::<Vec<&str>>().join(" "))
+}
+
+/// Returns the text of the page `html`, one line per text node, leaving out scripts and styles.
+fn page_text(html: &str) -> String {
+    let document = Html::parse_document(html);
+    document
+        .root_element()
+        .descendants()
+        .filter(|node| {
+            let parent = node.parent().and_then(|parent| parent.value().as_element());
+            !matches!(parent.map(|parent| parent.name()), Some("script" | "style" | "noscript"))
+        })
+        .filter_map(|node| node.value().as_text())
+        .map(|text| text.split_whitespace().collect::<Vec<&str>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Returns the current text watched by `watch`.
+async fn current_value(watch: &watch::Model) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let html = String::from_utf8_lossy(&crate::cache::fetch(&watch.url).await?).into_owned();
+    if watch.monitor {
+        return Ok(page_text(&html));
+    }
+    let selector = watch.selector.as_deref().unwrap_or("body");
+    Ok(element_text(&html, &parse_selector(selector)?)
+        .ok_or_else(|| format!("No element matches {} on {}", selector, watch.url))?)
+}
+
+fn shorten(value: &str) -> String {
+    if value.chars().count() <= MAX_VALUE_CHARS {
+        return value.to_string();
+    }
+    let short: String = value.chars().take(MAX_VALUE_CHARS).collect();
+    format!("{}…", short)
+}
+
+/// Returns the excerpt of the lines removed and added from `old` to `new`, if at least
+/// `threshold` percent of the text changed.
+fn diff_excerpt(old: &str, new: &str, threshold: i32) -> Option<String> {
+    let diff = TextDiff::from_lines(old, new);
+    let changed = 100.0 * (1.0 - diff.ratio());
+    if changed < threshold as f32 {
+        return None;
+    }
+    let lines: Vec<String> = diff
+        .iter_all_changes()
+        .filter_map(|change| {
+            let sign = match change.tag() {
+                ChangeTag::Delete => "-",
+                ChangeTag::Insert => "+",
+                ChangeTag::Equal => return None,
+            };
+            Some(format!("{} {}", sign, shorten(change.value().trim_end())))
+        })
+        .collect();
+    let mut excerpt = format!("{:.0}% of the page changed:", changed.ceil());
+    for line in lines.iter().take(EXCERPT_LINES) {
+        excerpt.push('\n');
+        excerpt.push_str(line);
+    }
+    if lines.len() > EXCERPT_LINES {
+        excerpt.push_str(&format!("\n… and {} more lines", lines.len() - EXCERPT_LINES));
+    }
+    Some(excerpt)
+}
+
+/// Returns whether the last change of `watch` is more recent than [`MONITOR_HOURS`] hours.
+async fn changed_recently(
+    db: &DatabaseConnection,
+    watch: &watch::Model,
+) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    let last_change = entity::prelude::WatchChange::find()
+        .filter(watch_change::Column::WatchId.eq(watch.id))
+        .order_by_desc(watch_change::Column::ChangedAt)
+        .one(db)
+        .await?;
+    let since = Utc::now().naive_utc() - Duration::hours(MONITOR_HOURS);
+    Ok(last_change.is_some_and(|change| change.changed_at > since))
+}
+
+/// Checks `watch` and records a change if its text is different from the last one.
+async fn check(
+    db: &DatabaseConnection,
+    watch: watch::Model,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let value = current_value(&watch).await?;
+    let Some(old_value) = watch.value.clone().filter(|old_value| *old_value != value) else {
+        return Ok(());
+    };
+    let title = if watch.monitor {
+        let Some(excerpt) = diff_excerpt(&old_value, &value, watch.threshold) else {
+            return Ok(());
+        };
+        if changed_recently(db, &watch).await? {
+            return Ok(());
+        }
+        escape(&excerpt)
+    } else {
+        format!("{} → {}", escape(&shorten(&old_value)), escape(&shorten(&value)))
+    };
+    let change = watch_change::ActiveModel {
+        watch_id: Set(watch.id),
+        title: Set(title),
+        ..Default::default()
+    };
+    change.insert(db).await?;
+    let mut changed_watch: watch::ActiveModel = watch.into();
+    changed_watch.value = Set(Some(value));
+    changed_watch.update(db).await?;
+    Ok(())
+}
+
+/// Checks the watch of the feed at `link` and returns the feed of its latest changes.
+pub(crate) async fn channel(
+    db: &DatabaseConnection,
+    link: &str,
+) -> Result<Channel, Box<dyn Error + Send + Sync>> {
+    let id: i64 = link
+        .strip_prefix(LINK_PREFIX)
+        .and_then(|id| id.parse().ok())
+        .ok_or_else(|| format!("{} is not a watch feed", link))?;
+    let watch = entity::prelude::Watch::find_by_id(id)
+        .one(db)
+        .await?
+        .ok_or_else(|| format!("No watch {}", id))?;
+    let url = watch.url.clone();
+    let title = if watch.monitor {
+        format!("Changes of {}", url)
+    } else {
+        format!("Watch of {}", url)
+    };
+    check(db, watch).await?;
+    let changes = entity::prelude::WatchChange::find()
+        .filter(watch_change::Column::WatchId.eq(id))
+        .order_by_desc(watch_change::Column::ChangedAt)
+        .limit(ITEMS)
+        .all(db)
+        .await?;
+    let mut channel = Channel::default();
+    channel.set_title(title);
+    channel.set_link(link);
+    channel.set_items(
+        changes
+            .into_iter()
+            .map(|change| {
+                let mut item = Item::default();
+                item.set_title(change.title);
+                item.set_link(url.clone());
+                item.set_pub_date(crate::dates::rfc2822(change.changed_at));
+                item
+            })
+            .collect::<Vec<Item>>(),
+    );
+    Ok(channel)
+}
+
+/// Creates the watch `new_watch` and subscribes `chat_id` to it. Returns the feed and the
+/// current text.
+async fn create(
+    db: &DatabaseConnection,
+    new_watch: watch::ActiveModel,
+    chat_id: i64,
+    bot_id: i64,
+) -> Result<(feed::Model, String), Box<dyn Error + Send + Sync>> {
+    let watch = new_watch.insert(db).await?;
+    let id = watch.id;
+    let created = async {
+        let value = current_value(&watch).await?;
+        let mut checked_watch: watch::ActiveModel = watch.into();
+        checked_watch.value = Set(Some(value.clone()));
+        checked_watch.update(db).await?;
+        let channel = channel(db, &format!("{}{}", LINK_PREFIX, id)).await?;
+        let feed = crate::create_feed(db, &channel, chat_id, bot_id).await?;
+        Ok::<_, Box<dyn Error + Send + Sync>>((feed, value))
+    }
+    .await;
+    if created.is_err() {
+        entity::prelude::Watch::delete_by_id(id).exec(db).await?;
+    }
+    created
+}
+
+/// Creates a watch of the element of `url` matching `selector`, or of the whole page if there
+/// is none, and subscribes `chat_id` to it. Returns the feed and the current text.
+pub(crate) async fn subscribe(
+    db: &DatabaseConnection,
+    url: &str,
+    selector: Option<&str>,
+    chat_id: i64,
+    bot_id: i64,
+) -> Result<(feed::Model, String), Box<dyn Error + Send + Sync>> {
+    reqwest::Url::parse(url)?;
+    if let Some(selector) = selector {
+        parse_selector(selector)?;
+    }
+    let new_watch = watch::ActiveModel {
+        url: Set(url.to_string()),
+        selector: Set(selector.map(str::to_string)),
+        ..Default::default()
+    };
+    create(db, new_watch, chat_id, bot_id).await
+}
+
+/// Creates a monitor of the text of the page at `url`, notifying of changes of at least
+/// `threshold` percent, and subscribes `chat_id` to it. Returns the feed and the current text.
+pub(crate) async fn monitor(
+    db: &DatabaseConnection,
+    url: &str,
+    threshold: i32,
+    chat_id: i64,
+    bot_id: i64,
+) -> Result<(feed::Model, String), Box<dyn Error + Send + Sync>> {
+    reqwest::Url::parse(url)?;
+    if !(0..=100).contains(&threshold) {
+        return Err(format!("Expected a percentage between 0 and 100, got {}", threshold).into());
+    }
+    let new_watch = watch::ActiveModel {
+        url: Set(url.to_string()),
+        monitor: Set(true),
+        threshold: Set(threshold),
+        ..Default::default()
+    };
+    create(db, new_watch, chat_id, bot_id).await
+}
@@ -0,0 +1,242 @@
+//! Outgoing webhooks of chats.
+//!
+//! `/webhook <url>` makes every item delivered to a chat also be POSTed as JSON to `url`, for
+//! automations such as n8n, Zapier or Notion. The body is signed with HMAC-SHA256 and a secret
+//! of the chat, which is shown once when the webhook is set, and the hex signature is sent in
+//! the [`SIGNATURE_HEADER`] header as `sha256=<signature>`.
+//!
+//! Webhooks share a client that gives up after [`TIMEOUT`], so that a slow endpoint can't hold
+//! up the delivery of the items, and addresses of this host or of private networks are refused.
+
+use std::error::Error;
+use std::net::IpAddr;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use hmac::{Hmac, Mac};
+use rand::{distributions::Alphanumeric, Rng};
+use reqwest::Client;
+use rss::Item;
+use sea_orm::{
+    sea_query::Expr, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, UpdateResult,
+};
+use serde::Serialize;
+use sha2::Sha256;
+
+use crate::bridges::Notifier;
+use entity::{chat, feed};
+
+pub(crate) const SIGNATURE_HEADER: &str = "X-Multitude-Signature";
+const SECRET_LENGTH: usize = 32;
+/// How long a webhook has to answer.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+static CLIENT: OnceLock<Client> = OnceLock::new();
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Returns the client shared by the webhooks.
+fn client() -> &'static Client {
+    CLIENT.get_or_init(|| {
+        Client::builder()
+            .timeout(TIMEOUT)
+            .build()
+            .expect("Can't build the webhook client")
+    })
+}
+
+/// Returns whether `ip` is an address of this host or of a private network.
+fn is_internal(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [first, second, ..] = ip.octets();
+            ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                // Shared address space of carrier-grade NAT, 100.64.0.0/10
+                || (first == 100 && (64..128).contains(&second))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_internal(IpAddr::V4(ip)),
+            None => {
+                let first = ip.segments()[0];
+                ip.is_loopback()
+                    || ip.is_unspecified()
+                    // Unique local addresses, fc00::/7, and link-local addresses, fe80::/10
+                    || (first & 0xfe00) == 0xfc00
+                    || (first & 0xffc0) == 0xfe80
+            }
+        },
+    }
+}
+
+/// Fails unless every address of the host of `url` is public.
+async fn check_public(url: &reqwest::Url) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let host = url.host_str().ok_or("The webhook needs a host")?;
+    let port = url.port_or_known_default().unwrap_or(443);
+    let ips: Vec<IpAddr> = match host.trim_matches(['[', ']']).parse::<IpAddr>() {
+        Ok(ip) => vec![ip],
+        Err(_) => tokio::net::lookup_host((host, port))
+            .await?
+            .map(|address| address.ip())
+            .collect(),
+    };
+    if ips.is_empty() || ips.into_iter().any(is_internal) {
+        return Err("The webhook must be a public address".into());
+    }
+    Ok(())
+}
+
+/// The JSON body posted for an item.
+#[derive(Serialize)]
+struct Payload<'a> {
+    chat_id: i64,
+    feed_id: i64,
+    feed_title: &'a str,
+    title: Option<&'a str>,
+    link: Option<&'a str>,
+    description: Option<&'a str>,
+    author: Option<&'a str>,
+    categories: Vec<&'a str>,
+    guid: Option<&'a str>,
+    pub_date: Option<&'a str>,
+}
+
+/// The webhook of a chat.
+pub(crate) struct Webhook {
+    url: String,
+    secret: String,
+}
+
+impl Webhook {
+    fn signature(&self, body: &[u8]) -> String {
+        let mut mac = HmacSha256::new_from_slice(self.secret.as_bytes())
+            .expect("HMAC accepts keys of any size");
+        mac.update(body);
+        hex::encode(mac.finalize().into_bytes())
+    }
+}
+
+impl Notifier for Webhook {
+    async fn notify(
+        &self,
+        feed: &feed::Model,
+        item: &Item,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let payload = Payload {
+            chat_id: feed.chat_id,
+            feed_id: feed.id,
+            feed_title: &feed.title,
+            title: item.title(),
+            link: item.link(),
+            description: item.description(),
+            author: item.author(),
+            categories: item.categories().iter().map(|category| category.name()).collect(),
+            guid: item.guid().map(|guid| guid.value()),
+            pub_date: item.pub_date(),
+        };
+        let body = serde_json::to_vec(&payload)?;
+        client()
+            .post(&self.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(SIGNATURE_HEADER, format!("sha256={}", self.signature(&body)))
+            .body(body)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Posts `item` of `feed` to the webhook of its chat, if it has one.
+pub(crate) async fn send_item(db: &DatabaseConnection, feed: &feed::Model, item: &Item) {
+    let chat = entity::prelude::Chat::find_by_id((feed.chat_id, feed.bot_id))
+        .one(db)
+        .await;
+    let chat = match chat {
+        Ok(chat) => chat,
+        Err(err) => {
+            println!("Error fetching chat {}: {:?}", feed.chat_id, err);
+            return;
+        }
+    };
+    let webhook = chat.and_then(|chat| Some((chat.webhook_url?, chat.webhook_secret?)));
+    let Some((url, secret)) = webhook else {
+        return;
+    };
+    if let Err(err) = (Webhook { url, secret }).notify(feed, item).await {
+        println!("Error posting to the webhook of chat {}: {:?}", feed.chat_id, err);
+    }
+}
+
+/// Sets the webhook of `chat_id` to `url` with a new secret, which is returned. `off` removes
+/// the webhook.
+pub(crate) async fn set_webhook(
+    db: &DatabaseConnection,
+    chat_id: i64,
+    bot_id: i64,
+    url: &str,
+) -> Result<(UpdateResult, Option<String>), Box<dyn Error + Send + Sync>> {
+    let (url, secret) = if url.eq_ignore_ascii_case("off") {
+        (None, None)
+    } else {
+        let parsed = reqwest::Url::parse(url)?;
+        if parsed.scheme() != "https" {
+            return Err("The webhook must be an https address".into());
+        }
+        check_public(&parsed).await?;
+        let secret: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(SECRET_LENGTH)
+            .map(char::from)
+            .collect();
+        (Some(url.to_string()), Some(secret))
+    };
+    let updated = entity::prelude::Chat::update_many()
+        .col_expr(chat::Column::WebhookUrl, Expr::value(url))
+        .col_expr(chat::Column::WebhookSecret, Expr::value(secret.clone()))
+        .filter(chat::Column::Id.eq(chat_id))
+        .filter(chat::Column::BotId.eq(bot_id))
+        .exec(db)
+        .await?;
+    Ok((updated, secret))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refuses_internal_addresses() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(is_internal(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[test]
+    fn accepts_public_addresses() {
+        for ip in [
+            "93.184.216.34",
+            "100.128.0.1",
+            "2606:4700::1111",
+            "::ffff:8.8.8.8",
+        ] {
+            assert!(!is_internal(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+}
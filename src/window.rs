@@ -0,0 +1,114 @@
+//! Delivery windows of feeds.
+//!
+//! `/window <feed id> 09:00-18:00 Mon-Fri` restricts the delivery of a feed to the given hours
+//! (UTC) and days. Items published outside the window are held and sent as a digest when it
+//! opens. A window ending before it starts, such as `22:00-06:00`, spans midnight.
+
+use std::error::Error;
+use std::str::FromStr;
+
+use chrono::{Datelike, NaiveDateTime, NaiveTime, Weekday};
+use sea_orm::{DatabaseConnection, UpdateResult};
+
+use entity::feed;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct DeliveryWindow {
+    start: NaiveTime,
+    end: NaiveTime,
+    days: Vec<Weekday>,
+}
+
+fn parse_time(s: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(s, "%H:%M")
+        .map_err(|_| format!("Expected a time such as 09:00, got {}", s))
+}
+
+fn parse_day(s: &str) -> Result<Weekday, String> {
+    s.parse::<Weekday>()
+        .map_err(|_| format!("Expected a day such as Mon, got {}", s))
+}
+
+/// Parses days such as `Mon-Fri` or `Sat,Sun`.
+fn parse_days(s: &str) -> Result<Vec<Weekday>, String> {
+    let mut days = Vec::new();
+    for part in s.split(',') {
+        match part.split_once('-') {
+            Some((first, last)) => {
+                let (mut day, last) = (parse_day(first)?, parse_day(last)?);
+                while day != last {
+                    days.push(day);
+                    day = day.succ();
+                }
+                days.push(last);
+            }
+            None => days.push(parse_day(part)?),
+        }
+    }
+    Ok(days)
+}
+
+impl FromStr for DeliveryWindow {
+    type Err = String;
+
+    /// Parses `<start>-<end> [days]`, every day if no days are given.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut words = s.split_whitespace();
+        let hours = words
+            .next()
+            .ok_or_else(|| "Expected hours such as 09:00-18:00".to_string())?;
+        let (start, end) = hours
+            .split_once('-')
+            .ok_or_else(|| format!("Expected hours such as 09:00-18:00, got {}", hours))?;
+        let days = match words.next() {
+            Some(days) => parse_days(days)?,
+            None => parse_days("Mon-Sun")?,
+        };
+        if let Some(word) = words.next() {
+            return Err(format!("Unexpected {}", word));
+        }
+        Ok(DeliveryWindow {
+            start: parse_time(start)?,
+            end: parse_time(end)?,
+            days,
+        })
+    }
+}
+
+impl DeliveryWindow {
+    fn contains(&self, time: NaiveDateTime) -> bool {
+        let now = time.time();
+        let in_hours = if self.start <= self.end {
+            self.start <= now && now < self.end
+        } else {
+            now >= self.start || now < self.end
+        };
+        in_hours && self.days.contains(&time.weekday())
+    }
+}
+
+/// Returns whether `feed` may deliver items at `time`.
+pub(crate) fn is_open(feed: &feed::Model, time: NaiveDateTime) -> bool {
+    match feed.delivery_window.as_deref().map(DeliveryWindow::from_str) {
+        Some(Ok(window)) => window.contains(time),
+        _ => true,
+    }
+}
+
+/// Sets the delivery window of feed `id`. `off` removes it.
+pub(crate) async fn set_window(
+    db: &DatabaseConnection,
+    id: i64,
+    chat_id: i64,
+    bot_id: i64,
+    window: &str,
+) -> Result<UpdateResult, Box<dyn Error + Send + Sync>> {
+    let window = if window.eq_ignore_ascii_case("off") {
+        None
+    } else {
+        window.parse::<DeliveryWindow>()?;
+        Some(window.to_string())
+    };
+    let column = feed::Column::DeliveryWindow;
+    crate::set_managed_feed_option(db, id, chat_id, bot_id, column, window).await
+}